@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use omnivore_core::CrawlConfig;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Server settings, read from the `[crawler]`, `[api]` and `[monitoring]`
+/// tables of a config file such as `configs/crawler.toml`. Other tables are
+/// ignored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// Base settings for every crawl job. Depth and worker count can be
+    /// overridden per request.
+    #[serde(default)]
+    pub crawler: CrawlConfig,
+
+    #[serde(default)]
+    pub api: ApiConfig,
+
+    #[serde(default)]
+    pub monitoring: MonitoringConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 3000,
+        }
+    }
+}
+
+/// Trace export. Metrics are always served on `/metrics`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitoringConfig {
+    /// Export crawl traces over OTLP to `tracing_endpoint`.
+    #[serde(default)]
+    pub enable_tracing: bool,
+    #[serde(default = "default_tracing_endpoint")]
+    pub tracing_endpoint: String,
+}
+
+fn default_tracing_endpoint() -> String {
+    "http://localhost:4317".to_string()
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            enable_tracing: false,
+            tracing_endpoint: default_tracing_endpoint(),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+}
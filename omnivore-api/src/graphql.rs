@@ -0,0 +1,607 @@
+use crate::jobs::{CrawlRequest, JobManager, JobStatus};
+use crate::results::{build_graph, PageRecord, Session};
+use async_graphql::connection::{query, Connection, Edge, EmptyFields};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{
+    Context, Error, Object, OutputType, Result, Schema, SimpleObject, Subscription, ID,
+};
+use async_graphql_axum::{GraphQL, GraphQLSubscription};
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use globset::GlobBuilder;
+use omnivore_core::graph::builder::{MENTIONS_EDGE, PAGE_NODE};
+use omnivore_core::graph::query::GraphQuery;
+use omnivore_core::graph::{self, KnowledgeGraph};
+use omnivore_core::CrawlStats;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+pub type OmnivoreSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+/// Where the schema reads crawls from.
+#[derive(Clone)]
+pub enum Source {
+    /// Jobs on the API server, which can also be started and followed.
+    Jobs(JobManager),
+    /// Crawl output loaded from disk by `results::load_sessions`.
+    Saved(Arc<Vec<Session>>),
+}
+
+impl Source {
+    /// Every session, or only `id` when given.
+    async fn sessions(&self, id: Option<&ID>) -> Result<Vec<Session>> {
+        match self {
+            Self::Jobs(jobs) => {
+                let selected = match id {
+                    Some(id) => {
+                        let id = Uuid::parse_str(id).map_err(|_| invalid_session(id))?;
+                        jobs.get(id).into_iter().collect()
+                    }
+                    None => jobs.list(),
+                };
+                let mut sessions = Vec::with_capacity(selected.len());
+                for job in selected {
+                    sessions.push(Session::from_job(&job).await);
+                }
+                Ok(sessions)
+            }
+            Self::Saved(sessions) => Ok(sessions
+                .iter()
+                .filter(|session| id.is_none_or(|id| session.id == id.as_str()))
+                .cloned()
+                .collect()),
+        }
+    }
+}
+
+/// Graphs already built, by the session they were built for (`None` for all
+/// of them).
+type GraphCache = Mutex<HashMap<Option<String>, CachedGraph>>;
+
+struct CachedGraph {
+    versions: Vec<SessionVersion>,
+    graph: Arc<KnowledgeGraph>,
+}
+
+/// What tells a session apart from how it was when a graph was built from
+/// it. Saved sessions never change, and a job only adds pages until it ends.
+#[derive(PartialEq)]
+struct SessionVersion {
+    id: String,
+    status: JobStatus,
+    pages: usize,
+}
+
+impl SessionVersion {
+    fn of(session: &Session) -> Self {
+        Self {
+            id: session.id.clone(),
+            status: session.status,
+            pages: session.pages.len(),
+        }
+    }
+}
+
+/// The graph of every session, or only `session`, reused until a session it
+/// was built from finishes or gets new pages.
+async fn session_graph(ctx: &Context<'_>, session: Option<&ID>) -> Result<Arc<KnowledgeGraph>> {
+    let sessions = ctx.data::<Source>()?.sessions(session).await?;
+    let versions: Vec<SessionVersion> = sessions.iter().map(SessionVersion::of).collect();
+    let key = session.map(|id| id.to_string());
+
+    let cache = ctx.data::<GraphCache>()?;
+    if let Some(cached) = cache.lock().unwrap().get(&key) {
+        if cached.versions == versions {
+            return Ok(cached.graph.clone());
+        }
+    }
+    let graph = Arc::new(build_graph(&sessions)?);
+    cache.lock().unwrap().insert(
+        key,
+        CachedGraph {
+            versions,
+            graph: graph.clone(),
+        },
+    );
+    Ok(graph)
+}
+
+fn invalid_session(id: &ID) -> Error {
+    Error::new(format!("Invalid session id '{}'", id.as_str()))
+}
+
+/// The schema over `source`.
+pub fn schema(source: Source) -> OmnivoreSchema {
+    Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
+        .data(source)
+        .data(GraphCache::default())
+        .finish()
+}
+
+/// `/graphql` for queries and mutations (with GraphiQL on `GET`) and
+/// `/graphql/ws` for subscriptions over WebSocket.
+pub fn router(schema: OmnivoreSchema) -> Router {
+    Router::new()
+        .route(
+            "/graphql",
+            get(graphiql).post_service(GraphQL::new(schema.clone())),
+        )
+        .route_service("/graphql/ws", GraphQLSubscription::new(schema))
+}
+
+async fn graphiql() -> Html<String> {
+    Html(
+        GraphiQLSource::build()
+            .endpoint("/graphql")
+            .subscription_endpoint("/graphql/ws")
+            .finish(),
+    )
+}
+
+/// Extra fields on every connection.
+#[derive(SimpleObject)]
+pub struct ConnectionFields {
+    /// Items matching the filters, across all pages.
+    pub total_count: usize,
+}
+
+type Page<T> = Connection<usize, T, ConnectionFields, EmptyFields>;
+
+/// Relay-style pagination over `items`, with offsets as cursors.
+async fn paginate<T: OutputType>(
+    items: Vec<T>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> Result<Page<T>> {
+    query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<usize>, before: Option<usize>, first, last| async move {
+            let total = items.len();
+            let mut start = after.map_or(0, |after| after + 1).min(total);
+            let mut end = before.unwrap_or(total).clamp(start, total);
+            if let Some(first) = first {
+                end = end.min(start + first);
+            }
+            if let Some(last) = last {
+                start = start.max(end.saturating_sub(last));
+            }
+
+            let mut connection = Connection::with_additional_fields(
+                start > 0,
+                end < total,
+                ConnectionFields { total_count: total },
+            );
+            connection.edges.extend(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .skip(start)
+                    .take(end - start)
+                    .map(|(offset, item)| Edge::new(offset, item)),
+            );
+            Ok::<_, Error>(connection)
+        },
+    )
+    .await
+}
+
+/// `CrawlStats`, with the elapsed time in seconds.
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "CrawlStats")]
+pub struct Stats {
+    pub total_urls: usize,
+    pub successful: usize,
+    pub failed: usize,
+    pub in_progress: usize,
+    pub average_response_time_ms: f64,
+    pub start_time: DateTime<Utc>,
+    pub elapsed_secs: f64,
+}
+
+impl From<&CrawlStats> for Stats {
+    fn from(stats: &CrawlStats) -> Self {
+        Self {
+            total_urls: stats.total_urls,
+            successful: stats.successful,
+            failed: stats.failed,
+            in_progress: stats.in_progress,
+            average_response_time_ms: stats.average_response_time_ms,
+            start_time: stats.start_time,
+            elapsed_secs: stats.elapsed_time.as_secs_f64(),
+        }
+    }
+}
+
+#[Object(name = "Session")]
+impl Session {
+    async fn id(&self) -> ID {
+        ID(self.id.clone())
+    }
+
+    async fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn status(&self) -> JobStatus {
+        self.status
+    }
+
+    async fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.started_at
+    }
+
+    async fn stats(&self) -> Option<Stats> {
+        self.stats.as_ref().map(Stats::from)
+    }
+
+    async fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// A node of the knowledge graph: a page (keyed by URL) or an entity.
+pub struct GraphNode {
+    node: graph::Node,
+    graph: Arc<KnowledgeGraph>,
+}
+
+#[Object(name = "Node")]
+impl GraphNode {
+    async fn id(&self) -> ID {
+        ID(self.node.id.clone())
+    }
+
+    /// `page`, or the entity kind (`EMAIL`, `URL`, ...).
+    #[graphql(name = "type")]
+    async fn node_type(&self) -> &str {
+        &self.node.node_type
+    }
+
+    async fn properties(&self) -> Value {
+        serde_json::to_value(&self.node.properties).unwrap_or_default()
+    }
+
+    /// Edges starting at this node, optionally only of `type`.
+    async fn outgoing(
+        &self,
+        #[graphql(name = "type")] edge_type: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<GraphEdge>> {
+        let edges = self.graph.outgoing(&self.node.id);
+        paginate(
+            edges_of_type(&self.graph, edges, edge_type),
+            after,
+            before,
+            first,
+            last,
+        )
+        .await
+    }
+
+    /// Edges ending at this node, optionally only of `type`.
+    async fn incoming(
+        &self,
+        #[graphql(name = "type")] edge_type: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<GraphEdge>> {
+        let edges = self.graph.incoming(&self.node.id);
+        paginate(
+            edges_of_type(&self.graph, edges, edge_type),
+            after,
+            before,
+            first,
+            last,
+        )
+        .await
+    }
+
+    /// Nodes within `depth` edges of this one in either direction,
+    /// nearest first.
+    async fn neighbors(&self, #[graphql(default = 1)] depth: usize) -> Vec<GraphNode> {
+        GraphQuery::new(&self.graph)
+            .find_connected(&self.node.id, depth)
+            .into_iter()
+            .map(|node| GraphNode::new(node, &self.graph))
+            .collect()
+    }
+}
+
+impl GraphNode {
+    fn new(node: &graph::Node, graph: &Arc<KnowledgeGraph>) -> Self {
+        Self {
+            node: node.clone(),
+            graph: graph.clone(),
+        }
+    }
+}
+
+pub struct GraphEdge {
+    edge: graph::Edge,
+    graph: Arc<KnowledgeGraph>,
+}
+
+#[Object(name = "Edge")]
+impl GraphEdge {
+    /// `links_to` between pages, or `mentions` from a page to an entity.
+    #[graphql(name = "type")]
+    async fn edge_type(&self) -> &str {
+        &self.edge.edge_type
+    }
+
+    async fn properties(&self) -> Value {
+        serde_json::to_value(&self.edge.properties).unwrap_or_default()
+    }
+
+    async fn source(&self) -> Option<GraphNode> {
+        self.graph
+            .get_node(&self.edge.from)
+            .map(|node| GraphNode::new(node, &self.graph))
+    }
+
+    async fn target(&self) -> Option<GraphNode> {
+        self.graph
+            .get_node(&self.edge.to)
+            .map(|node| GraphNode::new(node, &self.graph))
+    }
+}
+
+fn edges_of_type(
+    graph: &Arc<KnowledgeGraph>,
+    edges: Vec<&graph::Edge>,
+    edge_type: Option<String>,
+) -> Vec<GraphEdge> {
+    edges
+        .into_iter()
+        .filter(|edge| edge_type.as_ref().is_none_or(|t| &edge.edge_type == t))
+        .map(|edge| GraphEdge {
+            edge: edge.clone(),
+            graph: graph.clone(),
+        })
+        .collect()
+}
+
+/// An entity recognized in page text, with the pages mentioning it.
+#[derive(SimpleObject)]
+pub struct Entity {
+    pub id: ID,
+    pub text: String,
+    pub entity_type: String,
+    /// Number of times it appears, across all pages.
+    pub mentions: u64,
+    pub pages: Vec<String>,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn health(&self) -> &str {
+        "OK"
+    }
+
+    async fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    async fn sessions(&self, ctx: &Context<'_>) -> Result<Vec<Session>> {
+        ctx.data::<Source>()?.sessions(None).await
+    }
+
+    async fn session(&self, ctx: &Context<'_>, id: ID) -> Result<Option<Session>> {
+        Ok(ctx.data::<Source>()?.sessions(Some(&id)).await?.pop())
+    }
+
+    /// Crawled pages, optionally only from one session, with URLs matching
+    /// a glob pattern (`*` matches across `/`) or with an HTTP status.
+    #[allow(clippy::too_many_arguments)]
+    async fn pages(
+        &self,
+        ctx: &Context<'_>,
+        session: Option<ID>,
+        url_pattern: Option<String>,
+        status: Option<u16>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<PageRecord>> {
+        let pattern = url_pattern
+            .map(|pattern| {
+                GlobBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|glob| glob.compile_matcher())
+                    .map_err(|e| Error::new(format!("Invalid URL pattern: {}", e)))
+            })
+            .transpose()?;
+
+        let pages = ctx
+            .data::<Source>()?
+            .sessions(session.as_ref())
+            .await?
+            .into_iter()
+            .flat_map(|session| session.pages)
+            .filter(|page| pattern.as_ref().is_none_or(|p| p.is_match(&page.url)))
+            .filter(|page| status.is_none() || page.status_code == status)
+            .collect();
+        paginate(pages, after, before, first, last).await
+    }
+
+    /// Nodes of the knowledge graph built from a session's pages (or all
+    /// sessions), optionally only of `type`.
+    #[allow(clippy::too_many_arguments)]
+    async fn nodes(
+        &self,
+        ctx: &Context<'_>,
+        session: Option<ID>,
+        #[graphql(name = "type")] node_type: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<GraphNode>> {
+        let graph = session_graph(ctx, session.as_ref()).await?;
+        let nodes = match &node_type {
+            Some(node_type) => GraphQuery::new(&graph).find_by_type(node_type),
+            None => graph.nodes().collect(),
+        };
+        let nodes = nodes
+            .into_iter()
+            .map(|node| GraphNode::new(node, &graph))
+            .collect();
+        paginate(nodes, after, before, first, last).await
+    }
+
+    async fn node(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        session: Option<ID>,
+    ) -> Result<Option<GraphNode>> {
+        let graph = session_graph(ctx, session.as_ref()).await?;
+        Ok(graph.get_node(&id).map(|node| GraphNode::new(node, &graph)))
+    }
+
+    /// Edges of the knowledge graph, optionally only of `type`.
+    #[allow(clippy::too_many_arguments)]
+    async fn edges(
+        &self,
+        ctx: &Context<'_>,
+        session: Option<ID>,
+        #[graphql(name = "type")] edge_type: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<GraphEdge>> {
+        let graph = session_graph(ctx, session.as_ref()).await?;
+        let edges = edges_of_type(&graph, graph.edges().collect(), edge_type);
+        paginate(edges, after, before, first, last).await
+    }
+
+    /// Entities whose text contains `query` (ignoring case), optionally only
+    /// of `type` (`EMAIL`, `URL`, ...). Most mentioned first.
+    #[allow(clippy::too_many_arguments)]
+    async fn entities(
+        &self,
+        ctx: &Context<'_>,
+        query: Option<String>,
+        #[graphql(name = "type")] entity_type: Option<String>,
+        session: Option<ID>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Entity>> {
+        let graph = session_graph(ctx, session.as_ref()).await?;
+        let query = query.map(|q| q.to_lowercase());
+
+        let mut entities: Vec<Entity> = graph
+            .nodes()
+            .filter(|node| node.node_type != PAGE_NODE)
+            .filter(|node| {
+                entity_type
+                    .as_ref()
+                    .is_none_or(|t| node.node_type.eq_ignore_ascii_case(t))
+            })
+            .filter_map(|node| {
+                let text = node.properties.get("text")?.as_str()?.to_string();
+                if !query
+                    .as_ref()
+                    .is_none_or(|q| text.to_lowercase().contains(q))
+                {
+                    return None;
+                }
+
+                let mut pages = BTreeMap::new();
+                for edge in graph.incoming(&node.id) {
+                    if edge.edge_type == MENTIONS_EDGE {
+                        let count = edge.properties.get("count").and_then(Value::as_u64);
+                        *pages.entry(edge.from.clone()).or_insert(0) += count.unwrap_or(1);
+                    }
+                }
+                Some(Entity {
+                    id: ID(node.id.clone()),
+                    text,
+                    entity_type: node.node_type.clone(),
+                    mentions: pages.values().sum(),
+                    pages: pages.into_keys().collect(),
+                })
+            })
+            .collect();
+        entities.sort_by(|a, b| {
+            b.mentions
+                .cmp(&a.mentions)
+                .then_with(|| a.text.cmp(&b.text))
+        });
+
+        paginate(entities, after, before, first, last).await
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Starts a crawl in the background. Only available on the API server.
+    async fn start_crawl(
+        &self,
+        ctx: &Context<'_>,
+        url: String,
+        max_depth: Option<u32>,
+        max_workers: Option<usize>,
+    ) -> Result<Session> {
+        let Source::Jobs(jobs) = ctx.data::<Source>()? else {
+            return Err(Error::new("Crawls can only be started on the API server"));
+        };
+        let job = jobs
+            .start(CrawlRequest {
+                url,
+                max_depth,
+                max_workers,
+            })
+            .await?;
+        Ok(Session::from_job(&job).await)
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Statistics for a running crawl: the current values, then an update
+    /// twice a second until it finishes.
+    async fn crawl_stats(
+        &self,
+        ctx: &Context<'_>,
+        session: ID,
+    ) -> Result<impl Stream<Item = Stats>> {
+        let Source::Jobs(jobs) = ctx.data::<Source>()? else {
+            return Err(Error::new(
+                "Live statistics are only available on the API server",
+            ));
+        };
+        let id = Uuid::parse_str(&session).map_err(|_| invalid_session(&session))?;
+        let job = jobs.get(id)?;
+        Ok(job
+            .progress()
+            .await
+            .map(|snapshot| Stats::from(&snapshot.stats)))
+    }
+}
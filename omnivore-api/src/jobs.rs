@@ -0,0 +1,372 @@
+use crate::error::{ApiError, ApiResult};
+use async_graphql::Enum;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures::stream::{self, Stream};
+use omnivore_core::crawler::metrics::CrawlMetrics;
+use omnivore_core::crawler::Crawler;
+use omnivore_core::{CrawlConfig, CrawlResult, CrawlStats};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use url::Url;
+use uuid::Uuid;
+
+pub const DEFAULT_MAX_DEPTH: u32 = 5;
+pub const MAX_DEPTH_LIMIT: u32 = 20;
+pub const DEFAULT_MAX_WORKERS: usize = 10;
+pub const MAX_WORKERS_LIMIT: usize = 100;
+
+/// How often running jobs publish progress to their event streams.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Progress updates buffered per job before slow subscribers start
+/// skipping ahead.
+const EVENT_BUFFER: usize = 64;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrawlRequest {
+    pub url: String,
+    pub max_depth: Option<u32>,
+    pub max_workers: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Paused,
+    Stopped,
+    Completed,
+    Failed,
+}
+
+impl JobStatus {
+    /// Whether the job has stopped crawling for good.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Stopped | Self::Completed | Self::Failed)
+    }
+}
+
+/// A point-in-time view of a job, as returned by the status endpoint and
+/// sent on its event stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSnapshot {
+    pub id: Uuid,
+    pub url: String,
+    pub status: JobStatus,
+    pub max_depth: u32,
+    pub max_workers: usize,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub stats: CrawlStats,
+}
+
+/// One page of a job's results.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultsPage {
+    pub id: Uuid,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+    pub total_pages: usize,
+    pub results: Vec<CrawlResult>,
+}
+
+#[derive(Debug)]
+struct JobState {
+    status: JobStatus,
+    finished_at: Option<DateTime<Utc>>,
+    error: Option<String>,
+}
+
+/// A crawl running in the background.
+pub struct CrawlJob {
+    pub id: Uuid,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+    config: CrawlConfig,
+    crawler: Arc<Crawler>,
+    state: Mutex<JobState>,
+    events: broadcast::Sender<JobSnapshot>,
+}
+
+impl CrawlJob {
+    pub fn status(&self) -> JobStatus {
+        self.state.lock().unwrap().status
+    }
+
+    pub fn crawler(&self) -> &Arc<Crawler> {
+        &self.crawler
+    }
+
+    pub async fn snapshot(&self) -> JobSnapshot {
+        let stats = self.crawler.get_stats().await;
+        let state = self.state.lock().unwrap();
+        JobSnapshot {
+            id: self.id,
+            url: self.url.clone(),
+            status: state.status,
+            max_depth: self.config.max_depth,
+            max_workers: self.config.max_workers,
+            created_at: self.created_at,
+            finished_at: state.finished_at,
+            error: state.error.clone(),
+            stats,
+        }
+    }
+
+    /// The job's current state, then an update every `PROGRESS_INTERVAL`
+    /// and on every state change. Ends after the update for the job
+    /// finishing.
+    pub async fn progress(&self) -> impl Stream<Item = JobSnapshot> + Send + 'static {
+        // Subscribe before taking the snapshot so no update falls in between
+        let receiver = self.events.subscribe();
+        let current = self.snapshot().await;
+
+        stream::unfold(
+            (Some(current), receiver, false),
+            |(pending, mut receiver, done)| async move {
+                if done {
+                    return None;
+                }
+                let snapshot = match pending {
+                    Some(snapshot) => snapshot,
+                    None => next_snapshot(&mut receiver).await?,
+                };
+                let finished = snapshot.status.is_finished();
+                Some((snapshot, (None, receiver, finished)))
+            },
+        )
+    }
+
+    /// Results page `page` (1-based), `per_page` results each.
+    pub async fn results(&self, page: usize, per_page: usize) -> ResultsPage {
+        let page = page.max(1);
+        let per_page = per_page.max(1);
+        let (results, total) = self
+            .crawler
+            .get_results_page((page - 1).saturating_mul(per_page), per_page)
+            .await;
+        ResultsPage {
+            id: self.id,
+            page,
+            per_page,
+            total,
+            total_pages: total.div_ceil(per_page),
+            results,
+        }
+    }
+
+    pub async fn pause(&self) -> ApiResult<()> {
+        self.transition(JobStatus::Running, JobStatus::Paused)?;
+        self.crawler.pause();
+        self.publish().await;
+        Ok(())
+    }
+
+    pub async fn resume(&self) -> ApiResult<()> {
+        self.transition(JobStatus::Paused, JobStatus::Running)?;
+        self.crawler.resume();
+        self.publish().await;
+        Ok(())
+    }
+
+    /// Stops a running or paused job. Pages already being fetched are
+    /// still recorded.
+    pub async fn stop(&self) -> ApiResult<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.status.is_finished() {
+                return Err(ApiError::conflict(format!(
+                    "Crawl {} has already {}",
+                    self.id,
+                    describe(state.status)
+                )));
+            }
+            state.status = JobStatus::Stopped;
+            state.finished_at = Some(Utc::now());
+        }
+        self.crawler.stop().await;
+        self.publish().await;
+        Ok(())
+    }
+
+    fn transition(&self, from: JobStatus, to: JobStatus) -> ApiResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.status != from {
+            return Err(ApiError::conflict(format!(
+                "Crawl {} is {}",
+                self.id,
+                describe(state.status)
+            )));
+        }
+        state.status = to;
+        Ok(())
+    }
+
+    async fn publish(&self) {
+        // No subscribers is fine; progress is only pushed to open streams
+        let _ = self.events.send(self.snapshot().await);
+    }
+
+    async fn run(self: Arc<Self>) {
+        // The crawl gets its own task: it holds the stats and frontier locks
+        // across awaits, so publishing must not stop it from being polled
+        let crawler = self.crawler.clone();
+        let mut crawl = tokio::spawn(async move { crawler.start().await });
+
+        let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+        let outcome = loop {
+            tokio::select! {
+                outcome = &mut crawl => {
+                    break outcome.unwrap_or_else(|e| {
+                        Err(omnivore_core::Error::Unknown(format!("Crawl task failed: {}", e)))
+                    })
+                }
+                _ = ticker.tick() => self.publish().await,
+            }
+        };
+
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.status.is_finished() {
+                state.status = match &outcome {
+                    Ok(()) => JobStatus::Completed,
+                    Err(_) => JobStatus::Failed,
+                };
+                state.finished_at = Some(Utc::now());
+            }
+            if let Err(e) = outcome {
+                tracing::error!("Crawl {} failed: {}", self.id, e);
+                state.error = Some(e.to_string());
+            }
+        }
+        self.publish().await;
+    }
+}
+
+async fn next_snapshot(receiver: &mut broadcast::Receiver<JobSnapshot>) -> Option<JobSnapshot> {
+    loop {
+        match receiver.recv().await {
+            Ok(snapshot) => return Some(snapshot),
+            // A slow subscriber missed some updates; the next one is current anyway
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+fn describe(status: JobStatus) -> &'static str {
+    match status {
+        JobStatus::Running => "running",
+        JobStatus::Paused => "paused",
+        JobStatus::Stopped => "been stopped",
+        JobStatus::Completed => "completed",
+        JobStatus::Failed => "failed",
+    }
+}
+
+/// Every crawl job started on this server, keyed by id. Jobs are kept in
+/// memory until the server exits.
+#[derive(Clone)]
+pub struct JobManager {
+    config: Arc<CrawlConfig>,
+    jobs: Arc<DashMap<Uuid, Arc<CrawlJob>>>,
+    metrics: Arc<CrawlMetrics>,
+}
+
+impl JobManager {
+    /// `config` is the base configuration for every job.
+    pub fn new(config: CrawlConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            jobs: Arc::new(DashMap::new()),
+            metrics: Arc::new(CrawlMetrics::new()),
+        }
+    }
+
+    /// Metrics of every job's crawler combined.
+    pub fn metrics(&self) -> &Arc<CrawlMetrics> {
+        &self.metrics
+    }
+
+    /// Validates `request` and starts crawling in the background.
+    pub async fn start(&self, request: CrawlRequest) -> ApiResult<Arc<CrawlJob>> {
+        let url = Url::parse(request.url.trim())
+            .map_err(|e| ApiError::bad_request(format!("Invalid URL '{}': {}", request.url, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ApiError::bad_request(format!(
+                "Unsupported URL scheme '{}', expected http or https",
+                url.scheme()
+            )));
+        }
+
+        let max_depth = request.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        if max_depth > MAX_DEPTH_LIMIT {
+            return Err(ApiError::bad_request(format!(
+                "max_depth must be at most {}",
+                MAX_DEPTH_LIMIT
+            )));
+        }
+        let max_workers = request.max_workers.unwrap_or(DEFAULT_MAX_WORKERS);
+        if !(1..=MAX_WORKERS_LIMIT).contains(&max_workers) {
+            return Err(ApiError::bad_request(format!(
+                "max_workers must be between 1 and {}",
+                MAX_WORKERS_LIMIT
+            )));
+        }
+
+        let mut config = (*self.config).clone();
+        config.max_depth = max_depth;
+        config.max_workers = max_workers;
+
+        let crawler = Crawler::with_metrics(config.clone(), self.metrics.clone()).await?;
+        crawler.add_seed(url.clone()).await?;
+
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let job = Arc::new(CrawlJob {
+            id: Uuid::new_v4(),
+            url: url.to_string(),
+            created_at: Utc::now(),
+            config,
+            crawler: Arc::new(crawler),
+            state: Mutex::new(JobState {
+                status: JobStatus::Running,
+                finished_at: None,
+                error: None,
+            }),
+            events,
+        });
+
+        self.jobs.insert(job.id, job.clone());
+        tracing::info!("Started crawl {} for {}", job.id, job.url);
+        tokio::spawn(job.clone().run());
+
+        Ok(job)
+    }
+
+    pub fn get(&self, id: Uuid) -> ApiResult<Arc<CrawlJob>> {
+        self.jobs
+            .get(&id)
+            .map(|job| job.clone())
+            .ok_or_else(|| ApiError::not_found(format!("Crawl {} not found", id)))
+    }
+
+    /// Every job, oldest first.
+    pub fn list(&self) -> Vec<Arc<CrawlJob>> {
+        let mut jobs: Vec<_> = self.jobs.iter().map(|job| job.clone()).collect();
+        jobs.sort_by_key(|job| job.created_at);
+        jobs
+    }
+
+    /// The most recently started job.
+    pub fn latest(&self) -> Option<Arc<CrawlJob>> {
+        self.jobs
+            .iter()
+            .max_by_key(|job| job.created_at)
+            .map(|job| job.clone())
+    }
+}
@@ -0,0 +1,16 @@
+//! REST and GraphQL API server for Omnivore. Crawls run as background jobs
+//! keyed by UUID and can be paused, resumed, stopped and followed over
+//! Server-Sent Events while they run. The GraphQL schema can also be served
+//! on its own over crawl output saved to disk.
+
+pub mod config;
+pub mod error;
+pub mod graphql;
+pub mod jobs;
+pub mod results;
+pub mod routes;
+
+pub use config::ServerConfig;
+pub use error::{ApiError, ApiResult};
+pub use jobs::{CrawlJob, CrawlRequest, JobManager, JobSnapshot, JobStatus};
+pub use routes::{results_router, router};
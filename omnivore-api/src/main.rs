@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use omnivore_api::results::load_sessions;
+use omnivore_api::{results_router, router, JobManager, ServerConfig};
+use omnivore_core::telemetry::{self, TraceExporter};
+use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Parser)]
+#[command(name = "omnivore-api")]
+#[command(about = "Omnivore REST API server", version)]
+struct Args {
+    #[arg(
+        short,
+        long,
+        env = "OMNIVORE_API_PORT",
+        help = "Port to listen on (default: 3000)"
+    )]
+    port: Option<u16>,
+
+    #[arg(
+        long,
+        env = "OMNIVORE_API_HOST",
+        help = "Address to bind (default: 0.0.0.0)"
+    )]
+    host: Option<String>,
+
+    #[arg(short, long, help = "Config file with [crawler] and [api] settings")]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Serve only GraphQL over crawl output saved in this directory"
+    )]
+    results: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        env = "OMNIVORE_OTLP_ENDPOINT",
+        help = "Export crawl traces over OTLP/gRPC to this endpoint"
+    )]
+    otlp_endpoint: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "otlp_endpoint",
+        help = "Print crawl traces to stdout as JSON, one span per line"
+    )]
+    trace_stdout: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut config = match &args.config {
+        Some(path) => ServerConfig::load(path)?,
+        None => ServerConfig::default(),
+    };
+
+    let exporter = if let Some(endpoint) = args.otlp_endpoint {
+        Some(TraceExporter::Otlp { endpoint })
+    } else if args.trace_stdout {
+        Some(TraceExporter::Stdout)
+    } else if config.monitoring.enable_tracing {
+        Some(TraceExporter::Otlp {
+            endpoint: config.monitoring.tracing_endpoint.clone(),
+        })
+    } else {
+        None
+    };
+    let _telemetry = telemetry::init(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+        exporter,
+    )?;
+    if let Some(port) = args.port {
+        config.api.port = port;
+    }
+    if let Some(host) = args.host {
+        config.api.host = host;
+    }
+
+    let app = match &args.results {
+        Some(dir) => {
+            let sessions = load_sessions(dir)?;
+            tracing::info!(
+                "Loaded {} crawl sessions from {}",
+                sessions.len(),
+                dir.display()
+            );
+            results_router(sessions)
+        }
+        None => router(JobManager::new(config.crawler)),
+    };
+    let address = format!("{}:{}", config.api.host, config.api.port);
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .with_context(|| format!("Failed to bind {}", address))?;
+
+    tracing::info!("Omnivore API listening on http://{}", address);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,233 @@
+use crate::jobs::{CrawlJob, JobStatus};
+use anyhow::{Context, Result};
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use omnivore_core::graph::builder::GraphBuilder;
+use omnivore_core::graph::KnowledgeGraph;
+use omnivore_core::{CrawlResult, CrawlStats};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// A crawled page, from a live job or from saved crawl output.
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(name = "Page")]
+pub struct PageRecord {
+    /// The session the page was crawled in.
+    pub session: String,
+    pub url: String,
+    /// HTTP status, when the output it was loaded from recorded it.
+    pub status_code: Option<u16>,
+    pub title: Option<String>,
+    pub text: Option<String>,
+    pub word_count: usize,
+    pub links: Vec<String>,
+    pub crawled_at: Option<DateTime<Utc>>,
+}
+
+impl PageRecord {
+    pub fn from_result(session: &str, result: &CrawlResult) -> Self {
+        let cleaned = result.cleaned_content.as_ref();
+        Self {
+            session: session.to_string(),
+            url: result.url.clone(),
+            status_code: Some(result.status_code),
+            title: cleaned.and_then(|c| c.title.clone()),
+            text: cleaned.and_then(|c| c.content.clone()),
+            word_count: cleaned.map_or(0, |c| c.word_count),
+            links: result.links.clone(),
+            crawled_at: Some(result.crawled_at),
+        }
+    }
+}
+
+/// One crawl: a job on the API server, or one output file or folder written
+/// by `omnivore crawl`.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: String,
+    pub url: String,
+    pub status: JobStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub stats: Option<CrawlStats>,
+    pub pages: Vec<PageRecord>,
+}
+
+impl Session {
+    pub async fn from_job(job: &CrawlJob) -> Self {
+        let snapshot = job.snapshot().await;
+        let id = snapshot.id.to_string();
+        let pages = job
+            .crawler()
+            .get_results()
+            .await
+            .iter()
+            .map(|result| PageRecord::from_result(&id, result))
+            .collect();
+        Self {
+            id,
+            url: snapshot.url,
+            status: snapshot.status,
+            started_at: Some(snapshot.created_at),
+            stats: Some(snapshot.stats),
+            pages,
+        }
+    }
+}
+
+/// The knowledge graph of the pages in `sessions`: page nodes linked by
+/// `links_to` edges, and the entities each page mentions. A page crawled in
+/// several sessions gets its edges once.
+pub fn build_graph(sessions: &[Session]) -> omnivore_core::Result<KnowledgeGraph> {
+    let mut builder = GraphBuilder::new();
+    for page in sessions.iter().flat_map(|session| &session.pages) {
+        builder.add_page(
+            &page.url,
+            page.title.as_deref(),
+            page.text.as_deref().unwrap_or_default(),
+            &page.links,
+        )?;
+    }
+    Ok(builder.build())
+}
+
+/// A page as `omnivore crawl` writes it, in clean output files and in
+/// organized folders.
+#[derive(Debug, Deserialize)]
+struct SavedPage {
+    url: String,
+    title: Option<String>,
+    text: Option<String>,
+    #[serde(default)]
+    words: usize,
+    #[serde(default)]
+    links: Vec<String>,
+}
+
+impl SavedPage {
+    fn into_record(self, session: &str) -> PageRecord {
+        PageRecord {
+            session: session.to_string(),
+            url: self.url,
+            status_code: None,
+            title: self.title,
+            text: self.text,
+            word_count: self.words,
+            links: self.links,
+            crawled_at: None,
+        }
+    }
+}
+
+/// Loads the crawls saved under `dir`: JSON output files (clean, or raw from
+/// `--include-raw`) and folders written with `--organize`. `dir` may also be
+/// a single organized folder. Files that are not crawl output are skipped.
+pub fn load_sessions(dir: &Path) -> Result<Vec<Session>> {
+    if dir.join("index.json").is_file() {
+        return Ok(vec![load_organized(dir)?]);
+    }
+
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read results directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    paths.sort();
+
+    let mut sessions = Vec::new();
+    for path in paths {
+        let loaded = if path.is_dir() && path.join("index.json").is_file() {
+            load_organized(&path).map(Some)
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            load_output_file(&path)
+        } else {
+            continue;
+        };
+        match loaded {
+            Ok(Some(session)) => sessions.push(session),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Skipping {}: {:#}", path.display(), e),
+        }
+    }
+    Ok(sessions)
+}
+
+fn session_id(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    value.as_str()?.parse().ok()
+}
+
+/// A single output file, or `None` when the JSON is not crawl output.
+fn load_output_file(path: &Path) -> Result<Option<Session>> {
+    let json = read_json(path)?;
+    let id = session_id(path);
+
+    if let Some(results) = json.get("results") {
+        let results: Vec<CrawlResult> = serde_json::from_value(results.clone())?;
+        let stats: Option<CrawlStats> = serde_json::from_value(json["stats"].clone()).ok();
+        return Ok(Some(Session {
+            url: results.first().map(|r| r.url.clone()).unwrap_or_default(),
+            status: JobStatus::Completed,
+            started_at: stats.as_ref().map(|s| s.start_time),
+            pages: results
+                .iter()
+                .map(|result| PageRecord::from_result(&id, result))
+                .collect(),
+            stats,
+            id,
+        }));
+    }
+
+    if let Some(content) = json.get("content") {
+        let pages: Vec<SavedPage> = serde_json::from_value(content.clone())?;
+        return Ok(Some(Session {
+            url: json["url"].as_str().unwrap_or_default().to_string(),
+            status: JobStatus::Completed,
+            started_at: timestamp(&json["timestamp"]),
+            stats: None,
+            pages: pages.into_iter().map(|p| p.into_record(&id)).collect(),
+            id,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// A folder written with `--organize`: `index.json` plus one file per page.
+fn load_organized(dir: &Path) -> Result<Session> {
+    let index = read_json(&dir.join("index.json"))?;
+    let id = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut pages = Vec::new();
+    for entry in index["pages"].as_array().into_iter().flatten() {
+        let Some(file) = entry["file"].as_str() else {
+            continue;
+        };
+        let page: SavedPage = serde_json::from_value(read_json(&dir.join(file))?)
+            .with_context(|| format!("Unexpected page format in {}", file))?;
+        pages.push(page.into_record(&id));
+    }
+
+    Ok(Session {
+        url: index["start_url"].as_str().unwrap_or_default().to_string(),
+        status: JobStatus::Completed,
+        started_at: timestamp(&index["timestamp"]),
+        stats: serde_json::from_value(index["stats"].clone()).ok(),
+        pages,
+        id,
+    })
+}
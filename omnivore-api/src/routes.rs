@@ -0,0 +1,240 @@
+use crate::error::{ApiError, ApiResult};
+use crate::graphql::{self, Source};
+use crate::jobs::{CrawlJob, CrawlRequest, JobManager, JobSnapshot, ResultsPage};
+use crate::results::Session;
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::header;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::{Stream, StreamExt};
+use omnivore_core::crawler::metrics;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tower_http::cors::CorsLayer;
+use tower_http::trace::TraceLayer;
+use uuid::Uuid;
+
+pub const DEFAULT_PER_PAGE: usize = 50;
+pub const MAX_PER_PAGE: usize = 500;
+
+const ENDPOINTS: &[&str] = &[
+    "/",
+    "/health",
+    "/api/crawl",
+    "/api/crawls",
+    "/api/crawl/{id}",
+    "/api/crawl/{id}/stop",
+    "/api/crawl/{id}/pause",
+    "/api/crawl/{id}/resume",
+    "/api/crawl/{id}/results",
+    "/api/crawl/{id}/events",
+    "/api/stats",
+    "/metrics",
+    "/graphql",
+    "/graphql/ws",
+];
+
+const RESULTS_ENDPOINTS: &[&str] = &["/", "/health", "/graphql", "/graphql/ws"];
+
+/// The REST API and GraphQL over its jobs, with open CORS and request
+/// tracing.
+pub fn router(jobs: JobManager) -> Router {
+    let graphql = graphql::router(graphql::schema(Source::Jobs(jobs.clone())));
+    Router::new()
+        .route("/", get(|| index(ENDPOINTS)))
+        .route("/health", get(health))
+        .route("/api/crawl", post(start_crawl))
+        .route("/api/crawls", get(list_crawls))
+        .route("/api/crawl/{id}", get(crawl_status))
+        .route("/api/crawl/{id}/stop", post(stop_crawl))
+        .route("/api/crawl/{id}/pause", post(pause_crawl))
+        .route("/api/crawl/{id}/resume", post(resume_crawl))
+        .route("/api/crawl/{id}/results", get(crawl_results))
+        .route("/api/crawl/{id}/events", get(crawl_events))
+        .route("/api/stats", get(latest_stats))
+        .route("/metrics", get(prometheus_metrics))
+        .route("/api/metrics", get(prometheus_metrics))
+        .with_state(jobs)
+        .merge(graphql)
+        .fallback(not_found)
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+}
+
+/// GraphQL over saved crawl output, without the crawl endpoints.
+pub fn results_router(sessions: Vec<Session>) -> Router {
+    let graphql = graphql::router(graphql::schema(Source::Saved(Arc::new(sessions))));
+    Router::new()
+        .route("/", get(|| index(RESULTS_ENDPOINTS)))
+        .route("/health", get(health))
+        .merge(graphql)
+        .fallback(not_found)
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+}
+
+async fn index(endpoints: &'static [&'static str]) -> Json<Value> {
+    Json(json!({
+        "name": "Omnivore API",
+        "version": env!("CARGO_PKG_VERSION"),
+        "endpoints": endpoints,
+    }))
+}
+
+async fn health() -> &'static str {
+    "OK"
+}
+
+async fn prometheus_metrics(State(jobs): State<JobManager>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        jobs.metrics().render(),
+    )
+}
+
+async fn not_found() -> ApiError {
+    ApiError::not_found("Not found")
+}
+
+async fn start_crawl(
+    State(jobs): State<JobManager>,
+    request: Result<Json<CrawlRequest>, JsonRejection>,
+) -> ApiResult<Json<Value>> {
+    let Json(request) = request.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let job = jobs.start(request).await?;
+    Ok(Json(json!({
+        "id": job.id,
+        "status": "started",
+        "message": format!("Crawl started for URL: {}", job.url),
+    })))
+}
+
+async fn list_crawls(State(jobs): State<JobManager>) -> Json<Value> {
+    let mut crawls = Vec::new();
+    for job in jobs.list() {
+        crawls.push(job.snapshot().await);
+    }
+    Json(json!({ "crawls": crawls }))
+}
+
+fn find(jobs: &JobManager, id: &str) -> ApiResult<Arc<CrawlJob>> {
+    let id = Uuid::parse_str(id)
+        .map_err(|_| ApiError::bad_request(format!("Invalid crawl id '{}'", id)))?;
+    jobs.get(id)
+}
+
+async fn crawl_status(
+    State(jobs): State<JobManager>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<JobSnapshot>> {
+    Ok(Json(find(&jobs, &id)?.snapshot().await))
+}
+
+async fn stop_crawl(
+    State(jobs): State<JobManager>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<JobSnapshot>> {
+    let job = find(&jobs, &id)?;
+    job.stop().await?;
+    Ok(Json(job.snapshot().await))
+}
+
+async fn pause_crawl(
+    State(jobs): State<JobManager>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<JobSnapshot>> {
+    let job = find(&jobs, &id)?;
+    job.pause().await?;
+    Ok(Json(job.snapshot().await))
+}
+
+async fn resume_crawl(
+    State(jobs): State<JobManager>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<JobSnapshot>> {
+    let job = find(&jobs, &id)?;
+    job.resume().await?;
+    Ok(Json(job.snapshot().await))
+}
+
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+async fn crawl_results(
+    State(jobs): State<JobManager>,
+    Path(id): Path<String>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> ApiResult<Json<ResultsPage>> {
+    let job = find(&jobs, &id)?;
+    let Query(query) = query.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(ApiError::bad_request(format!(
+            "per_page must be between 1 and {}",
+            MAX_PER_PAGE
+        )));
+    }
+    Ok(Json(job.results(query.page.unwrap_or(1), per_page).await))
+}
+
+/// Server-Sent Events with the job's progress: the current state right
+/// away, then updates while it runs. Updates use the `progress` event
+/// name and the last one `finished`, after which the stream closes.
+async fn crawl_events(
+    State(jobs): State<JobManager>,
+    Path(id): Path<String>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let job = find(&jobs, &id)?;
+    let events = job.progress().await.map(|snapshot| {
+        let name = if snapshot.status.is_finished() {
+            "finished"
+        } else {
+            "progress"
+        };
+        Ok(Event::default()
+            .event(name)
+            .json_data(&snapshot)
+            .unwrap_or_else(|e| Event::default().event("error").data(e.to_string())))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Statistics for the most recently started crawl.
+async fn latest_stats(State(jobs): State<JobManager>) -> Json<Value> {
+    let Some(job) = jobs.latest() else {
+        return Json(json!({
+            "status": "no_data",
+            "message": "No crawl statistics available",
+        }));
+    };
+
+    let snapshot = job.snapshot().await;
+    let stats = &snapshot.stats;
+    let duration = stats.elapsed_time.as_secs_f64();
+    let visited = stats.successful + stats.failed;
+    Json(json!({
+        "id": snapshot.id,
+        "status": snapshot.status,
+        "stats": {
+            "urls_visited": visited,
+            "urls_in_progress": stats.in_progress,
+            "success_count": stats.successful,
+            "error_count": stats.failed,
+            "start_time": stats.start_time,
+            "end_time": snapshot.finished_at,
+            "duration_secs": duration,
+            "pages_per_second": if duration > 0.0 { visited as f64 / duration } else { 0.0 },
+            "average_response_time_ms": stats.average_response_time_ms,
+            "proxies": stats.proxies,
+        },
+    }))
+}
@@ -0,0 +1,297 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use futures::StreamExt;
+use http_body_util::BodyExt;
+use omnivore_api::graphql::{schema, Source};
+use omnivore_api::{router, JobManager};
+use omnivore_core::CrawlConfig;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tower::ServiceExt;
+
+fn app() -> Router {
+    let config = CrawlConfig {
+        max_retries: 1,
+        timeout_ms: 5000,
+        ..CrawlConfig::default()
+    };
+    router(JobManager::new(config))
+}
+
+/// Serves a small site on a local port: an index linking to `count` pages,
+/// each taking `delay` to respond.
+async fn serve_site(count: usize, delay: Duration) -> String {
+    let index = (0..count)
+        .map(|i| format!("<a href=\"/page/{}\">Page {}</a>", i, i))
+        .collect::<String>();
+    let site = Router::new()
+        .route(
+            "/",
+            get(move || async move { Html(format!("<html><body>{}</body></html>", index)) }),
+        )
+        .route(
+            "/page/{n}",
+            get(move || async move {
+                tokio::time::sleep(delay).await;
+                Html("<html><body><p>Leaf page</p></body></html>")
+            }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, site).await.unwrap() });
+    format!("http://localhost:{}/", address.port())
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    (status, value)
+}
+
+async fn start(app: &Router, url: &str) -> String {
+    let (status, body) = send(
+        app,
+        "POST",
+        "/api/crawl",
+        Some(json!({ "url": url, "max_depth": 1 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["status"], "started");
+    body["id"].as_str().unwrap().to_string()
+}
+
+async fn wait_until_finished(app: &Router, id: &str) -> Value {
+    let mut body = Value::Null;
+    for _ in 0..200 {
+        body = send(app, "GET", &format!("/api/crawl/{}", id), None)
+            .await
+            .1;
+        if matches!(
+            body["status"].as_str(),
+            Some("completed" | "stopped" | "failed")
+        ) {
+            return body;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("crawl {} did not finish: {}", id, body);
+}
+
+#[tokio::test]
+async fn test_health_and_errors() {
+    let app = app();
+
+    let (status, body) = send(&app, "GET", "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "OK");
+
+    let (_, body) = send(&app, "GET", "/", None).await;
+    assert_eq!(body["name"], "Omnivore API");
+    assert!(body["endpoints"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("/api/crawl/{id}/events")));
+
+    let (_, body) = send(&app, "GET", "/api/stats", None).await;
+    assert_eq!(body["status"], "no_data");
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/crawl",
+        Some(json!({ "url": "not a url" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["status"], 400);
+    assert!(body["error"].as_str().unwrap().contains("Invalid URL"));
+
+    let request = json!({ "url": "https://example.com", "max_depth": 21 });
+    let (status, _) = send(&app, "POST", "/api/crawl", Some(request)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = send(&app, "POST", "/api/crawl", Some(json!({ "max_depth": 2 }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["status"], 400);
+
+    let (status, _) = send(&app, "GET", "/api/crawl/not-a-uuid", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let missing = format!("/api/crawl/{}", uuid::Uuid::new_v4());
+    let (status, body) = send(&app, "GET", &missing, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["status"], 404);
+
+    let (status, _) = send(&app, "GET", "/api/nothing", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_crawl_job_results() {
+    let app = app();
+    let site = serve_site(4, Duration::ZERO).await;
+    let id = start(&app, &site).await;
+
+    let job = wait_until_finished(&app, &id).await;
+    assert_eq!(job["status"], "completed");
+    assert_eq!(job["stats"]["successful"], 5);
+    assert!(job["finished_at"].is_string());
+
+    let uri = format!("/api/crawl/{}/results?page=2&per_page=2", id);
+    let (status, page) = send(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 5);
+    assert_eq!(page["total_pages"], 3);
+    assert_eq!(page["results"].as_array().unwrap().len(), 2);
+
+    let uri = format!("/api/crawl/{}/results?per_page=1000", id);
+    let (status, _) = send(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, crawls) = send(&app, "GET", "/api/crawls", None).await;
+    assert_eq!(crawls["crawls"][0]["id"], id.as_str());
+
+    let (_, stats) = send(&app, "GET", "/api/stats", None).await;
+    assert_eq!(stats["status"], "completed");
+    assert_eq!(stats["stats"]["urls_visited"], 5);
+
+    // Prometheus scrapes both paths
+    for path in ["/metrics", "/api/metrics"] {
+        let (status, metrics) = send(&app, "GET", path, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let metrics = metrics.as_str().unwrap();
+        assert!(
+            metrics.contains(r#"omnivore_requests_total{domain="localhost",status_class="2xx"} 5"#)
+        );
+        assert!(metrics.contains("omnivore_active_workers 0"));
+    }
+
+    // Finished jobs can't be paused or stopped again
+    let (status, body) = send(&app, "POST", &format!("/api/crawl/{}/stop", id), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["status"], 409);
+}
+
+#[tokio::test]
+async fn test_pause_resume_and_stop() {
+    let app = app();
+    let site = serve_site(50, Duration::from_millis(200)).await;
+    let id = start(&app, &site).await;
+
+    let (status, job) = send(&app, "POST", &format!("/api/crawl/{}/pause", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(job["status"], "paused");
+
+    let (status, _) = send(&app, "POST", &format!("/api/crawl/{}/pause", id), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, job) = send(&app, "POST", &format!("/api/crawl/{}/resume", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(job["status"], "running");
+
+    let (status, job) = send(&app, "POST", &format!("/api/crawl/{}/stop", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(job["status"], "stopped");
+
+    let job = wait_until_finished(&app, &id).await;
+    assert_eq!(job["status"], "stopped");
+    assert!(job["stats"]["total_urls"].as_u64().unwrap() < 51);
+
+    let (status, _) = send(&app, "POST", &format!("/api/crawl/{}/resume", id), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_progress_events() {
+    let app = app();
+    let site = serve_site(3, Duration::from_millis(50)).await;
+    let id = start(&app, &site).await;
+
+    let request = Request::get(format!("/api/crawl/{}/events", id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+    // The stream closes by itself once the crawl finishes
+    let body = tokio::time::timeout(Duration::from_secs(30), response.into_body().collect())
+        .await
+        .expect("event stream did not close")
+        .unwrap()
+        .to_bytes();
+    let text = String::from_utf8_lossy(&body);
+
+    assert!(text.starts_with("event: progress\n") || text.starts_with("event: finished\n"));
+    let last = text.rsplit("event: ").next().unwrap();
+    assert!(last.starts_with("finished\n"), "{}", text);
+    let data: Value = serde_json::from_str(last.split("data: ").nth(1).unwrap().trim()).unwrap();
+    assert_eq!(data["id"], id.as_str());
+    assert_eq!(data["status"], "completed");
+    assert_eq!(data["stats"]["successful"], 4);
+}
+
+#[tokio::test]
+async fn test_graphql_crawl_and_stats_subscription() {
+    let jobs = JobManager::new(CrawlConfig {
+        max_retries: 1,
+        ..CrawlConfig::default()
+    });
+    let schema = schema(Source::Jobs(jobs));
+    let site = serve_site(3, Duration::from_millis(50)).await;
+
+    let mutation = format!(
+        r#"mutation {{ startCrawl(url: "{}", maxDepth: 1) {{ id status }} }}"#,
+        site
+    );
+    let response = schema.execute(mutation.as_str()).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let data = response.data.into_json().unwrap();
+    assert_eq!(data["startCrawl"]["status"], "RUNNING");
+    let id = data["startCrawl"]["id"].as_str().unwrap().to_string();
+
+    // The subscription ends by itself once the crawl finishes
+    let subscription = format!(
+        r#"subscription {{ crawlStats(session: "{}") {{ successful inProgress }} }}"#,
+        id
+    );
+    let updates: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(30),
+        schema
+            .execute_stream(subscription.as_str())
+            .collect::<Vec<_>>(),
+    )
+    .await
+    .expect("subscription did not end");
+    let last = updates.last().unwrap().data.clone().into_json().unwrap();
+    assert_eq!(last["crawlStats"]["successful"], 4);
+
+    let query = format!(
+        r#"{{ pages(session: "{}", urlPattern: "*/page/*") {{ totalCount }} }}"#,
+        id
+    );
+    let data = schema
+        .execute(query.as_str())
+        .await
+        .data
+        .into_json()
+        .unwrap();
+    assert_eq!(data["pages"]["totalCount"], 3);
+}
@@ -0,0 +1,295 @@
+use anyhow::Result;
+use async_graphql::{Request, Variables};
+use axum::body::Body;
+use axum::http::StatusCode;
+use http_body_util::BodyExt;
+use omnivore_api::graphql::{schema, OmnivoreSchema, Source};
+use omnivore_api::results::load_sessions;
+use omnivore_api::results_router;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+fn raw_result(url: &str, status: u16, text: &str, links: &[&str]) -> Value {
+    json!({
+        "url": url,
+        "status_code": status,
+        "content": format!("<html><body>{}</body></html>", text),
+        "cleaned_content": {
+            "title": format!("Title of {}", url),
+            "content": text,
+            "tables": [],
+            "links": [],
+            "word_count": text.split_whitespace().count(),
+        },
+        "headers": {},
+        "extracted_data": {},
+        "links": links,
+        "crawled_at": "2025-01-15T10:30:00Z",
+    })
+}
+
+/// A results directory with a raw output file and an organized folder.
+fn write_results(dir: &Path) -> Result<()> {
+    let raw = json!({
+        "stats": {
+            "total_urls": 3,
+            "successful": 2,
+            "failed": 1,
+            "in_progress": 0,
+            "average_response_time_ms": 120.0,
+            "start_time": "2025-01-15T10:30:00Z",
+            "elapsed_time": { "secs": 4, "nanos": 0 },
+        },
+        "results": [
+            raw_result(
+                "https://shop.example.com/",
+                200,
+                "Questions? Mail sales@example.com or support@example.com",
+                &["https://shop.example.com/about", "https://shop.example.com/missing"],
+            ),
+            raw_result(
+                "https://shop.example.com/about",
+                200,
+                "Write to sales@example.com",
+                // Linked twice, like from a header and a footer
+                &["https://shop.example.com/", "https://shop.example.com/"],
+            ),
+            raw_result("https://shop.example.com/missing", 404, "Not found", &[]),
+        ],
+    });
+    fs::write(dir.join("shop_crawl.json"), serde_json::to_string(&raw)?)?;
+
+    let blog = dir.join("blog_crawl");
+    fs::create_dir(&blog)?;
+    fs::write(
+        blog.join("index.json"),
+        serde_json::to_string(&json!({
+            "crawler": "omnivore",
+            "start_url": "https://blog.example.com/",
+            "timestamp": "2025-01-16T08:00:00Z",
+            "pages": [
+                { "url": "https://blog.example.com/", "file": "page_0001.json" },
+                { "url": "https://blog.example.com/posts/hello", "file": "page_0002.json" },
+            ],
+        }))?,
+    )?;
+    fs::write(
+        blog.join("page_0001.json"),
+        serde_json::to_string(&json!({
+            "url": "https://blog.example.com/",
+            "title": "Blog",
+            "text": "Latest posts",
+            "words": 2,
+            "links": ["https://blog.example.com/posts/hello"],
+        }))?,
+    )?;
+    fs::write(
+        blog.join("page_0002.json"),
+        serde_json::to_string(&json!({
+            "url": "https://blog.example.com/posts/hello",
+            "title": "Hello",
+            "text": "Say hi at editor@blog.example.com",
+            "words": 5,
+        }))?,
+    )?;
+
+    // Not crawl output
+    fs::write(dir.join("notes.json"), r#"{"todo": []}"#)?;
+    Ok(())
+}
+
+fn saved_schema() -> Result<(TempDir, OmnivoreSchema)> {
+    let dir = TempDir::new()?;
+    write_results(dir.path())?;
+    let sessions = load_sessions(dir.path())?;
+    Ok((dir, schema(Source::Saved(Arc::new(sessions)))))
+}
+
+async fn execute(schema: &OmnivoreSchema, query: &str, variables: Value) -> Value {
+    let request = Request::new(query).variables(Variables::from_json(variables));
+    let response = schema.execute(request).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().unwrap()
+}
+
+#[tokio::test]
+async fn test_sessions_and_pages() -> Result<()> {
+    let (_dir, schema) = saved_schema()?;
+
+    let data = execute(
+        &schema,
+        "{ sessions { id url status pageCount stats { failed elapsedSecs } } }",
+        json!({}),
+    )
+    .await;
+    assert_eq!(
+        data["sessions"],
+        json!([
+            { "id": "blog_crawl", "url": "https://blog.example.com/", "status": "COMPLETED", "pageCount": 2, "stats": null },
+            { "id": "shop_crawl", "url": "https://shop.example.com/", "status": "COMPLETED", "pageCount": 3, "stats": { "failed": 1, "elapsedSecs": 4.0 } },
+        ])
+    );
+
+    let query = r#"query($pattern: String, $status: Int, $session: ID) {
+        pages(urlPattern: $pattern, status: $status, session: $session) {
+            totalCount
+            nodes { url statusCode title }
+        }
+    }"#;
+
+    let data = execute(
+        &schema,
+        query,
+        json!({ "pattern": "https://*.example.com/*/hello" }),
+    )
+    .await;
+    assert_eq!(data["pages"]["totalCount"], 1);
+    assert_eq!(data["pages"]["nodes"][0]["title"], "Hello");
+
+    let data = execute(&schema, query, json!({ "status": 404 })).await;
+    assert_eq!(
+        data["pages"]["nodes"],
+        json!([{ "url": "https://shop.example.com/missing", "statusCode": 404, "title": "Title of https://shop.example.com/missing" }])
+    );
+
+    let data = execute(&schema, query, json!({ "session": "blog_crawl" })).await;
+    assert_eq!(data["pages"]["totalCount"], 2);
+
+    // Saved output can't start crawls
+    let response = schema
+        .execute(r#"mutation { startCrawl(url: "https://example.com") { id } }"#)
+        .await;
+    assert!(response.errors[0].message.contains("API server"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graph_traversal_and_pagination() -> Result<()> {
+    let (_dir, schema) = saved_schema()?;
+
+    let query = r#"query($after: String) {
+        nodes(session: "shop_crawl", type: "page", first: 2, after: $after) {
+            totalCount
+            pageInfo { hasNextPage endCursor }
+            nodes { id }
+        }
+    }"#;
+    let data = execute(&schema, query, json!({})).await;
+    let nodes = &data["nodes"];
+    assert_eq!(nodes["totalCount"], 3);
+    assert_eq!(nodes["pageInfo"]["hasNextPage"], true);
+    assert_eq!(
+        nodes["nodes"],
+        json!([{ "id": "https://shop.example.com/" }, { "id": "https://shop.example.com/about" }])
+    );
+
+    let after = nodes["pageInfo"]["endCursor"].clone();
+    let data = execute(&schema, query, json!({ "after": after })).await;
+    assert_eq!(data["nodes"]["pageInfo"]["hasNextPage"], false);
+    assert_eq!(
+        data["nodes"]["nodes"],
+        json!([{ "id": "https://shop.example.com/missing" }])
+    );
+
+    let query = r#"{
+        node(id: "https://shop.example.com/") {
+            type
+            properties
+            outgoing(type: "links_to") { totalCount nodes { target { id } } }
+            incoming { nodes { type source { id } } }
+            neighbors(depth: 1) { id }
+        }
+    }"#;
+    let data = execute(&schema, query, json!({})).await;
+    let node = &data["node"];
+    assert_eq!(node["type"], "page");
+    assert_eq!(node["properties"]["crawled"], true);
+    assert_eq!(node["outgoing"]["totalCount"], 2);
+    assert_eq!(
+        node["outgoing"]["nodes"][1]["target"]["id"],
+        "https://shop.example.com/missing"
+    );
+    assert_eq!(
+        node["incoming"]["nodes"],
+        json!([{ "type": "links_to", "source": { "id": "https://shop.example.com/about" } }])
+    );
+    assert_eq!(node["neighbors"].as_array().unwrap().len(), 4);
+
+    let data = execute(
+        &schema,
+        r#"{ edges(type: "mentions") { totalCount } }"#,
+        json!({}),
+    )
+    .await;
+    assert_eq!(data["edges"]["totalCount"], 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_entity_search() -> Result<()> {
+    let (_dir, schema) = saved_schema()?;
+
+    let query = r#"query($query: String, $type: String) {
+        entities(query: $query, type: $type) {
+            totalCount
+            nodes { text entityType mentions pages }
+        }
+    }"#;
+    let data = execute(
+        &schema,
+        query,
+        json!({ "query": "EXAMPLE.COM", "type": "email" }),
+    )
+    .await;
+    assert_eq!(data["entities"]["totalCount"], 3);
+    assert_eq!(
+        data["entities"]["nodes"][0],
+        json!({
+            "text": "sales@example.com",
+            "entityType": "EMAIL",
+            "mentions": 2,
+            "pages": ["https://shop.example.com/", "https://shop.example.com/about"],
+        })
+    );
+
+    let data = execute(&schema, query, json!({ "query": "editor" })).await;
+    assert_eq!(
+        data["entities"]["nodes"][0]["pages"],
+        json!(["https://blog.example.com/posts/hello"])
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_results_router_serves_graphql() -> Result<()> {
+    let dir = TempDir::new()?;
+    write_results(dir.path())?;
+    let app = results_router(load_sessions(dir.path())?);
+
+    let request = axum::http::Request::post("/graphql")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({ "query": "{ health sessions { id } }" }).to_string(),
+        ))?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+    assert_eq!(body["data"]["health"], "OK");
+    assert_eq!(body["data"]["sessions"].as_array().unwrap().len(), 2);
+
+    let response = app
+        .clone()
+        .oneshot(axum::http::Request::get("/graphql").body(Body::empty())?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(axum::http::Request::get("/api/crawls").body(Body::empty())?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
@@ -0,0 +1,570 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::detector::CodebaseInfo;
+use super::filter::FilteredFile;
+use super::organizer::CodeOrganizer;
+
+/// Space kept for the report title and the omitted-files heading.
+const HEADER_RESERVE: usize = 64;
+
+/// A file costing more than this share of the budget is cut down to its
+/// signatures when that is possible.
+const LARGE_FILE_SHARE: usize = 10;
+
+/// Estimates the number of tokens a BPE tokenizer (cl100k-like) produces for
+/// `text`. Words cost one token per four characters, punctuation one token
+/// per character, and whitespace is mostly folded into neighbouring tokens.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            let mut len = 1usize;
+            while matches!(chars.peek(), Some(n) if n.is_ascii_alphanumeric() || *n == '_') {
+                chars.next();
+                len += 1;
+            }
+            tokens += len.div_ceil(4);
+        } else if c.is_whitespace() {
+            let mut newlines = usize::from(c == '\n');
+            let mut spaces = 0;
+            while let Some(&n) = chars.peek() {
+                if !n.is_whitespace() {
+                    break;
+                }
+                if n == '\n' {
+                    newlines += 1;
+                } else {
+                    spaces += 1;
+                }
+                chars.next();
+            }
+            tokens += newlines + spaces / 4;
+        } else {
+            // Punctuation and non-ASCII characters
+            tokens += 1;
+        }
+    }
+
+    tokens
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackMode {
+    Full,
+    Signatures,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackedFile {
+    pub path: String,
+    pub category: String,
+    pub mode: PackMode,
+    pub tokens: usize,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OmittedFile {
+    pub path: String,
+    pub tokens: usize,
+    pub reason: String,
+}
+
+/// Files selected to fit a token budget, most important first, plus a
+/// manifest of what was left out.
+#[derive(Debug, Serialize)]
+pub struct PackedContext {
+    pub max_tokens: usize,
+    pub total_tokens: usize,
+    pub files: Vec<PackedFile>,
+    pub omitted: Vec<OmittedFile>,
+    /// How many omitted files the text manifest has room to name.
+    #[serde(skip)]
+    listed_omissions: usize,
+}
+
+/// Ranks files by importance and packs as many as fit in `max_tokens`.
+pub struct ContextPacker {
+    max_tokens: usize,
+    organizer: CodeOrganizer,
+    entry_points: HashSet<PathBuf>,
+}
+
+impl ContextPacker {
+    pub fn new(max_tokens: usize, codebase_info: &CodebaseInfo) -> Self {
+        Self {
+            max_tokens,
+            entry_points: codebase_info.entry_points.iter().cloned().collect(),
+            organizer: CodeOrganizer::new(codebase_info.clone(), Vec::new()),
+        }
+    }
+
+    pub fn pack(&self, files: Vec<FilteredFile>) -> PackedContext {
+        let mut ranked: Vec<(f64, String, FilteredFile)> = files
+            .into_iter()
+            .map(|file| {
+                let category = self.organizer.determine_category(&file.relative_path);
+                (self.score(&file, &category), category, file)
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| a.2.relative_path.cmp(&b.2.relative_path))
+        });
+
+        let large_file = self.max_tokens / LARGE_FILE_SHARE;
+        let mut remaining = self.max_tokens.saturating_sub(HEADER_RESERVE);
+        let mut packed = PackedContext {
+            max_tokens: self.max_tokens,
+            total_tokens: 0,
+            files: Vec::new(),
+            omitted: Vec::new(),
+            listed_omissions: 0,
+        };
+
+        for (_, category, file) in ranked {
+            let path = file.relative_path.display().to_string();
+
+            let content = match file.read_content() {
+                Ok(content) => content,
+                Err(_) => {
+                    packed.omit(path, 0, "unreadable or not UTF-8", &mut remaining);
+                    continue;
+                }
+            };
+
+            let full_tokens = estimate_tokens(&file_header(&path, PackMode::Full)) + estimate_tokens(&content);
+            let signatures = if full_tokens > large_file || full_tokens > remaining {
+                signatures(&file.relative_path, &content).map(|sig| {
+                    let tokens = estimate_tokens(&file_header(&path, PackMode::Signatures)) + estimate_tokens(&sig);
+                    (sig, tokens)
+                })
+            } else {
+                None
+            };
+
+            let choice = match signatures {
+                Some((sig, tokens)) if tokens < full_tokens && tokens <= remaining => {
+                    Some((PackMode::Signatures, sig, tokens))
+                }
+                _ if full_tokens <= remaining => Some((PackMode::Full, content, full_tokens)),
+                _ => None,
+            };
+
+            match choice {
+                Some((mode, content, tokens)) => {
+                    remaining -= tokens;
+                    packed.total_tokens += tokens;
+                    packed.files.push(PackedFile {
+                        path,
+                        category,
+                        mode,
+                        tokens,
+                        content,
+                    });
+                }
+                None => packed.omit(path, full_tokens, "over token budget", &mut remaining),
+            }
+        }
+
+        packed
+    }
+
+    /// Higher is more important: entry points and manifests first, then
+    /// source, then tests and assets. Smaller and shallower files are
+    /// preferred within a category.
+    fn score(&self, file: &FilteredFile, category: &str) -> f64 {
+        let file_name = file
+            .relative_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        let mut score = match category {
+            "Documentation" if file_name.starts_with("readme") => 0.9,
+            "Documentation" => 0.2,
+            "Configuration" if is_manifest(&file_name) => 0.8,
+            "Configuration" => 0.3,
+            "Source Code" | "Application" | "API" | "Services" | "Models" | "Controllers"
+            | "Pages" | "Components" | "Binaries" => 0.7,
+            "Utilities" | "Views" | "Templates" => 0.5,
+            "Database" => 0.4,
+            "Scripts" | "Infrastructure" => 0.3,
+            "Tests" => 0.25,
+            "Styles" => 0.2,
+            "Static Assets" => 0.1,
+            _ => 0.4,
+        };
+
+        if self.entry_points.contains(&file.relative_path) {
+            score += 1.0;
+        }
+        if matches!(file_name.as_str(), "mod.rs" | "lib.rs" | "__init__.py" | "index.ts" | "index.js") {
+            score += 0.1;
+        }
+
+        let depth = file.relative_path.components().count().saturating_sub(1);
+        score -= 0.03 * depth as f64;
+        score -= 0.05 * ((file.size as f64) + 1.0).ln();
+
+        score
+    }
+}
+
+impl PackedContext {
+    fn omit(&mut self, path: String, tokens: usize, reason: &str, remaining: &mut usize) {
+        let line_tokens = estimate_tokens(&omitted_line(&path, tokens, reason));
+        if line_tokens <= *remaining {
+            *remaining -= line_tokens;
+            self.total_tokens += line_tokens;
+            self.listed_omissions += 1;
+        }
+        self.omitted.push(OmittedFile {
+            path,
+            tokens,
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn signature_count(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|f| f.mode == PackMode::Signatures)
+            .count()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut output = format!(
+            "# Repository context: {} files, ~{} of {} tokens\n\n",
+            self.files.len(),
+            self.total_tokens,
+            self.max_tokens
+        );
+
+        for file in &self.files {
+            output.push_str(&file_header(&file.path, file.mode));
+            output.push_str(&file.content);
+            output.push('\n');
+        }
+
+        if !self.omitted.is_empty() {
+            output.push_str(&format!("---\nOmitted files ({})\n---\n", self.omitted.len()));
+            for file in self.omitted.iter().take(self.listed_omissions) {
+                output.push_str(&omitted_line(&file.path, file.tokens, &file.reason));
+            }
+            let unlisted = self.omitted.len() - self.listed_omissions;
+            if unlisted > 0 {
+                output.push_str(&format!("... and {} more\n", unlisted));
+            }
+        }
+
+        output
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize packed context")
+    }
+}
+
+fn file_header(path: &str, mode: PackMode) -> String {
+    match mode {
+        PackMode::Full => format!("---\nFile: {}\n---\n", path),
+        PackMode::Signatures => format!("---\nFile: {} (signatures only)\n---\n", path),
+    }
+}
+
+fn omitted_line(path: &str, tokens: usize, reason: &str) -> String {
+    format!("{} (~{} tokens, {})\n", path, tokens, reason)
+}
+
+fn is_manifest(file_name: &str) -> bool {
+    matches!(
+        file_name,
+        "cargo.toml"
+            | "package.json"
+            | "pyproject.toml"
+            | "setup.py"
+            | "go.mod"
+            | "pom.xml"
+            | "build.gradle"
+            | "build.gradle.kts"
+            | "gemfile"
+            | "composer.json"
+    )
+}
+
+/// Reduces a source file to its declarations: bodies of functions and
+/// methods are replaced with `...`, while type, trait, class and module
+/// outlines are kept. Returns `None` for languages it does not understand.
+pub fn signatures(path: &Path, content: &str) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "py" | "pyi" => Some(python_signatures(content)),
+        "rs" | "go" | "java" | "kt" | "scala" | "cs" | "js" | "jsx" | "mjs" | "cjs" | "ts"
+        | "tsx" | "c" | "h" | "cc" | "cpp" | "hpp" | "swift" | "php" => {
+            Some(brace_signatures(content))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Block {
+    /// A type, trait, class or module whose members are kept.
+    Outline,
+    /// A function body or other code that is collapsed.
+    Body,
+}
+
+fn brace_signatures(content: &str) -> String {
+    const FUNCTION_KEYWORDS: &[&str] = &["fn", "func", "function", "def"];
+    const OUTLINE_KEYWORDS: &[&str] = &[
+        "struct", "enum", "trait", "impl", "mod", "union", "class", "interface", "namespace",
+        "object", "extern", "record",
+    ];
+
+    let mut output = String::new();
+    let mut stack: Vec<Block> = Vec::new();
+    let mut in_block_comment = false;
+    let mut pending_body = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let collapsed = stack.contains(&Block::Body);
+
+        if in_block_comment {
+            if !collapsed {
+                output.push_str(line);
+                output.push('\n');
+            }
+            in_block_comment = !trimmed.contains("*/");
+            continue;
+        }
+        if trimmed.starts_with("/*") {
+            in_block_comment = !trimmed.contains("*/");
+            if !collapsed && trimmed.starts_with("/**") {
+                output.push_str(line);
+                output.push('\n');
+            }
+            continue;
+        }
+        if trimmed.starts_with("//") {
+            let is_doc = trimmed.starts_with("///") || trimmed.starts_with("//!");
+            if !collapsed && is_doc {
+                output.push_str(line);
+                output.push('\n');
+            }
+            continue;
+        }
+
+        let code = strip_strings(trimmed);
+        let opens = code.matches('{').count();
+        let closes = code.matches('}').count();
+
+        if collapsed {
+            for _ in 0..closes.saturating_sub(opens) {
+                stack.pop();
+            }
+            for _ in 0..opens.saturating_sub(closes) {
+                stack.push(Block::Body);
+            }
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            if !output.ends_with("\n\n") && !output.is_empty() {
+                output.push('\n');
+            }
+            continue;
+        }
+
+        let words: Vec<&str> = code
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|w| !w.is_empty())
+            .collect();
+        let has_function_keyword =
+            words.iter().any(|w| FUNCTION_KEYWORDS.contains(w)) || code.contains("=>");
+        let is_attribute = trimmed.starts_with('#') || trimmed.starts_with('@');
+        let is_outline = !has_function_keyword
+            && (words.iter().any(|w| OUTLINE_KEYWORDS.contains(w))
+                || matches!(words.first(), Some(&"use" | &"import"))
+                || (words.first() == Some(&"type")
+                    && (code.contains("struct") || code.contains("interface"))));
+        let is_function = !is_attribute
+            && (has_function_keyword || (!is_outline && is_method_signature(&code, &words)));
+
+        if opens > closes {
+            let block = if is_outline && !pending_body {
+                Block::Outline
+            } else {
+                Block::Body
+            };
+            pending_body = false;
+
+            match block {
+                Block::Outline => {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+                Block::Body => {
+                    // Keep everything up to the opening brace of the body
+                    let brace = line.find('{').unwrap_or(line.len());
+                    output.push_str(line[..brace].trim_end());
+                    output.push_str(" { ... }\n");
+                }
+            }
+            stack.push(block);
+            for _ in 1..(opens - closes) {
+                stack.push(Block::Body);
+            }
+        } else {
+            if closes > opens {
+                for _ in 0..(closes - opens) {
+                    stack.pop();
+                }
+            }
+            if is_function && !code.ends_with(';') && !code.ends_with('}') {
+                // A signature spanning several lines
+                pending_body = true;
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// Java/C-style methods: `Type name(args) {` without a control keyword.
+fn is_method_signature(code: &str, words: &[&str]) -> bool {
+    const CONTROL: &[&str] = &[
+        "if", "for", "while", "switch", "catch", "return", "new", "else", "do", "try", "class",
+        "interface", "enum", "struct", "record",
+    ];
+    code.contains('(')
+        && !words.first().is_some_and(|w| CONTROL.contains(w))
+        && !code.starts_with('.')
+        && !code.contains('=')
+}
+
+/// Blanks out string and char literals so braces inside them are not counted.
+fn strip_strings(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut output = String::with_capacity(line.len());
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for (i, &c) in chars.iter().enumerate() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                    output.push(c);
+                }
+            }
+            None => {
+                if c == '"' || c == '`' || (c == '\'' && !is_lifetime(&chars[i + 1..])) {
+                    quote = Some(c);
+                }
+                output.push(c);
+            }
+        }
+    }
+
+    output
+}
+
+/// Whether the text after a `'` is a Rust lifetime or loop label like `'a`
+/// or `'static`, rather than the rest of a char literal like `'{'` or a
+/// single-quoted string.
+fn is_lifetime(rest: &[char]) -> bool {
+    let name = rest
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count();
+    name > 0 && rest.get(name) != Some(&'\'')
+}
+
+fn python_signatures(content: &str) -> String {
+    let mut output = String::new();
+    let mut signature_open = false;
+    let mut body_indent: Option<usize> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if signature_open {
+            output.push_str(line);
+            output.push('\n');
+            if trimmed.trim_end().ends_with(':') {
+                signature_open = false;
+                let def_indent = body_indent.unwrap_or(0);
+                output.push_str(&format!("{}    ...\n", " ".repeat(def_indent)));
+            }
+            continue;
+        }
+
+        if let Some(body) = body_indent {
+            if indent > body {
+                continue;
+            }
+            body_indent = None;
+        }
+
+        let is_def = trimmed.starts_with("def ") || trimmed.starts_with("async def ");
+        let is_kept = is_def
+            || trimmed.starts_with("class ")
+            || trimmed.starts_with('@')
+            || (indent == 0
+                && (trimmed.starts_with("import ")
+                    || trimmed.starts_with("from ")
+                    || is_constant_assignment(trimmed)));
+
+        if !is_kept {
+            continue;
+        }
+
+        output.push_str(line);
+        output.push('\n');
+
+        if is_def {
+            if trimmed.trim_end().ends_with(':') {
+                output.push_str(&format!("{}    ...\n", " ".repeat(indent)));
+            } else {
+                signature_open = true;
+            }
+            body_indent = Some(indent);
+        }
+    }
+
+    output
+}
+
+fn is_constant_assignment(line: &str) -> bool {
+    match line.split_once('=') {
+        Some((name, _)) => {
+            let name = name.trim().split(':').next().unwrap_or("").trim();
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        }
+        None => false,
+    }
+}
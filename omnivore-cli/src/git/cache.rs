@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+/// Default cap on the total size of the cache (10GB).
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024 * 1024;
+
+const METADATA_FILE: &str = "omnivore-cache.json";
+const LOCK_FILE: &str = "omnivore-cache.lock";
+
+/// Locks older than this are left over from runs that crashed.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// How often a held lock is touched, so long clones and analyses keep it
+/// well short of `STALE_LOCK_AGE`.
+const LOCK_REFRESH: Duration = Duration::from_secs(5 * 60);
+
+/// Where cloned repositories are kept (`~/.omnivore/repos`).
+pub fn default_cache_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".omnivore")
+        .join("repos")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheMetadata {
+    url: String,
+    created: DateTime<Utc>,
+    last_used: DateTime<Utc>,
+}
+
+/// A cached clone, as listed by `omnivore git cache list`.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// `None` for a clone whose first fetch never finished, which is dated
+    /// by its directory.
+    pub url: Option<String>,
+    pub path: PathBuf,
+    pub created: DateTime<Utc>,
+    pub last_used: DateTime<Utc>,
+    pub size: u64,
+}
+
+/// On-disk clones of remote repositories, one per URL, reused and updated
+/// with an incremental fetch on later runs. Least recently used clones are
+/// evicted once the cache grows past its size limit.
+#[derive(Debug, Clone)]
+pub struct RepoCache {
+    root: PathBuf,
+    max_size: u64,
+}
+
+/// Held while a run uses a cached clone, so concurrent runs do not fetch into
+/// the same repository. Touched in the background while held, and released
+/// on drop.
+#[derive(Debug)]
+pub struct CacheLock {
+    path: PathBuf,
+    /// Dropping it stops the refresh thread.
+    _refresh: mpsc::Sender<()>,
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl RepoCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    pub fn set_max_size(&mut self, max_size: u64) {
+        self.max_size = max_size;
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The clone directory for `url`: a readable name plus a hash of the
+    /// normalized URL, e.g. `owner_repo-1f2e3d4c5b6a7980`.
+    pub fn entry_path(&self, url: &str) -> PathBuf {
+        let url = normalize_url(url);
+        let mut parts: Vec<&str> = url.rsplit(['/', ':']).take(2).collect();
+        parts.reverse();
+        let name: String = parts
+            .join("_")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+            .take(60)
+            .collect();
+        self.root.join(format!("{}-{:016x}", name, fnv1a(&url)))
+    }
+
+    /// Where the repository for `url` is checked out, inside its entry.
+    pub fn checkout_path(&self, url: &str) -> PathBuf {
+        self.entry_path(url).join("checkout")
+    }
+
+    /// Locks the entry for `url`, or returns `None` when another run holds it.
+    pub fn lock(&self, url: &str) -> Result<Option<CacheLock>> {
+        let entry = self.entry_path(url);
+        fs::create_dir_all(&entry)
+            .with_context(|| format!("Failed to create cache directory {}", entry.display()))?;
+        let path = entry.join(LOCK_FILE);
+
+        if is_stale(&path) {
+            let _ = fs::remove_file(&path);
+        }
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => Ok(Some(CacheLock {
+                _refresh: refresh_lock(path.clone()),
+                path,
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to lock {}", path.display())),
+        }
+    }
+
+    /// Records that the entry for `url` was just used.
+    pub fn touch(&self, url: &str) -> Result<()> {
+        let path = self.entry_path(url).join(METADATA_FILE);
+        let now = Utc::now();
+        let created = read_metadata(&path).map(|m| m.created).unwrap_or(now);
+        let metadata = CacheMetadata {
+            url: normalize_url(url),
+            created,
+            last_used: now,
+        };
+        fs::write(&path, serde_json::to_string_pretty(&metadata)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Every cached clone, most recently used first.
+    pub fn list(&self) -> Result<Vec<CacheEntry>> {
+        let Ok(dirs) = fs::read_dir(&self.root) else {
+            return Ok(Vec::new());
+        };
+
+        let mut entries = Vec::new();
+        for dir in dirs.flatten() {
+            let path = dir.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let (url, created, last_used) = match read_metadata(&path.join(METADATA_FILE)) {
+                Some(metadata) => (Some(metadata.url), metadata.created, metadata.last_used),
+                None => {
+                    let modified: DateTime<Utc> = dir
+                        .metadata()
+                        .and_then(|m| m.modified())
+                        .map_or_else(|_| Utc::now(), Into::into);
+                    (None, modified, modified)
+                }
+            };
+            entries.push(CacheEntry {
+                url,
+                size: dir_size(&path),
+                path,
+                created,
+                last_used,
+            });
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+        Ok(entries)
+    }
+
+    /// Removes clones unused for longer than `older_than`, then the least
+    /// recently used ones until the cache fits its size limit. Clones locked
+    /// by a running analysis are skipped. Returns the removed entries.
+    pub fn prune(&self, older_than: Option<chrono::Duration>) -> Result<Vec<CacheEntry>> {
+        let entries = self.list()?;
+        let cutoff = older_than.map(|age| Utc::now() - age);
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        let mut removed = Vec::new();
+
+        // Oldest first, so the size limit evicts least recently used clones
+        for entry in entries.into_iter().rev() {
+            let expired = cutoff.is_some_and(|cutoff| entry.last_used < cutoff);
+            if !expired && total <= self.max_size {
+                continue;
+            }
+            if entry.path.join(LOCK_FILE).exists() {
+                continue;
+            }
+
+            fs::remove_dir_all(&entry.path)
+                .with_context(|| format!("Failed to remove {}", entry.path.display()))?;
+            total = total.saturating_sub(entry.size);
+            removed.push(entry);
+        }
+
+        Ok(removed)
+    }
+}
+
+/// URLs that only differ by a trailing slash, `.git` or a `file://` prefix
+/// share a clone.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    url.strip_prefix("file://")
+        .unwrap_or(url)
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .to_string()
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is stable across Rust
+/// releases, so cache directories keep their names.
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn read_metadata(path: &Path) -> Option<CacheMetadata> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Touches the lock at `path` every `LOCK_REFRESH` until the returned
+/// sender is dropped.
+fn refresh_lock(path: PathBuf) -> mpsc::Sender<()> {
+    let (stop, stopped) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(LOCK_REFRESH) {
+            // Never recreates a lock that was released
+            if let Ok(file) = OpenOptions::new().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+        }
+    });
+    stop
+}
+
+fn is_stale(lock: &Path) -> bool {
+    fs::metadata(lock)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_LOCK_AGE)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
@@ -1,21 +1,44 @@
 use anyhow::{Context, Result};
-use clap::Args;
+use clap::{Args, Subcommand};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use super::{
-    detector::{CodebaseDetector, get_default_include_patterns, get_smart_exclude_patterns},
-    filter::FileFilter,
+    budget::ContextPacker,
+    cache::{default_cache_dir, CacheEntry, RepoCache},
+    dependencies::to_cyclonedx,
+    detector::{CodebaseDetector, CodebaseInfo, get_default_include_patterns, get_smart_exclude_patterns},
+    filter::{FileFilter, FilteredFile},
+    history::{require_repository, GitHistory},
     organizer::CodeOrganizer,
-    output::{OutputFormat, OutputWriter},
-    source::{SourceAcquisition, SourceType},
+    outline::CodeOutline,
+    output::{format_file_size, OutputFormat, OutputWriter},
+    secrets::{Allowlist, SecretMode, SecretScanner, DEFAULT_ALLOWLIST},
+    source::{SourceAcquisition, SourceType, TreeUrl},
+    stats::CodeStats,
+    utils::parse_size_string,
+    workspace::{WorkspaceKind, WorkspaceMember},
 };
 
 #[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct GitArgs {
-    #[arg(help = "Repository source (URL or local path)")]
-    pub source: String,
+    #[command(subcommand)]
+    pub command: Option<GitCommand>,
+
+    #[arg(
+        help = "Repository sources (URLs or local paths); several are combined into one report",
+        required_unless_present = "sources_file"
+    )]
+    pub sources: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Read more sources from FILE, one per line ('#' starts a comment)"
+    )]
+    pub sources_file: Option<PathBuf>,
 
     #[arg(
         long,
@@ -51,6 +74,23 @@ pub struct GitArgs {
     #[arg(long, help = "Output as JSON")]
     pub json: bool,
 
+    #[arg(
+        long,
+        conflicts_with_all = ["json", "xml", "outline", "max_tokens", "diff", "stats"],
+        help = "Output as Markdown with a file tree and fenced code blocks"
+    )]
+    pub markdown: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["json", "outline", "max_tokens", "diff", "stats"],
+        help = "Output as XML-tagged files (<file path=\"...\">) for LLM prompts"
+    )]
+    pub xml: bool,
+
+    #[arg(long, help = "Prefix file contents with line numbers (Markdown and XML output)")]
+    pub line_numbers: bool,
+
     #[arg(long, help = "Output to stdout instead of file")]
     pub stdout: bool,
 
@@ -60,6 +100,20 @@ pub struct GitArgs {
     #[arg(long, default_value = "1", help = "Clone depth for remote repositories")]
     pub depth: u32,
 
+    #[arg(
+        long = "ref",
+        value_name = "REF",
+        help = "Branch, tag or commit to check out for remote repositories"
+    )]
+    pub reference: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Only check out and analyze this subdirectory"
+    )]
+    pub subdir: Option<PathBuf>,
+
     #[arg(long, help = "Include binary files in output")]
     pub allow_binary: bool,
 
@@ -70,97 +124,252 @@ pub struct GitArgs {
     )]
     pub max_file_size: Option<u64>,
 
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Pack the most important files into about N tokens; large files are reduced to signatures"
+    )]
+    pub max_tokens: Option<usize>,
+
+    #[arg(
+        long,
+        conflicts_with = "max_tokens",
+        help = "Output per-file symbols (functions, types, impls) instead of file contents"
+    )]
+    pub outline: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["outline", "max_tokens", "diff"],
+        help = "Report lines of code, comments and complexity per language and section instead of file contents"
+    )]
+    pub stats: bool,
+
+    #[arg(
+        long,
+        value_name = "DATE|REV",
+        help = "Only include files changed since a date (2024-05-01, '2 weeks ago') or revision"
+    )]
+    pub since: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "BASE..HEAD",
+        conflicts_with_all = ["since", "outline", "max_tokens"],
+        help = "Output only files changed between two revisions, with unified diffs"
+    )]
+    pub diff: Option<String>,
+
+    #[arg(long, help = "Annotate files with authors, churn and last-modified date from Git history")]
+    pub authors: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write a CycloneDX SBOM of the detected dependencies to PATH"
+    )]
+    pub sbom: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "warn",
+        help = "What to do with credentials found in exported files"
+    )]
+    pub secrets: SecretMode,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Paths, rules and values to ignore when scanning for secrets (default: .omnivore-allowlist)"
+    )]
+    pub secrets_allowlist: Option<PathBuf>,
+
+    #[arg(long, help = "Clone into a temporary directory instead of the repository cache")]
+    pub no_cache: bool,
+
+    #[command(flatten)]
+    pub cache: CacheOptions,
+
     #[arg(long, help = "Verbose output")]
     pub verbose: bool,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum GitCommand {
+    #[command(about = "Manage the cache of cloned remote repositories")]
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    #[command(about = "List cached repositories with their size and last use")]
+    List {
+        #[command(flatten)]
+        cache: CacheOptions,
+    },
+    #[command(about = "Remove cached repositories that are old or over the size limit")]
+    Prune {
+        #[arg(long, value_name = "DAYS", help = "Remove clones unused for more than DAYS days")]
+        older_than: Option<u32>,
+
+        #[arg(long, conflicts_with = "older_than", help = "Remove every cached clone")]
+        all: bool,
+
+        #[command(flatten)]
+        cache: CacheOptions,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CacheOptions {
+    #[arg(
+        long,
+        value_name = "DIR",
+        env = "OMNIVORE_CACHE_DIR",
+        help = "Repository cache location (default: ~/.omnivore/repos)"
+    )]
+    pub cache_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        env = "OMNIVORE_CACHE_MAX_SIZE",
+        default_value = "10GB",
+        value_parser = parse_size_string,
+        help = "Evict the least recently used clones once the cache grows past SIZE"
+    )]
+    pub cache_max_size: u64,
+}
+
+impl CacheOptions {
+    pub fn repo_cache(&self) -> RepoCache {
+        let mut cache = RepoCache::new(self.cache_dir.clone().unwrap_or_else(default_cache_dir));
+        cache.set_max_size(self.cache_max_size);
+        cache
+    }
+}
+
+/// A source that has been acquired, detected, filtered and scanned.
+struct PreparedSource {
+    name: String,
+    acquisition: SourceAcquisition,
+    repo_path: PathBuf,
+    history: Option<GitHistory>,
+    codebase_info: CodebaseInfo,
+    files: Vec<FilteredFile>,
+    scanner: Option<SecretScanner>,
+}
+
 pub async fn execute_git_command(args: GitArgs) -> Result<()> {
+    if let Some(GitCommand::Cache { action }) = &args.command {
+        return execute_cache_command(action);
+    }
+
     println!("{}", "🔍 Omnivore Code Analyzer".bold().cyan());
     println!();
 
-    // Detect source type first, before creating progress bar
+    let sources = collect_sources(&args)?;
+    if sources.len() > 1 {
+        if args.diff.is_some() {
+            anyhow::bail!("--diff compares two revisions of a single source");
+        }
+        if args.reference.is_some() || args.subdir.is_some() {
+            anyhow::bail!("--ref and --subdir apply to a single source; use /tree/<ref>/<path> URLs to select them per source");
+        }
+    }
+
+    // Detect source types first, before creating progress bar
     // This allows the confirmation prompt to display properly for non-git directories
-    let source_type = SourceType::from_string(&args.source)?;
-    if args.verbose {
-        println!("Source type: {:?}", source_type);
+    let mut resolved = Vec::new();
+    for source in &sources {
+        // URLs like https://github.com/o/r/tree/<ref>/<path> carry their own ref and subdirectory
+        let tree_url = TreeUrl::parse(source);
+        if tree_url.is_some() && (args.reference.is_some() || args.subdir.is_some()) {
+            anyhow::bail!("The URL already selects a ref and path; drop --ref/--subdir or use the repository URL");
+        }
+        let source = tree_url
+            .as_ref()
+            .map(|t| t.repo_url.clone())
+            .unwrap_or_else(|| source.clone());
+
+        let source_type = SourceType::from_string(&source)?;
+        if args.verbose {
+            println!("Source type: {:?}", source_type);
+        }
+        resolved.push((source, tree_url, source_type));
     }
 
     // Now create the progress bar after any user interaction
     let progress = create_progress_bar("Initializing...");
 
-    // Show appropriate message based on source type
-    match &source_type {
-        SourceType::Remote(_) => {
-            println!("Analyzing remote Git repository...");
-        }
-        SourceType::Local(_) => {
-            println!("Analyzing local Git repository...");
-        }
-        SourceType::LocalNonGit(_) => {
-            println!("{}", "Analyzing local directory (non-Git)...".yellow());
-        }
+    let mut prepared = Vec::new();
+    for (source, tree_url, source_type) in resolved {
+        prepared.push(prepare_source(&args, &progress, source, tree_url, source_type).await?);
     }
 
-    progress.set_message("Acquiring source...");
-    let mut acquisition = SourceAcquisition::new(source_type.clone(), args.depth, args.keep);
-    let repo_path = acquisition
-        .acquire()
-        .await
-        .context("Failed to acquire source")?;
+    // Several sources are combined into one report, each as a member listed
+    // under its own directory
+    let (repo_name, repo_path, codebase_info, filtered_files, history, scanner) = if prepared.len() == 1 {
+        let source = &mut prepared[0];
+        (
+            source.name.clone(),
+            source.repo_path.clone(),
+            source.codebase_info.clone(),
+            std::mem::take(&mut source.files),
+            source.history.take(),
+            source.scanner.take(),
+        )
+    } else {
+        let mut members = Vec::new();
+        let mut files = Vec::new();
+        for source in &mut prepared {
+            let mut directory = source.name.clone();
+            let mut suffix = 2;
+            while members.iter().any(|m: &WorkspaceMember| m.path == Path::new(&directory)) {
+                directory = format!("{}-{}", source.name, suffix);
+                suffix += 1;
+            }
+
+            files.extend(std::mem::take(&mut source.files).into_iter().map(|mut file| {
+                file.relative_path = Path::new(&directory).join(&file.relative_path);
+                file
+            }));
+            members.push(WorkspaceMember {
+                name: source.name.clone(),
+                path: PathBuf::from(directory),
+                kind: WorkspaceKind::Repository,
+                info: source.codebase_info.clone(),
+            });
+        }
+        (
+            "combined".to_string(),
+            std::env::current_dir()?,
+            CodebaseInfo::combined(members),
+            files,
+            None,
+            None,
+        )
+    };
 
-    progress.set_message("Detecting codebase type...");
-    let detector = CodebaseDetector::new(repo_path.clone());
-    let codebase_info = detector.detect()?;
-    
     if args.verbose {
         println!("Detected: {}", codebase_info.description);
     }
 
-    progress.set_message("Setting up filters...");
-    let mut filter = FileFilter::new(repo_path.clone());
-    
-    if args.no_gitignore {
-        filter.ignore_gitignore();
-    }
-    
-    let include_patterns = if let Some(only_patterns) = &args.only {
-        only_patterns.iter().map(|p| normalize_pattern(p)).collect()
-    } else if let Some(include_patterns) = &args.include {
-        include_patterns.iter().map(|p| normalize_pattern(p)).collect()
-    } else if should_use_smart_defaults(&args) {
-        get_default_include_patterns(&codebase_info)
-    } else {
-        Vec::new()
-    };
-    
-    if !include_patterns.is_empty() {
-        filter.set_include_patterns(include_patterns)?;
-    }
-    
-    let exclude_patterns = if let Some(exclude) = &args.exclude {
-        exclude.iter().map(|p| normalize_pattern(p)).collect()
-    } else if should_use_smart_defaults(&args) {
-        get_smart_exclude_patterns(&codebase_info)
-    } else {
-        Vec::new()
-    };
-    
-    if !exclude_patterns.is_empty() {
-        filter.set_exclude_patterns(exclude_patterns)?;
-    }
-    
-    if !args.allow_binary {
-        filter.exclude_binary_files();
+    if let Some(sbom_path) = &args.sbom {
+        let sbom = to_cyclonedx(&repo_name, &codebase_info.dependencies);
+        tokio::fs::write(sbom_path, serde_json::to_string_pretty(&sbom)?)
+            .await
+            .with_context(|| format!("Failed to write SBOM to {}", sbom_path.display()))?;
+        println!(
+            "SBOM with {} components written to: {}",
+            codebase_info.dependencies.len(),
+            sbom_path.display().to_string().cyan()
+        );
     }
-    
-    // Set a default max file size of 10MB if not specified
-    let max_size = args.max_file_size.unwrap_or(10 * 1024 * 1024); // 10MB default
-    filter.set_max_file_size(max_size);
-
-    progress.set_message("Filtering files...");
-    let filtered_files = filter
-        .filter_files()
-        .context("Failed to filter files")?;
 
     if filtered_files.is_empty() {
         progress.finish_with_message("No files matched the filter criteria");
@@ -172,16 +381,127 @@ pub async fn execute_git_command(args: GitArgs) -> Result<()> {
 
     let output_format = determine_output_format(&args);
     
-    let output_path = if !args.stdout && args.output.is_none() {
-        let repo_name = extract_repo_name(&args.source);
+    // Statistics are printed to the terminal unless --output is given
+    let output_path = if !args.stdout && !args.stats && args.output.is_none() {
         let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-        let extension = if args.json { "json" } else { "txt" };
+        let extension = match output_format {
+            OutputFormat::Json => "json",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Xml => "xml",
+            _ => "txt",
+        };
         Some(PathBuf::from(format!("{}_{}.{}", repo_name, timestamp, extension)))
     } else {
         args.output.clone()
     };
     
-    let files_written = if should_use_organized_output(&args, &output_path) {
+    let files_written = if let (Some(history), Some(range)) = (&history, &args.diff) {
+        let mut report = history.diff_range(range, &filtered_files)?;
+        if let Some(scanner) = scanner.as_ref().filter(|_| args.secrets == SecretMode::Redact) {
+            for file in &mut report.files {
+                let path = PathBuf::from(&file.path);
+                file.diff = scanner.redact_text(&path, &file.diff);
+                file.content = file.content.take().map(|c| scanner.redact_text(&path, &c));
+            }
+        } else if let Some(scanner) = &scanner {
+            // Lines removed in the range are exported but not in the working tree
+            let secrets = scanner.scan_changes(&report.files);
+            if !secrets.findings.is_empty() {
+                progress.suspend(|| eprint!("{}", format!("⚠️  In the diff: {}", secrets.summary()).yellow()));
+                if args.secrets == SecretMode::Fail {
+                    return Err(refuse_export(&args, &repo_path));
+                }
+            }
+        }
+        
+        let output_content = if args.json {
+            report.to_json()?
+        } else {
+            report.to_text(true)
+        };
+        
+        progress.finish_and_clear();
+        if args.stdout {
+            print!("{}", output_content);
+            std::io::Write::flush(&mut std::io::stdout())?;
+        } else if let Some(ref path) = output_path {
+            tokio::fs::write(path, output_content).await?;
+        }
+        
+        report.files.len()
+    } else if args.stats {
+        let stats = CodeStats::from_files(&filtered_files, &codebase_info);
+        
+        let output_content = if args.json {
+            stats.to_json()?
+        } else {
+            stats.to_table()
+        };
+        
+        progress.finish_and_clear();
+        match output_path.as_ref().filter(|_| !args.stdout) {
+            Some(path) => tokio::fs::write(path, output_content).await?,
+            None => {
+                print!("{}", output_content);
+                std::io::Write::flush(&mut std::io::stdout())?;
+            }
+        }
+        
+        stats.total.files
+    } else if args.outline {
+        let outline = CodeOutline::from_files(&filtered_files);
+        
+        let output_content = if args.json {
+            outline.to_json()?
+        } else {
+            outline.to_text()
+        };
+        
+        if args.stdout {
+            print!("{}", output_content);
+            std::io::Write::flush(&mut std::io::stdout())?;
+        } else if let Some(ref path) = output_path {
+            tokio::fs::write(path, output_content).await?;
+        }
+        
+        progress.finish_and_clear();
+        println!(
+            "Outlined {} symbols in {} files ({} files without a supported grammar)",
+            outline.symbol_count().to_string().cyan(),
+            outline.files.len(),
+            outline.skipped
+        );
+        
+        outline.files.len()
+    } else if let Some(max_tokens) = args.max_tokens {
+        let packer = ContextPacker::new(max_tokens, &codebase_info);
+        let packed = packer.pack(filtered_files);
+        
+        let output_content = if args.json {
+            packed.to_json()?
+        } else {
+            packed.to_text()
+        };
+        
+        if args.stdout {
+            print!("{}", output_content);
+            std::io::Write::flush(&mut std::io::stdout())?;
+        } else if let Some(ref path) = output_path {
+            tokio::fs::write(path, output_content).await?;
+        }
+        
+        progress.finish_and_clear();
+        println!(
+            "Packed ~{} of {} tokens: {} files ({} as signatures), {} omitted",
+            packed.total_tokens.to_string().cyan(),
+            max_tokens,
+            packed.files.len(),
+            packed.signature_count(),
+            packed.omitted.len().to_string().yellow()
+        );
+        
+        packed.files.len()
+    } else if should_use_organized_output(&args, &output_path) {
         let organizer = CodeOrganizer::new(codebase_info, filtered_files);
         let organized = organizer.organize();
         
@@ -201,6 +521,8 @@ pub async fn execute_git_command(args: GitArgs) -> Result<()> {
         organized.metadata.total_files
     } else {
         let mut writer = OutputWriter::new(output_format, repo_path.clone());
+        writer.set_codebase_info(codebase_info);
+        writer.set_line_numbers(args.line_numbers);
         
         if let Some(ref path) = output_path {
             writer.set_output_path(path.clone());
@@ -234,19 +556,290 @@ pub async fn execute_git_command(args: GitArgs) -> Result<()> {
         }
     }
 
-    acquisition.cleanup().await?;
+    for mut source in prepared {
+        source.acquisition.cleanup().await?;
+    }
+
+    Ok(())
+}
+
+/// Acquires one source and returns its detected project info and the files
+/// that pass the filters, annotated and scanned for secrets.
+async fn prepare_source(
+    args: &GitArgs,
+    progress: &ProgressBar,
+    source: String,
+    tree_url: Option<TreeUrl>,
+    source_type: SourceType,
+) -> Result<PreparedSource> {
+    // Show appropriate message based on source type
+    let label = match &source_type {
+        SourceType::Remote(_) => "Analyzing remote Git repository",
+        SourceType::Local(_) => "Analyzing local Git repository",
+        SourceType::LocalNonGit(_) => "Analyzing local directory (non-Git)",
+    };
+    let label = if matches!(source_type, SourceType::LocalNonGit(_)) {
+        format!("{} {}...", label, source).yellow().to_string()
+    } else {
+        format!("{} {}...", label, source)
+    };
+    progress.suspend(|| println!("{}", label));
+
+    let needs_history = args.since.is_some() || args.diff.is_some() || args.authors;
+    if needs_history {
+        if let SourceType::LocalNonGit(path) = &source_type {
+            require_repository(path)?;
+        }
+    }
+
+    // History-based options need the full history, not a shallow clone
+    let depth = if needs_history && matches!(source_type, SourceType::Remote(_)) {
+        0
+    } else {
+        args.depth
+    };
+
+    progress.set_message("Acquiring source...");
+    let mut acquisition = SourceAcquisition::new(source_type.clone(), depth, args.keep);
+    if let Some(tree_url) = tree_url {
+        acquisition.set_tree_url(tree_url);
+    }
+    if let Some(reference) = &args.reference {
+        acquisition.set_reference(reference.clone());
+    }
+    if let Some(subdir) = &args.subdir {
+        acquisition.set_subdir(subdir.clone());
+    }
+    if !args.no_cache && !args.keep {
+        acquisition.set_cache(args.cache.repo_cache());
+    }
+    let repo_path = acquisition
+        .acquire()
+        .await
+        .with_context(|| format!("Failed to acquire {}", source))?;
+    match acquisition.cache_hit() {
+        Some(true) => progress.suspend(|| println!("Updated cached clone")),
+        Some(false) => progress.suspend(|| println!("Cloned into the repository cache")),
+        None => {}
+    }
+    if let Some(revision) = acquisition.revision() {
+        progress.suspend(|| println!("Checked out {}", revision.cyan()));
+    }
+    let history = if needs_history {
+        Some(GitHistory::open(&repo_path)?)
+    } else {
+        None
+    };
+
+    progress.set_message("Detecting codebase type...");
+    let detector = CodebaseDetector::new(repo_path.clone());
+    let codebase_info = detector.detect()?;
+
+    progress.set_message("Setting up filters...");
+    let mut filter = FileFilter::new(repo_path.clone());
+    
+    if args.no_gitignore {
+        filter.ignore_gitignore();
+    }
+    
+    let include_patterns = if let Some(only_patterns) = &args.only {
+        only_patterns.iter().map(|p| normalize_pattern(p)).collect()
+    } else if let Some(include_patterns) = &args.include {
+        include_patterns.iter().map(|p| normalize_pattern(p)).collect()
+    } else if should_use_smart_defaults(args) {
+        get_default_include_patterns(&codebase_info)
+    } else {
+        Vec::new()
+    };
+    
+    if !include_patterns.is_empty() {
+        filter.set_include_patterns(include_patterns)?;
+    }
+    
+    let exclude_patterns = if let Some(exclude) = &args.exclude {
+        exclude.iter().map(|p| normalize_pattern(p)).collect()
+    } else if should_use_smart_defaults(args) {
+        get_smart_exclude_patterns(&codebase_info)
+    } else {
+        Vec::new()
+    };
+    
+    if !exclude_patterns.is_empty() {
+        filter.set_exclude_patterns(exclude_patterns)?;
+    }
+    
+    if !args.allow_binary {
+        filter.exclude_binary_files();
+    }
+    
+    // Set a default max file size of 10MB if not specified
+    let max_size = args.max_file_size.unwrap_or(10 * 1024 * 1024); // 10MB default
+    filter.set_max_file_size(max_size);
+
+    progress.set_message("Filtering files...");
+    let mut files = filter
+        .filter_files()
+        .context("Failed to filter files")?;
+
+    if let (Some(history), Some(since)) = (&history, &args.since) {
+        progress.set_message(format!("Finding files changed since {}...", since));
+        let changed = history.changed_since(since)?;
+        files.retain(|f| changed.contains(&f.relative_path));
+    }
+
+    if let Some(history) = history.as_ref().filter(|_| args.authors && !files.is_empty()) {
+        progress.set_message("Reading commit history...");
+        history.annotate(&mut files)?;
+    }
 
+    let scanner = if args.secrets == SecretMode::Off {
+        None
+    } else {
+        progress.set_message("Scanning for secrets...");
+        let allowlist_path = allowlist_path(args, &repo_path);
+        let allowlist = if args.secrets_allowlist.is_some() || allowlist_path.exists() {
+            Allowlist::load(&allowlist_path)?
+        } else {
+            Allowlist::default()
+        };
+        let scanner = SecretScanner::new(allowlist);
+        let report = scanner.scan_files(&mut files, args.secrets == SecretMode::Redact);
+
+        if !report.findings.is_empty() {
+            progress.suspend(|| eprint!("{}", format!("⚠️  {}", report.summary()).yellow()));
+            if args.secrets == SecretMode::Fail {
+                return Err(refuse_export(args, &repo_path));
+            }
+        } else if args.verbose {
+            println!("{}", report.summary().trim_end());
+        }
+        Some(scanner)
+    };
+
+    Ok(PreparedSource {
+        name: extract_repo_name(&source),
+        acquisition,
+        repo_path,
+        history,
+        codebase_info,
+        files,
+        scanner,
+    })
+}
+
+/// The allowlist `--secrets-allowlist` names, or the default one in the
+/// repository, which may not exist.
+fn allowlist_path(args: &GitArgs, repo_path: &Path) -> PathBuf {
+    args.secrets_allowlist
+        .clone()
+        .unwrap_or_else(|| repo_path.join(DEFAULT_ALLOWLIST))
+}
+
+/// The error for `--secrets fail` when secrets were found.
+fn refuse_export(args: &GitArgs, repo_path: &Path) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Refusing to export: secrets found. Use --secrets redact, or allowlist false positives in {}",
+        allowlist_path(args, repo_path).display()
+    )
+}
+
+fn execute_cache_command(action: &CacheCommand) -> Result<()> {
+    match action {
+        CacheCommand::List { cache } => {
+            let repo_cache = cache.repo_cache();
+            let entries = repo_cache.list()?;
+            if entries.is_empty() {
+                println!("No cached repositories in {}", repo_cache.root().display());
+                return Ok(());
+            }
+
+            println!("{:<10} {:<17} {:<17} URL", "Size", "Last used", "Cached");
+            for entry in &entries {
+                println!(
+                    "{:<10} {:<17} {:<17} {}",
+                    format_file_size(entry.size),
+                    entry.last_used.format("%Y-%m-%d %H:%M"),
+                    entry.created.format("%Y-%m-%d %H:%M"),
+                    entry_name(entry).cyan()
+                );
+            }
+            println!(
+                "\n{} repositories, {} of {} in {}",
+                entries.len(),
+                format_file_size(entries.iter().map(|e| e.size).sum()),
+                format_file_size(cache.cache_max_size),
+                repo_cache.root().display()
+            );
+        }
+        CacheCommand::Prune { older_than, all, cache } => {
+            let older_than = if *all {
+                Some(chrono::Duration::zero())
+            } else {
+                older_than.map(|days| chrono::Duration::days(days as i64))
+            };
+            let removed = cache.repo_cache().prune(older_than)?;
+            for entry in &removed {
+                println!("Removed {} ({})", entry_name(entry), format_file_size(entry.size));
+            }
+            println!(
+                "{}",
+                format!(
+                    "✅ Pruned {} repositories, freeing {}",
+                    removed.len(),
+                    format_file_size(removed.iter().map(|e| e.size).sum())
+                )
+                .green()
+            );
+        }
+    }
     Ok(())
 }
 
+/// The URL of a cached clone, or its directory when its first fetch never
+/// finished.
+fn entry_name(entry: &CacheEntry) -> String {
+    match &entry.url {
+        Some(url) => url.clone(),
+        None => format!("{} (unfinished clone)", entry.path.display()),
+    }
+}
+
+/// Sources from the command line followed by those in `--sources-file`.
+fn collect_sources(args: &GitArgs) -> Result<Vec<String>> {
+    let mut sources = args.sources.clone();
+
+    if let Some(path) = &args.sources_file {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read sources file {}", path.display()))?;
+        sources.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+
+    if sources.is_empty() {
+        anyhow::bail!("No sources given; pass a repository URL or path, or --sources-file");
+    }
+    Ok(sources)
+}
+
 fn determine_output_format(args: &GitArgs) -> OutputFormat {
     if args.json {
         OutputFormat::Json
+    } else if args.markdown {
+        OutputFormat::Markdown
+    } else if args.xml {
+        OutputFormat::Xml
     } else if let Some(ref output) = args.output {
-        if output.extension().and_then(|e| e.to_str()) == Some("txt") || args.stdout {
-            OutputFormat::Text
-        } else {
-            OutputFormat::Directory
+        match output.extension().and_then(|e| e.to_str()) {
+            Some("md") => OutputFormat::Markdown,
+            Some("xml") => OutputFormat::Xml,
+            Some("txt") => OutputFormat::Text,
+            _ if args.stdout => OutputFormat::Text,
+            _ => OutputFormat::Directory,
         }
     } else {
         OutputFormat::Text
@@ -258,6 +851,12 @@ fn should_use_smart_defaults(args: &GitArgs) -> bool {
 }
 
 fn should_use_organized_output(args: &GitArgs, output_path: &Option<PathBuf>) -> bool {
+    if matches!(
+        determine_output_format(args),
+        OutputFormat::Markdown | OutputFormat::Xml
+    ) {
+        return false;
+    }
     if let Some(ref path) = output_path {
         if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
             return ext == "txt" || args.json;
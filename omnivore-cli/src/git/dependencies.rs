@@ -0,0 +1,730 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    PyPI,
+    Go,
+    Maven,
+    RubyGems,
+}
+
+impl Ecosystem {
+    /// The package-url type (https://github.com/package-url/purl-spec).
+    fn purl_type(&self) -> &'static str {
+        match self {
+            Ecosystem::Cargo => "cargo",
+            Ecosystem::Npm => "npm",
+            Ecosystem::PyPI => "pypi",
+            Ecosystem::Go => "golang",
+            Ecosystem::Maven => "maven",
+            Ecosystem::RubyGems => "gem",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyScope {
+    Runtime,
+    Dev,
+    Build,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    pub ecosystem: Ecosystem,
+    pub scope: DependencyScope,
+    /// The requirement as declared in the manifest (`^1.2`, `>=2,<3`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The exact version pinned by the lockfile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<String>,
+    /// False for transitive dependencies that only appear in a lockfile.
+    pub direct: bool,
+    /// Manifest or lockfile the dependency was read from.
+    pub source: String,
+}
+
+impl Dependency {
+    fn direct(name: &str, ecosystem: Ecosystem, scope: DependencyScope, version: Option<String>, source: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ecosystem,
+            scope,
+            version: version.filter(|v| !v.is_empty() && v != "*"),
+            resolved: None,
+            direct: true,
+            source: source.to_string(),
+        }
+    }
+
+    /// The resolved version, or the declared one when it pins an exact version.
+    pub fn exact_version(&self) -> Option<String> {
+        if let Some(resolved) = &self.resolved {
+            return Some(resolved.clone());
+        }
+        let declared = self.version.as_deref()?.trim();
+        // A bare `1.0` is a caret requirement for Cargo and a prefix match for pip
+        let version = match self.ecosystem {
+            Ecosystem::Cargo => declared.strip_prefix('=')?,
+            Ecosystem::PyPI => declared.strip_prefix("==")?,
+            _ => declared.trim_start_matches('='),
+        }
+        .trim();
+        let exact = version.starts_with(|c: char| c.is_ascii_digit())
+            && version
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'));
+        exact.then(|| version.to_string())
+    }
+
+    pub fn purl(&self) -> String {
+        let name = match self.ecosystem {
+            Ecosystem::Npm => self.name.replace('@', "%40"),
+            Ecosystem::PyPI => self.name.to_lowercase().replace('_', "-"),
+            Ecosystem::Maven => self.name.replacen(':', "/", 1),
+            _ => self.name.clone(),
+        };
+        match self.exact_version() {
+            Some(version) => format!("pkg:{}/{}@{}", self.ecosystem.purl_type(), name, version),
+            None => format!("pkg:{}/{}", self.ecosystem.purl_type(), name),
+        }
+    }
+}
+
+type ManifestParser = fn(&str, &str) -> Result<Vec<Dependency>>;
+type LockfileParser = fn(&str) -> Result<Vec<Locked>>;
+
+/// Reads the dependency manifests and lockfiles in `root` and in workspace
+/// `members` (relative to `root`). Manifests give the direct dependencies and
+/// their scope; lockfiles add resolved versions and transitive dependencies,
+/// so members' dependencies pick up versions from the workspace lockfile.
+/// Files that fail to parse are skipped. Sources are relative to `root`.
+pub fn parse_workspace_dependencies(root: &Path, members: &[PathBuf]) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    let dirs: Vec<PathBuf> = std::iter::once(PathBuf::new()).chain(members.iter().cloned()).collect();
+
+    let manifests: [(&str, ManifestParser); 8] = [
+        ("Cargo.toml", parse_cargo_toml),
+        ("package.json", parse_package_json),
+        ("pyproject.toml", parse_pyproject),
+        ("go.mod", parse_go_mod),
+        ("pom.xml", parse_pom),
+        ("build.gradle", parse_gradle),
+        ("build.gradle.kts", parse_gradle),
+        ("Gemfile", parse_gemfile),
+    ];
+    for dir in &dirs {
+        for (file, parser) in manifests {
+            let source = source_path(dir, file);
+            if let Some(parsed) = read(root, &source).and_then(|content| parser(&content, &source).ok()) {
+                dependencies.extend(parsed);
+            }
+        }
+
+        for (file, scope) in [
+            ("requirements.txt", DependencyScope::Runtime),
+            ("requirements-dev.txt", DependencyScope::Dev),
+            ("dev-requirements.txt", DependencyScope::Dev),
+            ("requirements-test.txt", DependencyScope::Dev),
+        ] {
+            let source = source_path(dir, file);
+            if let Some(content) = read(root, &source) {
+                dependencies.extend(parse_requirements(&content, &source, scope));
+            }
+        }
+    }
+
+    let lockfiles: [(&str, Ecosystem, LockfileParser); 6] = [
+        ("Cargo.lock", Ecosystem::Cargo, parse_cargo_lock),
+        ("package-lock.json", Ecosystem::Npm, parse_package_lock),
+        ("yarn.lock", Ecosystem::Npm, parse_yarn_lock),
+        ("pnpm-lock.yaml", Ecosystem::Npm, parse_pnpm_lock),
+        ("poetry.lock", Ecosystem::PyPI, parse_poetry_lock),
+        ("Gemfile.lock", Ecosystem::RubyGems, parse_gemfile_lock),
+    ];
+    for dir in &dirs {
+        for (file, ecosystem, parser) in lockfiles {
+            let source = source_path(dir, file);
+            if let Some(locked) = read(root, &source).and_then(|content| parser(&content).ok()) {
+                merge_lockfile(&mut dependencies, ecosystem, &source, locked);
+            }
+        }
+    }
+
+    dependencies
+}
+
+fn source_path(dir: &Path, file: &str) -> String {
+    dir.join(file).to_string_lossy().replace('\\', "/")
+}
+
+fn read(root: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(root.join(file)).ok()
+}
+
+/// A package pinned by a lockfile.
+struct Locked {
+    name: String,
+    version: String,
+    /// Whether the lockfile marks the package as dev-only, when it says.
+    dev: Option<bool>,
+}
+
+fn merge_lockfile(dependencies: &mut Vec<Dependency>, ecosystem: Ecosystem, source: &str, locked: Vec<Locked>) {
+    let normalize = |name: &str| match ecosystem {
+        Ecosystem::PyPI => name.to_lowercase().replace('_', "-"),
+        _ => name.to_string(),
+    };
+    // Workspace members may each declare the same package
+    let mut direct: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, dependency) in dependencies.iter().enumerate() {
+        if dependency.ecosystem == ecosystem {
+            direct.entry(normalize(&dependency.name)).or_default().push(index);
+        }
+    }
+    // Lockfiles may pin several versions of one package, and list it again
+    // for each place it is installed
+    let mut transitive: HashSet<(String, String)> = HashSet::new();
+
+    for package in locked {
+        match direct.get(&normalize(&package.name)) {
+            Some(indices) => {
+                for &index in indices {
+                    let dependency = &mut dependencies[index];
+                    if dependency.resolved.is_none() {
+                        dependency.resolved = Some(package.version.clone());
+                    }
+                }
+            }
+            None => {
+                if !transitive.insert((normalize(&package.name), package.version.clone())) {
+                    continue;
+                }
+                dependencies.push(Dependency {
+                    name: package.name,
+                    ecosystem,
+                    scope: if package.dev == Some(true) { DependencyScope::Dev } else { DependencyScope::Runtime },
+                    version: None,
+                    resolved: Some(package.version),
+                    direct: false,
+                    source: source.to_string(),
+                });
+            }
+        }
+    }
+}
+
+fn parse_cargo_toml(content: &str, source: &str) -> Result<Vec<Dependency>> {
+    let manifest: toml::Value = toml::from_str(content)?;
+    let mut dependencies = Vec::new();
+
+    // The package itself, `[workspace]` and `[target.'cfg(..)']` tables
+    let mut tables = vec![&manifest];
+    tables.extend(manifest.get("workspace"));
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        tables.extend(targets.values());
+    }
+
+    for table in tables {
+        for (key, scope) in [
+            ("dependencies", DependencyScope::Runtime),
+            ("dev-dependencies", DependencyScope::Dev),
+            ("build-dependencies", DependencyScope::Build),
+        ] {
+            let Some(deps) = table.get(key).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            for (name, spec) in deps {
+                let version = match spec {
+                    toml::Value::String(version) => Some(version.clone()),
+                    toml::Value::Table(spec) => spec.get("version").and_then(|v| v.as_str()).map(str::to_string),
+                    _ => None,
+                };
+                // `foo = { package = "bar" }` renames the crate
+                let name = spec.get("package").and_then(|p| p.as_str()).unwrap_or(name);
+                if !dependencies.iter().any(|d: &Dependency| d.name == name && d.scope == scope) {
+                    dependencies.push(Dependency::direct(name, Ecosystem::Cargo, scope, version, source));
+                }
+            }
+        }
+    }
+
+    Ok(dependencies)
+}
+
+fn parse_cargo_lock(content: &str) -> Result<Vec<Locked>> {
+    let lock: toml::Value = toml::from_str(content)?;
+    Ok(lock
+        .get("package")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|package| package.get("source").is_some())
+        .filter_map(|package| {
+            Some(Locked {
+                name: package.get("name")?.as_str()?.to_string(),
+                version: package.get("version")?.as_str()?.to_string(),
+                dev: None,
+            })
+        })
+        .collect())
+}
+
+fn parse_package_json(content: &str, source: &str) -> Result<Vec<Dependency>> {
+    let manifest: Value = serde_json::from_str(content)?;
+    let mut dependencies = Vec::new();
+
+    for (key, scope) in [
+        ("dependencies", DependencyScope::Runtime),
+        ("peerDependencies", DependencyScope::Runtime),
+        ("optionalDependencies", DependencyScope::Runtime),
+        ("devDependencies", DependencyScope::Dev),
+    ] {
+        let Some(deps) = manifest.get(key).and_then(|d| d.as_object()) else {
+            continue;
+        };
+        for (name, version) in deps {
+            if !dependencies.iter().any(|d: &Dependency| &d.name == name) {
+                let version = version.as_str().map(str::to_string);
+                dependencies.push(Dependency::direct(name, Ecosystem::Npm, scope, version, source));
+            }
+        }
+    }
+
+    Ok(dependencies)
+}
+
+fn parse_package_lock(content: &str) -> Result<Vec<Locked>> {
+    let lock: Value = serde_json::from_str(content)?;
+    let mut locked = Vec::new();
+
+    // lockfileVersion 2 and 3
+    if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+        for (path, package) in packages {
+            let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                locked.push(Locked {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    dev: Some(package.get("dev").and_then(|d| d.as_bool()).unwrap_or(false)),
+                });
+            }
+        }
+        return Ok(locked);
+    }
+
+    // lockfileVersion 1 nests dependencies of dependencies
+    fn walk(deps: &serde_json::Map<String, Value>, locked: &mut Vec<Locked>) {
+        for (name, package) in deps {
+            if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                locked.push(Locked {
+                    name: name.clone(),
+                    version: version.to_string(),
+                    dev: Some(package.get("dev").and_then(|d| d.as_bool()).unwrap_or(false)),
+                });
+            }
+            if let Some(nested) = package.get("dependencies").and_then(|d| d.as_object()) {
+                walk(nested, locked);
+            }
+        }
+    }
+    if let Some(deps) = lock.get("dependencies").and_then(|d| d.as_object()) {
+        walk(deps, &mut locked);
+    }
+    Ok(locked)
+}
+
+/// Handles both the classic (`version "1.2.3"`) and Berry (`version: 1.2.3`) formats.
+fn parse_yarn_lock(content: &str) -> Result<Vec<Locked>> {
+    let mut locked = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in content.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            // `"@babel/core@^7.0.0", "@babel/core@^7.1.0":`
+            let first = line.trim_end_matches(':').split(", ").next().unwrap_or_default().trim_matches('"');
+            current = first
+                .get(1..)
+                .and_then(|rest| rest.find('@'))
+                .map(|at| first[..at + 1].to_string())
+                .filter(|name| name != "__metadata");
+            continue;
+        }
+        if let (Some(name), Some(version)) = (&current, line.trim().strip_prefix("version")) {
+            let version = version.trim_start_matches(':').trim().trim_matches('"');
+            locked.push(Locked {
+                name: name.clone(),
+                version: version.to_string(),
+                dev: None,
+            });
+            current = None;
+        }
+    }
+
+    Ok(locked)
+}
+
+fn parse_pnpm_lock(content: &str) -> Result<Vec<Locked>> {
+    let lock: serde_yaml::Value = serde_yaml::from_str(content)?;
+    let Some(packages) = lock.get("packages").and_then(|p| p.as_mapping()) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(packages
+        .iter()
+        .filter_map(|(key, package)| {
+            // `/name@1.2.3(peer@1.0.0)` (v6), `name@1.2.3` (v9) or `/name/1.2.3` (v5)
+            let key = key.as_str()?.trim_start_matches('/');
+            let key = key.split('(').next()?;
+            let (name, version) = match key.get(1..).and_then(|rest| rest.rfind('@')) {
+                Some(at) => (&key[..at + 1], &key[at + 2..]),
+                None => key.rsplit_once('/')?,
+            };
+            Some(Locked {
+                name: name.to_string(),
+                version: version.to_string(),
+                dev: package.get("dev").and_then(|d| d.as_bool()),
+            })
+        })
+        .collect())
+}
+
+/// Splits a PEP 508 requirement such as `requests[socks]>=2.31; python_version > "3.8"`.
+fn parse_requirement(requirement: &str) -> Option<(String, Option<String>)> {
+    let requirement = requirement.split(';').next()?.trim();
+    let end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..end];
+    if name.is_empty() {
+        return None;
+    }
+
+    let rest = requirement[end..].trim_start();
+    let rest = match rest.strip_prefix('[') {
+        Some(extras) => extras.split_once(']').map(|(_, rest)| rest).unwrap_or_default(),
+        None => rest,
+    };
+    let version = rest.trim().trim_start_matches('(').trim_end_matches(')').trim();
+    Some((name.to_string(), (!version.is_empty()).then(|| version.to_string())))
+}
+
+fn parse_requirements(content: &str, source: &str, scope: DependencyScope) -> Vec<Dependency> {
+    content
+        .lines()
+        .map(|line| line.split(" #").next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('-') && !line.contains("://"))
+        .filter_map(parse_requirement)
+        .map(|(name, version)| Dependency::direct(&name, Ecosystem::PyPI, scope, version, source))
+        .collect()
+}
+
+fn parse_pyproject(content: &str, source: &str) -> Result<Vec<Dependency>> {
+    let manifest: toml::Value = toml::from_str(content)?;
+    let mut dependencies = Vec::new();
+    let is_dev_group = |group: &str| matches!(group, "dev" | "test" | "tests" | "lint" | "docs" | "typing");
+
+    let mut add_requirements = |list: Option<&toml::Value>, scope| {
+        for requirement in list.and_then(|l| l.as_array()).into_iter().flatten().filter_map(|r| r.as_str()) {
+            if let Some((name, version)) = parse_requirement(requirement) {
+                dependencies.push(Dependency::direct(&name, Ecosystem::PyPI, scope, version, source));
+            }
+        }
+    };
+
+    // PEP 621
+    let project = manifest.get("project");
+    add_requirements(project.and_then(|p| p.get("dependencies")), DependencyScope::Runtime);
+    for (group, list) in project
+        .and_then(|p| p.get("optional-dependencies"))
+        .and_then(|o| o.as_table())
+        .into_iter()
+        .flatten()
+    {
+        let scope = if is_dev_group(group) { DependencyScope::Dev } else { DependencyScope::Runtime };
+        add_requirements(Some(list), scope);
+    }
+    // PEP 735
+    for (_, list) in manifest.get("dependency-groups").and_then(|g| g.as_table()).into_iter().flatten() {
+        add_requirements(Some(list), DependencyScope::Dev);
+    }
+
+    // Poetry
+    let poetry = manifest.get("tool").and_then(|t| t.get("poetry"));
+    let mut poetry_tables = vec![
+        (poetry.and_then(|p| p.get("dependencies")), DependencyScope::Runtime),
+        (poetry.and_then(|p| p.get("dev-dependencies")), DependencyScope::Dev),
+    ];
+    for (group, table) in poetry.and_then(|p| p.get("group")).and_then(|g| g.as_table()).into_iter().flatten() {
+        let scope = if group == "main" { DependencyScope::Runtime } else { DependencyScope::Dev };
+        poetry_tables.push((table.get("dependencies"), scope));
+    }
+    for (table, scope) in poetry_tables {
+        for (name, spec) in table.and_then(|t| t.as_table()).into_iter().flatten() {
+            if name == "python" {
+                continue;
+            }
+            let version = match spec {
+                toml::Value::String(version) => Some(version.clone()),
+                toml::Value::Table(spec) => spec.get("version").and_then(|v| v.as_str()).map(str::to_string),
+                _ => None,
+            };
+            dependencies.push(Dependency::direct(name, Ecosystem::PyPI, scope, version, source));
+        }
+    }
+
+    Ok(dependencies)
+}
+
+fn parse_poetry_lock(content: &str) -> Result<Vec<Locked>> {
+    let lock: toml::Value = toml::from_str(content)?;
+    Ok(lock
+        .get("package")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|package| {
+            Some(Locked {
+                name: package.get("name")?.as_str()?.to_string(),
+                version: package.get("version")?.as_str()?.to_string(),
+                // Only written by Poetry < 1.2
+                dev: package.get("category").and_then(|c| c.as_str()).map(|c| c == "dev"),
+            })
+        })
+        .collect())
+}
+
+fn parse_go_mod(content: &str, source: &str) -> Result<Vec<Dependency>> {
+    let mut dependencies = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        let spec = if in_block {
+            if line == ")" {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if line == "require (" {
+            in_block = true;
+            continue;
+        } else if let Some(spec) = line.strip_prefix("require ") {
+            spec
+        } else {
+            continue;
+        };
+
+        let (spec, comment) = spec.split_once("//").unwrap_or((spec, ""));
+        let mut parts = spec.split_whitespace();
+        if let (Some(module), Some(version)) = (parts.next(), parts.next()) {
+            let mut dependency =
+                Dependency::direct(module, Ecosystem::Go, DependencyScope::Runtime, None, source);
+            dependency.resolved = Some(version.to_string());
+            dependency.direct = !comment.contains("indirect");
+            dependencies.push(dependency);
+        }
+    }
+
+    Ok(dependencies)
+}
+
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(xml[start..end].trim())
+}
+
+fn parse_pom(content: &str, source: &str) -> Result<Vec<Dependency>> {
+    let comments = Regex::new(r"(?s)<!--.*?-->")?;
+    let content = comments.replace_all(content, "");
+
+    let properties: HashMap<String, String> = match xml_tag(&content, "properties") {
+        Some(block) => Regex::new(r"<([\w.-]+)>([^<]*)</[\w.-]+>")?
+            .captures_iter(block)
+            .map(|c| (c[1].to_string(), c[2].trim().to_string()))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let property = Regex::new(r"\$\{([^}]+)\}")?;
+
+    // Managed versions and build plugins are not dependencies of the project
+    let managed = Regex::new(r"(?s)<dependencyManagement>.*?</dependencyManagement>|<build>.*?</build>")?;
+    let content = managed.replace_all(&content, "");
+
+    let mut dependencies = Vec::new();
+    for block in Regex::new(r"(?s)<dependency>(.*?)</dependency>")?.captures_iter(&content) {
+        let block = &block[1];
+        let (Some(group), Some(artifact)) = (xml_tag(block, "groupId"), xml_tag(block, "artifactId")) else {
+            continue;
+        };
+        let version = xml_tag(block, "version").map(|v| {
+            property
+                .replace_all(v, |c: &regex::Captures| properties.get(&c[1]).cloned().unwrap_or_else(|| c[0].to_string()))
+                .into_owned()
+        });
+        let scope = match xml_tag(block, "scope") {
+            Some("test") => DependencyScope::Dev,
+            Some("provided") | Some("system") => DependencyScope::Build,
+            _ => DependencyScope::Runtime,
+        };
+        let name = format!("{}:{}", group, artifact);
+        dependencies.push(Dependency::direct(&name, Ecosystem::Maven, scope, version, source));
+    }
+
+    Ok(dependencies)
+}
+
+fn parse_gradle(content: &str, source: &str) -> Result<Vec<Dependency>> {
+    let declaration = Regex::new(
+        r#"\b(implementation|api|compile|runtimeOnly|compileOnly|annotationProcessor|kapt|ksp|testImplementation|testRuntimeOnly|testCompileOnly|androidTestImplementation|debugImplementation)\s*\(?\s*["']([^:"'\s]+):([^:"'\s]+)(?::([^"'\s]+))?["']"#,
+    )?;
+
+    Ok(declaration
+        .captures_iter(content)
+        .map(|c| {
+            let configuration = &c[1];
+            let scope = if configuration.starts_with("test") || configuration.starts_with("androidTest") || configuration.starts_with("debug") {
+                DependencyScope::Dev
+            } else if matches!(configuration, "compileOnly" | "annotationProcessor" | "kapt" | "ksp") {
+                DependencyScope::Build
+            } else {
+                DependencyScope::Runtime
+            };
+            let name = format!("{}:{}", &c[2], &c[3]);
+            let version = c.get(4).map(|v| v.as_str().to_string());
+            Dependency::direct(&name, Ecosystem::Maven, scope, version, source)
+        })
+        .collect())
+}
+
+fn parse_gemfile(content: &str, source: &str) -> Result<Vec<Dependency>> {
+    let gem = Regex::new(r#"^\s*gem\s+["']([^"']+)["'](?:\s*,\s*["']([^"']+)["'])?(.*)$"#)?;
+    let mut dependencies = Vec::new();
+    let mut dev_group = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("group ") {
+            dev_group = !trimmed.contains(":production") && !trimmed.contains(":default");
+            continue;
+        }
+        if trimmed == "end" {
+            dev_group = false;
+            continue;
+        }
+        let Some(c) = gem.captures(line) else {
+            continue;
+        };
+        let inline_dev = c.get(3).is_some_and(|rest| {
+            let rest = rest.as_str();
+            rest.contains("group") && (rest.contains(":development") || rest.contains(":test"))
+        });
+        let scope = if dev_group || inline_dev { DependencyScope::Dev } else { DependencyScope::Runtime };
+        let version = c.get(2).map(|v| v.as_str().to_string());
+        dependencies.push(Dependency::direct(&c[1], Ecosystem::RubyGems, scope, version, source));
+    }
+
+    Ok(dependencies)
+}
+
+fn parse_gemfile_lock(content: &str) -> Result<Vec<Locked>> {
+    let spec = Regex::new(r"^    ([^\s(]+) \(([^)]+)\)$")?;
+    let mut in_specs = false;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            if !line.starts_with(' ') {
+                in_specs = false;
+            } else if line.trim() == "specs:" {
+                in_specs = true;
+            } else if in_specs {
+                let c = spec.captures(line)?;
+                return Some(Locked {
+                    name: c[1].to_string(),
+                    version: c[2].to_string(),
+                    dev: None,
+                });
+            }
+            None
+        })
+        .collect())
+}
+
+/// A CycloneDX 1.5 JSON bill of materials for `dependencies`.
+pub fn to_cyclonedx(project_name: &str, dependencies: &[Dependency]) -> Value {
+    let components: Vec<Value> = dependencies
+        .iter()
+        .map(|dependency| {
+            let purl = dependency.purl();
+            let (group, name) = match dependency.ecosystem {
+                Ecosystem::Maven => match dependency.name.split_once(':') {
+                    Some((group, name)) => (Some(group), name),
+                    None => (None, dependency.name.as_str()),
+                },
+                _ => (None, dependency.name.as_str()),
+            };
+
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "name": name,
+                "purl": purl,
+                "scope": if dependency.scope == DependencyScope::Runtime { "required" } else { "optional" },
+                "properties": [
+                    { "name": "omnivore:scope", "value": dependency.scope },
+                    { "name": "omnivore:direct", "value": dependency.direct.to_string() },
+                    { "name": "omnivore:source", "value": dependency.source },
+                ],
+            });
+            if let Some(group) = group {
+                component["group"] = json!(group);
+            }
+            if let Some(version) = dependency.exact_version() {
+                component["version"] = json!(version);
+            }
+            component
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "omnivore",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }]
+            },
+            "component": {
+                "type": "application",
+                "bom-ref": project_name,
+                "name": project_name,
+            },
+        },
+        "components": components,
+    })
+}
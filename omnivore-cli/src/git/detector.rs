@@ -4,6 +4,9 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use super::dependencies::{parse_workspace_dependencies, Dependency};
+use super::workspace::{find_workspace_members, WorkspaceMember};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodebaseInfo {
     pub project_type: ProjectType,
@@ -12,6 +15,90 @@ pub struct CodebaseInfo {
     pub build_tools: Vec<BuildTool>,
     pub main_language: Option<Language>,
     pub description: String,
+    /// Files where execution starts or the public API is rooted, relative to
+    /// the repository root.
+    #[serde(default)]
+    pub entry_points: Vec<PathBuf>,
+    /// Dependencies declared in manifests, plus transitive ones from lockfiles.
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    /// Workspace members (or the sources of a multi-source run), each
+    /// analyzed as its own project.
+    #[serde(default)]
+    pub members: Vec<WorkspaceMember>,
+}
+
+impl CodebaseInfo {
+    fn empty() -> Self {
+        Self {
+            project_type: ProjectType::Unknown,
+            languages: Vec::new(),
+            frameworks: Vec::new(),
+            build_tools: Vec::new(),
+            main_language: None,
+            description: String::new(),
+            entry_points: Vec::new(),
+            dependencies: Vec::new(),
+            members: Vec::new(),
+        }
+    }
+
+    /// Info for several repositories analyzed together, with each one as a
+    /// member whose path is the directory its files are listed under.
+    pub fn combined(members: Vec<WorkspaceMember>) -> Self {
+        let mut info = Self::empty();
+        for member in &members {
+            info.dependencies.extend(member.info.dependencies.iter().map(|dependency| Dependency {
+                source: format!("{}/{}", member.path.display(), dependency.source),
+                ..dependency.clone()
+            }));
+        }
+        info.members = members;
+        info.include_members();
+
+        let names: Vec<_> = info.members.iter().map(|m| m.name.as_str()).collect();
+        info.description = format!("{} repositories: {}", names.len(), names.join(", "));
+        info
+    }
+
+    /// Adds the members' languages, frameworks, build tools and entry points
+    /// to the workspace as a whole.
+    fn include_members(&mut self) {
+        let mut main_languages: HashMap<Language, usize> = HashMap::new();
+
+        for member in &self.members {
+            for language in &member.info.languages {
+                if !self.languages.contains(language) {
+                    self.languages.push(language.clone());
+                }
+            }
+            for framework in &member.info.frameworks {
+                if !self.frameworks.contains(framework) {
+                    self.frameworks.push(framework.clone());
+                }
+            }
+            for build_tool in &member.info.build_tools {
+                if !self.build_tools.contains(build_tool) {
+                    self.build_tools.push(build_tool.clone());
+                }
+            }
+            self.entry_points
+                .extend(member.info.entry_points.iter().map(|entry| member.path.join(entry)));
+            if let Some(language) = &member.info.main_language {
+                *main_languages.entry(language.clone()).or_insert(0) += 1;
+            }
+        }
+
+        if self.main_language.is_none() {
+            self.main_language = main_languages
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| format!("{:?}", b.0).cmp(&format!("{:?}", a.0))))
+                .map(|(language, _)| language);
+        }
+        self.entry_points.sort();
+        self.entry_points.dedup();
+        self.project_type = ProjectType::Monorepo;
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -104,20 +191,55 @@ impl CodebaseDetector {
     }
 
     pub fn detect(&self) -> Result<CodebaseInfo> {
-        let mut info = CodebaseInfo {
-            project_type: ProjectType::Unknown,
-            languages: Vec::new(),
-            frameworks: Vec::new(),
-            build_tools: Vec::new(),
-            main_language: None,
-            description: String::new(),
-        };
+        let mut info = self.detect_project()?;
+
+        let locations = find_workspace_members(&self.root_path);
+        let member_paths: Vec<PathBuf> = locations.iter().map(|m| m.path.clone()).collect();
+        info.dependencies = parse_workspace_dependencies(&self.root_path, &member_paths);
+
+        for location in locations {
+            let detector = CodebaseDetector::new(self.root_path.join(&location.path));
+            let mut member_info = detector.detect_project()?;
+            // The member's own manifests, with versions from the workspace lockfile
+            member_info.dependencies = info
+                .dependencies
+                .iter()
+                .filter(|d| d.direct && Path::new(&d.source).parent() == Some(location.path.as_path()))
+                .map(|d| Dependency {
+                    source: Path::new(&d.source)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    ..d.clone()
+                })
+                .collect();
+            detector.generate_description(&mut member_info);
+
+            info.members.push(WorkspaceMember {
+                name: location.name,
+                path: location.path,
+                kind: location.kind,
+                info: member_info,
+            });
+        }
+
+        if !info.members.is_empty() {
+            info.include_members();
+        }
+        self.generate_description(&mut info);
+
+        Ok(info)
+    }
+
+    /// Detects everything but dependencies and workspace members.
+    fn detect_project(&self) -> Result<CodebaseInfo> {
+        let mut info = CodebaseInfo::empty();
 
         self.detect_by_config_files(&mut info)?;
         self.detect_by_file_extensions(&mut info)?;
         self.determine_project_type(&mut info);
         self.determine_main_language(&mut info);
-        self.generate_description(&mut info);
+        self.detect_entry_points(&mut info);
 
         Ok(info)
     }
@@ -341,35 +463,10 @@ impl CodebaseDetector {
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
         {
-            if let Some(ext) = entry.path().extension().and_then(|e| e.to_str()) {
-                let lang = match ext {
-                    "rs" => Some(Language::Rust),
-                    "js" | "mjs" | "cjs" => Some(Language::JavaScript),
-                    "ts" | "tsx" => Some(Language::TypeScript),
-                    "py" => Some(Language::Python),
-                    "go" => Some(Language::Go),
-                    "java" => Some(Language::Java),
-                    "cs" => Some(Language::CSharp),
-                    "cpp" | "cc" | "cxx" => Some(Language::CPlusPlus),
-                    "c" | "h" => Some(Language::C),
-                    "rb" => Some(Language::Ruby),
-                    "php" => Some(Language::PHP),
-                    "swift" => Some(Language::Swift),
-                    "kt" | "kts" => Some(Language::Kotlin),
-                    "scala" => Some(Language::Scala),
-                    "ex" | "exs" => Some(Language::Elixir),
-                    "hs" => Some(Language::Haskell),
-                    "sh" | "bash" | "zsh" => Some(Language::Shell),
-                    "html" | "htm" => Some(Language::HTML),
-                    "css" | "scss" | "sass" | "less" => Some(Language::CSS),
-                    _ => None,
-                };
-
-                if let Some(lang) = lang {
-                    *language_counts.entry(lang.clone()).or_insert(0) += 1;
-                    if !info.languages.contains(&lang) {
-                        info.languages.push(lang);
-                    }
+            if let Some(lang) = language_for_path(entry.path()) {
+                *language_counts.entry(lang.clone()).or_insert(0) += 1;
+                if !info.languages.contains(&lang) {
+                    info.languages.push(lang);
                 }
             }
         }
@@ -426,6 +523,91 @@ impl CodebaseDetector {
         }
     }
 
+    fn detect_entry_points(&self, info: &mut CodebaseInfo) {
+        const CANDIDATES: &[&str] = &[
+            "src/main.rs",
+            "src/lib.rs",
+            "main.go",
+            "main.py",
+            "__main__.py",
+            "app.py",
+            "manage.py",
+            "cli.py",
+            "index.js",
+            "index.ts",
+            "src/index.js",
+            "src/index.ts",
+            "src/main.js",
+            "src/main.ts",
+            "src/App.tsx",
+            "src/App.jsx",
+            "app/page.tsx",
+            "pages/index.tsx",
+            "pages/index.js",
+            "server.js",
+            "app.js",
+        ];
+
+        let mut entry_points: Vec<PathBuf> = CANDIDATES
+            .iter()
+            .map(|path| PathBuf::from(*path))
+            .filter(|path| self.root_path.join(path).is_file())
+            .collect();
+
+        // package.json "main" and "bin" point at the real entry files
+        if let Ok(content) = fs::read_to_string(self.root_path.join("package.json")) {
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
+                let mut targets: Vec<&str> = json.get("main").and_then(|m| m.as_str()).into_iter().collect();
+                match json.get("bin") {
+                    Some(serde_json::Value::String(bin)) => targets.push(bin),
+                    Some(serde_json::Value::Object(bins)) => {
+                        targets.extend(bins.values().filter_map(|b| b.as_str()))
+                    }
+                    _ => {}
+                }
+                for target in targets {
+                    entry_points.push(PathBuf::from(target.trim_start_matches("./")));
+                }
+            }
+        }
+
+        // Binaries under src/bin and cmd/<name>/main.go
+        for (dir, file_name) in [("src/bin", None), ("cmd", Some("main.go"))] {
+            let Ok(entries) = fs::read_dir(self.root_path.join(dir)) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = match file_name {
+                    Some(name) => entry.path().join(name),
+                    None => entry.path(),
+                };
+                if path.is_file() {
+                    if let Ok(relative) = path.strip_prefix(&self.root_path) {
+                        entry_points.push(relative.to_path_buf());
+                    }
+                }
+            }
+        }
+
+        // Java/Kotlin classes with a main method are found by name convention
+        for entry in walkdir::WalkDir::new(self.root_path.join("src/main"))
+            .max_depth(8)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let name = entry.file_name().to_string_lossy();
+            if name == "Main.java" || name == "Application.java" || name.ends_with("Application.kt") {
+                if let Ok(relative) = entry.path().strip_prefix(&self.root_path) {
+                    entry_points.push(relative.to_path_buf());
+                }
+            }
+        }
+
+        entry_points.sort();
+        entry_points.dedup();
+        info.entry_points = entry_points;
+    }
+
     fn generate_description(&self, info: &mut CodebaseInfo) {
         let project_type = match &info.project_type {
             ProjectType::WebApplication => "web application",
@@ -450,8 +632,14 @@ impl CodebaseDetector {
             String::new()
         };
 
+        let members_str = match info.members.len() {
+            0 => String::new(),
+            1 => " with 1 workspace member".to_string(),
+            count => format!(" with {} workspace members", count),
+        };
+
         info.description = format!(
-            "{} {} written in {}{}",
+            "{} {} written in {}{}{}",
             if matches!(info.project_type, ProjectType::API | ProjectType::Unknown) {
                 "A"
             } else {
@@ -459,11 +647,67 @@ impl CodebaseDetector {
             },
             project_type,
             main_lang,
-            framework_str
+            framework_str,
+            members_str
         );
     }
 }
 
+/// The programming language of a file, judged by its extension.
+pub fn language_for_path(path: &Path) -> Option<Language> {
+    match path.extension()?.to_str()? {
+        "rs" => Some(Language::Rust),
+        "js" | "mjs" | "cjs" => Some(Language::JavaScript),
+        "ts" | "tsx" => Some(Language::TypeScript),
+        "py" => Some(Language::Python),
+        "go" => Some(Language::Go),
+        "java" => Some(Language::Java),
+        "cs" => Some(Language::CSharp),
+        "cpp" | "cc" | "cxx" => Some(Language::CPlusPlus),
+        "c" | "h" => Some(Language::C),
+        "rb" => Some(Language::Ruby),
+        "php" => Some(Language::PHP),
+        "swift" => Some(Language::Swift),
+        "kt" | "kts" => Some(Language::Kotlin),
+        "scala" => Some(Language::Scala),
+        "ex" | "exs" => Some(Language::Elixir),
+        "hs" => Some(Language::Haskell),
+        "sh" | "bash" | "zsh" => Some(Language::Shell),
+        "html" | "htm" => Some(Language::HTML),
+        "css" | "scss" | "sass" | "less" => Some(Language::CSS),
+        _ => None,
+    }
+}
+
+impl Language {
+    /// The info string for a Markdown code fence.
+    pub fn fence_tag(&self) -> String {
+        match self {
+            Language::Rust => "rust",
+            Language::JavaScript => "javascript",
+            Language::TypeScript => "typescript",
+            Language::Python => "python",
+            Language::Go => "go",
+            Language::Java => "java",
+            Language::CSharp => "csharp",
+            Language::CPlusPlus => "cpp",
+            Language::C => "c",
+            Language::Ruby => "ruby",
+            Language::PHP => "php",
+            Language::Swift => "swift",
+            Language::Kotlin => "kotlin",
+            Language::Scala => "scala",
+            Language::Elixir => "elixir",
+            Language::Haskell => "haskell",
+            Language::Shell => "bash",
+            Language::HTML => "html",
+            Language::CSS => "css",
+            Language::Other(name) => return name.to_lowercase(),
+        }
+        .to_string()
+    }
+}
+
 pub fn get_default_include_patterns(info: &CodebaseInfo) -> Vec<String> {
     let mut patterns = Vec::new();
 
@@ -6,12 +6,30 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use walkdir::{DirEntry, WalkDir};
 
+use super::history::FileHistory;
+
 #[derive(Debug, Clone)]
 pub struct FilteredFile {
     pub path: PathBuf,
     pub relative_path: PathBuf,
     #[allow(dead_code)]
     pub size: u64,
+    /// Git history of the file, filled in by `GitHistory::annotate`.
+    pub history: Option<FileHistory>,
+    /// Replaces the file's contents in the output, e.g. after secrets were
+    /// redacted.
+    pub content: Option<String>,
+}
+
+impl FilteredFile {
+    /// The contents to export: the replacement if one is set, otherwise the
+    /// file on disk.
+    pub fn read_content(&self) -> std::io::Result<String> {
+        match &self.content {
+            Some(content) => Ok(content.clone()),
+            None => fs::read_to_string(&self.path),
+        }
+    }
 }
 
 pub struct FileFilter {
@@ -108,6 +126,8 @@ impl FileFilter {
                 path: path.to_path_buf(),
                 relative_path,
                 size: metadata.len(),
+                history: None,
+                content: None,
             });
         }
 
@@ -0,0 +1,464 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use git2::{Commit, Delta, DiffOptions, Oid, Patch, Repository, Sort, Tree};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::filter::FilteredFile;
+
+/// Commit history of one file, attached to `FilteredFile::history` with
+/// `--authors`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHistory {
+    pub commits: usize,
+    pub additions: usize,
+    pub deletions: usize,
+    pub churn: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_author: Option<String>,
+    /// Authors by number of commits touching the file, most active first.
+    pub authors: Vec<AuthorStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorStats {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+}
+
+impl FileHistory {
+    /// One-line summary used by the text output formats.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(date) = self.last_modified {
+            let by = self
+                .last_author
+                .as_ref()
+                .map(|a| format!(" by {}", a))
+                .unwrap_or_default();
+            parts.push(format!("last modified {}{}", date.format("%Y-%m-%d"), by));
+        }
+        parts.push(format!(
+            "{} commit{}",
+            self.commits,
+            if self.commits == 1 { "" } else { "s" }
+        ));
+        parts.push(format!("churn +{}/-{}", self.additions, self.deletions));
+        if !self.authors.is_empty() {
+            let authors = self
+                .authors
+                .iter()
+                .map(|a| format!("{} ({})", a.name, a.commits))
+                .collect::<Vec<_>>()
+                .join(", ");
+            parts.push(format!("authors: {}", authors));
+        }
+
+        parts.join(" · ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedFile {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub status: ChangeStatus,
+    pub additions: usize,
+    pub deletions: usize,
+    /// The file at the head revision; absent for deletions and binary files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    pub diff: String,
+}
+
+/// Changed files between two revisions with their unified diffs.
+#[derive(Debug, Serialize)]
+pub struct DiffReport {
+    pub base: String,
+    pub head: String,
+    pub files: Vec<ChangedFile>,
+}
+
+impl DiffReport {
+    pub fn to_text(&self, include_content: bool) -> String {
+        let additions: usize = self.files.iter().map(|f| f.additions).sum();
+        let deletions: usize = self.files.iter().map(|f| f.deletions).sum();
+        let mut output = format!(
+            "# Changes {}..{}: {} files, +{}/-{}\n\n",
+            self.base,
+            self.head,
+            self.files.len(),
+            additions,
+            deletions
+        );
+
+        for file in &self.files {
+            let status = match (&file.status, &file.old_path) {
+                (ChangeStatus::Renamed, Some(old)) => format!("renamed from {}", old),
+                (status, _) => format!("{:?}", status).to_lowercase(),
+            };
+            output.push_str(&format!(
+                "---\nFile: {} ({}, +{}/-{})\n---\n",
+                file.path, status, file.additions, file.deletions
+            ));
+            output.push_str(&file.diff);
+            if !file.diff.ends_with('\n') {
+                output.push('\n');
+            }
+            if include_content {
+                if let Some(content) = &file.content {
+                    output.push_str(&format!("---\nFile: {} (at {})\n---\n{}\n", file.path, self.head, content));
+                }
+            }
+        }
+
+        output
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize diff")
+    }
+}
+
+/// Read-only access to the history of the repository being analyzed.
+pub struct GitHistory {
+    repo: Repository,
+    /// Where the analyzed directory sits inside the repository, so history
+    /// paths can be matched against `FilteredFile::relative_path` when only a
+    /// subdirectory is analyzed.
+    prefix: PathBuf,
+}
+
+impl GitHistory {
+    pub fn open(path: &Path) -> Result<Self> {
+        let repo = Repository::discover(path)
+            .with_context(|| format!("{} is not a Git repository", path.display()))?;
+        let prefix = repo
+            .workdir()
+            .and_then(|workdir| {
+                let workdir = workdir.canonicalize().ok()?;
+                let path = path.canonicalize().ok()?;
+                path.strip_prefix(workdir).ok().map(Path::to_path_buf)
+            })
+            .unwrap_or_default();
+        Ok(Self { repo, prefix })
+    }
+
+    /// Maps a repository path to one relative to the analyzed directory.
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        path.strip_prefix(&self.prefix).ok().map(Path::to_path_buf)
+    }
+
+    /// Paths changed since `since`, which is either a date (`2024-05-01`,
+    /// RFC 3339, `2 weeks ago`) or a revision. For a revision, uncommitted
+    /// changes in the working tree count as well.
+    pub fn changed_since(&self, since: &str) -> Result<HashSet<PathBuf>> {
+        let mut changed = HashSet::new();
+
+        if let Some(cutoff) = parse_since_date(since) {
+            let mut revwalk = self.repo.revwalk()?;
+            revwalk.push_head()?;
+            revwalk.set_sorting(Sort::TIME)?;
+
+            for oid in revwalk {
+                let commit = self.repo.find_commit(oid?)?;
+                if commit.time().seconds() < cutoff.timestamp() {
+                    break;
+                }
+                if is_merge(&commit) {
+                    continue;
+                }
+                let diff = self.commit_diff(&commit)?;
+                for delta in diff.deltas() {
+                    changed.extend(delta.new_file().path().and_then(|p| self.local_path(p)));
+                }
+            }
+        } else {
+            let base = self
+                .repo
+                .revparse_single(since)
+                .with_context(|| format!("'{}' is neither a date nor a revision", since))?
+                .peel_to_tree()?;
+            let diff = self
+                .repo
+                .diff_tree_to_workdir_with_index(Some(&base), None)?;
+            for delta in diff.deltas() {
+                changed.extend(delta.new_file().path().and_then(|p| self.local_path(p)));
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Diffs `<base>..<head>`, or `<base>...<head>` against their merge base.
+    /// An omitted head means `HEAD`. Only files in `files` (or deleted ones)
+    /// are reported.
+    pub fn diff_range(&self, range: &str, files: &[FilteredFile]) -> Result<DiffReport> {
+        let (base_spec, head_spec, symmetric) = match range.split_once("...") {
+            Some((base, head)) => (base, head, true),
+            None => match range.split_once("..") {
+                Some((base, head)) => (base, head, false),
+                None => (range, "", false),
+            },
+        };
+        let head_spec = if head_spec.is_empty() { "HEAD" } else { head_spec };
+
+        let head = self.resolve_commit(head_spec)?;
+        let mut base = self.resolve_commit(base_spec)?;
+        if symmetric {
+            let merge_base = self.repo.merge_base(base, head)?;
+            base = merge_base;
+        }
+
+        let base_tree = self.repo.find_commit(base)?.tree()?;
+        let head_tree = self.repo.find_commit(head)?.tree()?;
+
+        let mut options = DiffOptions::new();
+        options.context_lines(3);
+        let mut diff = self
+            .repo
+            .diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut options))?;
+        diff.find_similar(None)?;
+
+        let included: HashSet<&Path> = files.iter().map(|f| f.relative_path.as_path()).collect();
+        let mut changed = Vec::new();
+
+        for (idx, delta) in diff.deltas().enumerate() {
+            let status = match delta.status() {
+                Delta::Added => ChangeStatus::Added,
+                Delta::Deleted => ChangeStatus::Deleted,
+                Delta::Renamed => ChangeStatus::Renamed,
+                _ => ChangeStatus::Modified,
+            };
+            let path = match delta.new_file().path().or_else(|| delta.old_file().path()) {
+                Some(path) => path.to_path_buf(),
+                None => continue,
+            };
+            let Some(local) = self.local_path(&path) else {
+                continue;
+            };
+            if status != ChangeStatus::Deleted && !included.contains(local.as_path()) {
+                continue;
+            }
+
+            let (diff_text, additions, deletions) = match Patch::from_diff(&diff, idx)? {
+                Some(mut patch) => {
+                    let (_, additions, deletions) = patch.line_stats()?;
+                    let buf = patch.to_buf()?;
+                    (String::from_utf8_lossy(&buf).into_owned(), additions, deletions)
+                }
+                None => (String::new(), 0, 0),
+            };
+
+            let content = if status == ChangeStatus::Deleted {
+                None
+            } else {
+                blob_text(&self.repo, &head_tree, &path)
+            };
+
+            changed.push(ChangedFile {
+                path: local.display().to_string(),
+                old_path: (status == ChangeStatus::Renamed)
+                    .then(|| delta.old_file().path().map(|p| p.display().to_string()))
+                    .flatten(),
+                status,
+                additions,
+                deletions,
+                content,
+                diff: diff_text,
+            });
+        }
+
+        Ok(DiffReport {
+            base: base_spec.to_string(),
+            head: head_spec.to_string(),
+            files: changed,
+        })
+    }
+
+    /// Attaches authors, churn and last-modified metadata to `files` by
+    /// walking the history reachable from `HEAD`.
+    pub fn annotate(&self, files: &mut [FilteredFile]) -> Result<()> {
+        #[derive(Default)]
+        struct Accumulator {
+            commits: usize,
+            additions: usize,
+            deletions: usize,
+            last_modified: Option<DateTime<Utc>>,
+            last_author: Option<String>,
+            authors: HashMap<(String, String), usize>,
+        }
+
+        let wanted: HashSet<PathBuf> = files.iter().map(|f| f.relative_path.clone()).collect();
+        let mut stats: HashMap<PathBuf, Accumulator> = HashMap::new();
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        // Children before parents even when they share a timestamp
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if is_merge(&commit) {
+                continue;
+            }
+            let diff = self.commit_diff(&commit)?;
+            let author = commit.author();
+            let name = author.name().unwrap_or("unknown").to_string();
+            let email = author.email().unwrap_or_default().to_string();
+            let time = Utc.timestamp_opt(commit.time().seconds(), 0).single();
+
+            for (idx, delta) in diff.deltas().enumerate() {
+                let Some(path) = delta.new_file().path().and_then(|p| self.local_path(p)) else {
+                    continue;
+                };
+                if !wanted.contains(&path) {
+                    continue;
+                }
+
+                let (additions, deletions) = match Patch::from_diff(&diff, idx)? {
+                    Some(patch) => {
+                        let (_, additions, deletions) = patch.line_stats()?;
+                        (additions, deletions)
+                    }
+                    None => (0, 0),
+                };
+
+                let entry = stats.entry(path).or_default();
+                entry.commits += 1;
+                entry.additions += additions;
+                entry.deletions += deletions;
+                // Commits arrive newest first
+                if entry.last_modified.is_none() {
+                    entry.last_modified = time;
+                    entry.last_author = Some(name.clone());
+                }
+                *entry.authors.entry((name.clone(), email.clone())).or_insert(0) += 1;
+            }
+        }
+
+        for file in files {
+            let Some(acc) = stats.remove(&file.relative_path) else {
+                continue;
+            };
+            let mut authors: Vec<AuthorStats> = acc
+                .authors
+                .into_iter()
+                .map(|((name, email), commits)| AuthorStats { name, email, commits })
+                .collect();
+            authors.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+
+            file.history = Some(FileHistory {
+                commits: acc.commits,
+                additions: acc.additions,
+                deletions: acc.deletions,
+                churn: acc.additions + acc.deletions,
+                last_modified: acc.last_modified,
+                last_author: acc.last_author,
+                authors,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn resolve_commit(&self, spec: &str) -> Result<Oid> {
+        let commit = self
+            .repo
+            .revparse_single(spec)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Unknown revision '{}'", spec))?;
+        Ok(commit.id())
+    }
+
+    /// Diff of a commit against its first parent (or the empty tree).
+    fn commit_diff(&self, commit: &git2::Commit) -> Result<git2::Diff<'_>> {
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        Ok(self
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?)
+    }
+}
+
+fn blob_text(repo: &Repository, tree: &Tree, path: &Path) -> Option<String> {
+    let blob = tree
+        .get_path(path)
+        .ok()?
+        .to_object(repo)
+        .ok()?
+        .peel_to_blob()
+        .ok()?;
+    if blob.is_binary() {
+        return None;
+    }
+    String::from_utf8(blob.content().to_vec()).ok()
+}
+
+/// Parses `2024-05-01`, RFC 3339 timestamps and `<n> <unit>s ago`.
+pub fn parse_since_date(since: &str) -> Option<DateTime<Utc>> {
+    let since = since.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(since) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?));
+    }
+
+    let mut words = since.strip_suffix("ago")?.split_whitespace();
+    let amount: i64 = words.next()?.parse().ok()?;
+    let unit = words.next()?.trim_end_matches('s');
+    // Amounts too large for a date are rejected rather than overflowing
+    let duration = match unit {
+        "minute" => Duration::try_minutes(amount)?,
+        "hour" => Duration::try_hours(amount)?,
+        "day" => Duration::try_days(amount)?,
+        "week" => Duration::try_weeks(amount)?,
+        "month" => Duration::try_days(amount.checked_mul(30)?)?,
+        "year" => Duration::try_days(amount.checked_mul(365)?)?,
+        _ => return None,
+    };
+    if words.next().is_some() {
+        return None;
+    }
+
+    Utc::now().checked_sub_signed(duration)
+}
+
+/// Merge commits are skipped, as with `git log --no-merges`: diffed against
+/// their first parent they would repeat the merged branch's changes and
+/// credit them to whoever merged.
+fn is_merge(commit: &Commit) -> bool {
+    commit.parent_count() > 1
+}
+
+/// Fails with a clear message when `--since`, `--diff` or `--authors` is
+/// used on something that is not a Git checkout.
+pub fn require_repository(path: &Path) -> Result<()> {
+    if Repository::discover(path).is_err() {
+        return Err(anyhow!(
+            "--since, --diff and --authors need a Git repository, but {} is not one",
+            path.display()
+        ));
+    }
+    Ok(())
+}
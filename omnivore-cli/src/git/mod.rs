@@ -1,10 +1,18 @@
+pub mod budget;
+pub mod cache;
 pub mod command;
+pub mod dependencies;
 pub mod detector;
 pub mod filter;
+pub mod history;
 pub mod organizer;
+pub mod outline;
 pub mod output;
+pub mod secrets;
 pub mod source;
+pub mod stats;
 pub mod utils;
+pub mod workspace;
 
 pub use command::{execute_git_command, GitArgs};
 
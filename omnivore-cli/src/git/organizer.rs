@@ -2,8 +2,10 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::path::Path;
 
-use super::detector::CodebaseInfo;
+use super::dependencies::{Dependency, DependencyScope};
+use super::detector::{language_for_path, CodebaseInfo};
 use super::filter::FilteredFile;
+use super::workspace::WorkspaceMember;
 
 pub struct CodeOrganizer {
     codebase_info: CodebaseInfo,
@@ -68,6 +70,20 @@ impl CodeOrganizer {
                 .map(|b| format!("{:?}", b))
                 .collect(),
             total_files: self.files.len(),
+            dependencies: self.codebase_info.dependencies.clone(),
+            members: self
+                .codebase_info
+                .members
+                .iter()
+                .map(|member| {
+                    let files = self
+                        .files
+                        .iter()
+                        .filter(|f| f.relative_path.starts_with(&member.path))
+                        .count();
+                    (member.clone(), files)
+                })
+                .collect(),
         }
     }
 
@@ -89,7 +105,7 @@ impl CodeOrganizer {
         categories
     }
 
-    fn determine_category(&self, path: &Path) -> String {
+    pub fn determine_category(&self, path: &Path) -> String {
         let path_str = path.to_string_lossy().to_lowercase();
         let file_name = path
             .file_name()
@@ -201,7 +217,7 @@ impl CodeOrganizer {
         }
     }
 
-    fn get_section_priority(&self, section: &str) -> usize {
+    pub fn get_section_priority(&self, section: &str) -> usize {
         match section {
             "Documentation" => 0,
             "Configuration" => 1,
@@ -242,6 +258,9 @@ pub struct ProjectMetadata {
     pub frameworks: Vec<String>,
     pub build_tools: Vec<String>,
     pub total_files: usize,
+    pub dependencies: Vec<Dependency>,
+    /// Workspace members with the number of files in the report under each.
+    pub members: Vec<(WorkspaceMember, usize)>,
 }
 
 #[derive(Debug)]
@@ -287,6 +306,29 @@ Total Files:  {}
             self.metadata.total_files
         ));
 
+        if !self.metadata.members.is_empty() {
+            output.push_str("\nWORKSPACE MEMBERS\n");
+            output.push_str("-----------------\n\n");
+            for (member, files) in &self.metadata.members {
+                output.push_str(&format!("   • {} ({} files)\n", member.summary(), files));
+            }
+            output.push_str("\n================================================================================\n");
+        }
+
+        let direct = self.metadata.direct_dependencies();
+        if !direct.is_empty() {
+            output.push_str("\nDEPENDENCIES\n");
+            output.push_str("------------\n\n");
+            for dependency in &direct {
+                output.push_str(&format!("   • {}\n", describe_dependency(dependency)));
+            }
+            let transitive = self.metadata.dependencies.len() - direct.len();
+            if transitive > 0 {
+                output.push_str(&format!("\n   + {} transitive dependencies from lockfiles\n", transitive));
+            }
+            output.push_str("\n================================================================================\n");
+        }
+
         output.push_str("\nPROJECT STRUCTURE\n");
         output.push_str("-----------------\n\n");
 
@@ -328,11 +370,14 @@ Total Files:  {}
                         "┌─────────────────────────────────────────────────────────────────────────────┐\n"
                     ));
                     output.push_str(&format!("│ File: {}\n", file.relative_path.display()));
+                    if let Some(history) = &file.history {
+                        output.push_str(&format!("│ History: {}\n", history.summary()));
+                    }
                     output.push_str(&format!(
                         "└─────────────────────────────────────────────────────────────────────────────┘\n\n"
                     ));
 
-                    if let Ok(content) = std::fs::read_to_string(&file.path) {
+                    if let Ok(content) = file.read_content() {
                         let lines: Vec<&str> = content.lines().collect();
                         for (i, line) in lines.iter().enumerate() {
                             output.push_str(&format!("{:4} │ {}\n", i + 1, line));
@@ -361,6 +406,23 @@ Total Files:  {}
                 "frameworks": self.metadata.frameworks,
                 "build_tools": self.metadata.build_tools,
                 "total_files": self.metadata.total_files,
+                "dependencies": self.metadata.dependencies,
+                "members": self.metadata.members.iter().map(|(member, files)| {
+                    serde_json::json!({
+                        "name": member.name,
+                        "path": member.path,
+                        "kind": member.kind,
+                        "project_type": member.info.project_type,
+                        "description": member.info.description,
+                        "main_language": member.info.main_language,
+                        "languages": member.info.languages,
+                        "frameworks": member.info.frameworks,
+                        "build_tools": member.info.build_tools,
+                        "entry_points": member.info.entry_points,
+                        "dependencies": member.info.dependencies,
+                        "total_files": files,
+                    })
+                }).collect::<Vec<_>>(),
             },
             "sections": []
         });
@@ -376,11 +438,15 @@ Total Files:  {}
 
             let files = section_json["files"].as_array_mut().unwrap();
             for file in &section.files {
-                let content = std::fs::read_to_string(&file.path).unwrap_or_default();
-                files.push(serde_json::json!({
+                let content = file.read_content().unwrap_or_default();
+                let mut file_json = serde_json::json!({
                     "path": file.relative_path.display().to_string(),
                     "content": content
-                }));
+                });
+                if let Some(history) = &file.history {
+                    file_json["history"] = serde_json::to_value(history)?;
+                }
+                files.push(file_json);
             }
 
             sections.push(section_json);
@@ -388,4 +454,299 @@ Total Files:  {}
 
         Ok(serde_json::to_string_pretty(&json_output)?)
     }
-}
\ No newline at end of file
+
+    /// Markdown with a metadata header, a file tree and one fenced code block
+    /// per file, labeled with the file's language.
+    pub fn to_markdown(&self, line_numbers: bool) -> String {
+        let mut output = String::from("# Omnivore Code Analysis\n\n## Project\n\n");
+
+        for (label, value) in self.metadata.fields() {
+            output.push_str(&format!("- **{}:** {}\n", label, value));
+        }
+
+        if !self.metadata.members.is_empty() {
+            output.push_str("\n## Workspace Members\n\n| Path | Name | Kind | Description | Files |\n|------|------|------|-------------|-------|\n");
+            for (member, files) in &self.metadata.members {
+                output.push_str(&format!(
+                    "| `{}` | {} | {:?} | {} | {} |\n",
+                    member.path.display(),
+                    member.name,
+                    member.kind,
+                    member.info.description,
+                    files
+                ));
+            }
+        }
+
+        let direct = self.metadata.direct_dependencies();
+        if !direct.is_empty() {
+            output.push_str("\n## Dependencies\n\n| Name | Version | Scope | Ecosystem |\n|------|---------|-------|-----------|\n");
+            for dependency in &direct {
+                output.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    dependency.name,
+                    dependency_version(dependency),
+                    scope_label(dependency.scope),
+                    format!("{:?}", dependency.ecosystem).to_lowercase()
+                ));
+            }
+            let transitive = self.metadata.dependencies.len() - direct.len();
+            if transitive > 0 {
+                output.push_str(&format!("\nPlus {} transitive dependencies from lockfiles.\n", transitive));
+            }
+        }
+
+        output.push_str("\n## File Tree\n\n```text\n");
+        output.push_str(&self.file_tree());
+        output.push_str("```\n");
+
+        for section in &self.sections {
+            output.push_str(&format!("\n## {}\n\n{}\n", section.name, section.description));
+
+            for file in &section.files {
+                output.push_str(&format!("\n### `{}`\n\n", file.relative_path.display()));
+                if let Some(history) = &file.history {
+                    output.push_str(&format!("_History: {}_\n\n", history.summary()));
+                }
+
+                match file.read_content() {
+                    Ok(content) => {
+                        let content = number_lines(&content, line_numbers);
+                        // Fences must be longer than any backtick run in the file
+                        let fence = "`".repeat(longest_backtick_run(&content).max(2) + 1);
+                        output.push_str(&format!(
+                            "{}{}\n{}\n{}\n",
+                            fence,
+                            fence_language(&file.relative_path),
+                            content.trim_end_matches('\n'),
+                            fence
+                        ));
+                    }
+                    Err(_) => output.push_str("_Unable to read file content_\n"),
+                }
+            }
+        }
+
+        output
+    }
+
+    /// XML-tagged output for LLM prompts. File contents are included verbatim
+    /// inside `<file>` tags; only attribute values and metadata are escaped.
+    pub fn to_xml(&self, line_numbers: bool) -> String {
+        let mut output = String::from("<repository>\n<metadata>\n");
+
+        for (label, value) in self.metadata.fields() {
+            let tag = label.to_lowercase().replace(' ', "_");
+            output.push_str(&format!("<{0}>{1}</{0}>\n", tag, escape_xml(&value)));
+        }
+        output.push_str("</metadata>\n");
+
+        if !self.metadata.members.is_empty() {
+            output.push_str("\n<members>\n");
+            for (member, files) in &self.metadata.members {
+                output.push_str(&format!(
+                    "<member path=\"{}\" name=\"{}\" kind=\"{:?}\" files=\"{}\">{}</member>\n",
+                    escape_xml(&member.path.display().to_string()),
+                    escape_xml(&member.name),
+                    member.kind,
+                    files,
+                    escape_xml(&member.info.description)
+                ));
+            }
+            output.push_str("</members>\n");
+        }
+
+        let direct = self.metadata.direct_dependencies();
+        if !direct.is_empty() {
+            output.push_str(&format!(
+                "\n<dependencies transitive=\"{}\">\n",
+                self.metadata.dependencies.len() - direct.len()
+            ));
+            for dependency in &direct {
+                output.push_str(&format!(
+                    "<dependency name=\"{}\" version=\"{}\" scope=\"{}\" ecosystem=\"{}\"/>\n",
+                    escape_xml(&dependency.name),
+                    escape_xml(&dependency_version(dependency)),
+                    scope_label(dependency.scope),
+                    format!("{:?}", dependency.ecosystem).to_lowercase()
+                ));
+            }
+            output.push_str("</dependencies>\n");
+        }
+
+        output.push_str("\n<directory_structure>\n");
+        output.push_str(&self.file_tree());
+        output.push_str("</directory_structure>\n\n<files>\n");
+
+        for section in &self.sections {
+            for file in &section.files {
+                let Ok(content) = file.read_content() else {
+                    continue;
+                };
+
+                output.push_str(&format!(
+                    "<file path=\"{}\" language=\"{}\" section=\"{}\"",
+                    escape_xml(&file.relative_path.display().to_string()),
+                    escape_xml(&fence_language(&file.relative_path)),
+                    escape_xml(&section.name)
+                ));
+                if let Some(history) = &file.history {
+                    output.push_str(&format!(" history=\"{}\"", escape_xml(&history.summary())));
+                }
+                output.push_str(">\n");
+                output.push_str(number_lines(&content, line_numbers).trim_end_matches('\n'));
+                output.push_str("\n</file>\n");
+            }
+        }
+
+        output.push_str("</files>\n</repository>\n");
+        output
+    }
+
+    /// Indented tree of every file in the report.
+    fn file_tree(&self) -> String {
+        let mut paths: Vec<&Path> = self
+            .sections
+            .iter()
+            .flat_map(|s| s.files.iter().map(|f| f.relative_path.as_path()))
+            .collect();
+        paths.sort();
+
+        let mut tree = String::new();
+        let mut previous: Vec<String> = Vec::new();
+
+        for path in paths {
+            let mut parts: Vec<String> = path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            let Some(name) = parts.pop() else {
+                continue;
+            };
+
+            let shared = previous
+                .iter()
+                .zip(&parts)
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (depth, dir) in parts.iter().enumerate().skip(shared) {
+                tree.push_str(&format!("{}{}/\n", "  ".repeat(depth), dir));
+            }
+            tree.push_str(&format!("{}{}\n", "  ".repeat(parts.len()), name));
+            previous = parts;
+        }
+
+        tree
+    }
+}
+
+impl ProjectMetadata {
+    fn direct_dependencies(&self) -> Vec<&Dependency> {
+        self.dependencies.iter().filter(|d| d.direct).collect()
+    }
+
+    /// Label/value pairs shared by the Markdown and XML headers.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let list = |items: &[String]| {
+            if items.is_empty() {
+                "None detected".to_string()
+            } else {
+                items.join(", ")
+            }
+        };
+
+        vec![
+            ("Type", self.project_type.clone()),
+            ("Description", self.description.clone()),
+            ("Language", self.main_language.clone()),
+            ("Frameworks", list(&self.frameworks)),
+            ("Build Tools", list(&self.build_tools)),
+            ("Total Files", self.total_files.to_string()),
+        ]
+    }
+}
+
+fn scope_label(scope: DependencyScope) -> &'static str {
+    match scope {
+        DependencyScope::Runtime => "runtime",
+        DependencyScope::Dev => "dev",
+        DependencyScope::Build => "build",
+    }
+}
+
+/// `^1.2 → 1.2.4` when the lockfile pinned a version for the requirement.
+fn dependency_version(dependency: &Dependency) -> String {
+    match (&dependency.version, &dependency.resolved) {
+        (Some(version), Some(resolved)) if version != resolved => format!("{} → {}", version, resolved),
+        (_, Some(resolved)) => resolved.clone(),
+        (Some(version), None) => version.clone(),
+        (None, None) => "*".to_string(),
+    }
+}
+
+fn describe_dependency(dependency: &Dependency) -> String {
+    let scope = match dependency.scope {
+        DependencyScope::Runtime => String::new(),
+        scope => format!(" [{}]", scope_label(scope)),
+    };
+    format!("{} {}{}", dependency.name, dependency_version(dependency), scope)
+}
+
+/// Fence label for a file: the detected programming language, or a common
+/// name for config and markup formats.
+fn fence_language(path: &Path) -> String {
+    if let Some(language) = language_for_path(path) {
+        return language.fence_tag();
+    }
+
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if file_name == "Dockerfile" {
+        return "dockerfile".to_string();
+    }
+    if file_name == "Makefile" {
+        return "makefile".to_string();
+    }
+
+    match path.extension().and_then(|e| e.to_str()).unwrap_or_default() {
+        "md" | "markdown" => "markdown",
+        "yml" | "yaml" => "yaml",
+        "toml" => "toml",
+        "json" => "json",
+        "xml" => "xml",
+        "sql" => "sql",
+        "jsx" => "jsx",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        _ => "",
+    }
+    .to_string()
+}
+
+fn number_lines(content: &str, line_numbers: bool) -> String {
+    if !line_numbers {
+        return content.to_string();
+    }
+
+    let width = content.lines().count().to_string().len();
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>width$} | {}\n", i + 1, line, width = width))
+        .collect()
+}
+
+fn longest_backtick_run(content: &str) -> usize {
+    content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
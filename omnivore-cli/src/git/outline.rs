@@ -0,0 +1,448 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use tree_sitter::{Node, Parser, Tree};
+
+use super::detector::Language;
+use super::filter::FilteredFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Class,
+    Trait,
+    Interface,
+    Impl,
+    Module,
+    Type,
+}
+
+impl SymbolKind {
+    fn is_container(self) -> bool {
+        matches!(
+            self,
+            SymbolKind::Class
+                | SymbolKind::Trait
+                | SymbolKind::Interface
+                | SymbolKind::Impl
+                | SymbolKind::Module
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    pub signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    pub line: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Symbol>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileOutline {
+    pub path: String,
+    pub language: Language,
+    pub symbols: Vec<Symbol>,
+}
+
+/// Per-file symbol maps for the files of a codebase whose language has a
+/// grammar; other files are counted in `skipped`.
+#[derive(Debug, Serialize)]
+pub struct CodeOutline {
+    pub files: Vec<FileOutline>,
+    pub skipped: usize,
+}
+
+impl CodeOutline {
+    pub fn from_files(files: &[FilteredFile]) -> Self {
+        let mut outline = CodeOutline {
+            files: Vec::new(),
+            skipped: 0,
+        };
+
+        for file in files {
+            let parsed = file
+                .read_content()
+                .ok()
+                .and_then(|content| outline_file(&file.relative_path, &content).transpose())
+                .and_then(|result| result.ok());
+
+            match parsed {
+                Some(file_outline) => outline.files.push(file_outline),
+                None => outline.skipped += 1,
+            }
+        }
+
+        outline
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        fn count(symbols: &[Symbol]) -> usize {
+            symbols.iter().map(|s| 1 + count(&s.children)).sum()
+        }
+        self.files.iter().map(|f| count(&f.symbols)).sum()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+
+        for file in &self.files {
+            output.push_str(&format!("---\nFile: {} ({:?})\n---\n", file.path, file.language));
+            write_symbols(&mut output, &file.symbols, 0);
+            output.push('\n');
+        }
+
+        output
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize outline")
+    }
+}
+
+fn write_symbols(output: &mut String, symbols: &[Symbol], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for symbol in symbols {
+        output.push_str(&format!("{}L{:<5} {}\n", indent, symbol.line, symbol.signature));
+        if let Some(doc) = &symbol.doc {
+            // First paragraph only
+            let summary = doc.split("\n\n").next().unwrap_or_default().replace('\n', " ");
+            output.push_str(&format!("{}       {}\n", indent, summary));
+        }
+        write_symbols(output, &symbol.children, depth + 1);
+    }
+}
+
+/// The outline language for a file, by extension.
+pub fn outline_language(path: &Path) -> Option<Language> {
+    match path.extension()?.to_str()? {
+        "rs" => Some(Language::Rust),
+        "py" | "pyi" => Some(Language::Python),
+        "js" | "jsx" | "mjs" | "cjs" => Some(Language::JavaScript),
+        "ts" | "tsx" | "mts" | "cts" => Some(Language::TypeScript),
+        "go" => Some(Language::Go),
+        "java" => Some(Language::Java),
+        _ => None,
+    }
+}
+
+/// Parses `content` and returns its symbols, or `None` when the language of
+/// `path` is not supported.
+pub fn outline_file(path: &Path, content: &str) -> Result<Option<FileOutline>> {
+    let Some((language, tree)) = parse_source(path, content)? else {
+        return Ok(None);
+    };
+
+    let walker = Walker {
+        language: language.clone(),
+        source: content.as_bytes(),
+    };
+    let mut symbols = Vec::new();
+    walker.collect(tree.root_node(), false, &mut symbols);
+
+    Ok(Some(FileOutline {
+        path: path.display().to_string(),
+        language,
+        symbols,
+    }))
+}
+
+/// Parses `content` with the tree-sitter grammar for `path`, or returns
+/// `None` when the language is not supported.
+pub fn parse_source(path: &Path, content: &str) -> Result<Option<(Language, Tree)>> {
+    let Some(language) = outline_language(path) else {
+        return Ok(None);
+    };
+
+    let grammar: tree_sitter::Language = match language {
+        Language::Rust => tree_sitter_rust::LANGUAGE.into(),
+        Language::Python => tree_sitter_python::LANGUAGE.into(),
+        Language::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+        Language::TypeScript if path.extension().is_some_and(|e| e == "tsx") => {
+            tree_sitter_typescript::LANGUAGE_TSX.into()
+        }
+        Language::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        Language::Go => tree_sitter_go::LANGUAGE.into(),
+        Language::Java => tree_sitter_java::LANGUAGE.into(),
+        _ => return Ok(None),
+    };
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&grammar)
+        .context("Failed to load grammar")?;
+    let tree = parser
+        .parse(content, None)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    Ok(Some((language, tree)))
+}
+
+struct Walker<'a> {
+    language: Language,
+    source: &'a [u8],
+}
+
+impl Walker<'_> {
+    fn collect(&self, node: Node, in_type: bool, symbols: &mut Vec<Symbol>) {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            match self.classify(child, in_type) {
+                Some(kind) => symbols.push(self.symbol(child, kind)),
+                None if self.is_wrapper(child) => self.collect(child, in_type, symbols),
+                None => {}
+            }
+        }
+    }
+
+    /// Declarations that only wrap the symbol we are interested in.
+    fn is_wrapper(&self, node: Node) -> bool {
+        matches!(
+            (&self.language, node.kind()),
+            (Language::Python, "decorated_definition")
+                | (
+                    Language::JavaScript | Language::TypeScript,
+                    "export_statement" | "lexical_declaration" | "variable_declaration" | "ambient_declaration"
+                )
+                | (Language::Go, "type_declaration")
+        )
+    }
+
+    fn classify(&self, node: Node, in_type: bool) -> Option<SymbolKind> {
+        let function = if in_type {
+            SymbolKind::Method
+        } else {
+            SymbolKind::Function
+        };
+
+        let kind = match (&self.language, node.kind()) {
+            (Language::Rust, "function_item" | "function_signature_item") => function,
+            (Language::Rust, "struct_item" | "union_item") => SymbolKind::Struct,
+            (Language::Rust, "enum_item") => SymbolKind::Enum,
+            (Language::Rust, "trait_item") => SymbolKind::Trait,
+            (Language::Rust, "impl_item") => SymbolKind::Impl,
+            (Language::Rust, "mod_item") => SymbolKind::Module,
+            (Language::Rust, "type_item") => SymbolKind::Type,
+
+            (Language::Python, "function_definition") => function,
+            (Language::Python, "class_definition") => SymbolKind::Class,
+
+            (Language::JavaScript | Language::TypeScript, kind) => match kind {
+                "function_declaration" | "generator_function_declaration" => function,
+                "method_definition" | "method_signature" | "abstract_method_signature" => SymbolKind::Method,
+                "class_declaration" | "abstract_class_declaration" => SymbolKind::Class,
+                "interface_declaration" => SymbolKind::Interface,
+                "type_alias_declaration" => SymbolKind::Type,
+                "enum_declaration" => SymbolKind::Enum,
+                "internal_module" | "module" => SymbolKind::Module,
+                "variable_declarator" => {
+                    let value = node.child_by_field_name("value")?;
+                    match value.kind() {
+                        "arrow_function" | "function_expression" | "function" => function,
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            },
+
+            (Language::Go, "function_declaration") => SymbolKind::Function,
+            (Language::Go, "method_declaration") => SymbolKind::Method,
+            (Language::Go, "type_spec") => match node.child_by_field_name("type")?.kind() {
+                "struct_type" => SymbolKind::Struct,
+                "interface_type" => SymbolKind::Interface,
+                _ => SymbolKind::Type,
+            },
+
+            (Language::Java, "method_declaration" | "constructor_declaration") => SymbolKind::Method,
+            (Language::Java, "class_declaration" | "record_declaration") => SymbolKind::Class,
+            (Language::Java, "interface_declaration" | "annotation_type_declaration") => {
+                SymbolKind::Interface
+            }
+            (Language::Java, "enum_declaration") => SymbolKind::Enum,
+
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    fn symbol(&self, node: Node, kind: SymbolKind) -> Symbol {
+        let body = self.body(node);
+
+        let mut children = Vec::new();
+        if kind.is_container() {
+            if let Some(body) = body {
+                self.collect(body, kind != SymbolKind::Module, &mut children);
+            }
+        }
+
+        Symbol {
+            kind,
+            name: self.name(node),
+            signature: self.signature(node, kind, body),
+            doc: self.doc(node, body),
+            line: node.start_position().row + 1,
+            children,
+        }
+    }
+
+    fn text(&self, node: Node) -> &str {
+        node.utf8_text(self.source).unwrap_or_default()
+    }
+
+    fn body<'t>(&self, node: Node<'t>) -> Option<Node<'t>> {
+        match node.kind() {
+            "variable_declarator" => node
+                .child_by_field_name("value")
+                .and_then(|value| value.child_by_field_name("body")),
+            "type_spec" => node
+                .child_by_field_name("type")
+                .filter(|t| matches!(t.kind(), "struct_type" | "interface_type")),
+            _ => node.child_by_field_name("body"),
+        }
+    }
+
+    fn name(&self, node: Node) -> String {
+        if node.kind() == "impl_item" {
+            let target = node
+                .child_by_field_name("type")
+                .map(|t| self.text(t))
+                .unwrap_or_default();
+            return match node.child_by_field_name("trait") {
+                Some(trait_node) => format!("{} for {}", self.text(trait_node), target),
+                None => target.to_string(),
+            };
+        }
+
+        node.child_by_field_name("name")
+            .map(|n| self.text(n).to_string())
+            .unwrap_or_default()
+    }
+
+    /// The declaration up to (not including) its body, on one line.
+    fn signature(&self, node: Node, kind: SymbolKind, body: Option<Node>) -> String {
+        let start = match node.parent() {
+            // `const handler = async (req) => ...` keeps its `const`
+            Some(parent) if node.kind() == "variable_declarator" => parent.start_byte(),
+            _ => node.start_byte(),
+        };
+
+        let raw = match (node.kind(), body) {
+            ("type_spec", Some(_)) => {
+                let keyword = if kind == SymbolKind::Struct {
+                    "struct"
+                } else {
+                    "interface"
+                };
+                format!("type {} {}", self.name(node), keyword)
+            }
+            ("type_spec", None) => format!("type {}", self.text(node)),
+            (_, Some(body)) => String::from_utf8_lossy(&self.source[start..body.start_byte()]).into_owned(),
+            (_, None) => String::from_utf8_lossy(&self.source[start..node.end_byte()]).into_owned(),
+        };
+
+        let signature = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        signature
+            .trim_end_matches(|c: char| c == '{' || c == ':' || c == ';' || c == '=' || c.is_whitespace())
+            .trim_end_matches("=>")
+            .trim_end()
+            .to_string()
+    }
+
+    fn doc(&self, node: Node, body: Option<Node>) -> Option<String> {
+        if self.language == Language::Python {
+            return self.docstring(body?);
+        }
+
+        // Comments sit before the outermost wrapper (`export`, `const`, ...)
+        let mut anchor = node;
+        while let Some(parent) = anchor.parent() {
+            if self.is_wrapper(parent) {
+                anchor = parent;
+            } else {
+                break;
+            }
+        }
+
+        let mut lines = Vec::new();
+        let mut next_row = anchor.start_position().row;
+        let mut sibling = anchor.prev_sibling();
+
+        while let Some(prev) = sibling {
+            if prev.end_position().row + 1 < next_row {
+                break;
+            }
+            if prev.kind() == "attribute_item" {
+                next_row = prev.start_position().row;
+                sibling = prev.prev_sibling();
+                continue;
+            }
+            if !prev.kind().contains("comment") {
+                break;
+            }
+
+            let text = self.text(prev).trim();
+            let is_doc = match self.language {
+                Language::Rust => text.starts_with("///") || text.starts_with("/**"),
+                Language::Go => true,
+                _ => text.starts_with("/**"),
+            };
+            if !is_doc {
+                break;
+            }
+
+            lines.insert(0, clean_comment(text));
+            next_row = prev.start_position().row;
+            sibling = prev.prev_sibling();
+        }
+
+        let doc = lines.join("\n").trim().to_string();
+        (!doc.is_empty()).then_some(doc)
+    }
+
+    fn docstring(&self, body: Node) -> Option<String> {
+        let first = body.named_child(0)?;
+        if first.kind() != "expression_statement" {
+            return None;
+        }
+        let string = first.named_child(0).filter(|n| n.kind() == "string")?;
+        let text = self.text(string);
+        let doc = text
+            .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+            .trim_matches(|c| c == '"' || c == '\'')
+            .trim();
+        let doc = doc
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n");
+        (!doc.is_empty()).then_some(doc)
+    }
+}
+
+/// Strips comment markers from a doc comment, keeping blank lines between
+/// paragraphs.
+fn clean_comment(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line
+                .trim_start_matches("///")
+                .trim_start_matches("//")
+                .trim_start_matches("/**")
+                .trim_end_matches("*/");
+            line.trim_start_matches('*').trim()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
@@ -3,29 +3,37 @@ use encoding_rs::UTF_8;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{self, Write};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
+use super::detector::{CodebaseDetector, CodebaseInfo};
 use super::filter::FilteredFile;
+use super::history::FileHistory;
+use super::organizer::CodeOrganizer;
 
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
     Json,
     Text,
     Directory,
+    Markdown,
+    Xml,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileContent {
     pub path: String,
     pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<FileHistory>,
 }
 
 pub struct OutputWriter {
     format: OutputFormat,
-    #[allow(dead_code)]
     root_path: PathBuf,
     output_path: Option<PathBuf>,
     force_stdout: bool,
+    codebase_info: Option<CodebaseInfo>,
+    line_numbers: bool,
 }
 
 impl OutputWriter {
@@ -35,9 +43,21 @@ impl OutputWriter {
             root_path,
             output_path: None,
             force_stdout: false,
+            codebase_info: None,
+            line_numbers: false,
         }
     }
 
+    /// Project information for the Markdown and XML headers; detected from
+    /// the root path when not set.
+    pub fn set_codebase_info(&mut self, info: CodebaseInfo) {
+        self.codebase_info = Some(info);
+    }
+
+    pub fn set_line_numbers(&mut self, line_numbers: bool) {
+        self.line_numbers = line_numbers;
+    }
+
     pub fn set_output_path(&mut self, path: PathBuf) {
         self.output_path = Some(path);
     }
@@ -52,7 +72,32 @@ impl OutputWriter {
             OutputFormat::Json => self.write_json(files).await,
             OutputFormat::Text => self.write_text(files).await,
             OutputFormat::Directory => self.write_directory(files).await,
+            OutputFormat::Markdown | OutputFormat::Xml => self.write_organized(files).await,
+        }
+    }
+
+    async fn write_organized(&self, files: Vec<FilteredFile>) -> Result<usize> {
+        let info = match &self.codebase_info {
+            Some(info) => info.clone(),
+            None => CodebaseDetector::new(self.root_path.clone()).detect()?,
+        };
+        let organized = CodeOrganizer::new(info, files).organize();
+
+        let output = match self.format {
+            OutputFormat::Xml => organized.to_xml(self.line_numbers),
+            _ => organized.to_markdown(self.line_numbers),
+        };
+
+        if self.force_stdout || self.output_path.is_none() {
+            print!("{}", output);
+            io::stdout().flush()?;
+        } else if let Some(ref output_path) = self.output_path {
+            tokio::fs::write(output_path, output)
+                .await
+                .context("Failed to write output to file")?;
         }
+
+        Ok(organized.metadata.total_files)
     }
 
     async fn write_json(&self, files: Vec<FilteredFile>) -> Result<usize> {
@@ -60,10 +105,11 @@ impl OutputWriter {
         let mut count = 0;
 
         for file in files {
-            if let Ok(content) = read_file_content(&file.path) {
+            if let Ok(content) = read_file_content(&file) {
                 file_contents.push(FileContent {
                     path: file.relative_path.display().to_string(),
                     content,
+                    history: file.history,
                 });
                 count += 1;
             }
@@ -89,10 +135,16 @@ impl OutputWriter {
         let mut count = 0;
 
         for file in files {
-            if let Ok(content) = read_file_content(&file.path) {
+            if let Ok(content) = read_file_content(&file) {
+                let history = file
+                    .history
+                    .as_ref()
+                    .map(|h| format!("History: {}\n", h.summary()))
+                    .unwrap_or_default();
                 output.push_str(&format!(
-                    "---\nFile: {}\n---\n{}\n",
+                    "---\nFile: {}\n{}---\n{}\n",
                     file.relative_path.display(),
+                    history,
                     content
                 ));
                 count += 1;
@@ -139,8 +191,12 @@ impl OutputWriter {
                     .context("Failed to create parent directories")?;
             }
 
-            match tokio::fs::copy(&file.path, &dest_path).await {
-                Ok(_) => count += 1,
+            let copied = match &file.content {
+                Some(content) => tokio::fs::write(&dest_path, content).await,
+                None => tokio::fs::copy(&file.path, &dest_path).await.map(|_| ()),
+            };
+            match copied {
+                Ok(()) => count += 1,
                 Err(e) => {
                     eprintln!(
                         "Warning: Failed to copy {}: {}",
@@ -155,7 +211,12 @@ impl OutputWriter {
     }
 }
 
-fn read_file_content(path: &Path) -> Result<String> {
+fn read_file_content(file: &FilteredFile) -> Result<String> {
+    if let Some(content) = &file.content {
+        return Ok(content.clone());
+    }
+
+    let path = file.path.as_path();
     let bytes = fs::read(path).context("Failed to read file")?;
     
     let (cow, _, had_errors) = UTF_8.decode(&bytes);
@@ -1,11 +1,16 @@
 use anyhow::{anyhow, Context, Result};
-use git2::{build::RepoBuilder, Cred, FetchOptions, RemoteCallbacks};
-use std::path::{Path, PathBuf};
+use git2::{
+    build::{CheckoutBuilder, RepoBuilder},
+    Cred, Direction, ErrorClass, FetchOptions, Oid, RemoteCallbacks, Repository,
+};
+use std::path::{Component, Path, PathBuf};
 use tempfile::TempDir;
 use url::Url;
 use colored::*;
 use std::io::{self, Write};
 
+use super::cache::{CacheLock, RepoCache};
+
 #[derive(Debug, Clone)]
 pub enum SourceType {
     Remote(String),
@@ -19,6 +24,7 @@ impl SourceType {
             || source.starts_with("https://")
             || source.starts_with("git@")
             || source.starts_with("ssh://")
+            || source.starts_with("file://")
         {
             Ok(SourceType::Remote(source.to_string()))
         } else {
@@ -31,6 +37,12 @@ impl SourceType {
             };
             
             if resolved_path.is_dir() {
+                // Bare repositories (mirrors) have no working tree to read, so
+                // they are cloned like remotes
+                if Repository::open_bare(&resolved_path).is_ok() {
+                    return Ok(SourceType::Remote(resolved_path.canonicalize()?.display().to_string()));
+                }
+
                 let git_dir = resolved_path.join(".git");
                 if !git_dir.exists() {
                     // Ask for confirmation to proceed with non-git directory
@@ -74,11 +86,79 @@ impl SourceType {
     }
 }
 
+/// A repository URL that points into its tree, such as
+/// `https://github.com/owner/repo/tree/<ref>/<path>` or the GitLab
+/// `/-/tree/` equivalent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeUrl {
+    pub repo_url: String,
+    /// Everything after `tree/`. Branch names may contain slashes, so where the
+    /// ref ends and the path begins is only known once the remote's refs are
+    /// listed (see [`TreeUrl::split`]).
+    pub segments: Vec<String>,
+}
+
+impl TreeUrl {
+    pub fn parse(source: &str) -> Option<Self> {
+        let url = Url::parse(source).ok()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        let tree = segments.iter().position(|s| *s == "tree")?;
+        // owner/repo/tree/... or group/project/-/tree/...
+        let repo_end = if tree > 0 && segments[tree - 1] == "-" { tree - 1 } else { tree };
+        if repo_end < 2 || tree + 1 >= segments.len() {
+            return None;
+        }
+
+        let mut repo_url = url.clone();
+        repo_url.set_path(&segments[..repo_end].join("/"));
+        repo_url.set_query(None);
+        repo_url.set_fragment(None);
+
+        Some(Self {
+            repo_url: repo_url.to_string(),
+            segments: segments[tree + 1..]
+                .iter()
+                .map(|s| {
+                    percent_encoding::percent_decode_str(s)
+                        .decode_utf8_lossy()
+                        .into_owned()
+                })
+                .collect(),
+        })
+    }
+
+    /// Splits the segments into a ref and an optional subdirectory, taking the
+    /// longest leading run of segments that names one of `refs` (short branch
+    /// or tag names). Without a match the first segment is the ref, which
+    /// covers commit SHAs.
+    pub fn split(&self, refs: &[String]) -> (String, Option<PathBuf>) {
+        let ref_len = (1..=self.segments.len())
+            .rev()
+            .find(|&n| refs.contains(&self.segments[..n].join("/")))
+            .unwrap_or(1);
+
+        let subdir = (ref_len < self.segments.len())
+            .then(|| self.segments[ref_len..].iter().collect::<PathBuf>());
+        (self.segments[..ref_len].join("/"), subdir)
+    }
+}
+
 pub struct SourceAcquisition {
     source_type: SourceType,
     depth: u32,
     keep_temp: bool,
     temp_dir: Option<TempDir>,
+    reference: Option<String>,
+    subdir: Option<PathBuf>,
+    tree_url: Option<TreeUrl>,
+    revision: Option<String>,
+    cache: Option<RepoCache>,
+    cache_lock: Option<CacheLock>,
+    cache_hit: Option<bool>,
 }
 
 impl SourceAcquisition {
@@ -88,59 +168,118 @@ impl SourceAcquisition {
             depth,
             keep_temp,
             temp_dir: None,
+            reference: None,
+            subdir: None,
+            tree_url: None,
+            revision: None,
+            cache: None,
+            cache_lock: None,
+            cache_hit: None,
         }
     }
 
+    /// Clones remote repositories into `cache`, or updates the cached clone
+    /// with an incremental fetch, instead of cloning into a temporary
+    /// directory.
+    pub fn set_cache(&mut self, cache: RepoCache) {
+        self.cache = Some(cache);
+    }
+
+    /// Whether an existing cached clone was reused (`Some(true)`) or a new
+    /// one was made (`Some(false)`); `None` when the cache was not used.
+    pub fn cache_hit(&self) -> Option<bool> {
+        self.cache_hit
+    }
+
+    /// Checks out a branch, tag or commit instead of the default branch.
+    pub fn set_reference(&mut self, reference: String) {
+        self.reference = Some(reference);
+    }
+
+    /// Restricts the checkout, and the returned path, to a subdirectory.
+    pub fn set_subdir(&mut self, subdir: PathBuf) {
+        self.subdir = Some(subdir);
+    }
+
+    /// Takes the ref and subdirectory from a `/tree/` URL.
+    pub fn set_tree_url(&mut self, tree_url: TreeUrl) {
+        self.tree_url = Some(tree_url);
+    }
+
+    /// The ref and commit that were checked out, once acquired.
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
     pub async fn acquire(&mut self) -> Result<PathBuf> {
-        match self.source_type.clone() {
-            SourceType::Remote(url) => self.clone_remote(&url).await,
-            SourceType::Local(path) => Ok(path),
-            SourceType::LocalNonGit(path) => Ok(path),
+        let root = match self.source_type.clone() {
+            SourceType::Remote(url) => self.clone_remote(&url).await?,
+            SourceType::Local(path) | SourceType::LocalNonGit(path) => {
+                if self.reference.is_some() {
+                    return Err(anyhow!(
+                        "--ref is only supported for remote repositories; check out the revision locally instead"
+                    ));
+                }
+                path
+            }
+        };
+
+        match &self.subdir {
+            Some(subdir) => {
+                validate_subdir(subdir)?;
+                let path = root.join(subdir);
+                if !path.is_dir() {
+                    return Err(anyhow!(
+                        "Subdirectory '{}' not found in the repository",
+                        subdir.display()
+                    ));
+                }
+                Ok(path)
+            }
+            None => Ok(root),
         }
     }
 
     async fn clone_remote(&mut self, url: &str) -> Result<PathBuf> {
+        if let Some(cache) = self.cache.clone() {
+            if let Some(path) = self.clone_cached(url, &cache).await? {
+                return Ok(path);
+            }
+            println!(
+                "{}",
+                "Cached clone is in use by another run; cloning into a temporary directory".yellow()
+            );
+        }
+
         let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
         let repo_path = temp_dir.path().to_path_buf();
 
         let url_str = url.to_string();
         let repo_path_clone = repo_path.clone();
-        let depth = self.depth;
-        
-        let clone_result = tokio::task::spawn_blocking(move || {
-            let mut callbacks = RemoteCallbacks::new();
-            callbacks.credentials(|_url, username_from_url, _allowed_types| {
-                if let Ok(home) = std::env::var("HOME") {
-                    let ssh_key = PathBuf::from(&home).join(".ssh/id_rsa");
-                    if ssh_key.exists() {
-                        return Cred::ssh_key(
-                            username_from_url.unwrap_or("git"),
-                            None,
-                            &ssh_key,
-                            None,
-                        );
-                    }
-                    
-                    let ssh_key = PathBuf::from(&home).join(".ssh/id_ed25519");
-                    if ssh_key.exists() {
-                        return Cred::ssh_key(
-                            username_from_url.unwrap_or("git"),
-                            None,
-                            &ssh_key,
-                            None,
-                        );
-                    }
-                }
-                
-                Cred::default()
-            });
+        let depth = self.fetch_depth(url);
 
-            callbacks.certificate_check(|_cert, _host| {
-                Ok(git2::CertificateCheckStatus::CertificateOk)
-            });
+        if self.reference.is_some() || self.subdir.is_some() || self.tree_url.is_some() {
+            let request = RevisionRequest {
+                reference: self.reference.clone(),
+                subdir: self.subdir.clone(),
+                tree_url: self.tree_url.clone(),
+            };
+            let checkout = tokio::task::spawn_blocking(move || {
+                fetch_revision(&url_str, &repo_path_clone, depth, request)
+            })
+            .await
+            .context("Failed to spawn blocking task")?
+            .map_err(|e| clone_error(e, url))?;
 
+            self.subdir = checkout.subdir;
+            self.revision = Some(checkout.revision);
+            self.temp_dir = Some(temp_dir);
+            return Ok(repo_path);
+        }
+
+        let clone_result = tokio::task::spawn_blocking(move || {
             let mut fetch_options = FetchOptions::new();
-            fetch_options.remote_callbacks(callbacks);
+            fetch_options.remote_callbacks(remote_callbacks());
             fetch_options.depth(depth as i32);
 
             let mut builder = RepoBuilder::new();
@@ -155,18 +294,64 @@ impl SourceAcquisition {
                 self.temp_dir = Some(temp_dir);
                 Ok(self.temp_dir.as_ref().unwrap().path().to_path_buf())
             }
-            Err(e) => {
-                if e.message().contains("authentication") || e.message().contains("401") {
-                    Err(anyhow!(
-                        "Authentication failed. Please ensure your Git credentials (SSH key, etc.) are configured correctly.\nError: {}",
-                        e
-                    ))
-                } else if e.message().contains("not found") || e.message().contains("404") {
-                    Err(anyhow!("Repository not found: {}", url))
-                } else {
-                    Err(anyhow!("Failed to clone repository: {}", e))
-                }
+            Err(e) => Err(clone_error(e, url)),
+        }
+    }
+
+    /// Fetches into the cached clone for `url`, creating it on first use.
+    /// Returns `None` when another run holds the clone.
+    async fn clone_cached(&mut self, url: &str, cache: &RepoCache) -> Result<Option<PathBuf>> {
+        let Some(lock) = cache.lock(url)? else {
+            return Ok(None);
+        };
+        let repo_path = cache.checkout_path(url);
+        let reused = repo_path.join(".git").exists();
+
+        let attempt = || {
+            let url_str = url.to_string();
+            let repo_path = repo_path.clone();
+            let depth = self.fetch_depth(url);
+            let request = RevisionRequest {
+                reference: self.reference.clone(),
+                subdir: self.subdir.clone(),
+                tree_url: self.tree_url.clone(),
+            };
+            tokio::task::spawn_blocking(move || fetch_revision(&url_str, &repo_path, depth, request))
+        };
+
+        let checkout = match attempt().await.context("Failed to spawn blocking task")? {
+            Ok(checkout) => checkout,
+            // A clone left broken by an interrupted run is made again from
+            // scratch; a bad ref or a network error leaves it as it is
+            Err(e) if reused && is_broken_clone(&repo_path, &e) => {
+                std::fs::remove_dir_all(&repo_path)
+                    .with_context(|| format!("Failed to remove {}", repo_path.display()))?;
+                attempt()
+                    .await
+                    .context("Failed to spawn blocking task")?
+                    .map_err(|e| clone_error(e, url))?
             }
+            Err(e) => return Err(clone_error(e, url)),
+        };
+
+        self.subdir = checkout.subdir;
+        self.revision = Some(checkout.revision);
+        self.cache_lock = Some(lock);
+        self.cache_hit = Some(reused);
+
+        cache.touch(url)?;
+        cache.prune(None)?;
+
+        Ok(Some(repo_path))
+    }
+
+    /// The local transport cannot make shallow clones, and copying a local
+    /// repository in full is cheap anyway.
+    fn fetch_depth(&self, url: &str) -> u32 {
+        if url.starts_with("file://") || Path::new(url).is_absolute() {
+            0
+        } else {
+            self.depth
         }
     }
 
@@ -184,6 +369,201 @@ impl SourceAcquisition {
     }
 }
 
+struct RevisionRequest {
+    reference: Option<String>,
+    subdir: Option<PathBuf>,
+    tree_url: Option<TreeUrl>,
+}
+
+struct Checkout {
+    revision: String,
+    subdir: Option<PathBuf>,
+}
+
+/// Fetches a single ref (or commit) instead of cloning every branch, and
+/// checks out only `subdir` when one is given. An existing repository at
+/// `path` is updated in place, fetching only what it does not have yet.
+fn fetch_revision(
+    url: &str,
+    path: &Path,
+    depth: u32,
+    request: RevisionRequest,
+) -> std::result::Result<Checkout, git2::Error> {
+    let repo = match Repository::open(path) {
+        Ok(repo) => repo,
+        Err(_) => Repository::init(path)?,
+    };
+    let mut remote = match repo.find_remote("origin") {
+        Ok(remote) if remote.url() == Some(url) => remote,
+        Ok(_) => {
+            repo.remote_set_url("origin", url)?;
+            repo.find_remote("origin")?
+        }
+        Err(_) => repo.remote("origin", url)?,
+    };
+
+    // List the remote's refs without downloading anything
+    let (heads, default_branch) = {
+        let connection = remote.connect_auth(Direction::Fetch, Some(remote_callbacks()), None)?;
+        let heads: Vec<(String, Oid)> = connection
+            .list()?
+            .iter()
+            .map(|head| (head.name().to_string(), head.oid()))
+            .collect();
+        let default_branch = connection
+            .default_branch()
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_string));
+        (heads, default_branch)
+    };
+
+    let (reference, subdir) = match request.tree_url {
+        Some(tree_url) => {
+            let names: Vec<String> = heads
+                .iter()
+                .filter_map(|(name, _)| {
+                    name.strip_prefix("refs/heads/")
+                        .or_else(|| name.strip_prefix("refs/tags/"))
+                        .map(|n| n.trim_end_matches("^{}").to_string())
+                })
+                .collect();
+            let (reference, subdir) = tree_url.split(&names);
+            (Some(reference), subdir)
+        }
+        None => (request.reference, request.subdir),
+    };
+
+    let mut depth = depth;
+    let (refspec, local_ref) = match reference.as_deref() {
+        None => {
+            let branch = default_branch.unwrap_or_else(|| "HEAD".to_string());
+            (branch.clone(), None)
+        }
+        Some(name) => {
+            let candidates = [
+                name.to_string(),
+                format!("refs/heads/{}", name),
+                format!("refs/tags/{}", name),
+            ];
+            match candidates.iter().find(|c| heads.iter().any(|(n, _)| n == *c)) {
+                Some(full) if full.starts_with("refs/tags/") => {
+                    (format!("+{0}:{0}", full), Some(full.clone()))
+                }
+                Some(full) => {
+                    let local = format!("refs/remotes/origin/{}", full.trim_start_matches("refs/heads/"));
+                    (format!("+{}:{}", full, local), Some(local))
+                }
+                None if name.len() == 40 && name.chars().all(|c| c.is_ascii_hexdigit()) => {
+                    (name.to_string(), None)
+                }
+                None => {
+                    // An abbreviated SHA can only be resolved against the full history
+                    depth = 0;
+                    ("+refs/heads/*:refs/remotes/origin/*".to_string(), None)
+                }
+            }
+        }
+    };
+
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(remote_callbacks());
+    // Depth 0 on a shallow clone means "fetch the rest of the history"
+    fetch_options.depth(if depth == 0 && repo.is_shallow() { i32::MAX } else { depth as i32 });
+    remote.fetch(&[refspec.as_str()], Some(&mut fetch_options), None)?;
+
+    let commit = match (&local_ref, reference.as_deref()) {
+        (Some(local), _) => repo.find_reference(local)?.peel_to_commit()?,
+        (None, Some(name)) => repo.revparse_single(name)?.peel_to_commit()?,
+        (None, None) => repo
+            .find_reference("FETCH_HEAD")?
+            .peel_to_commit()?,
+    };
+
+    let mut checkout = CheckoutBuilder::new();
+    // Reused clones may have files left from the previous checkout
+    checkout.force().remove_untracked(true);
+    if let Some(subdir) = &subdir {
+        checkout.path(subdir.to_string_lossy().trim_end_matches('/').to_string());
+    }
+    repo.checkout_tree(commit.as_object(), Some(&mut checkout))?;
+    repo.set_head_detached(commit.id())?;
+
+    let label = reference
+        .or_else(|| refspec.strip_prefix("refs/heads/").map(str::to_string))
+        .unwrap_or(refspec);
+    Ok(Checkout {
+        revision: format!("{} ({})", label, &commit.id().to_string()[..7]),
+        subdir,
+    })
+}
+
+fn remote_callbacks() -> RemoteCallbacks<'static> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|_url, username_from_url, _allowed_types| {
+        if let Ok(home) = std::env::var("HOME") {
+            let ssh_key = PathBuf::from(&home).join(".ssh/id_rsa");
+            if ssh_key.exists() {
+                return Cred::ssh_key(
+                    username_from_url.unwrap_or("git"),
+                    None,
+                    &ssh_key,
+                    None,
+                );
+            }
+            
+            let ssh_key = PathBuf::from(&home).join(".ssh/id_ed25519");
+            if ssh_key.exists() {
+                return Cred::ssh_key(
+                    username_from_url.unwrap_or("git"),
+                    None,
+                    &ssh_key,
+                    None,
+                );
+            }
+        }
+        
+        Cred::default()
+    });
+
+    callbacks.certificate_check(|_cert, _host| {
+        Ok(git2::CertificateCheckStatus::CertificateOk)
+    });
+
+    callbacks
+}
+
+/// Whether `e`, from fetching into the existing clone at `path`, means the
+/// clone itself is damaged.
+fn is_broken_clone(path: &Path, e: &git2::Error) -> bool {
+    Repository::open(path).is_err() || matches!(e.class(), ErrorClass::Odb | ErrorClass::Repository)
+}
+
+fn clone_error(e: git2::Error, url: &str) -> anyhow::Error {
+    if e.message().contains("authentication") || e.message().contains("401") {
+        anyhow!(
+            "Authentication failed. Please ensure your Git credentials (SSH key, etc.) are configured correctly.\nError: {}",
+            e
+        )
+    } else if e.message().contains("not found") || e.message().contains("404") {
+        anyhow!("Repository or revision not found: {} ({})", url, e.message())
+    } else {
+        anyhow!("Failed to clone repository: {}", e)
+    }
+}
+
+fn validate_subdir(subdir: &Path) -> Result<()> {
+    if subdir
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(anyhow!(
+            "--subdir must be a relative path inside the repository: {}",
+            subdir.display()
+        ));
+    }
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub fn is_git_repository(path: &Path) -> bool {
     path.join(".git").exists()
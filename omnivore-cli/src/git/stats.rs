@@ -0,0 +1,391 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tree_sitter::Node;
+
+use super::detector::{language_for_path, CodebaseInfo, Language};
+use super::filter::FilteredFile;
+use super::organizer::CodeOrganizer;
+use super::outline::parse_source;
+
+/// How many entries the largest and most complex file lists keep.
+const TOP_FILES: usize = 10;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LineCounts {
+    pub files: usize,
+    pub code: usize,
+    pub comments: usize,
+    pub blank: usize,
+    /// Sum of the per-file complexity of files with a supported grammar.
+    pub complexity: usize,
+}
+
+impl LineCounts {
+    pub fn lines(&self) -> usize {
+        self.code + self.comments + self.blank
+    }
+
+    fn add(&mut self, file: &FileStats) {
+        self.files += 1;
+        self.code += file.code;
+        self.comments += file.comments;
+        self.blank += file.blank;
+        self.complexity += file.complexity.unwrap_or(0);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStats {
+    pub path: String,
+    pub language: String,
+    pub section: String,
+    pub bytes: u64,
+    pub code: usize,
+    pub comments: usize,
+    pub blank: usize,
+    /// Approximate cyclomatic complexity: one per function plus one per
+    /// branch (`if`, loops, `case`/match arms, `catch`, `&&`/`||`, ternaries).
+    /// `None` for languages without a grammar.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub functions: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CodeStats {
+    pub total: LineCounts,
+    pub languages: Vec<(String, LineCounts)>,
+    pub sections: Vec<(String, LineCounts)>,
+    pub largest_files: Vec<FileStats>,
+    pub most_complex_files: Vec<FileStats>,
+}
+
+impl CodeStats {
+    pub fn from_files(files: &[FilteredFile], codebase_info: &CodebaseInfo) -> Self {
+        let organizer = CodeOrganizer::new(codebase_info.clone(), Vec::new());
+        let mut all = Vec::new();
+
+        for file in files {
+            let Ok(content) = file.read_content() else {
+                continue;
+            };
+            let section = organizer.determine_category(&file.relative_path);
+            all.push(file_stats(&file.relative_path, &content, file.size, section));
+        }
+
+        let mut total = LineCounts::default();
+        let mut languages: HashMap<String, LineCounts> = HashMap::new();
+        let mut sections: HashMap<String, LineCounts> = HashMap::new();
+        for file in &all {
+            total.add(file);
+            languages.entry(file.language.clone()).or_default().add(file);
+            sections.entry(file.section.clone()).or_default().add(file);
+        }
+
+        let mut languages: Vec<_> = languages.into_iter().collect();
+        languages.sort_by(|a, b| b.1.code.cmp(&a.1.code).then_with(|| a.0.cmp(&b.0)));
+        let mut sections: Vec<_> = sections.into_iter().collect();
+        sections.sort_by_key(|(name, _)| organizer.get_section_priority(name));
+
+        let mut largest_files = all.clone();
+        largest_files.sort_by(|a, b| b.code.cmp(&a.code).then_with(|| a.path.cmp(&b.path)));
+        largest_files.truncate(TOP_FILES);
+
+        let mut most_complex_files: Vec<_> = all.into_iter().filter(|f| f.complexity.is_some()).collect();
+        most_complex_files.sort_by(|a, b| b.complexity.cmp(&a.complexity).then_with(|| a.path.cmp(&b.path)));
+        most_complex_files.truncate(TOP_FILES);
+
+        Self {
+            total,
+            languages,
+            sections,
+            largest_files,
+            most_complex_files,
+        }
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut output = String::new();
+
+        for (title, rows) in [("Language", &self.languages), ("Section", &self.sections)] {
+            output.push_str(&format!(
+                "{:<22} {:>7} {:>10} {:>10} {:>10} {:>10} {:>11}\n",
+                title, "Files", "Lines", "Code", "Comments", "Blank", "Complexity"
+            ));
+            output.push_str(&format!("{}\n", "─".repeat(86)));
+            for (name, counts) in rows.iter() {
+                output.push_str(&table_row(name, counts));
+            }
+            output.push_str(&format!("{}\n", "─".repeat(86)));
+            output.push_str(&table_row("Total", &self.total));
+            output.push('\n');
+        }
+
+        output.push_str("Largest files (code lines)\n");
+        for file in &self.largest_files {
+            output.push_str(&format!("  {:>7}  {}\n", file.code, file.path));
+        }
+
+        if !self.most_complex_files.is_empty() {
+            output.push_str("\nMost complex files (complexity / functions)\n");
+            for file in &self.most_complex_files {
+                output.push_str(&format!(
+                    "  {:>7}  {:>5}  {}\n",
+                    file.complexity.unwrap_or(0),
+                    file.functions.unwrap_or(0),
+                    file.path
+                ));
+            }
+        }
+
+        output
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let named = |rows: &[(String, LineCounts)]| {
+            rows.iter()
+                .map(|(name, counts)| {
+                    let mut value = serde_json::to_value(counts).unwrap_or_default();
+                    value["name"] = serde_json::json!(name);
+                    value["lines"] = serde_json::json!(counts.lines());
+                    value
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Ok(serde_json::to_string_pretty(&serde_json::json!({
+            "total": self.total,
+            "languages": named(&self.languages),
+            "sections": named(&self.sections),
+            "largest_files": self.largest_files,
+            "most_complex_files": self.most_complex_files,
+        }))?)
+    }
+}
+
+fn table_row(name: &str, counts: &LineCounts) -> String {
+    format!(
+        "{:<22} {:>7} {:>10} {:>10} {:>10} {:>10} {:>11}\n",
+        name,
+        counts.files,
+        counts.lines(),
+        counts.code,
+        counts.comments,
+        counts.blank,
+        counts.complexity
+    )
+}
+
+pub fn file_stats(path: &Path, content: &str, bytes: u64, section: String) -> FileStats {
+    let (code, comments, blank) = count_lines(path, content);
+    let (complexity, functions) = match complexity(path, content) {
+        Some((complexity, functions)) => (Some(complexity), Some(functions)),
+        None => (None, None),
+    };
+
+    FileStats {
+        path: path.display().to_string(),
+        language: language_name(path),
+        section,
+        bytes,
+        code,
+        comments,
+        blank,
+        complexity,
+        functions,
+    }
+}
+
+fn language_name(path: &Path) -> String {
+    if let Some(language) = language_for_path(path) {
+        return match language {
+            Language::CPlusPlus => "C++".to_string(),
+            Language::CSharp => "C#".to_string(),
+            Language::Other(name) => name,
+            language => format!("{:?}", language),
+        };
+    }
+
+    match path.extension().and_then(|e| e.to_str()).unwrap_or_default() {
+        "md" | "markdown" => "Markdown",
+        "toml" => "TOML",
+        "json" => "JSON",
+        "yml" | "yaml" => "YAML",
+        "xml" => "XML",
+        "sql" => "SQL",
+        "txt" => "Text",
+        "jsx" => "JavaScript",
+        _ => "Other",
+    }
+    .to_string()
+}
+
+/// Line and block comment delimiters for a file.
+fn comment_syntax(path: &Path) -> (&'static [&'static str], Option<(&'static str, &'static str)>) {
+    const C: (&[&str], Option<(&str, &str)>) = (&["//"], Some(("/*", "*/")));
+    const HASH: (&[&str], Option<(&str, &str)>) = (&["#"], None);
+
+    match language_for_path(path) {
+        Some(Language::Python) => (&["#"], Some(("\"\"\"", "\"\"\""))),
+        Some(Language::Ruby) => (&["#"], Some(("=begin", "=end"))),
+        Some(Language::PHP) => (&["//", "#"], Some(("/*", "*/"))),
+        Some(Language::Shell) | Some(Language::Elixir) => HASH,
+        Some(Language::Haskell) => (&["--"], Some(("{-", "-}"))),
+        Some(Language::HTML) => (&[], Some(("<!--", "-->"))),
+        Some(Language::CSS) => C,
+        Some(Language::Other(_)) => (&[], None),
+        Some(_) => C,
+        None => match path.extension().and_then(|e| e.to_str()).unwrap_or_default() {
+            "toml" | "yml" | "yaml" | "cfg" | "ini" | "r" | "pl" => HASH,
+            "sql" | "lua" => (&["--"], Some(("/*", "*/"))),
+            "md" | "markdown" | "xml" | "vue" | "svelte" => (&[], Some(("<!--", "-->"))),
+            "jsx" => C,
+            _ if matches!(
+                path.file_name().and_then(|n| n.to_str()),
+                Some("Dockerfile" | "Makefile" | ".gitignore")
+            ) => HASH,
+            _ => (&[], None),
+        },
+    }
+}
+
+/// Counts code, comment and blank lines. A line with both code and a comment
+/// counts as code; delimiters inside string literals are not recognized.
+pub fn count_lines(path: &Path, content: &str) -> (usize, usize, usize) {
+    let (line_comments, block) = comment_syntax(path);
+    let (mut code, mut comments, mut blank) = (0, 0, 0);
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        if in_block {
+            comments += 1;
+            if let Some((_, end)) = block {
+                if trimmed.contains(end) {
+                    in_block = false;
+                }
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            blank += 1;
+            continue;
+        }
+        if line_comments.iter().any(|marker| trimmed.starts_with(marker)) {
+            comments += 1;
+            continue;
+        }
+
+        match block {
+            Some((start, end)) if trimmed.starts_with(start) => {
+                comments += 1;
+                in_block = !trimmed[start.len()..].contains(end);
+            }
+            Some((start, end)) => {
+                code += 1;
+                // A block comment opened after code on the same line
+                if let Some(index) = trimmed.find(start) {
+                    in_block = !trimmed[index + start.len()..].contains(end);
+                }
+            }
+            None => code += 1,
+        }
+    }
+
+    (code, comments, blank)
+}
+
+/// Approximate cyclomatic complexity and function count, for languages with a
+/// tree-sitter grammar.
+pub fn complexity(path: &Path, content: &str) -> Option<(usize, usize)> {
+    let (language, tree) = parse_source(path, content).ok()??;
+    let (mut functions, mut branches) = (0, 0);
+    let mut stack = vec![tree.root_node()];
+
+    while let Some(node) = stack.pop() {
+        if is_function(&language, node.kind()) {
+            functions += 1;
+        }
+        if is_branch(&language, node, content) {
+            branches += 1;
+        }
+
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+
+    Some((functions + branches, functions))
+}
+
+fn is_function(language: &Language, kind: &str) -> bool {
+    match language {
+        Language::Rust => matches!(kind, "function_item" | "closure_expression"),
+        Language::Python => matches!(kind, "function_definition" | "lambda"),
+        Language::JavaScript | Language::TypeScript => matches!(
+            kind,
+            "function_declaration"
+                | "function_expression"
+                | "function"
+                | "arrow_function"
+                | "method_definition"
+                | "generator_function_declaration"
+        ),
+        Language::Go => matches!(kind, "function_declaration" | "method_declaration" | "func_literal"),
+        Language::Java => matches!(kind, "method_declaration" | "constructor_declaration" | "lambda_expression"),
+        _ => false,
+    }
+}
+
+fn is_branch(language: &Language, node: Node, content: &str) -> bool {
+    let kind = node.kind();
+    let logical_operator = || {
+        node.child_by_field_name("operator")
+            .and_then(|op| op.utf8_text(content.as_bytes()).ok())
+            .is_some_and(|op| matches!(op, "&&" | "||" | "??"))
+    };
+
+    match language {
+        Language::Rust => match kind {
+            "if_expression" | "while_expression" | "for_expression" | "match_arm" => true,
+            "binary_expression" => logical_operator(),
+            _ => false,
+        },
+        Language::Python => matches!(
+            kind,
+            "if_statement"
+                | "elif_clause"
+                | "for_statement"
+                | "while_statement"
+                | "except_clause"
+                | "conditional_expression"
+                | "boolean_operator"
+                | "if_clause"
+                | "case_clause"
+        ),
+        Language::JavaScript | Language::TypeScript => match kind {
+            "if_statement" | "for_statement" | "for_in_statement" | "while_statement" | "do_statement"
+            | "switch_case" | "catch_clause" | "ternary_expression" => true,
+            "binary_expression" => logical_operator(),
+            _ => false,
+        },
+        Language::Go => match kind {
+            "if_statement" | "for_statement" | "expression_case" | "type_case" | "communication_case" => true,
+            "binary_expression" => logical_operator(),
+            _ => false,
+        },
+        Language::Java => match kind {
+            "if_statement" | "for_statement" | "enhanced_for_statement" | "while_statement" | "do_statement"
+            | "catch_clause" | "ternary_expression" => true,
+            "switch_label" => node
+                .utf8_text(content.as_bytes())
+                .is_ok_and(|label| !label.starts_with("default")),
+            "binary_expression" => logical_operator(),
+            _ => false,
+        },
+        _ => false,
+    }
+}
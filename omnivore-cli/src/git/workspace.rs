@@ -0,0 +1,386 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::detector::CodebaseInfo;
+
+/// Directories never searched for workspace members.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", ".git", "vendor", "dist", "build"];
+
+/// How deep `**` patterns and Nx project discovery search below the root.
+const MAX_MEMBER_DEPTH: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorkspaceKind {
+    Cargo,
+    Npm,
+    Pnpm,
+    Yarn,
+    Go,
+    Lerna,
+    Nx,
+    Maven,
+    /// One of several sources given to `omnivore git`.
+    Repository,
+}
+
+/// A sub-project of a monorepo, or one source of a multi-source run,
+/// analyzed on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    pub name: String,
+    /// Location relative to the workspace root.
+    pub path: PathBuf,
+    pub kind: WorkspaceKind,
+    pub info: CodebaseInfo,
+}
+
+impl WorkspaceMember {
+    /// One-line summary: `crates/core (Cargo): A library written in Rust`.
+    pub fn summary(&self) -> String {
+        let location = self.path.display().to_string();
+        let label = if location == self.name {
+            location
+        } else {
+            format!("{} [{}]", location, self.name)
+        };
+        format!("{} ({:?}): {}", label, self.kind, self.info.description)
+    }
+}
+
+/// A member directory found in a workspace manifest, before it is analyzed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberLocation {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: WorkspaceKind,
+}
+
+/// Finds the members declared by Cargo, npm/pnpm/yarn, Go, Lerna, Nx and
+/// Maven workspaces at `root`. A directory listed by several tools (npm
+/// workspaces and Lerna, say) is reported once, for the first one.
+pub fn find_workspace_members(root: &Path) -> Vec<MemberLocation> {
+    let finders: [fn(&Path) -> Vec<MemberLocation>; 7] = [
+        cargo_members,
+        go_members,
+        maven_members,
+        pnpm_members,
+        npm_members,
+        lerna_members,
+        nx_members,
+    ];
+
+    let mut seen = HashSet::new();
+    let mut members: Vec<MemberLocation> = finders
+        .iter()
+        .flat_map(|finder| finder(root))
+        .filter(|member| !member.path.as_os_str().is_empty() && seen.insert(member.path.clone()))
+        .collect();
+    members.sort_by(|a, b| a.path.cmp(&b.path));
+    members
+}
+
+fn cargo_members(root: &Path) -> Vec<MemberLocation> {
+    let Some(manifest) = read_toml(&root.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let Some(workspace) = manifest.get("workspace") else {
+        return Vec::new();
+    };
+
+    let patterns = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    let mut patterns_with_excludes = patterns("members");
+    patterns_with_excludes.extend(patterns("exclude").into_iter().map(|p| format!("!{}", p)));
+
+    expand_members(root, &patterns_with_excludes, "Cargo.toml")
+        .into_iter()
+        .map(|path| MemberLocation {
+            name: read_toml(&root.join(&path).join("Cargo.toml"))
+                .and_then(|m| m.get("package")?.get("name")?.as_str().map(String::from))
+                .unwrap_or_else(|| dir_name(&path)),
+            path,
+            kind: WorkspaceKind::Cargo,
+        })
+        .collect()
+}
+
+fn npm_members(root: &Path) -> Vec<MemberLocation> {
+    let Some(package) = read_json(&root.join("package.json")) else {
+        return Vec::new();
+    };
+    // Either a list of globs or, with Yarn, `{ "packages": [...] }`
+    let workspaces = match package.get("workspaces") {
+        Some(serde_json::Value::Object(config)) => config.get("packages"),
+        workspaces => workspaces,
+    };
+    let kind = if root.join("yarn.lock").exists() || root.join(".yarnrc.yml").exists() {
+        WorkspaceKind::Yarn
+    } else {
+        WorkspaceKind::Npm
+    };
+    package_json_members(root, &json_strings(workspaces), kind)
+}
+
+fn pnpm_members(root: &Path) -> Vec<MemberLocation> {
+    let Some(config) = fs::read_to_string(root.join("pnpm-workspace.yaml"))
+        .ok()
+        .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+    else {
+        return Vec::new();
+    };
+    let patterns: Vec<String> = config
+        .get("packages")
+        .and_then(|p| p.as_sequence())
+        .map(|s| s.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    package_json_members(root, &patterns, WorkspaceKind::Pnpm)
+}
+
+fn lerna_members(root: &Path) -> Vec<MemberLocation> {
+    let Some(config) = read_json(&root.join("lerna.json")) else {
+        return Vec::new();
+    };
+    let mut patterns = json_strings(config.get("packages"));
+    if patterns.is_empty() {
+        patterns.push("packages/*".to_string());
+    }
+    package_json_members(root, &patterns, WorkspaceKind::Lerna)
+}
+
+fn package_json_members(root: &Path, patterns: &[String], kind: WorkspaceKind) -> Vec<MemberLocation> {
+    expand_members(root, patterns, "package.json")
+        .into_iter()
+        .map(|path| MemberLocation {
+            name: read_json(&root.join(&path).join("package.json"))
+                .and_then(|p| p.get("name")?.as_str().map(String::from))
+                .unwrap_or_else(|| dir_name(&path)),
+            path,
+            kind,
+        })
+        .collect()
+}
+
+fn nx_members(root: &Path) -> Vec<MemberLocation> {
+    // Older Nx workspaces list projects in workspace.json; newer ones put a
+    // project.json in each project directory
+    if let Some(workspace) = read_json(&root.join("workspace.json")) {
+        if let Some(projects) = workspace.get("projects").and_then(|p| p.as_object()) {
+            return projects
+                .iter()
+                .filter_map(|(name, project)| {
+                    let path = project.as_str().or_else(|| project.get("root")?.as_str())?;
+                    Some(MemberLocation {
+                        name: name.clone(),
+                        path: normalize(path).into(),
+                        kind: WorkspaceKind::Nx,
+                    })
+                })
+                .filter(|member| root.join(&member.path).is_dir())
+                .collect();
+        }
+    }
+    if !root.join("nx.json").exists() {
+        return Vec::new();
+    }
+
+    walk_dirs(root, MAX_MEMBER_DEPTH)
+        .into_iter()
+        .filter(|path| !path.as_os_str().is_empty())
+        .filter_map(|path| {
+            let project = read_json(&root.join(&path).join("project.json"))?;
+            Some(MemberLocation {
+                name: project
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .map(String::from)
+                    .unwrap_or_else(|| dir_name(&path)),
+                path,
+                kind: WorkspaceKind::Nx,
+            })
+        })
+        .collect()
+}
+
+fn go_members(root: &Path) -> Vec<MemberLocation> {
+    let Ok(content) = fs::read_to_string(root.join("go.work")) else {
+        return Vec::new();
+    };
+
+    // `use ./a` or a `use ( ... )` block
+    let mut paths = Vec::new();
+    let mut in_block = false;
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                paths.push(line.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("use") {
+            match rest.trim() {
+                "(" => in_block = true,
+                path if !path.is_empty() => paths.push(path.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    paths
+        .into_iter()
+        .map(|path| PathBuf::from(normalize(path.trim_matches('"'))))
+        .filter(|path| root.join(path).join("go.mod").is_file())
+        .map(|path| MemberLocation {
+            name: fs::read_to_string(root.join(&path).join("go.mod"))
+                .ok()
+                .and_then(|m| {
+                    m.lines()
+                        .find_map(|l| l.trim().strip_prefix("module ").map(|n| n.trim().to_string()))
+                })
+                .unwrap_or_else(|| dir_name(&path)),
+            path,
+            kind: WorkspaceKind::Go,
+        })
+        .collect()
+}
+
+fn maven_members(root: &Path) -> Vec<MemberLocation> {
+    let Ok(content) = fs::read_to_string(root.join("pom.xml")) else {
+        return Vec::new();
+    };
+    let modules = Regex::new(r"(?s)<modules>(.*?)</modules>").unwrap();
+    let module = Regex::new(r"<module>\s*([^<]+?)\s*</module>").unwrap();
+    let artifact = Regex::new(r"<artifactId>\s*([^<]+?)\s*</artifactId>").unwrap();
+
+    modules
+        .captures_iter(&content)
+        .flat_map(|block| {
+            module
+                .captures_iter(block.get(1).map_or("", |m| m.as_str()))
+                .map(|c| PathBuf::from(normalize(&c[1])))
+                .collect::<Vec<_>>()
+        })
+        .filter(|path| root.join(path).join("pom.xml").is_file())
+        .map(|path| {
+            // The module's own artifactId follows its <parent> block, if any
+            let name = fs::read_to_string(root.join(&path).join("pom.xml"))
+                .ok()
+                .and_then(|pom| {
+                    let body = match pom.find("</parent>") {
+                        Some(end) => pom[end..].to_string(),
+                        None => pom,
+                    };
+                    artifact.captures(&body).map(|c| c[1].to_string())
+                })
+                .unwrap_or_else(|| dir_name(&path));
+            MemberLocation {
+                name,
+                path,
+                kind: WorkspaceKind::Maven,
+            }
+        })
+        .collect()
+}
+
+/// Resolves member patterns (`crates/*`, `packages/**`, `tools/cli`, and
+/// `!excluded` negations) to directories containing `manifest`.
+fn expand_members(root: &Path, patterns: &[String], manifest: &str) -> Vec<PathBuf> {
+    let mut literal = Vec::new();
+    let mut includes = GlobSetBuilder::new();
+    let mut excludes = GlobSetBuilder::new();
+    let mut has_globs = false;
+
+    for pattern in patterns {
+        let (pattern, negated) = match pattern.strip_prefix('!') {
+            Some(pattern) => (normalize(pattern), true),
+            None => (normalize(pattern), false),
+        };
+        // `*` stays within one directory, as in Cargo and npm
+        let Ok(glob) = GlobBuilder::new(&pattern).literal_separator(true).build() else {
+            continue;
+        };
+        if negated {
+            excludes.add(glob);
+        } else if pattern.contains(['*', '?', '[', '{']) {
+            includes.add(glob);
+            has_globs = true;
+        } else {
+            literal.push(PathBuf::from(pattern));
+        }
+    }
+
+    let includes = includes.build().unwrap_or_else(|_| GlobSet::empty());
+    let excludes = excludes.build().unwrap_or_else(|_| GlobSet::empty());
+
+    let mut members = literal;
+    if has_globs {
+        members.extend(
+            walk_dirs(root, MAX_MEMBER_DEPTH)
+                .into_iter()
+                .filter(|path| includes.is_match(path)),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    members
+        .into_iter()
+        .filter(|path| !excludes.is_match(path))
+        .filter(|path| root.join(path).join(manifest).is_file())
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// Directories below `root`, relative to it, skipping dependency and build
+/// output directories.
+fn walk_dirs(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            !SKIPPED_DIRS.contains(&name.as_ref())
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .filter_map(|e| e.path().strip_prefix(root).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+fn normalize(pattern: &str) -> String {
+    pattern
+        .trim()
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn json_strings(value: Option<&serde_json::Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default()
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn read_toml(path: &Path) -> Option<toml::Value> {
+    toml::from_str(&fs::read_to_string(path).ok()?).ok()
+}
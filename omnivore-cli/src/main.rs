@@ -2,9 +2,18 @@ use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser, Subcommand};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
-use omnivore_core::{crawler::Crawler, CrawlConfig, CrawlResult, CrawlStats, PolitenessConfig, table_extractor::TableData};
+use omnivore_core::{crawler::Crawler, CrawlConfig, CrawlResult, CrawlStats, PaginationConfig, PolitenessConfig, ProxyConfig, ProxyRotation, LoginConfig, SessionConfig, table_extractor::TableData};
+use omnivore_core::config::{CloudConfig, DatabaseConfig, ExtractionTemplate, OutputConfig};
+use omnivore_core::crawler::distributed::{DistributedConfig, SharedFrontier};
+use omnivore_core::crawler::pagination::{stitch_listings, ListingDataset};
+use omnivore_core::parser::template::TemplateEngine;
+use omnivore_core::storage::cloud;
+use omnivore_core::storage::sql::{SessionRecord, SessionStatus, SqlSink};
+use omnivore_core::telemetry::{self, TraceExporter};
+use omnivore_core::webhook::{self, WebhookEvent, WebhookNotifier};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
 use url::Url;
 use std::fs::File;
 use std::io::{Write, Read as IORead};
@@ -21,6 +30,100 @@ enum OutputFormat {
     Text,
 }
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ProxyRotationArg {
+    /// The next proxy for each request
+    PerRequest,
+    /// One proxy per domain, kept until it fails
+    PerDomain,
+}
+
+impl From<ProxyRotationArg> for ProxyRotation {
+    fn from(rotation: ProxyRotationArg) -> Self {
+        match rotation {
+            ProxyRotationArg::PerRequest => ProxyRotation::PerRequest,
+            ProxyRotationArg::PerDomain => ProxyRotation::PerDomain,
+        }
+    }
+}
+
+// How a crawl authenticates: cookies, headers and a login form
+#[derive(Debug, clap::Args)]
+struct SessionArgs {
+    #[arg(long, value_name = "FILE", help = "Start with the cookies in FILE, a Netscape cookies.txt or a browser's JSON export; repeat for more files")]
+    cookies: Vec<PathBuf>,
+
+    #[arg(long = "header", value_name = "[DOMAIN=]NAME: VALUE", help = "Send this header to DOMAIN and its subdomains (default: the start URL's domain, * for all); repeat for more")]
+    headers: Vec<String>,
+
+    #[arg(long, value_name = "URL", requires = "login_user", help = "Log in with the form on this page before crawling")]
+    login_url: Option<String>,
+
+    #[arg(long, value_name = "USER", requires = "login_url", help = "Username for --login-url")]
+    login_user: Option<String>,
+
+    #[arg(long, value_name = "PASSWORD", env = "OMNIVORE_LOGIN_PASSWORD", hide_env_values = true, help = "Password for --login-url")]
+    login_password: Option<String>,
+
+    #[arg(long, value_name = "TEXT", requires = "login_url", help = "Text on the page after a successful login (default: the page has no password field)")]
+    login_success: Option<String>,
+}
+
+impl SessionArgs {
+    /// The session of a crawl from `url`. Headers are `[DOMAIN=]NAME: VALUE`,
+    /// for `url`'s domain when no domain is given.
+    fn into_config(self, url: &str) -> Result<SessionConfig> {
+        let login = match self.login_url {
+            Some(login_url) => Some(LoginConfig {
+                url: login_url,
+                username: self.login_user.unwrap_or_default(),
+                password: self.login_password.context("--login-url needs a password, from --login-password or OMNIVORE_LOGIN_PASSWORD")?,
+                success_text: self.login_success,
+                ..LoginConfig::default()
+            }),
+            None => None,
+        };
+        let mut session = SessionConfig { cookie_files: self.cookies, login, ..SessionConfig::default() };
+        for header in &self.headers {
+            let (target, value) = header.split_once(':').with_context(|| format!("Invalid header '{}', expected [DOMAIN=]NAME: VALUE", header))?;
+            let (domain, name) = match target.split_once('=') {
+                Some((domain, name)) => (domain.to_string(), name),
+                None => (Url::parse(url)?.host_str().unwrap_or("*").to_string(), target),
+            };
+            session.headers.entry(domain).or_default().insert(name.trim().to_string(), value.trim().to_string());
+        }
+        Ok(session)
+    }
+}
+
+/// What `omnivore crawl` was asked to do, with the Redis, proxy and session
+/// flags already turned into their configs.
+struct CrawlOptions {
+    url: String,
+    workers: usize,
+    depth: u32,
+    output: Option<PathBuf>,
+    respect_robots: bool,
+    delay: u64,
+    include_raw: bool,
+    exclude_urls: bool,
+    organize: bool,
+    format: OutputFormat,
+    zip: bool,
+    extract_tables: bool,
+    browser: bool,
+    interact: bool,
+    auto: bool,
+    ai: Option<String>,
+    template: Option<String>,
+    metrics_addr: Option<String>,
+    distributed: Option<DistributedConfig>,
+    incremental: Option<PathBuf>,
+    pagination: PaginationConfig,
+    proxies: ProxyConfig,
+    session: SessionConfig,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct CrawlOutput {
     stats: CrawlStats,
@@ -35,6 +138,8 @@ struct CleanCrawlOutput {
     duration_ms: u128,
     timestamp: String,
     content: Vec<PageContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    listings: Vec<ListingDataset>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,6 +156,8 @@ struct PageContent {
     words: usize,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     links: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extracted: Option<serde_json::Value>,
 }
 
 #[derive(Parser)]
@@ -65,6 +172,12 @@ struct Cli {
 
     #[arg(short, long, value_name = "FILE", global = true)]
     config: Option<PathBuf>,
+
+    #[arg(long, value_name = "URL", global = true, help = "Export crawl traces over OTLP/gRPC (e.g. http://localhost:4317)")]
+    otlp_endpoint: Option<String>,
+
+    #[arg(long, global = true, conflicts_with = "otlp_endpoint", help = "Print crawl traces to stdout as JSON, one span per line")]
+    trace_stdout: bool,
 }
 
 #[derive(Subcommand)]
@@ -132,6 +245,33 @@ enum Commands {
         
         #[arg(long, help = "Use extraction template")]
         template: Option<String>,
+
+        #[arg(long, value_name = "ADDR", help = "Serve Prometheus metrics on /metrics at this address while crawling (e.g. 127.0.0.1:9091)")]
+        metrics_addr: Option<String>,
+
+        #[arg(long, value_name = "URL", requires = "crawl_id", conflicts_with = "browser", help = "Share the frontier with other omnivore processes through this Redis server (e.g. redis://localhost:6379)")]
+        redis_url: Option<String>,
+
+        #[arg(long, value_name = "ID", requires = "redis_url", help = "Name of the distributed crawl; processes with the same id crawl together")]
+        crawl_id: Option<String>,
+
+        #[arg(long, value_name = "FILE", help = "Compare pages with the content hashes saved in FILE by the previous crawl, report the changed ones and save the new hashes")]
+        incremental: Option<PathBuf>,
+
+        #[arg(long, help = "Follow detected \"next\" links ahead of other links (also enabled by auto_follow_pagination in the config)")]
+        follow_pagination: bool,
+
+        #[arg(long, value_name = "N", help = "Maximum pages per followed listing, including the first (default: max_pagination_pages from the config)")]
+        max_pages: Option<usize>,
+
+        #[arg(long = "proxy", value_name = "URL", help = "Crawl through this proxy (http://, https://, socks5:// or socks5h://, with user:password@ for auth); repeat for a pool")]
+        proxies: Vec<String>,
+
+        #[arg(long, value_enum, default_value = "per-request", help = "How requests are spread over the proxies")]
+        proxy_rotation: ProxyRotationArg,
+
+        #[command(flatten)]
+        session: Box<SessionArgs>,
     },
 
     Parse {
@@ -143,6 +283,12 @@ enum Commands {
 
         #[arg(short, long, help = "Output file for parsed results")]
         output: Option<PathBuf>,
+
+        #[arg(long, help = "Validate output against a schema (name or path to a schema file)")]
+        schema: Option<String>,
+
+        #[arg(long, help = "Directory to resolve schema names from (default: ~/.omnivore/schemas)")]
+        schema_dir: Option<PathBuf>,
     },
 
 
@@ -151,7 +297,7 @@ enum Commands {
         session: Option<String>,
     },
 
-    Git(git::GitArgs),
+    Git(Box<git::GitArgs>),
     
     Docs {
         #[arg(help = "Open Omnivore documentation in browser")]
@@ -175,9 +321,14 @@ async fn main() -> Result<()> {
     
     let cli = Cli::parse();
 
-    tracing_subscriber::fmt()
-        .with_env_filter(if cli.verbose { "debug" } else { "info" })
-        .init();
+    let exporter = match cli.otlp_endpoint.clone() {
+        Some(endpoint) => Some(TraceExporter::Otlp { endpoint }),
+        None => cli.trace_stdout.then_some(TraceExporter::Stdout),
+    };
+    let _telemetry = telemetry::init(
+        EnvFilter::new(if cli.verbose { "debug" } else { "info" }),
+        exporter,
+    )?;
 
     match cli.command {
         Commands::Setup { .. } => {
@@ -204,17 +355,56 @@ async fn main() -> Result<()> {
             auto,
             ai,
             template,
+            metrics_addr,
+            redis_url,
+            crawl_id,
+            incremental,
+            follow_pagination,
+            max_pages,
+            proxies,
+            proxy_rotation,
+            session,
         } => {
-            crawl_command(url, workers, depth, output, respect_robots, delay, include_raw, exclude_urls, organize, format, zip, extract_tables, browser, interact, auto, ai, template).await?;
+            let distributed = redis_url.zip(crawl_id).map(|(redis_url, crawl_id)| DistributedConfig::new(redis_url, crawl_id));
+            let mut pagination = PaginationConfig::from(&omnivore_core::config::OmnivoreConfig::load().unwrap_or_default().extraction);
+            pagination.follow |= follow_pagination;
+            pagination.max_pages = max_pages.unwrap_or(pagination.max_pages);
+            let session = session.into_config(&url)?;
+            let proxies = ProxyConfig { urls: proxies, rotation: proxy_rotation.into(), ..ProxyConfig::default() };
+            crawl_command(CrawlOptions {
+                url,
+                workers,
+                depth,
+                output,
+                respect_robots,
+                delay,
+                include_raw,
+                exclude_urls,
+                organize,
+                format,
+                zip,
+                extract_tables,
+                browser,
+                interact,
+                auto,
+                ai,
+                template,
+                metrics_addr,
+                distributed,
+                incremental,
+                pagination,
+                proxies,
+                session,
+            }).await?;
         }
-        Commands::Parse { file, rules, output } => {
-            parse_command(file, rules, output).await?;
+        Commands::Parse { file, rules, output, schema, schema_dir } => {
+            parse_command(file, rules, output, schema, schema_dir).await?;
         }
         Commands::Stats { session } => {
             stats_command(session).await?;
         }
         Commands::Git(args) => {
-            git::execute_git_command(args).await?;
+            git::execute_git_command(*args).await?;
         }
         Commands::Docs { .. } => {
             docs_command().await?;
@@ -351,25 +541,67 @@ fn format_output_content(
     }
 }
 
-async fn crawl_command(
-    url: String,
-    workers: usize,
-    depth: u32,
-    output: Option<PathBuf>,
-    respect_robots: bool,
-    delay: u64,
-    include_raw: bool,
-    exclude_urls: bool,
-    organize: bool,
-    format: OutputFormat,
-    zip: bool,
-    extract_tables: bool,
-    browser: bool,
-    interact: bool,
-    auto: bool,
-    ai: Option<String>,
-    template: Option<String>,
-) -> Result<()> {
+/// Runs the crawl, then sends `crawl.completed`, or `crawl.failed` if it
+/// stopped on an error, and waits for the webhooks to go out.
+async fn crawl_command(options: CrawlOptions) -> Result<()> {
+    let output_config = {
+        let mut omnivore_config = omnivore_core::config::OmnivoreConfig::load().unwrap_or_default();
+        omnivore_config.merge_with_env();
+        omnivore_config.output
+    };
+    let session_id = uuid::Uuid::new_v4();
+    let webhooks = match output_config.webhook_url {
+        Some(ref url) => {
+            let notifier = WebhookNotifier::new(url, &session_id.to_string(), &output_config.webhook)?;
+            Some(Webhooks::start(notifier))
+        }
+        None => None,
+    };
+
+    let start_url = options.url.clone();
+    let crawled = crawl(options, output_config, session_id, webhooks.as_ref()).await;
+    if let Some(webhooks) = webhooks {
+        webhooks.send(match &crawled {
+            Ok(stats) => WebhookEvent::CrawlCompleted { start_url, stats: stats.clone() },
+            Err(e) => WebhookEvent::CrawlFailed { start_url, error: format!("{:#}", e) },
+        });
+        webhooks.finish().await;
+    }
+    crawled.map(|_| ())
+}
+
+async fn crawl(
+    options: CrawlOptions,
+    output_config: OutputConfig,
+    session_id: uuid::Uuid,
+    webhooks: Option<&Webhooks>,
+) -> Result<CrawlStats> {
+    let CrawlOptions {
+        url,
+        workers,
+        depth,
+        output,
+        respect_robots,
+        delay,
+        include_raw,
+        exclude_urls,
+        organize,
+        format,
+        zip,
+        extract_tables,
+        browser,
+        interact,
+        auto,
+        ai,
+        template,
+        metrics_addr,
+        distributed,
+        incremental,
+        pagination,
+        proxies,
+        session: session_config,
+    } = options;
+
     println!("{}", "🕸️  Omnivore Web Crawler".bold().cyan());
     println!();
 
@@ -383,6 +615,22 @@ async fn crawl_command(
         respect_robots.to_string().yellow()
     );
     println!("  Delay: {}ms", delay.to_string().yellow());
+    if !proxies.urls.is_empty() {
+        let rotation = match proxies.rotation {
+            ProxyRotation::PerRequest => "per request",
+            ProxyRotation::PerDomain => "per domain",
+        };
+        println!("  Proxies: {}", format!("{} ({})", proxies.urls.len(), rotation).yellow());
+    }
+    if !session_config.cookie_files.is_empty() {
+        println!("  Cookie files: {}", session_config.cookie_files.len().to_string().yellow());
+    }
+    if let Some(ref login) = session_config.login {
+        println!("  Login: {}", format!("{} as {}", login.url, login.username).yellow());
+    }
+    if let Some(ref distributed) = distributed {
+        println!("  Distributed crawl: {}", format!("{} via {}", distributed.crawl_id, distributed.redis_url).yellow());
+    }
     
     if browser {
         println!("  Browser mode: {}", "enabled".green());
@@ -407,6 +655,13 @@ async fn crawl_command(
         println!("  Template: {}", template_name.yellow());
     }
     
+    // Compile the template up front so a broken template fails before crawling
+    let template_engine = template.as_deref().map(load_template_engine).transpose()?;
+    
+    if pagination.follow {
+        println!("  Follow pagination: {}", format!("up to {} pages", pagination.max_pages).yellow());
+    }
+    
     println!();
 
     let config = CrawlConfig {
@@ -421,7 +676,25 @@ async fn crawl_command(
         },
         timeout_ms: 30000,
         max_retries: 3,
+        pagination: pagination.clone(),
+        proxies,
+        session: session_config,
     };
+    let session = SessionRecord {
+        id: session_id,
+        name: start_url.host_str().unwrap_or("crawl").to_string(),
+        start_url: start_url.to_string(),
+        max_depth: depth,
+        max_workers: workers,
+        status: SessionStatus::Running,
+        settings: serde_json::to_value(&config)?,
+    };
+    let include_html = include_raw || output_config.save_raw_html;
+    let database_export = output_config.database_export;
+    let cloud_export = output_config.cloud_export;
+    if let Some(webhooks) = webhooks {
+        webhooks.send(WebhookEvent::CrawlStarted { start_url: start_url.to_string(), config: Box::new(config.clone()) });
+    }
 
     // Handle browser mode separately
     if browser {
@@ -436,12 +709,15 @@ async fn crawl_command(
             let mut browser_engine = BrowserEngine::new().await?;
             browser_engine.connect().await.context("Failed to connect to browser. Make sure ChromeDriver is running (chromedriver --port=9515)")?;
             
-            let crawl_results = if interact {
+            let mut crawl_results = if interact {
                 println!("Crawling with interactive mode (dropdowns and filters)...");
                 let dynamic_content = browser_engine.crawl_with_interactions(start_url.clone()).await?;
                 
                 // Convert dynamic content to regular crawl results
                 vec![convert_dynamic_to_crawl_result(dynamic_content)?]
+            } else if pagination.follow {
+                println!("Crawling with browser, loading more items...");
+                vec![browser_engine.crawl_paginated(start_url.clone(), pagination.max_pages).await?]
             } else {
                 println!("Crawling with browser (JavaScript rendering)...");
                 vec![browser_engine.crawl_dynamic(start_url.clone()).await?]
@@ -449,10 +725,35 @@ async fn crawl_command(
             
             browser_engine.disconnect().await?;
             
+            if let Some(ref engine) = template_engine {
+                apply_template(engine, &mut crawl_results);
+            }
+            
+            if let Some(ref database) = database_export {
+                export_to_database(database, &session, &crawl_results, include_html).await?;
+            }
+            let stats = CrawlStats {
+                total_urls: crawl_results.len(),
+                successful: crawl_results.len(),
+                failed: 0,
+                in_progress: 0,
+                average_response_time_ms: 0.0,
+                start_time: chrono::Utc::now(),
+                elapsed_time: std::time::Duration::from_secs(0),
+                proxies: Vec::new(),
+            };
+            if let Some(ref cloud_config) = cloud_export {
+                export_to_cloud(cloud_config, &session, &start_url, &stats, &crawl_results, include_html).await?;
+            }
+            
+            if let Some(ref state) = incremental {
+                report_changes(state, &crawl_results, webhooks)?;
+            }
+            
             // Process results similar to regular crawl
             handle_crawl_results(crawl_results, &start_url, output, organize, format, zip, extract_tables, exclude_urls).await?;
             
-            return Ok(());
+            return Ok(stats);
         }
         
         #[cfg(not(all()))]
@@ -464,9 +765,23 @@ async fn crawl_command(
     }
     
     use std::sync::Arc;
-    let crawler: Arc<Crawler> = Arc::new(Crawler::new(config).await?);
+    let mut crawler = Crawler::new(config).await?;
+    if let Some(ref distributed) = distributed {
+        let frontier = SharedFrontier::connect(distributed).await.context("Failed to connect to the shared frontier")?;
+        crawler = crawler.with_shared_frontier(frontier);
+    }
+    let crawler: Arc<Crawler> = Arc::new(crawler);
     crawler.add_seed(start_url.clone()).await?;
 
+    let metrics_handle = match &metrics_addr {
+        Some(addr) => {
+            let handle = omnivore_core::crawler::metrics::serve(addr, crawler.metrics().clone()).await?;
+            println!("Metrics: {}", format!("http://{}/metrics", addr).cyan());
+            Some(handle)
+        }
+        None => None,
+    };
+
     let progress = ProgressBar::new_spinner();
     progress.set_style(
         ProgressStyle::default_spinner()
@@ -477,6 +792,9 @@ async fn crawl_command(
     let stats_handle = tokio::spawn({
         let crawler = Arc::clone(&crawler);
         let progress = progress.clone();
+        let mut error_alert = webhooks.map(|webhooks| (webhooks.notifier.clone(), webhooks.sender.clone()));
+        let error_threshold = output_config.webhook.error_threshold;
+        let start_url = start_url.to_string();
         async move {
             loop {
                 let stats = crawler.get_stats().await;
@@ -487,6 +805,17 @@ async fn crawl_command(
                     stats.failed.to_string().red(),
                     stats.in_progress.to_string().yellow()
                 ));
+                // Alert once, the first time the error rate reaches the threshold
+                let error_rate = error_alert.as_ref().and_then(|(notifier, _)| notifier.exceeded_error_rate(&stats));
+                if let (Some(error_rate), Some((_, sender))) = (error_rate, error_alert.as_ref()) {
+                    let _ = sender.send(WebhookEvent::ErrorThreshold {
+                        start_url: start_url.clone(),
+                        error_rate,
+                        threshold: error_threshold,
+                        stats,
+                    });
+                    error_alert = None;
+                }
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
             }
         }
@@ -495,6 +824,9 @@ async fn crawl_command(
     let crawler = Arc::clone(&crawler);
     crawler.start().await?;
     stats_handle.abort();
+    if let Some(handle) = metrics_handle {
+        handle.abort();
+    }
     progress.finish_with_message("Crawl completed!");
 
     let final_stats = crawler.get_stats().await;
@@ -592,6 +924,12 @@ async fn crawl_command(
         }
     }
     
+    if let Some(ref engine) = template_engine {
+        apply_template(engine, &mut crawl_results);
+    }
+    
+    let listings = stitch_listings(&crawl_results, &crawler.get_listings().await);
+    
     println!();
     println!("{}", "📊 Final Statistics:".bold().green());
     println!(
@@ -604,10 +942,38 @@ async fn crawl_command(
     );
     println!("  Failed: {}", final_stats.failed.to_string().red());
     println!("  Time elapsed: {:?}", final_stats.elapsed_time);
+    for proxy in &final_stats.proxies {
+        println!(
+            "  Proxy {}: {} requests, {} ok, {} failed, {} blocked{}",
+            proxy.proxy.cyan(),
+            proxy.requests,
+            proxy.successful.to_string().green(),
+            proxy.failed.to_string().red(),
+            proxy.blocked.to_string().red(),
+            if proxy.healthy { String::new() } else { " (out of rotation)".yellow().to_string() }
+        );
+    }
     println!(
         "  Pages with content: {}",
         crawl_results.len().to_string().cyan()
     );
+    for listing in &listings {
+        println!(
+            "  Paginated listing: {} ({} pages)",
+            listing.listing_url.cyan(),
+            listing.pages.len().to_string().yellow()
+        );
+    }
+
+    if let Some(ref database) = database_export {
+        export_to_database(database, &session, &crawl_results, include_html).await?;
+    }
+    if let Some(ref cloud_config) = cloud_export {
+        export_to_cloud(cloud_config, &session, &start_url, &final_stats, &crawl_results, include_html).await?;
+    }
+    if let Some(ref state) = incremental {
+        report_changes(state, &crawl_results, webhooks)?;
+    }
 
     // Handle organized output
     if organize {
@@ -649,6 +1015,7 @@ async fn crawl_command(
                     tables: if extract_tables { cleaned.tables.clone() } else { Vec::new() },
                     words: cleaned.word_count,
                     links: if exclude_urls { Vec::new() } else { cleaned.links.clone() },
+                    extracted: extracted_value(result),
                 };
                 
                 let page_json = serde_json::to_string_pretty(&page_content)?;
@@ -704,6 +1071,10 @@ async fn crawl_command(
             index_data["tables"] = serde_json::json!(all_tables);
         }
         
+        if !listings.is_empty() {
+            index_data["listings"] = serde_json::to_value(&listings)?;
+        }
+        
         let index_path = output_dir.join("index.json");
         tokio::fs::write(&index_path, serde_json::to_string_pretty(&index_data)?).await?;
         
@@ -751,7 +1122,7 @@ async fn crawl_command(
             );
         }
         
-        return Ok(());
+        return Ok(final_stats);
     }
     
     // Determine output path
@@ -761,7 +1132,7 @@ async fn crawl_command(
     let output_content = if include_raw {
         // Include full content with raw HTML (JSON only for raw)
         let crawl_output = CrawlOutput {
-            stats: final_stats,
+            stats: final_stats.clone(),
             results: crawl_results.clone(),
         };
         serde_json::to_string_pretty(&crawl_output)?
@@ -804,6 +1175,7 @@ async fn crawl_command(
                             tables: Vec::new(), // Tables not included in non-organized output yet
                             words: cleaned.word_count,
                             links: if exclude_urls { Vec::new() } else { cleaned.links.clone() },
+                            extracted: extracted_value(result),
                         })
                     } else {
                         None
@@ -821,6 +1193,7 @@ async fn crawl_command(
             duration_ms: final_stats.elapsed_time.as_millis(),
             timestamp: chrono::Utc::now().to_rfc3339(),
             content,
+            listings,
         };
         
         format_output_content(&clean_output, &format, exclude_urls)?
@@ -849,7 +1222,7 @@ async fn crawl_command(
             "📦".bold().green(),
             zip_path.display().to_string().yellow()
         );
-        return Ok(());
+        return Ok(final_stats);
     }
     
     // Write output to file (for non-ZIP case)
@@ -877,7 +1250,7 @@ async fn crawl_command(
         );
     }
 
-    Ok(())
+    Ok(final_stats)
 }
 
 fn generate_parse_filename(input_file: &PathBuf) -> PathBuf {
@@ -889,7 +1262,13 @@ fn generate_parse_filename(input_file: &PathBuf) -> PathBuf {
     PathBuf::from(format!("parsed_{}_{}.json", file_stem, timestamp))
 }
 
-async fn parse_command(file: PathBuf, rules: Option<PathBuf>, output: Option<PathBuf>) -> Result<()> {
+async fn parse_command(
+    file: PathBuf,
+    rules: Option<PathBuf>,
+    output: Option<PathBuf>,
+    schema: Option<String>,
+    schema_dir: Option<PathBuf>,
+) -> Result<()> {
     println!("{}", "📄 Parsing HTML file...".bold().cyan());
     println!("Input file: {}", file.display().to_string().yellow());
 
@@ -903,14 +1282,31 @@ async fn parse_command(file: PathBuf, rules: Option<PathBuf>, output: Option<Pat
         Vec::new()
     };
 
-    let parser = omnivore_core::parser::Parser::new(omnivore_core::parser::ParseConfig {
+    // A schema argument that points at a file is loaded directly, anything
+    // else is treated as a name to look up in the schema directory
+    let schema_file = schema.as_ref().map(PathBuf::from).filter(|p| p.is_file());
+    let schema_name = if schema_file.is_some() { None } else { schema.clone() };
+    
+    let mut parser = omnivore_core::parser::Parser::new(omnivore_core::parser::ParseConfig {
         rules,
-        schema_name: None,
+        schema_name,
         clean_text: true,
         extract_metadata: true,
     });
+    
+    if let Some(dir) = schema_dir {
+        parser = parser.with_schema_dir(dir);
+    }
+    if let Some(path) = schema_file {
+        parser = parser.with_schema(omnivore_core::parser::schema::Schema::load_from_path(&path)?);
+    }
+    
+    if let Some(ref schema) = schema {
+        println!("Validating against schema: {}", schema.yellow());
+    }
 
-    let result = parser.parse(&content)?;
+    let parsed = parser.parse_with_report(&content)?;
+    let result = parsed.data;
     
     // Extract text content for summary
     let text_content = parser.extract_text(&content);
@@ -924,17 +1320,47 @@ async fn parse_command(file: PathBuf, rules: Option<PathBuf>, output: Option<Pat
     println!("{}", "✅ Parsing complete!".bold().green());
     println!("Extracted {} characters of text", text_content.len().to_string().cyan());
     
+    if let Some(ref report) = parsed.validation {
+        if report.is_valid() {
+            println!(
+                "{}",
+                format!("✓ {} record(s) valid against schema '{}'", report.total_records, report.schema).green()
+            );
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "⚠️  {} of {} record(s) failed schema '{}' ({} error(s))",
+                    report.records.len(),
+                    report.total_records,
+                    report.schema,
+                    report.error_count()
+                )
+                .yellow()
+            );
+            for record in &report.records {
+                for error in &record.errors {
+                    println!("  record {}: {}: {}", record.record, error.field.cyan(), error.message);
+                }
+            }
+        }
+    }
+    
     // Determine output path
     let output_path = output.unwrap_or_else(|| generate_parse_filename(&file));
     
     // Create output with both parsed result and text content
-    let output_data = serde_json::json!({
+    let mut output_data = serde_json::json!({
         "parsed": result,
         "text_content": text_content,
         "source_file": file.display().to_string(),
         "parsed_at": chrono::Utc::now().to_rfc3339()
     });
     
+    if let Some(report) = parsed.validation {
+        output_data["validation"] = serde_json::to_value(report)?;
+    }
+    
     let output_json = serde_json::to_string_pretty(&output_data)?;
     tokio::fs::write(&output_path, output_json).await?;
     
@@ -1000,6 +1426,60 @@ async fn docs_command() -> Result<()> {
     Ok(())
 }
 
+fn load_template_engine(template: &str) -> Result<TemplateEngine> {
+    // Accept either a path to a template file or the name of a saved template
+    let path = std::path::Path::new(template);
+    let template = if path.is_file() {
+        ExtractionTemplate::load_from_path(path)?
+    } else {
+        ExtractionTemplate::load(template)?
+    };
+    
+    TemplateEngine::new(template)
+        .context("Failed to compile extraction template")
+}
+
+fn apply_template(engine: &TemplateEngine, results: &mut [CrawlResult]) {
+    println!();
+    println!("{}", format!("📋 Template: applying '{}'...", engine.template().name).bold().cyan());
+    
+    for result in results.iter_mut() {
+        match engine.execute(&result.content) {
+            Ok(output) => {
+                if !result.extracted_data.is_object() {
+                    result.extracted_data = serde_json::json!({});
+                }
+                result.extracted_data["template"] = output.data;
+                
+                if output.violations.is_empty() {
+                    println!("  ✓ Extracted data from {}", result.url.green());
+                } else {
+                    println!(
+                        "  ⚠️  {} schema violation(s) in {}",
+                        output.violations.len().to_string().yellow(),
+                        result.url
+                    );
+                    for violation in &output.violations {
+                        println!("      {}", violation.dimmed());
+                    }
+                    result.extracted_data["template_violations"] = serde_json::json!(output.violations);
+                }
+            }
+            Err(e) => {
+                println!("  ✗ Failed to extract from {}: {}", result.url.red(), e);
+            }
+        }
+    }
+}
+
+fn extracted_value(result: &CrawlResult) -> Option<serde_json::Value> {
+    match &result.extracted_data {
+        serde_json::Value::Null => None,
+        serde_json::Value::Object(map) if map.is_empty() => None,
+        value => Some(value.clone()),
+    }
+}
+
 #[cfg(all())]
 fn convert_dynamic_to_crawl_result(dynamic: omnivore_core::crawler::browser::DynamicContent) -> Result<CrawlResult> {
     use omnivore_core::extractor::ContentExtractor;
@@ -1036,6 +1516,130 @@ fn convert_dynamic_to_crawl_result(dynamic: omnivore_core::crawler::browser::Dyn
     })
 }
 
+/// Writes the crawl into the `[output.database_export]` database as one session.
+async fn export_to_database(
+    database: &DatabaseConfig,
+    session: &SessionRecord,
+    results: &[CrawlResult],
+    include_html: bool,
+) -> Result<()> {
+    let mut sink = SqlSink::connect(database)
+        .await
+        .context("Failed to connect to the export database")?
+        .include_html(include_html);
+    sink.create_tables().await?;
+    sink.start_session(session).await?;
+    for result in results {
+        sink.write(result).await?;
+    }
+    sink.finish_session(SessionStatus::Completed).await?;
+
+    println!(
+        "{}  Exported {} pages to the database (session {})",
+        "✅".bold().green(),
+        results.len().to_string().cyan(),
+        session.id.to_string().yellow()
+    );
+    Ok(())
+}
+
+/// Delivers webhook events in order on a background task, so a slow or
+/// unreachable receiver doesn't hold up the crawl.
+struct Webhooks {
+    notifier: WebhookNotifier,
+    sender: tokio::sync::mpsc::UnboundedSender<WebhookEvent>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Webhooks {
+    fn start(notifier: WebhookNotifier) -> Self {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<WebhookEvent>();
+        let task = tokio::spawn({
+            let notifier = notifier.clone();
+            async move {
+                while let Some(event) = receiver.recv().await {
+                    // Failures are logged and dead-lettered by the notifier
+                    let _ = notifier.send(event).await;
+                }
+            }
+        });
+        Self { notifier, sender, task }
+    }
+
+    fn send(&self, event: WebhookEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Waits for the events sent so far to be delivered or dead-lettered.
+    async fn finish(self) {
+        drop(self.sender);
+        let _ = self.task.await;
+    }
+}
+
+/// Compares the crawled pages with the content hashes in `state`, saved by
+/// the previous incremental crawl, then saves the new hashes. Changed pages
+/// are listed and sent as `page.changed` webhooks.
+fn report_changes(state: &std::path::Path, results: &[CrawlResult], webhooks: Option<&Webhooks>) -> Result<()> {
+    let mut hashes: std::collections::HashMap<String, String> = if state.exists() {
+        let content = std::fs::read_to_string(state)
+            .with_context(|| format!("Failed to read {}", state.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", state.display()))?
+    } else {
+        Default::default()
+    };
+    let changed = webhook::changed_pages(&mut hashes, results);
+    std::fs::write(state, serde_json::to_string_pretty(&hashes)?)
+        .with_context(|| format!("Failed to write {}", state.display()))?;
+
+    println!(
+        "{}  {} of {} pages changed since the last crawl",
+        "🔁".bold(),
+        changed.len().to_string().cyan(),
+        results.len()
+    );
+    for event in changed {
+        if let WebhookEvent::PageChanged { ref url, .. } = event {
+            println!("  {}", url.yellow());
+        }
+        if let Some(webhooks) = webhooks {
+            webhooks.send(event);
+        }
+    }
+    Ok(())
+}
+
+/// Uploads the crawl to the `[output.cloud_export]` bucket under its key layout.
+async fn export_to_cloud(
+    cloud_config: &CloudConfig,
+    session: &SessionRecord,
+    start_url: &Url,
+    stats: &CrawlStats,
+    results: &[CrawlResult],
+    include_html: bool,
+) -> Result<()> {
+    let store = cloud::connect(cloud_config).context("Failed to set up the cloud export")?;
+    let prefix = cloud::key_prefix(
+        &cloud_config.key_layout,
+        &session.id.to_string(),
+        chrono::Utc::now().date_naive(),
+        start_url.host_str().unwrap_or("unknown"),
+    );
+    let summary = cloud::export(store.as_ref(), &prefix, stats, results, include_html).await?;
+
+    println!(
+        "{}  Uploaded {} files ({} bytes) to {}://{}/{}",
+        "✅".bold().green(),
+        summary.files.to_string().cyan(),
+        summary.bytes,
+        cloud_config.provider,
+        cloud_config.bucket,
+        prefix.yellow()
+    );
+    Ok(())
+}
+
 #[allow(dead_code)]
 async fn handle_crawl_results(
     crawl_results: Vec<CrawlResult>,
@@ -1055,6 +1659,7 @@ async fn handle_crawl_results(
         average_response_time_ms: 0.0,
         start_time: chrono::Utc::now(),
         elapsed_time: std::time::Duration::from_secs(0),
+        proxies: Vec::new(),
     };
     
     println!();
@@ -309,6 +309,7 @@ fn create_template() -> Result<()> {
                 extract: vec!["name".to_string(), "price".to_string(), "description".to_string()],
                 transform: None,
                 required: true,
+                multiple: true,
             });
         }
         1 => {
@@ -320,6 +321,7 @@ fn create_template() -> Result<()> {
                 extract: vec!["title".to_string(), "authors".to_string(), "abstract".to_string()],
                 transform: None,
                 required: true,
+                multiple: true,
             });
         }
         2 => {
@@ -331,6 +333,7 @@ fn create_template() -> Result<()> {
                 extract: vec!["headline".to_string(), "date".to_string(), "content".to_string()],
                 transform: None,
                 required: true,
+                multiple: true,
             });
         }
         3 => {
@@ -342,6 +345,7 @@ fn create_template() -> Result<()> {
                 extract: vec!["email".to_string()],
                 transform: None,
                 required: false,
+                multiple: true,
             });
         }
         _ => {
@@ -388,6 +392,7 @@ fn create_template() -> Result<()> {
                     extract,
                     transform: None,
                     required: false,
+                    multiple: false,
                 });
             }
         }
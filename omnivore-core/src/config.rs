@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::env;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,20 +67,105 @@ pub struct OutputConfig {
     pub database_export: Option<DatabaseConfig>,
     pub cloud_export: Option<CloudConfig>,
     pub webhook_url: Option<String>,
+    /// How deliveries to `webhook_url` are signed, retried and alerted on
+    #[serde(default)]
+    pub webhook: WebhookConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     pub connection_string: String,
+    /// Prefix for the table names, empty for the tables of `scripts/init-db.sql`
+    #[serde(default)]
     pub table_name: String,
+    #[serde(default = "default_batch_size")]
     pub batch_size: u32,
 }
 
+fn default_batch_size() -> u32 {
+    100
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloudConfig {
     pub provider: String, // "s3", "gcs", "azure"
     pub bucket: String,
+    /// `ACCESS_KEY_ID:SECRET_ACCESS_KEY` for S3, read from the `AWS_*`
+    /// environment variables when unset
     pub credentials: Option<String>,
+    /// For S3-compatible services, e.g. `http://localhost:9000` for MinIO
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Key prefix of a crawl's files, with `{session}`, `{date}` and `{domain}` filled in
+    #[serde(default = "default_key_layout")]
+    pub key_layout: String,
+    /// Files larger than this are uploaded in parts of this size (at least 5)
+    #[serde(default = "default_part_size_mb")]
+    pub part_size_mb: usize,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+}
+
+fn default_key_layout() -> String {
+    "omnivore/{domain}/{date}/{session}".to_string()
+}
+
+fn default_part_size_mb() -> usize {
+    8
+}
+
+fn default_max_retries() -> usize {
+    3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// Key for the `X-Omnivore-Signature` HMAC-SHA256 header; deliveries are
+    /// unsigned without it
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Fraction of failed pages that sends an `error_threshold` event
+    #[serde(default = "default_error_threshold")]
+    pub error_threshold: f64,
+    /// Pages to crawl before the error threshold is checked
+    #[serde(default = "default_error_min_pages")]
+    pub error_min_pages: usize,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled for each one after it
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// JSON lines file for deliveries that still failed after every retry,
+    /// `~/.omnivore/webhook_dead_letters.jsonl` when unset
+    #[serde(default)]
+    pub dead_letter_path: Option<PathBuf>,
+}
+
+fn default_error_threshold() -> f64 {
+    0.5
+}
+
+fn default_error_min_pages() -> usize {
+    10
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    1000
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            error_threshold: default_error_threshold(),
+            error_min_pages: default_error_min_pages(),
+            max_retries: default_max_retries(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            dead_letter_path: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -165,6 +250,7 @@ impl Default for OutputConfig {
             database_export: None,
             cloud_export: None,
             webhook_url: None,
+            webhook: WebhookConfig::default(),
         }
     }
 }
@@ -257,6 +343,10 @@ impl OmnivoreConfig {
         if let Ok(webhook) = env::var("OMNIVORE_WEBHOOK_URL") {
             self.output.webhook_url = Some(webhook);
         }
+
+        if let Ok(secret) = env::var("OMNIVORE_WEBHOOK_SECRET") {
+            self.output.webhook.secret = Some(secret);
+        }
         
         if let Ok(ua) = env::var("OMNIVORE_USER_AGENT") {
             self.advanced.user_agent = ua;
@@ -309,6 +399,8 @@ pub struct PatternRule {
     pub extract: Vec<String>,
     pub transform: Option<String>,
     pub required: bool,
+    #[serde(default)]
+    pub multiple: bool,
 }
 
 impl ExtractionTemplate {
@@ -320,7 +412,11 @@ impl ExtractionTemplate {
             anyhow::bail!("Template '{}' not found", name);
         }
         
-        let content = fs::read_to_string(&template_path)
+        Self::load_from_path(&template_path)
+    }
+    
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
             .context("Failed to read template file")?;
         
         let template: Self = serde_yaml::from_str(&content)
@@ -122,6 +122,98 @@ impl BrowserEngine {
         })
     }
     
+    /// Renders `url` and pulls in up to `max_pages - 1` further pages of
+    /// items in place, by clicking a "load more" control or, failing that,
+    /// scrolling an infinite-scroll listing. The returned page has all loaded
+    /// items, and `extracted_data.pagination` records how they were loaded.
+    pub async fn crawl_paginated(&self, url: Url, max_pages: usize) -> Result<CrawlResult> {
+        let driver = self
+            .driver
+            .as_ref()
+            .ok_or_else(|| Error::Browser("Browser not connected".to_string()))?;
+
+        driver
+            .goto(url.as_str())
+            .await
+            .map_err(|e| Error::Browser(format!("Navigation failed: {e}")))?;
+
+        self.wait_for_page_ready(driver).await?;
+
+        let mut mode = "none";
+        let mut pages_loaded = 1;
+
+        while pages_loaded < max_pages {
+            if self.click_load_more(driver).await? {
+                mode = "load_more";
+            } else if self.detect_infinite_scroll(driver).await? {
+                mode = "infinite_scroll";
+            } else {
+                break;
+            }
+
+            pages_loaded += 1;
+            self.wait_for_page_ready(driver).await?;
+        }
+
+        info!("Loaded {} page(s) of {} via {}", pages_loaded, url, mode);
+
+        let content = driver
+            .source()
+            .await
+            .map_err(|e| Error::Browser(format!("Failed to get page source: {e}")))?;
+
+        let links = self.extract_links_js(driver, &url).await?;
+
+        let extractor = ContentExtractor::new();
+        let cleaned_content = Some(extractor.extract_clean_content(&content));
+
+        Ok(CrawlResult {
+            url: url.to_string(),
+            status_code: 200,
+            content,
+            cleaned_content,
+            headers: std::collections::HashMap::new(),
+            extracted_data: serde_json::json!({
+                "pagination": { "mode": mode, "pages_loaded": pages_loaded }
+            }),
+            links: links.into_iter().map(|u| u.to_string()).collect(),
+            crawled_at: chrono::Utc::now(),
+        })
+    }
+
+    /// Clicks the first visible "load more" control. Returns whether one was
+    /// found.
+    async fn click_load_more(&self, driver: &WebDriver) -> Result<bool> {
+        let script = r#"
+            const labelled = /^(load|show|view|see)\s+more/i;
+            const candidates = Array.from(document.querySelectorAll(
+                '.load-more, [data-load-more], button, a[role="button"], a'
+            ));
+            const button = candidates.find(el =>
+                el.offsetParent !== null && !el.disabled &&
+                (el.matches('.load-more, [data-load-more]') || labelled.test(el.textContent.trim()))
+            );
+            if (!button) return false;
+            button.scrollIntoView();
+            button.click();
+            return true;
+        "#;
+
+        let clicked = driver
+            .execute(script, vec![])
+            .await
+            .map_err(|e| Error::Browser(format!("Script execution failed: {e}")))?
+            .json()
+            .as_bool()
+            .unwrap_or(false);
+
+        if clicked {
+            sleep(Duration::from_secs(2)).await;
+        }
+
+        Ok(clicked)
+    }
+
     pub async fn crawl_with_interactions(&self, url: Url) -> Result<DynamicContent> {
         let driver = self
             .driver
@@ -0,0 +1,344 @@
+//! A crawl frontier shared by several crawler processes through Redis.
+//!
+//! Every process started with the same crawl id takes URLs from one queue.
+//! A URL is leased to the process that claims it until that process reports
+//! it done; leases that expire, e.g. because the process died, are put back
+//! in the queue by the next claim. The seen-set and the per-domain delay and
+//! rate limit are shared too, so the processes together crawl each URL once
+//! and no faster than one process would.
+//!
+//! Keys, all under `omnivore:<crawl id>:`:
+//!
+//! | Key | Type | Holds |
+//! |-----|------|-------|
+//! | `queue` | sorted set | URLs waiting, scored by priority |
+//! | `entries` | hash | Priority of every queued or leased URL |
+//! | `seen` | set | Every URL ever added |
+//! | `leases` | sorted set | Leased URLs, and URLs waiting for their domain's next request, scored by expiry in Unix milliseconds |
+//! | `owners` | hash | Id of the process holding each lease |
+//! | `domain:<host>:delay` | string | Set while the domain's delay runs |
+//! | `domain:<host>:rate` | string | Requests to the domain in the current second |
+
+use crate::{Error, PolitenessConfig, Result};
+use redis::aio::ConnectionManager;
+use redis::Script;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+/// Scores at or above this are regular entries, below it high priority
+/// ones. Either way the rest of the score is the depth, so high priority
+/// URLs come first, then shallower ones.
+const REGULAR: u64 = 1 << 20;
+
+/// Adds the URLs in `ARGV[3..]` with score `ARGV[1]` unless they were seen.
+/// When `ARGV[2]` is 1, seen URLs still waiting in the queue move up to
+/// `ARGV[1]` if that is lower.
+const ADD: &str = r#"
+local added = 0
+for i = 3, #ARGV do
+    if redis.call('SADD', KEYS[1], ARGV[i]) == 1 then
+        redis.call('HSET', KEYS[2], ARGV[i], ARGV[1])
+        redis.call('ZADD', KEYS[3], ARGV[1], ARGV[i])
+        added = added + 1
+    elseif ARGV[2] == '1' then
+        local queued = redis.call('ZSCORE', KEYS[3], ARGV[i])
+        if queued and tonumber(ARGV[1]) < tonumber(queued) then
+            redis.call('HSET', KEYS[2], ARGV[i], ARGV[1])
+            redis.call('ZADD', KEYS[3], ARGV[1], ARGV[i])
+        end
+    end
+end
+return added
+"#;
+
+/// Requeues expired leases, then leases the first URL in the queue to
+/// `ARGV[2]` for `ARGV[1]` ms. Returns the queue length left, the URL and
+/// its score, with an empty URL when the queue is empty.
+const CLAIM: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+for _, url in ipairs(redis.call('ZRANGEBYSCORE', KEYS[3], '-inf', now)) do
+    redis.call('ZREM', KEYS[3], url)
+    redis.call('HDEL', KEYS[4], url)
+    local score = redis.call('HGET', KEYS[2], url)
+    if score then
+        redis.call('ZADD', KEYS[1], score, url)
+    end
+end
+local next = redis.call('ZPOPMIN', KEYS[1])
+if #next == 0 then
+    return {0, '', 0}
+end
+redis.call('ZADD', KEYS[3], now + tonumber(ARGV[1]), next[1])
+redis.call('HSET', KEYS[4], next[1], ARGV[2])
+return {redis.call('ZCARD', KEYS[1]), next[1], tonumber(next[2])}
+"#;
+
+/// Ends the lease on `ARGV[1]` if `ARGV[2]` still holds it, putting the URL
+/// back in the queue when `ARGV[3]` is 1 and forgetting it otherwise.
+const RETURN: &str = r#"
+if redis.call('HGET', KEYS[3], ARGV[1]) ~= ARGV[2] then
+    return 0
+end
+redis.call('ZREM', KEYS[1], ARGV[1])
+redis.call('HDEL', KEYS[3], ARGV[1])
+if ARGV[3] == '1' then
+    redis.call('ZADD', KEYS[4], redis.call('HGET', KEYS[2], ARGV[1]), ARGV[1])
+else
+    redis.call('HDEL', KEYS[2], ARGV[1])
+end
+return 1
+"#;
+
+/// Ends the lease on `ARGV[1]` if `ARGV[2]` holds it, leasing the URL to
+/// nobody until its domain's delay (`KEYS[3]`) ends and, once `ARGV[3]`
+/// requests were made this second (`KEYS[4]`), the second does. The claim
+/// after that puts it back in the queue.
+const DEFER: &str = r#"
+if redis.call('HGET', KEYS[2], ARGV[1]) ~= ARGV[2] then
+    return 0
+end
+local wait = redis.call('PTTL', KEYS[3])
+if tonumber(redis.call('GET', KEYS[4]) or '0') >= tonumber(ARGV[3]) then
+    wait = math.max(wait, redis.call('PTTL', KEYS[4]))
+end
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+redis.call('ZADD', KEYS[1], now + math.max(wait, 1), ARGV[1])
+redis.call('HDEL', KEYS[2], ARGV[1])
+return 1
+"#;
+
+/// Takes a request slot for a domain: fails while its delay of `ARGV[1]` ms
+/// runs or once `ARGV[2]` requests were made in the current second.
+const ACQUIRE: &str = r#"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    return 0
+end
+if tonumber(redis.call('GET', KEYS[2]) or '0') >= tonumber(ARGV[2]) then
+    return 0
+end
+if tonumber(ARGV[1]) > 0 then
+    redis.call('SET', KEYS[1], 1, 'PX', ARGV[1])
+end
+if redis.call('INCR', KEYS[2]) == 1 then
+    redis.call('PEXPIRE', KEYS[2], 1000)
+end
+return 1
+"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributedConfig {
+    /// e.g. `redis://localhost:6379`
+    pub redis_url: String,
+    /// Processes with the same crawl id share a frontier.
+    pub crawl_id: String,
+    /// How long a claimed URL stays leased before other processes may take
+    /// it over. Should be longer than the slowest fetch, with retries.
+    #[serde(default = "default_lease_ms")]
+    pub lease_ms: u64,
+}
+
+fn default_lease_ms() -> u64 {
+    300_000
+}
+
+impl DistributedConfig {
+    pub fn new(redis_url: impl Into<String>, crawl_id: impl Into<String>) -> Self {
+        Self {
+            redis_url: redis_url.into(),
+            crawl_id: crawl_id.into(),
+            lease_ms: default_lease_ms(),
+        }
+    }
+}
+
+/// A URL claimed from the shared queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Claim {
+    pub url: Url,
+    pub depth: u32,
+    pub high_priority: bool,
+    /// URLs left in the queue after this one was taken.
+    pub remaining: usize,
+}
+
+pub struct SharedFrontier {
+    conn: ConnectionManager,
+    prefix: String,
+    owner: String,
+    lease_ms: u64,
+    add: Script,
+    claim: Script,
+    finish: Script,
+    defer: Script,
+    acquire: Script,
+}
+
+impl SharedFrontier {
+    pub async fn connect(config: &DistributedConfig) -> Result<Self> {
+        if config.crawl_id.is_empty() {
+            return Err(Error::InvalidConfig("crawl_id can't be empty".to_string()));
+        }
+        let conn = redis::Client::open(config.redis_url.as_str())?
+            .get_connection_manager()
+            .await?;
+
+        Ok(Self {
+            conn,
+            prefix: format!("omnivore:{}:", config.crawl_id),
+            owner: Uuid::new_v4().to_string(),
+            lease_ms: config.lease_ms.max(1),
+            add: Script::new(ADD),
+            claim: Script::new(CLAIM),
+            finish: Script::new(RETURN),
+            defer: Script::new(DEFER),
+            acquire: Script::new(ACQUIRE),
+        })
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    fn domain_key(&self, host: &str, name: &str) -> String {
+        self.key(&format!("domain:{}:{}", host, name))
+    }
+
+    /// Queues the URLs not seen before in this crawl and returns how many
+    /// were new. High priority URLs that are still queued move to the front.
+    pub async fn add(&self, urls: &[Url], depth: u32, high_priority: bool) -> Result<usize> {
+        if urls.is_empty() {
+            return Ok(0);
+        }
+        let score = if high_priority { 0 } else { REGULAR } + depth as u64;
+        let mut invocation = self.add.prepare_invoke();
+        invocation
+            .key(self.key("seen"))
+            .key(self.key("entries"))
+            .key(self.key("queue"))
+            .arg(score)
+            .arg(u8::from(high_priority));
+        for url in urls {
+            invocation.arg(url.as_str());
+        }
+        Ok(invocation.invoke_async(&mut self.conn.clone()).await?)
+    }
+
+    /// Leases the next URL to this process, after putting back URLs whose
+    /// lease expired.
+    pub async fn claim(&self) -> Result<Option<Claim>> {
+        let (remaining, url, score): (usize, String, u64) = self
+            .claim
+            .key(self.key("queue"))
+            .key(self.key("entries"))
+            .key(self.key("leases"))
+            .key(self.key("owners"))
+            .arg(self.lease_ms)
+            .arg(&self.owner)
+            .invoke_async(&mut self.conn.clone())
+            .await?;
+        if url.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Claim {
+            url: Url::parse(&url)?,
+            depth: (score % REGULAR) as u32,
+            high_priority: score < REGULAR,
+            remaining,
+        }))
+    }
+
+    /// Puts a claimed URL back in the queue for any process to take.
+    pub async fn release(&self, url: &Url) -> Result<bool> {
+        self.end_lease(url, true).await
+    }
+
+    /// Marks a claimed URL as crawled, or given up on. Returns false if the
+    /// lease had expired and another process took the URL over.
+    pub async fn complete(&self, url: &Url) -> Result<bool> {
+        self.end_lease(url, false).await
+    }
+
+    async fn end_lease(&self, url: &Url, requeue: bool) -> Result<bool> {
+        let ended: i64 = self
+            .finish
+            .key(self.key("leases"))
+            .key(self.key("entries"))
+            .key(self.key("owners"))
+            .key(self.key("queue"))
+            .arg(url.as_str())
+            .arg(&self.owner)
+            .arg(if requeue { "1" } else { "0" })
+            .invoke_async(&mut self.conn.clone())
+            .await?;
+        Ok(ended == 1)
+    }
+
+    /// Puts a claimed URL that `try_acquire` turned down back in the queue
+    /// once its domain has a request slot again, so it isn't claimed again
+    /// before then. Returns false if the lease had expired.
+    pub async fn defer(&self, url: &Url, politeness: &PolitenessConfig) -> Result<bool> {
+        let host = url.host_str().unwrap_or_default();
+        let deferred: i64 = self
+            .defer
+            .key(self.key("leases"))
+            .key(self.key("owners"))
+            .key(self.domain_key(host, "delay"))
+            .key(self.domain_key(host, "rate"))
+            .arg(url.as_str())
+            .arg(&self.owner)
+            .arg(requests_per_second(politeness))
+            .invoke_async(&mut self.conn.clone())
+            .await?;
+        Ok(deferred == 1)
+    }
+
+    /// Takes a request slot for `url`'s domain under `politeness`, counting
+    /// the requests of every process in the crawl. The delay runs from when
+    /// the slot is taken.
+    pub async fn try_acquire(&self, url: &Url, politeness: &PolitenessConfig) -> Result<bool> {
+        let Some(host) = url.host_str() else {
+            return Ok(false);
+        };
+        let acquired: i64 = self
+            .acquire
+            .key(self.domain_key(host, "delay"))
+            .key(self.domain_key(host, "rate"))
+            .arg(politeness.default_delay_ms)
+            .arg(requests_per_second(politeness))
+            .invoke_async(&mut self.conn.clone())
+            .await?;
+        Ok(acquired == 1)
+    }
+
+    /// URLs queued or leased by any process. Zero once the crawl is done.
+    pub async fn pending(&self) -> Result<usize> {
+        let (queued, leased): (usize, usize) = redis::pipe()
+            .zcard(self.key("queue"))
+            .zcard(self.key("leases"))
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(queued + leased)
+    }
+
+    /// Deletes the crawl's keys, so the crawl id can be used for a new
+    /// crawl. Domain keys expire on their own.
+    pub async fn clear(&self) -> Result<()> {
+        let keys: Vec<String> = ["queue", "entries", "seen", "leases", "owners"]
+            .iter()
+            .map(|name| self.key(name))
+            .collect();
+        redis::cmd("DEL")
+            .arg(keys)
+            .query_async::<()>(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+}
+
+fn requests_per_second(politeness: &PolitenessConfig) -> u64 {
+    (politeness.max_requests_per_second as u64).max(1)
+}
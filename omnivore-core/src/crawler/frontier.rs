@@ -3,9 +3,13 @@ use priority_queue::PriorityQueue;
 use std::collections::HashSet;
 use url::Url;
 
+/// Queue priority: high-priority entries (pagination) first, then shallower
+/// depths first.
+type Priority = (bool, i32);
+
 #[derive(Clone)]
 pub struct Frontier {
-    queue: PriorityQueue<Url, i32>,
+    queue: PriorityQueue<Url, Priority>,
     seen: HashSet<String>,
 }
 
@@ -24,21 +28,48 @@ impl Frontier {
     }
 
     pub fn add(&mut self, url: Url, depth: u32) -> Result<()> {
+        self.push_unseen(url, (false, -(depth as i32)));
+        Ok(())
+    }
+
+    /// Adds a URL ahead of regular links at any depth, e.g. the next page of
+    /// a listing that is being followed. A seen URL that is still queued is
+    /// moved up; one that was already taken is left alone.
+    pub fn add_high_priority(&mut self, url: Url, depth: u32) -> Result<()> {
+        let priority = (true, -(depth as i32));
+        if self.queue.get_priority(&url).is_some() {
+            self.queue.push_increase(url, priority);
+        } else {
+            self.push_unseen(url, priority);
+        }
+        Ok(())
+    }
+
+    /// Puts back a URL that was taken but could not be crawled yet. Unlike
+    /// `add`, this ignores the seen-set.
+    pub fn requeue(&mut self, url: Url, depth: u32, high_priority: bool) -> Result<()> {
+        self.queue.push(url, (high_priority, -(depth as i32)));
+        Ok(())
+    }
+
+    fn push_unseen(&mut self, url: Url, priority: Priority) {
         let url_str = url.as_str();
 
         if !self.seen.contains(url_str) {
             self.seen.insert(url_str.to_string());
-            let priority = -(depth as i32);
             self.queue.push(url, priority);
         }
-
-        Ok(())
     }
 
     pub fn get_next(&mut self) -> Option<(Url, u32)> {
-        self.queue.pop().map(|(url, priority)| {
+        self.get_next_entry().map(|(url, depth, _)| (url, depth))
+    }
+
+    /// Like `get_next`, but also reports whether the entry was high priority.
+    pub fn get_next_entry(&mut self) -> Option<(Url, u32, bool)> {
+        self.queue.pop().map(|(url, (high_priority, priority))| {
             let depth = (-priority) as u32;
-            (url, depth)
+            (url, depth, high_priority)
         })
     }
 
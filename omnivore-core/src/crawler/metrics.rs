@@ -0,0 +1,279 @@
+use crate::{Error, Result};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// Content type of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// How long `serve` waits after failing to accept a connection.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Fetch latency buckets in seconds, from fast local pages to the default
+/// 30s timeout.
+const LATENCY_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Prometheus metrics for one or more crawlers. Crawlers created with
+/// `Crawler::with_metrics` and the same `CrawlMetrics` report into the same
+/// series, so counters add up and gauges hold the sum over all of them.
+pub struct CrawlMetrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    bytes_fetched: IntCounter,
+    fetch_duration: Histogram,
+    frontier_size: IntGauge,
+    active_workers: IntGauge,
+    robots_denials: IntCounterVec,
+    retries: IntCounter,
+    politeness_waits: IntCounterVec,
+}
+
+impl Default for CrawlMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrawlMetrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "omnivore_requests_total",
+                "Pages fetched, by status class (2xx-5xx, or error when no response was received) and domain",
+            ),
+            &["status_class", "domain"],
+        )
+        .unwrap();
+        let bytes_fetched = IntCounter::new(
+            "omnivore_bytes_fetched_total",
+            "Response body bytes fetched",
+        )
+        .unwrap();
+        let fetch_duration = Histogram::with_opts(
+            HistogramOpts::new(
+                "omnivore_fetch_duration_seconds",
+                "Time to fetch a page, including retries",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        let frontier_size = IntGauge::new(
+            "omnivore_frontier_size",
+            "URLs waiting in the crawl frontier",
+        )
+        .unwrap();
+        let active_workers = IntGauge::new(
+            "omnivore_active_workers",
+            "Workers currently fetching a page",
+        )
+        .unwrap();
+        let robots_denials = IntCounterVec::new(
+            Opts::new(
+                "omnivore_robots_denials_total",
+                "URLs skipped because robots.txt disallows them",
+            ),
+            &["domain"],
+        )
+        .unwrap();
+        let retries = IntCounter::new(
+            "omnivore_retries_total",
+            "Fetch attempts retried after a network error or a proxy failure or block",
+        )
+        .unwrap();
+        let politeness_waits = IntCounterVec::new(
+            Opts::new(
+                "omnivore_politeness_waits_total",
+                "URLs put back in the frontier to respect the per-domain delay or rate limit",
+            ),
+            &["domain"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(bytes_fetched.clone())).unwrap();
+        registry.register(Box::new(fetch_duration.clone())).unwrap();
+        registry.register(Box::new(frontier_size.clone())).unwrap();
+        registry.register(Box::new(active_workers.clone())).unwrap();
+        registry.register(Box::new(robots_denials.clone())).unwrap();
+        registry.register(Box::new(retries.clone())).unwrap();
+        registry
+            .register(Box::new(politeness_waits.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            requests,
+            bytes_fetched,
+            fetch_duration,
+            frontier_size,
+            active_workers,
+            robots_denials,
+            retries,
+            politeness_waits,
+        }
+    }
+
+    /// The registry the crawl metrics live in. Register additional metrics
+    /// here to export them alongside.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn record_response(&self, url: &Url, status_code: u16, bytes: usize, elapsed: Duration) {
+        let class = format!("{}xx", status_code / 100);
+        self.requests
+            .with_label_values(&[class.as_str(), domain(url)])
+            .inc();
+        self.bytes_fetched.inc_by(bytes as u64);
+        self.fetch_duration.observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_error(&self, url: &Url, elapsed: Duration) {
+        self.requests
+            .with_label_values(&["error", domain(url)])
+            .inc();
+        self.fetch_duration.observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_retry(&self) {
+        self.retries.inc();
+    }
+
+    pub fn record_robots_denial(&self, url: &Url) {
+        self.robots_denials.with_label_values(&[domain(url)]).inc();
+    }
+
+    pub fn record_politeness_wait(&self, url: &Url) {
+        self.politeness_waits
+            .with_label_values(&[domain(url)])
+            .inc();
+    }
+
+    pub(crate) fn frontier_share(&self) -> GaugeShare {
+        GaugeShare::new(self.frontier_size.clone())
+    }
+
+    pub(crate) fn workers_share(&self) -> GaugeShare {
+        GaugeShare::new(self.active_workers.clone())
+    }
+
+    /// Every metric in the registry, in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        // Encoding into a Vec only fails on metric families the registry
+        // refuses to hold in the first place
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+fn domain(url: &Url) -> &str {
+    url.host_str().unwrap_or("unknown")
+}
+
+/// One crawler's part of a gauge that several crawlers report into. Setting
+/// it moves the gauge by the difference, and dropping it takes its part out.
+pub(crate) struct GaugeShare {
+    gauge: IntGauge,
+    value: AtomicI64,
+}
+
+impl GaugeShare {
+    fn new(gauge: IntGauge) -> Self {
+        Self {
+            gauge,
+            value: AtomicI64::new(0),
+        }
+    }
+
+    pub(crate) fn set(&self, value: usize) {
+        let value = value as i64;
+        let previous = self.value.swap(value, Ordering::SeqCst);
+        self.gauge.add(value - previous);
+    }
+}
+
+impl Drop for GaugeShare {
+    fn drop(&mut self) {
+        self.gauge.sub(self.value.load(Ordering::SeqCst));
+    }
+}
+
+/// Serves `metrics` on `GET /metrics` at `addr` in a background task, for
+/// processes that don't run an HTTP server of their own. Fails if `addr`
+/// can't be bound; the task runs until it is aborted.
+pub async fn serve(addr: &str, metrics: Arc<CrawlMetrics>) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr).await.map_err(|e| {
+        Error::InvalidConfig(format!("Failed to bind metrics address {}: {}", addr, e))
+    })?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // Usually out of file descriptors, so wait for some to close
+                    tracing::warn!("Failed to accept metrics connection: {}", e);
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                if let Err(e) = respond(stream, &metrics).await {
+                    tracing::debug!("Metrics request failed: {}", e);
+                }
+            });
+        }
+    }))
+}
+
+async fn respond(mut stream: TcpStream, metrics: &CrawlMetrics) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path.split('?').next()) {
+        ("GET", Some("/metrics")) => ("200 OK", CONTENT_TYPE, metrics.render()),
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n".to_string(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -1,31 +1,144 @@
 pub mod browser;
+pub mod distributed;
 pub mod frontier;
+pub mod metrics;
+pub mod pagination;
 pub mod politeness;
+pub mod proxy;
 pub mod robots;
 pub mod scheduler;
+pub mod session;
 pub mod worker;
 
-use crate::{CrawlConfig, CrawlResult, CrawlStats, Result};
-use std::sync::Arc;
+use crate::{CrawlConfig, CrawlResult, CrawlStats, PolitenessConfig, Result};
+use distributed::SharedFrontier;
+use metrics::{CrawlMetrics, GaugeShare};
+use proxy::ProxyPool;
+use session::CrawlSession;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use url::Url;
 
+/// The crawler's own frontier, or one shared with other processes.
+#[derive(Clone)]
+enum Queue {
+    Local(Arc<RwLock<frontier::Frontier>>),
+    Shared(Arc<SharedFrontier>),
+}
+
+impl Queue {
+    async fn add(&self, urls: Vec<Url>, depth: u32, high_priority: bool) -> Result<()> {
+        match self {
+            Queue::Local(frontier) => {
+                let mut frontier = frontier.write().await;
+                for url in urls {
+                    if high_priority {
+                        frontier.add_high_priority(url, depth)?;
+                    } else {
+                        frontier.add(url, depth)?;
+                    }
+                }
+                Ok(())
+            }
+            Queue::Shared(shared) => shared.add(&urls, depth, high_priority).await.map(|_| ()),
+        }
+    }
+
+    /// The next URL with its depth and priority, and the number of URLs
+    /// left in the queue.
+    async fn next(&self) -> Result<(Option<(Url, u32, bool)>, usize)> {
+        match self {
+            Queue::Local(frontier) => {
+                let mut frontier = frontier.write().await;
+                let entry = frontier.get_next_entry();
+                Ok((entry, frontier.size()))
+            }
+            Queue::Shared(shared) => Ok(match shared.claim().await? {
+                Some(claim) => (
+                    Some((claim.url, claim.depth, claim.high_priority)),
+                    claim.remaining,
+                ),
+                None => (None, 0),
+            }),
+        }
+    }
+
+    /// Puts back a URL the politeness engine turned down. A shared frontier
+    /// holds it until its domain has a request slot again.
+    async fn requeue(
+        &self,
+        url: Url,
+        depth: u32,
+        high_priority: bool,
+        politeness: &PolitenessConfig,
+    ) -> Result<()> {
+        match self {
+            Queue::Local(frontier) => frontier.write().await.requeue(url, depth, high_priority),
+            Queue::Shared(shared) => shared.defer(&url, politeness).await.map(|_| ()),
+        }
+    }
+
+    /// Tells the other processes that `url` was handled, crawled or not.
+    async fn done(&self, url: &Url) {
+        if let Queue::Shared(shared) = self {
+            if let Err(e) = shared.complete(url).await {
+                tracing::warn!("Failed to complete {} in the shared frontier: {}", url, e);
+            }
+        }
+    }
+
+    /// Whether no process has URLs left to crawl. Only asked once this
+    /// crawler has nothing in progress.
+    async fn is_drained(&self) -> Result<bool> {
+        match self {
+            Queue::Local(_) => Ok(true),
+            Queue::Shared(shared) => Ok(shared.pending().await? == 0),
+        }
+    }
+}
+
 pub struct Crawler {
     config: Arc<CrawlConfig>,
     scheduler: scheduler::Scheduler,
-    frontier: Arc<RwLock<frontier::Frontier>>,
+    frontier: Queue,
     politeness_engine: Arc<politeness::PolitenessEngine>,
+    robots: Option<Arc<robots::RobotsChecker>>,
+    metrics: Arc<CrawlMetrics>,
+    frontier_gauge: GaugeShare,
+    workers_gauge: GaugeShare,
     stats: Arc<RwLock<CrawlStats>>,
     results: Arc<RwLock<Vec<CrawlResult>>>,
+    pagination: Arc<RwLock<pagination::PaginationTracker>>,
+    proxies: Option<Arc<ProxyPool>>,
+    session: Arc<CrawlSession>,
+    /// Trace spans of URLs put back in the frontier by the politeness
+    /// engine: the URL's `crawl_url` span and its open `politeness_wait`.
+    waiting: Mutex<HashMap<String, (Span, Span)>>,
+    paused: AtomicBool,
+    cancelled: AtomicBool,
 }
 
 impl Crawler {
     pub async fn new(config: CrawlConfig) -> Result<Self> {
+        Self::with_metrics(config, Arc::new(CrawlMetrics::new())).await
+    }
+
+    /// Like `new`, but reports into `metrics`, which may be shared with
+    /// other crawlers.
+    pub async fn with_metrics(config: CrawlConfig, metrics: Arc<CrawlMetrics>) -> Result<Self> {
         let config = Arc::new(config);
         let scheduler = scheduler::Scheduler::new(config.max_workers);
-        let frontier = Arc::new(RwLock::new(frontier::Frontier::new()));
+        let frontier = Queue::Local(Arc::new(RwLock::new(frontier::Frontier::new())));
         let politeness_engine =
             Arc::new(politeness::PolitenessEngine::new(config.politeness.clone()));
+        let robots = config
+            .respect_robots_txt
+            .then(|| Arc::new(robots::RobotsChecker::new(config.user_agent.clone())));
         let stats = Arc::new(RwLock::new(CrawlStats {
             total_urls: 0,
             successful: 0,
@@ -34,51 +147,121 @@ impl Crawler {
             average_response_time_ms: 0.0,
             start_time: chrono::Utc::now(),
             elapsed_time: std::time::Duration::from_secs(0),
+            proxies: Vec::new(),
         }));
         let results = Arc::new(RwLock::new(Vec::new()));
+        let pagination = Arc::new(RwLock::new(pagination::PaginationTracker::new(
+            config.pagination.max_pages,
+        )));
+        let session = Arc::new(CrawlSession::new(&config.session)?);
+        let proxies = ProxyPool::new(&config, &session)?.map(Arc::new);
 
         Ok(Self {
             config,
             scheduler,
             frontier,
             politeness_engine,
+            robots,
+            frontier_gauge: metrics.frontier_share(),
+            workers_gauge: metrics.workers_share(),
+            metrics,
             stats,
             results,
+            pagination,
+            proxies,
+            session,
+            waiting: Mutex::new(HashMap::new()),
+            paused: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
         })
     }
 
+    /// Takes URLs from `frontier`, shared with the other processes of a
+    /// distributed crawl, instead of a frontier of its own. The per-domain
+    /// delay and rate limit then apply to all the processes together.
+    pub fn with_shared_frontier(mut self, frontier: SharedFrontier) -> Self {
+        self.frontier = Queue::Shared(Arc::new(frontier));
+        self
+    }
+
     pub async fn add_seed(&self, url: Url) -> Result<()> {
-        let mut frontier = self.frontier.write().await;
-        frontier.add(url, 0)?;
-        Ok(())
+        self.frontier.add(vec![url], 0, false).await
     }
 
     pub async fn add_seeds(&self, urls: Vec<Url>) -> Result<()> {
-        let mut frontier = self.frontier.write().await;
-        for url in urls {
-            frontier.add(url, 0)?;
-        }
-        Ok(())
+        self.frontier.add(urls, 0, false).await
     }
 
     pub async fn start(self: &Arc<Self>) -> Result<()> {
         let start_time = std::time::Instant::now();
+        self.login().await?;
+        let mut queued = true;
 
         loop {
-            let url_entry = {
-                let mut frontier = self.frontier.write().await;
-                frontier.get_next()
+            if self.cancelled.load(Ordering::SeqCst) {
+                break;
+            }
+            if self.paused.load(Ordering::SeqCst) {
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                continue;
+            }
+
+            // Opened before the pop to time it, and given its `crawl_url`
+            // parent once the URL is known. Polling an empty frontier gets
+            // no span, since there would be no URL to parent it.
+            let pop = if queued {
+                tracing::info_span!(parent: None, "frontier_pop")
+            } else {
+                Span::none()
             };
+            let url_entry = match self.frontier.next().await {
+                Ok((entry, size)) => {
+                    self.frontier_gauge.set(size);
+                    queued = size > 0;
+                    entry
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to take a URL from the frontier: {}", e);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            self.workers_gauge.set(self.scheduler.active_workers());
 
-            if let Some((url, depth)) = url_entry {
+            if let Some((url, depth, high_priority)) = url_entry {
                 if depth > self.config.max_depth {
+                    self.frontier.done(&url).await;
                     continue;
                 }
 
-                let can_crawl = self.politeness_engine.can_crawl(&url).await;
+                let span = self.url_span(&url, depth);
+                let pop = if pop.is_disabled() {
+                    tracing::info_span!(parent: &span, "frontier_pop")
+                } else {
+                    let _ = pop.set_parent(span.context());
+                    pop
+                };
+
+                let can_crawl = match &self.frontier {
+                    Queue::Local(_) => self.politeness_engine.can_crawl(&url).await,
+                    Queue::Shared(shared) => shared
+                        .try_acquire(&url, &self.config.politeness)
+                        .await
+                        .unwrap_or_else(|e| {
+                            tracing::warn!("Failed to check the shared rate limit: {}", e);
+                            false
+                        }),
+                };
                 if !can_crawl {
-                    let mut frontier = self.frontier.write().await;
-                    frontier.add(url, depth)?;
+                    self.metrics.record_politeness_wait(&url);
+                    let wait = tracing::info_span!(parent: &span, "politeness_wait");
+                    self.waiting
+                        .lock()
+                        .unwrap()
+                        .insert(url.to_string(), (span, wait));
+                    self.frontier
+                        .requeue(url, depth, high_priority, &self.config.politeness)
+                        .await?;
                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     continue;
                 }
@@ -88,32 +271,85 @@ impl Crawler {
                 let politeness = self.politeness_engine.clone();
                 let stats = self.stats.clone();
                 let results = self.results.clone();
+                let pagination = self.pagination.clone();
+                let metrics = self.metrics.clone();
+                let proxies = self.proxies.clone();
+                let session = self.session.clone();
+                let robots = self.robots.clone();
+
+                {
+                    let mut stats = self.stats.write().await;
+                    stats.in_progress += 1;
+                    stats.total_urls += 1;
+                }
 
                 self.scheduler
                     .spawn(async move {
-                        let worker = worker::Worker::new(config.clone());
+                        // Checked by the worker rather than the dispatch loop,
+                        // since the first check for a domain fetches its robots.txt
+                        if let Some(robots) = robots {
+                            if !robots.is_allowed(&url).await.unwrap_or(true) {
+                                tracing::debug!("robots.txt disallows {}", url);
+                                metrics.record_robots_denial(&url);
+                                let mut stats = stats.write().await;
+                                stats.in_progress -= 1;
+                                stats.total_urls -= 1;
+                                drop(stats);
+                                frontier.done(&url).await;
+                                return;
+                            }
+                        }
+
+                        let mut worker = worker::Worker::new(config.clone(), metrics, session);
+                        if let Some(proxies) = proxies {
+                            worker = worker.with_proxies(proxies);
+                        }
                         match worker.crawl(url.clone()).await {
                             Ok(result) => {
                                 politeness.record_crawl(&url).await;
 
                                 // Store the crawl result
-                                let mut results_guard = results.write().await;
-                                results_guard.push(result.clone());
+                                async {
+                                    results.write().await.push(result.clone());
+                                }
+                                .instrument(tracing::info_span!("sink_write"))
+                                .await;
 
                                 let mut stats = stats.write().await;
                                 stats.successful += 1;
                                 stats.in_progress -= 1;
 
-                                let mut frontier = frontier.write().await;
-                                for link_str in result.links.iter() {
-                                    if let Ok(link_url) = url::Url::parse(link_str) {
-                                        let _ = frontier.add(link_url, depth + 1);
+                                // Next pages of a listing go ahead of regular links and
+                                // don't count as an extra level of depth
+                                let next_pages = if config.pagination.follow {
+                                    pagination.write().await.targets(&result)
+                                } else {
+                                    Vec::new()
+                                };
+
+                                // Links past the depth limit aren't queued at all
+                                let links = if depth < config.max_depth {
+                                    result
+                                        .links
+                                        .iter()
+                                        .filter_map(|link| Url::parse(link).ok())
+                                        .collect()
+                                } else {
+                                    Vec::new()
+                                };
+                                for (urls, depth, high_priority) in
+                                    [(next_pages, depth, true), (links, depth + 1, false)]
+                                {
+                                    if let Err(e) = frontier.add(urls, depth, high_priority).await {
+                                        tracing::warn!("Failed to queue links of {}: {}", url, e);
                                     }
                                 }
                             }
                             Err(e) => {
                                 let error_msg = format!("Failed to crawl {}: {}", url, e);
                                 tracing::error!("{}", error_msg);
+                                Span::current().record("otel.status_code", "ERROR");
+                                Span::current().record("otel.status_message", e.to_string());
                                 
                                 // Write to error log file
                                 let error_entry = format!(
@@ -136,15 +372,13 @@ impl Crawler {
                                 stats.in_progress -= 1;
                             }
                         }
-                    })
+                        frontier.done(&url).await;
+                    }.instrument(span))
                     .await;
-
-                let mut stats = self.stats.write().await;
-                stats.in_progress += 1;
-                stats.total_urls += 1;
+                drop(pop);
             } else {
-                let stats = self.stats.read().await;
-                if stats.in_progress == 0 {
+                let idle = self.stats.read().await.in_progress == 0;
+                if idle && self.frontier.is_drained().await? {
                     break;
                 }
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -154,18 +388,96 @@ impl Crawler {
             stats.elapsed_time = start_time.elapsed();
         }
 
+        // URLs put back for politeness that another process of a
+        // distributed crawl went on to crawl never come back here
+        self.waiting.lock().unwrap().clear();
+        self.frontier_gauge.set(0);
+        self.workers_gauge.set(0);
         Ok(())
     }
 
+    /// Submits the login form of `config.session.login`, if any, so the
+    /// pages are crawled with the cookies it sets. Goes through a proxy when
+    /// there are some, like the crawl itself.
+    async fn login(&self) -> Result<()> {
+        let Some(login) = &self.config.session.login else {
+            return Ok(());
+        };
+        let client = match &self.proxies {
+            Some(proxies) => proxies.pick(&Url::parse(&login.url)?).client,
+            None => worker::client_builder(&self.config, &self.session).build()?,
+        };
+        self.session.login(&client, login).await
+    }
+
+    /// The `crawl_url` span that traces `url` from leaving the frontier until
+    /// its result is stored. A URL that waited for politeness continues the
+    /// span it started with, closing its `politeness_wait`.
+    fn url_span(&self, url: &Url, depth: u32) -> Span {
+        match self.waiting.lock().unwrap().remove(url.as_str()) {
+            Some((span, _wait)) => span,
+            None => tracing::info_span!(
+                parent: None,
+                "crawl_url",
+                "url.full" = %url,
+                "crawl.depth" = i64::from(depth),
+                "otel.status_code" = Empty,
+                "otel.status_message" = Empty,
+            ),
+        }
+    }
+
+    pub fn metrics(&self) -> &Arc<CrawlMetrics> {
+        &self.metrics
+    }
+
     pub async fn get_stats(&self) -> CrawlStats {
-        self.stats.read().await.clone()
+        let mut stats = self.stats.read().await.clone();
+        if let Some(proxies) = &self.proxies {
+            stats.proxies = proxies.stats();
+        }
+        stats
     }
 
     pub async fn get_results(&self) -> Vec<CrawlResult> {
         self.results.read().await.clone()
     }
 
+    /// Up to `limit` results starting at `offset`, in the order they were
+    /// crawled, along with the total number of results so far.
+    pub async fn get_results_page(&self, offset: usize, limit: usize) -> (Vec<CrawlResult>, usize) {
+        let results = self.results.read().await;
+        let page = results.iter().skip(offset).take(limit).cloned().collect();
+        (page, results.len())
+    }
+
+    /// Paginated listings followed during the crawl, for stitching with
+    /// `pagination::stitch_listings`.
+    pub async fn get_listings(&self) -> Vec<pagination::PaginatedListing> {
+        self.pagination.read().await.listings()
+    }
+
+    /// Stops taking URLs from the frontier until `resume` is called. Pages
+    /// already being fetched still complete.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Makes `start` return without crawling the rest of the frontier.
     pub async fn stop(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
         self.scheduler.shutdown().await;
     }
 }
@@ -0,0 +1,156 @@
+use crate::detector::UniversalDetector;
+use crate::CrawlResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use url::Url;
+
+/// The pages of one paginated listing, in the order they were followed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginatedListing {
+    pub url: String,
+    pub pages: Vec<String>,
+}
+
+/// The extracted data of every page of a listing merged into one record set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListingDataset {
+    pub listing_url: String,
+    pub pages: Vec<String>,
+    pub data: Value,
+}
+
+/// Tracks which listing each followed page belongs to, so pagination links
+/// found on page N are attributed to the listing that page 1 started.
+#[derive(Debug, Default)]
+pub struct PaginationTracker {
+    max_pages: usize,
+    listings: HashMap<String, Vec<String>>,
+    page_to_listing: HashMap<String, String>,
+    order: Vec<String>,
+}
+
+impl PaginationTracker {
+    pub fn new(max_pages: usize) -> Self {
+        Self {
+            max_pages,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the pagination URLs of `result` that should be crawled next.
+    ///
+    /// The "next" link is preferred so pages are followed in order; numbered
+    /// page links are used only when there is none. Links to other hosts and
+    /// pages beyond `max_pages` for the listing are dropped.
+    pub fn targets(&mut self, result: &CrawlResult) -> Vec<Url> {
+        let Ok(page_url) = Url::parse(&result.url) else {
+            return Vec::new();
+        };
+
+        let pagination = match UniversalDetector::new(&result.content, Some(&result.url))
+            .detect_pagination()
+        {
+            Some(pagination) => pagination,
+            None => return Vec::new(),
+        };
+
+        let candidates: Vec<String> = match pagination.next_url {
+            Some(next) => vec![next],
+            None => pagination.page_urls,
+        };
+
+        let root = self.listing_for(&result.url);
+        let mut targets = Vec::new();
+
+        for candidate in candidates {
+            let Ok(url) = Url::parse(&candidate) else {
+                continue;
+            };
+            if url.host_str() != page_url.host_str() || self.page_to_listing.contains_key(url.as_str()) {
+                continue;
+            }
+
+            let pages = self.listings.entry(root.clone()).or_default();
+            if pages.len() >= self.max_pages {
+                break;
+            }
+            pages.push(url.to_string());
+            self.page_to_listing.insert(url.to_string(), root.clone());
+            targets.push(url);
+        }
+
+        targets
+    }
+
+    /// Listings that spanned more than one page.
+    pub fn listings(&self) -> Vec<PaginatedListing> {
+        self.order
+            .iter()
+            .filter_map(|root| {
+                let pages = &self.listings[root];
+                (pages.len() > 1).then(|| PaginatedListing {
+                    url: root.clone(),
+                    pages: pages.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn listing_for(&mut self, url: &str) -> String {
+        if let Some(root) = self.page_to_listing.get(url) {
+            return root.clone();
+        }
+
+        self.page_to_listing.insert(url.to_string(), url.to_string());
+        self.listings.insert(url.to_string(), vec![url.to_string()]);
+        self.order.push(url.to_string());
+        url.to_string()
+    }
+}
+
+/// Merges the `extracted_data` of each listing's pages in page order. Arrays
+/// are concatenated, objects are merged key by key, and for scalars the first
+/// page wins. Pages that were not crawled are skipped.
+pub fn stitch_listings(results: &[CrawlResult], listings: &[PaginatedListing]) -> Vec<ListingDataset> {
+    let by_url: HashMap<&str, &CrawlResult> = results.iter().map(|r| (r.url.as_str(), r)).collect();
+
+    listings
+        .iter()
+        .map(|listing| {
+            let mut pages = Vec::new();
+            let mut data = Value::Null;
+
+            for page in &listing.pages {
+                if let Some(result) = by_url.get(page.as_str()) {
+                    pages.push(page.clone());
+                    merge_values(&mut data, result.extracted_data.clone());
+                }
+            }
+
+            ListingDataset {
+                listing_url: listing.url.clone(),
+                pages,
+                data,
+            }
+        })
+        .collect()
+}
+
+fn merge_values(target: &mut Value, value: Value) {
+    match (target, value) {
+        (Value::Array(items), Value::Array(more)) => items.extend(more),
+        (Value::Object(fields), Value::Object(more)) => {
+            for (key, value) in more {
+                match fields.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        fields.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target @ Value::Null, value) => *target = value,
+        _ => {}
+    }
+}
@@ -0,0 +1,289 @@
+//! Crawling through a pool of proxies.
+//!
+//! Each proxy gets an HTTP client of its own. Requests take the next proxy
+//! in turn, or with `ProxyRotation::PerDomain` keep using the proxy their
+//! domain was first given. A proxy whose request fails, or that refuses its
+//! credentials, is left out of rotation for `ProxyConfig::cooldown_ms`,
+//! doubled for each failure in a row, and then tried again by the next
+//! request that gets it. A site blocking a proxy only takes it out of
+//! rotation for that site's domain, the same way.
+
+use super::session::CrawlSession;
+use crate::{CrawlConfig, Error, ProxyRotation, Result};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Cooldowns stop doubling after this many failures in a row.
+const MAX_COOLDOWN_DOUBLINGS: u32 = 4;
+
+/// Responses that mean the site turned the proxy away.
+const BLOCKED_STATUSES: [StatusCode; 2] = [StatusCode::FORBIDDEN, StatusCode::TOO_MANY_REQUESTS];
+
+/// Requests through one proxy so far.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProxyStats {
+    /// The proxy URL without its password.
+    pub proxy: String,
+    pub requests: u64,
+    pub successful: u64,
+    pub failed: u64,
+    pub blocked: u64,
+    pub average_response_time_ms: f64,
+    /// False while the proxy is out of rotation for every domain.
+    pub healthy: bool,
+}
+
+/// What came of a request through a proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyOutcome {
+    Success(Duration),
+    /// The request didn't get a response, or the proxy wanted other
+    /// credentials.
+    Failed,
+    /// The site answered with one of the blocked statuses.
+    Blocked,
+}
+
+impl ProxyOutcome {
+    /// `Failed` for a 407 from the proxy, `Blocked` for statuses that mean
+    /// the site turned the proxy away, otherwise `Success`.
+    pub fn from_status(status: StatusCode, elapsed: Duration) -> Self {
+        if status == StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+            ProxyOutcome::Failed
+        } else if BLOCKED_STATUSES.contains(&status) {
+            ProxyOutcome::Blocked
+        } else {
+            ProxyOutcome::Success(elapsed)
+        }
+    }
+}
+
+/// The proxy picked for a request.
+#[derive(Clone)]
+pub struct ProxyChoice {
+    pub index: usize,
+    pub client: Client,
+    /// The domain of the URL it was picked for.
+    pub domain: String,
+}
+
+struct Proxy {
+    label: String,
+    client: Client,
+    health: Mutex<Health>,
+}
+
+#[derive(Default)]
+struct Health {
+    stats: ProxyStats,
+    total_response_ms: f64,
+    cooldown: Cooldown,
+    /// Cooldowns of the domains that blocked the proxy.
+    blocked_by: HashMap<String, Cooldown>,
+}
+
+impl Health {
+    /// When the proxy can be used for `domain` again, `None` if it can now.
+    fn out_until(&self, domain: &str, now: Instant) -> Option<Instant> {
+        let blocked = self.blocked_by.get(domain).and_then(|c| c.out_until);
+        self.cooldown.out_until.max(blocked).filter(|&until| until > now)
+    }
+}
+
+#[derive(Default)]
+struct Cooldown {
+    failures_in_a_row: u32,
+    out_until: Option<Instant>,
+}
+
+impl Cooldown {
+    /// Starts the next, longer cooldown and returns its length.
+    fn extend(&mut self, base: Duration) -> Duration {
+        let cooldown = base * 2u32.pow(self.failures_in_a_row.min(MAX_COOLDOWN_DOUBLINGS));
+        self.failures_in_a_row += 1;
+        self.out_until = Some(Instant::now() + cooldown);
+        cooldown
+    }
+}
+
+pub struct ProxyPool {
+    proxies: Vec<Proxy>,
+    rotation: ProxyRotation,
+    cooldown: Duration,
+    next: AtomicUsize,
+    /// Proxy of each domain, with `ProxyRotation::PerDomain`.
+    domains: Mutex<HashMap<String, usize>>,
+}
+
+impl ProxyPool {
+    /// A pool of the proxies in `config.proxies`, or `None` when there are
+    /// none. Their clients all keep cookies in `session`'s jar.
+    pub fn new(config: &CrawlConfig, session: &CrawlSession) -> Result<Option<Self>> {
+        if config.proxies.urls.is_empty() {
+            return Ok(None);
+        }
+
+        let proxies = config
+            .proxies
+            .urls
+            .iter()
+            .map(|proxy_url| {
+                let label = label(proxy_url)?;
+                let proxy = reqwest::Proxy::all(proxy_url.as_str())
+                    .map_err(|e| Error::InvalidConfig(format!("Invalid proxy {}: {}", label, e)))?;
+                let client = super::worker::client_builder(config, session)
+                    .proxy(proxy)
+                    .build()?;
+                Ok(Proxy {
+                    health: Mutex::new(Health {
+                        stats: ProxyStats {
+                            proxy: label.clone(),
+                            healthy: true,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }),
+                    label,
+                    client,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(Self {
+            proxies,
+            rotation: config.proxies.rotation,
+            cooldown: Duration::from_millis(config.proxies.cooldown_ms),
+            next: AtomicUsize::new(0),
+            domains: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// The proxy to fetch `url` through. When every proxy is out of
+    /// rotation, the one due back first is used rather than stalling the
+    /// crawl.
+    pub fn pick(&self, url: &Url) -> ProxyChoice {
+        let now = Instant::now();
+        let domain = url.host_str().unwrap_or_default().to_string();
+        let index = match self.rotation {
+            ProxyRotation::PerRequest => self.next_available(&domain, now),
+            ProxyRotation::PerDomain => {
+                let mut domains = self.domains.lock().unwrap();
+                match domains.get(&domain) {
+                    Some(&index) if self.out_until(index, &domain, now).is_none() => index,
+                    _ => {
+                        let index = self.next_available(&domain, now);
+                        domains.insert(domain.clone(), index);
+                        index
+                    }
+                }
+            }
+        };
+
+        ProxyChoice {
+            index,
+            client: self.proxies[index].client.clone(),
+            domain,
+        }
+    }
+
+    pub fn record(&self, choice: &ProxyChoice, outcome: ProxyOutcome) {
+        let proxy = &self.proxies[choice.index];
+        let mut health = proxy.health.lock().unwrap();
+        health.stats.requests += 1;
+
+        match outcome {
+            ProxyOutcome::Success(elapsed) => {
+                health.stats.successful += 1;
+                health.total_response_ms += elapsed.as_secs_f64() * 1000.0;
+                health.stats.average_response_time_ms =
+                    health.total_response_ms / health.stats.successful as f64;
+                health.cooldown = Cooldown::default();
+                health.blocked_by.remove(&choice.domain);
+            }
+            ProxyOutcome::Failed => {
+                health.stats.failed += 1;
+                let cooldown = health.cooldown.extend(self.cooldown);
+                tracing::warn!(
+                    "Proxy {} failed, out of rotation for {:?}",
+                    proxy.label,
+                    cooldown
+                );
+            }
+            ProxyOutcome::Blocked => {
+                health.stats.blocked += 1;
+                let cooldown = health
+                    .blocked_by
+                    .entry(choice.domain.clone())
+                    .or_default()
+                    .extend(self.cooldown);
+                tracing::warn!(
+                    "Proxy {} was blocked by {}, out of rotation there for {:?}",
+                    proxy.label,
+                    choice.domain,
+                    cooldown
+                );
+            }
+        }
+    }
+
+    pub fn stats(&self) -> Vec<ProxyStats> {
+        let now = Instant::now();
+        self.proxies
+            .iter()
+            .map(|proxy| {
+                let health = proxy.health.lock().unwrap();
+                ProxyStats {
+                    healthy: health.cooldown.out_until.is_none_or(|until| until <= now),
+                    ..health.stats.clone()
+                }
+            })
+            .collect()
+    }
+
+    fn out_until(&self, index: usize, domain: &str, now: Instant) -> Option<Instant> {
+        self.proxies[index].health.lock().unwrap().out_until(domain, now)
+    }
+
+    fn next_available(&self, domain: &str, now: Instant) -> usize {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.proxies.len())
+            .map(|offset| (start + offset) % self.proxies.len())
+            .find(|&index| self.out_until(index, domain, now).is_none())
+            .unwrap_or_else(|| {
+                (0..self.proxies.len())
+                    .min_by_key(|&index| self.out_until(index, domain, now))
+                    .unwrap_or(0)
+            })
+    }
+}
+
+/// Serializes proxy URLs without their passwords, so crawl configs can be
+/// logged, stored and sent in webhooks.
+pub fn serialize_without_passwords<S: serde::Serializer>(
+    urls: &[String],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        urls.iter()
+            .map(|proxy_url| label(proxy_url).unwrap_or_else(|_| "<invalid>".to_string())),
+    )
+}
+
+/// `proxy_url` without its password, for logs and stats.
+fn label(proxy_url: &str) -> Result<String> {
+    let mut url = Url::parse(proxy_url)
+        .map_err(|e| Error::InvalidConfig(format!("Invalid proxy URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(Error::InvalidConfig(format!(
+            "Unsupported proxy scheme '{}', expected http, https, socks5 or socks5h",
+            url.scheme()
+        )));
+    }
+    let _ = url.set_password(None);
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
@@ -1,5 +1,6 @@
 use crate::{Error, Result};
 use dashmap::DashMap;
+use robotstxt::DefaultMatcher;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use url::Url;
@@ -7,6 +8,7 @@ use url::Url;
 pub struct RobotsChecker {
     cache: Arc<DashMap<String, CachedRobots>>,
     client: reqwest::Client,
+    user_agent: String,
 }
 
 struct CachedRobots {
@@ -16,8 +18,9 @@ struct CachedRobots {
 }
 
 impl RobotsChecker {
-    pub fn new(_user_agent: String) -> Self {
+    pub fn new(user_agent: String) -> Self {
         let client = reqwest::Client::builder()
+            .user_agent(&user_agent)
             .timeout(Duration::from_secs(10))
             .build()
             .expect("Failed to build HTTP client");
@@ -25,6 +28,7 @@ impl RobotsChecker {
         Self {
             cache: Arc::new(DashMap::new()),
             client,
+            user_agent,
         }
     }
 
@@ -56,10 +60,10 @@ impl RobotsChecker {
         Ok(allowed)
     }
 
-    fn check_robots_txt(&self, _robots_txt: &str, _url: &str) -> bool {
-        // TODO: Implement proper robots.txt parsing
-        // For now, allow all URLs
-        true
+    fn check_robots_txt(&self, robots_txt: &str, url: &str) -> bool {
+        // Rules are matched on the product token, e.g. "Omnivore" in "Omnivore/1.0"
+        let agent = self.user_agent.split('/').next().unwrap_or_default();
+        DefaultMatcher::default().one_agent_allowed_by_robots(robots_txt, agent, url)
     }
 
     fn get_robots_url(&self, url: &Url) -> Result<Url> {
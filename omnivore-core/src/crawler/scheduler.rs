@@ -5,6 +5,7 @@ use tokio::task::JoinHandle;
 
 pub struct Scheduler {
     semaphore: Arc<Semaphore>,
+    max_workers: usize,
     handles: Vec<JoinHandle<()>>,
 }
 
@@ -12,6 +13,7 @@ impl Scheduler {
     pub fn new(max_workers: usize) -> Self {
         Self {
             semaphore: Arc::new(Semaphore::new(max_workers)),
+            max_workers,
             handles: Vec::new(),
         }
     }
@@ -33,10 +35,8 @@ impl Scheduler {
         }
     }
 
+    /// Tasks spawned that haven't finished yet.
     pub fn active_workers(&self) -> usize {
-        let max = self.semaphore.available_permits();
-        let total = self.semaphore.available_permits() + 
-                   (self.handles.len() - self.handles.iter().filter(|h| h.is_finished()).count());
-        total - max
+        self.max_workers - self.semaphore.available_permits()
     }
 }
@@ -0,0 +1,381 @@
+//! What every request of a crawl shares: one cookie jar, headers by domain,
+//! and the login they're made with.
+//!
+//! The jar starts with the cookies of `SessionConfig::cookie_files`, in
+//! either of the formats browsers and their extensions export:
+//!
+//! - Netscape `cookies.txt`: tab separated domain, subdomains flag, path,
+//!   secure flag, expiry and name and value, with `#HttpOnly_` before the
+//!   domain of HTTP-only cookies
+//! - JSON: an array of cookies, or an object with a `cookies` array, each
+//!   with `name`, `value`, `domain`, and optionally `path`, `secure`,
+//!   `httpOnly`, `hostOnly` and `expirationDate` or `expires`
+//!
+//! With `SessionConfig::login`, `CrawlSession::login` finds the login form
+//! with `UniversalDetector::detect_forms` and submits it, keeping whatever
+//! cookies the site sets, before the first page is crawled.
+
+use crate::detector::{FormElement, UniversalDetector};
+use crate::{Error, Result};
+use crate::{LoginConfig, SessionConfig};
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use url::Url;
+
+/// Field types a username can be typed into.
+const USERNAME_TYPES: [&str; 3] = ["text", "email", "tel"];
+
+/// One cookie from an imported file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedCookie {
+    pub name: String,
+    pub value: String,
+    /// Without a leading dot.
+    pub domain: String,
+    /// Whether subdomains of `domain` get the cookie too.
+    pub include_subdomains: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// Unix seconds, `None` for a session cookie.
+    pub expires: Option<i64>,
+}
+
+impl ImportedCookie {
+    /// The cookie as a `Set-Cookie` header, and the URL it would have come
+    /// from.
+    fn to_set_cookie(&self, now: i64) -> Option<(String, Url)> {
+        let mut header = format!("{}={}; Path={}", self.name, self.value, self.path);
+        if self.include_subdomains {
+            header.push_str(&format!("; Domain={}", self.domain));
+        }
+        if self.secure {
+            header.push_str("; Secure");
+        }
+        if self.http_only {
+            header.push_str("; HttpOnly");
+        }
+        if let Some(expires) = self.expires {
+            if expires <= now {
+                return None;
+            }
+            header.push_str(&format!("; Max-Age={}", expires - now));
+        }
+
+        let scheme = if self.secure { "https" } else { "http" };
+        let url = Url::parse(&format!("{}://{}{}", scheme, self.domain, self.path)).ok()?;
+        Some((header, url))
+    }
+}
+
+/// The cookies in a Netscape `cookies.txt` or browser-exported JSON file,
+/// told apart by their first character.
+pub fn parse_cookies(content: &str) -> Result<Vec<ImportedCookie>> {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        parse_json_cookies(trimmed)
+    } else {
+        parse_netscape_cookies(content)
+    }
+}
+
+fn parse_netscape_cookies(content: &str) -> Result<Vec<ImportedCookie>> {
+    let mut cookies = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let columns: Vec<&str> = line.split('\t').collect();
+        let [domain, subdomains, path, secure, expires, name, value] = columns[..] else {
+            return Err(Error::Parse(format!(
+                "Line {} of the cookie file doesn't have 7 tab separated columns",
+                number + 1
+            )));
+        };
+        let expires: i64 = expires.trim().parse().map_err(|_| {
+            Error::Parse(format!(
+                "Line {} of the cookie file has an invalid expiry",
+                number + 1
+            ))
+        })?;
+        cookies.push(ImportedCookie {
+            name: name.to_string(),
+            value: value.trim_end_matches('\r').to_string(),
+            domain: domain.trim_start_matches('.').to_string(),
+            include_subdomains: subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            expires: (expires > 0).then_some(expires),
+        });
+    }
+    Ok(cookies)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonCookies {
+    List(Vec<JsonCookie>),
+    /// Like Playwright's `storageState`.
+    State {
+        cookies: Vec<JsonCookie>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonCookie {
+    name: String,
+    value: String,
+    domain: String,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default)]
+    http_only: bool,
+    #[serde(default)]
+    host_only: Option<bool>,
+    #[serde(default)]
+    session: bool,
+    #[serde(default, alias = "expires")]
+    expiration_date: Option<f64>,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+fn parse_json_cookies(content: &str) -> Result<Vec<ImportedCookie>> {
+    let cookies = match serde_json::from_str(content)? {
+        JsonCookies::List(cookies) | JsonCookies::State { cookies } => cookies,
+    };
+    Ok(cookies
+        .into_iter()
+        .map(|cookie| ImportedCookie {
+            include_subdomains: cookie
+                .host_only
+                .map_or(cookie.domain.starts_with('.'), |host_only| !host_only),
+            domain: cookie.domain.trim_start_matches('.').to_string(),
+            name: cookie.name,
+            value: cookie.value,
+            path: cookie.path,
+            secure: cookie.secure,
+            http_only: cookie.http_only,
+            // Playwright marks session cookies with an expiry of -1
+            expires: cookie
+                .expiration_date
+                .filter(|expires| !cookie.session && *expires > 0.0)
+                .map(|expires| expires as i64),
+        })
+        .collect())
+}
+
+/// Serializes headers by domain with their values left out, so crawl
+/// configs can be logged, stored and sent in webhooks.
+pub fn serialize_without_values<S: serde::Serializer>(
+    headers: &HashMap<String, HashMap<String, String>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_map(headers.iter().map(|(domain, headers)| {
+        let names: HashMap<&str, &str> = headers
+            .keys()
+            .map(|name| (name.as_str(), "<redacted>"))
+            .collect();
+        (domain, names)
+    }))
+}
+
+/// The cookie jar and headers of one crawl.
+pub struct CrawlSession {
+    jar: Arc<Jar>,
+    /// `*` first, then by domain length, so the most specific domain's
+    /// headers are applied last and win.
+    headers: Vec<(String, HeaderMap)>,
+}
+
+impl CrawlSession {
+    /// A session with the cookies of `config.cookie_files` and its headers,
+    /// which are checked here.
+    pub fn new(config: &SessionConfig) -> Result<Self> {
+        let mut session = Self {
+            jar: Arc::new(Jar::default()),
+            headers: config
+                .headers
+                .iter()
+                .map(|(domain, headers)| {
+                    let map = headers
+                        .iter()
+                        .map(|(name, value)| {
+                            let invalid = || {
+                                Error::InvalidConfig(format!(
+                                    "Invalid header '{}' for {}",
+                                    name, domain
+                                ))
+                            };
+                            let name =
+                                HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+                            let mut value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+                            value.set_sensitive(true);
+                            Ok((name, value))
+                        })
+                        .collect::<Result<HeaderMap>>()?;
+                    Ok((domain.trim_start_matches('.').to_string(), map))
+                })
+                .collect::<Result<_>>()?,
+        };
+        session
+            .headers
+            .sort_by_key(|(domain, _)| (domain != "*", domain.len()));
+
+        for path in &config.cookie_files {
+            let count = session.import_cookies(path)?;
+            tracing::info!("Loaded {} cookies from {}", count, path.display());
+        }
+        Ok(session)
+    }
+
+    /// The jar every client of the crawl keeps its cookies in.
+    pub fn jar(&self) -> Arc<Jar> {
+        self.jar.clone()
+    }
+
+    /// Adds the unexpired cookies of a Netscape or JSON cookie file to the
+    /// jar, returning how many.
+    pub fn import_cookies(&self, path: &Path) -> Result<usize> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidConfig(format!("Can't read cookies from {}: {}", path.display(), e))
+        })?;
+        let cookies = parse_cookies(&content).map_err(|e| {
+            Error::InvalidConfig(format!("Can't read cookies from {}: {}", path.display(), e))
+        })?;
+
+        let now = chrono::Utc::now().timestamp();
+        let mut count = 0;
+        for (header, url) in cookies.iter().filter_map(|c| c.to_set_cookie(now)) {
+            self.jar.add_cookie_str(&header, &url);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// The headers configured for `url`'s domain, its parent domains and `*`,
+    /// a header set for several of them taking the most specific one's value.
+    pub fn headers_for(&self, url: &Url) -> HeaderMap {
+        let host = url.host_str().unwrap_or_default();
+        let mut headers = HeaderMap::new();
+        for (domain, map) in &self.headers {
+            let matches = domain == "*"
+                || host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'));
+            if matches {
+                headers.extend(map.clone());
+            }
+        }
+        headers
+    }
+
+    /// Submits the login form on `login.url` with `client`, which has to use
+    /// this session's jar for the cookies it gets to be kept.
+    pub async fn login(&self, client: &Client, login: &LoginConfig) -> Result<()> {
+        let page_url = Url::parse(&login.url)?;
+        let response = client
+            .get(page_url.clone())
+            .headers(self.headers_for(&page_url))
+            .send()
+            .await?
+            .error_for_status()?;
+        let page_url = response.url().clone();
+        let html = response.text().await?;
+
+        let forms = UniversalDetector::new(&html, Some(page_url.as_str())).detect_forms();
+        let form = forms
+            .iter()
+            .find(|form| password_field(form, login).is_some())
+            .ok_or_else(|| Error::Login(format!("No login form on {}", page_url)))?;
+        let password = password_field(form, login).unwrap_or_default();
+        let username = match &login.username_field {
+            Some(name) => name.clone(),
+            None => form
+                .fields
+                .iter()
+                .find(|field| USERNAME_TYPES.contains(&field.field_type.as_str()))
+                .map(|field| field.name.clone())
+                .ok_or_else(|| {
+                    Error::Login(format!("No username field in the form on {}", page_url))
+                })?,
+        };
+
+        // Keep hidden fields like CSRF tokens as the page set them
+        let mut values: Vec<(String, String)> = form
+            .fields
+            .iter()
+            .filter(|field| {
+                field.name != username
+                    && field.name != password
+                    && !login.fields.contains_key(&field.name)
+            })
+            .filter_map(|field| Some((field.name.clone(), field.value.clone()?)))
+            .collect();
+        values.push((username, login.username.clone()));
+        values.push((password, login.password.clone()));
+        values.extend(login.fields.clone());
+
+        let action = match form.action.as_deref() {
+            Some(action) if !action.is_empty() => page_url.join(action)?,
+            _ => page_url.clone(),
+        };
+        let request = if form.method.eq_ignore_ascii_case("post") {
+            client.post(action.clone()).form(&values)
+        } else {
+            client.get(action.clone()).query(&values)
+        };
+        let response = request.headers(self.headers_for(&action)).send().await?;
+        let status = response.status();
+        let landed = response.url().clone();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return Err(Error::Login(format!("{} answered {}", landed, status)));
+        }
+        let logged_in = match &login.success_text {
+            Some(text) => body.contains(text.as_str()),
+            None => !UniversalDetector::new(&body, Some(landed.as_str()))
+                .detect_forms()
+                .iter()
+                .any(|form| password_field(form, login).is_some()),
+        };
+        if !logged_in {
+            return Err(Error::Login(format!(
+                "{} doesn't look logged in, check the username and password",
+                landed
+            )));
+        }
+        tracing::info!("Logged in at {} as {}", action, login.username);
+        Ok(())
+    }
+}
+
+/// The form's password field: the one named in `login`, or else its first
+/// password input.
+fn password_field(form: &FormElement, login: &LoginConfig) -> Option<String> {
+    form.fields
+        .iter()
+        .find(|field| match &login.password_field {
+            Some(name) => &field.name == name,
+            None => field.field_type == "password",
+        })
+        .map(|field| field.name.clone())
+}
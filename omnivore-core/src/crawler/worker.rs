@@ -1,46 +1,84 @@
+use super::metrics::CrawlMetrics;
+use super::proxy::{ProxyOutcome, ProxyPool};
+use super::session::CrawlSession;
 use crate::{CrawlConfig, CrawlResult, Error, Result};
 use crate::extractor::ContentExtractor;
 use reqwest::Client;
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
 use url::Url;
 
 pub struct Worker {
     client: Client,
     config: Arc<CrawlConfig>,
+    metrics: Arc<CrawlMetrics>,
+    proxies: Option<Arc<ProxyPool>>,
+    session: Arc<CrawlSession>,
+}
+
+/// The HTTP client settings every crawl request uses, with or without a
+/// proxy, keeping cookies in the session's jar.
+pub(crate) fn client_builder(
+    config: &CrawlConfig,
+    session: &CrawlSession,
+) -> reqwest::ClientBuilder {
+    Client::builder()
+        .user_agent(&config.user_agent)
+        .cookie_provider(session.jar())
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .gzip(true)
+        .brotli(true)
 }
 
 impl Worker {
-    pub fn new(config: Arc<CrawlConfig>) -> Self {
-        let client = Client::builder()
-            .user_agent(&config.user_agent)
-            .timeout(Duration::from_millis(config.timeout_ms))
-            .redirect(reqwest::redirect::Policy::limited(10))
-            .gzip(true)
-            .brotli(true)
+    pub fn new(
+        config: Arc<CrawlConfig>,
+        metrics: Arc<CrawlMetrics>,
+        session: Arc<CrawlSession>,
+    ) -> Self {
+        let client = client_builder(&config, &session)
             .build()
             .expect("Failed to build HTTP client");
 
-        Self { client, config }
+        Self {
+            client,
+            config,
+            metrics,
+            proxies: None,
+            session,
+        }
     }
 
-    pub async fn crawl(&self, url: Url) -> Result<CrawlResult> {
-        let response = self.fetch_with_retry(&url).await?;
-        let status_code = response.status().as_u16();
-
-        let headers = response
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
-            .collect();
+    /// Sends requests through the proxies of `pool` instead of directly.
+    pub fn with_proxies(mut self, pool: Arc<ProxyPool>) -> Self {
+        self.proxies = Some(pool);
+        self
+    }
 
-        let content = response.text().await?;
+    pub async fn crawl(&self, url: Url) -> Result<CrawlResult> {
+        let fetch_span = tracing::info_span!(
+            "fetch",
+            "http.response.status_code" = Empty,
+            "http.response.body.size" = Empty,
+            "http.request.resend_count" = Empty,
+        );
+        let (status_code, headers, content) = self.fetch(&url).instrument(fetch_span).await?;
 
         // Extract clean content
-        let extractor = ContentExtractor::new();
-        let cleaned_content = Some(extractor.extract_clean_content(&content));
+        let cleaned_content = tracing::info_span!("extract_content").in_scope(|| {
+            let extractor = ContentExtractor::new();
+            Some(extractor.extract_clean_content(&content))
+        });
 
-        let links = self.extract_links(&url, &content)?;
+        let links = tracing::info_span!("extract_links", "link.count" = Empty).in_scope(|| {
+            let links = self.extract_links(&url, &content)?;
+            Span::current().record("link.count", links.len() as i64);
+            Ok::<_, Error>(links)
+        })?;
 
         Ok(CrawlResult {
             url: url.to_string(),
@@ -54,13 +92,61 @@ impl Worker {
         })
     }
 
+    /// The status, headers and body of `url`, recorded in the metrics and on
+    /// the current span.
+    async fn fetch(&self, url: &Url) -> Result<(u16, HashMap<String, String>, String)> {
+        let started = Instant::now();
+        let fetched = async {
+            let response = self.fetch_with_retry(url).await?;
+            let status_code = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
+                .collect();
+            let content = response.text().await?;
+            Ok((status_code, headers, content))
+        }
+        .await;
+
+        match &fetched {
+            Ok((status_code, _, content)) => {
+                self.metrics
+                    .record_response(url, *status_code, content.len(), started.elapsed());
+                let span = Span::current();
+                span.record("http.response.status_code", i64::from(*status_code));
+                span.record("http.response.body.size", content.len() as i64);
+            }
+            Err(_) => self.metrics.record_error(url, started.elapsed()),
+        }
+        fetched
+    }
+
     async fn fetch_with_retry(&self, url: &Url) -> Result<reqwest::Response> {
         let mut attempts = 0;
         let mut last_error = None;
 
         while attempts < self.config.max_retries {
-            match self.client.get(url.as_str()).send().await {
+            let proxy = self.proxies.as_ref().map(|pool| pool.pick(url));
+            let client = proxy.as_ref().map_or(&self.client, |proxy| &proxy.client);
+            let sent = Instant::now();
+            let request = client.get(url.as_str()).headers(self.session.headers_for(url));
+            match request.send().await {
                 Ok(response) => {
+                    if let (Some(pool), Some(proxy)) = (&self.proxies, &proxy) {
+                        let outcome = ProxyOutcome::from_status(response.status(), sent.elapsed());
+                        pool.record(proxy, outcome);
+                        // Try again through another proxy
+                        if !matches!(outcome, ProxyOutcome::Success(_))
+                            && attempts + 1 < self.config.max_retries
+                        {
+                            attempts += 1;
+                            self.metrics.record_retry();
+                            Span::current()
+                                .record("http.request.resend_count", i64::from(attempts));
+                            continue;
+                        }
+                    }
                     // Log redirect chain if any
                     if response.url() != url {
                         let redirect_msg = format!("Redirected: {} -> {}", url, response.url());
@@ -85,10 +171,15 @@ impl Worker {
                     return Ok(response);
                 }
                 Err(e) => {
+                    if let (Some(pool), Some(proxy)) = (&self.proxies, &proxy) {
+                        pool.record(proxy, ProxyOutcome::Failed);
+                    }
                     attempts += 1;
                     last_error = Some(e);
 
                     if attempts < self.config.max_retries {
+                        self.metrics.record_retry();
+                        Span::current().record("http.request.resend_count", i64::from(attempts));
                         let delay = Duration::from_millis(
                             100 * (self
                                 .config
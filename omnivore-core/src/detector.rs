@@ -41,6 +41,10 @@ pub struct FormField {
     pub label: Option<String>,
     pub required: bool,
     pub options: Vec<String>, // For select/radio/checkbox
+    /// What submitting the form as it is would send: the value of inputs,
+    /// checked checkboxes and radio buttons, and the selected option.
+    #[serde(default)]
+    pub value: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +64,15 @@ pub struct PaginationElement {
     pub total_pages: Option<usize>,
     pub current_page: Option<usize>,
     pub pagination_type: String, // "numbered", "next_prev", "infinite_scroll"
+    /// Resolved URL of the next page, when there is a link to follow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_url: Option<String>,
+    /// Resolved URLs of the numbered page links, in page order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub page_urls: Vec<String>,
+    /// Button or link that loads more items in place.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_more_selector: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -302,6 +315,7 @@ impl UniversalDetector {
                 let label = self.find_label_for_field(&field);
                 let required = field.value().attr("required").is_some();
                 let options = self.extract_field_options(&field);
+                let value = Self::field_value(&field, &field_type);
                 
                 if !name.is_empty() {
                     fields.push(FormField {
@@ -310,6 +324,7 @@ impl UniversalDetector {
                         label,
                         required,
                         options,
+                        value,
                     });
                 }
             }
@@ -318,6 +333,26 @@ impl UniversalDetector {
         fields
     }
     
+    fn field_value(field: &ElementRef, field_type: &str) -> Option<String> {
+        match field_type {
+            "submit" | "button" | "reset" | "image" | "file" => None,
+            "checkbox" | "radio" => field.value().attr("checked")
+                .map(|_| field.value().attr("value").unwrap_or("on").to_string()),
+            "textarea" => Some(field.text().collect()),
+            "select" => {
+                let selector = Selector::parse("option").ok()?;
+                let mut options = field.select(&selector);
+                let option = options.clone()
+                    .find(|option| option.value().attr("selected").is_some())
+                    .or_else(|| options.next())?;
+                Some(option.value().attr("value")
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| option.text().collect::<String>().trim().to_string()))
+            }
+            _ => Some(field.value().attr("value").unwrap_or_default().to_string()),
+        }
+    }
+    
     fn find_label_for_field(&self, field: &ElementRef) -> Option<String> {
         // Try to find associated label
         if let Some(id) = field.value().attr("id") {
@@ -413,11 +448,30 @@ impl UniversalDetector {
         for pattern in page_patterns {
             if let Ok(selector) = Selector::parse(pattern) {
                 if let Some(element) = self.document.select(&selector).next() {
-                    return Some(self.analyze_pagination(&element));
+                    let mut pagination = self.analyze_pagination(&element);
+                    if pagination.next_url.is_none() {
+                        pagination.next_url = self.find_rel_next();
+                    }
+                    return Some(pagination);
                 }
             }
         }
         
+        // A bare rel="next" link (often only in <head>) is still pagination
+        if let Some(next_url) = self.find_rel_next() {
+            return Some(PaginationElement {
+                next_selector: Some("a[rel='next'], link[rel='next']".to_string()),
+                prev_selector: None,
+                page_numbers: Vec::new(),
+                total_pages: None,
+                current_page: None,
+                pagination_type: "next_prev".to_string(),
+                next_url: Some(next_url),
+                page_urls: Vec::new(),
+                load_more_selector: None,
+            });
+        }
+        
         // Check for infinite scroll indicators
         if self.detect_infinite_scroll() {
             return Some(PaginationElement {
@@ -427,12 +481,35 @@ impl UniversalDetector {
                 total_pages: None,
                 current_page: None,
                 pagination_type: "infinite_scroll".to_string(),
+                next_url: None,
+                page_urls: Vec::new(),
+                load_more_selector: self.find_load_more(),
             });
         }
         
         None
     }
     
+    fn find_rel_next(&self) -> Option<String> {
+        let selector = Selector::parse("link[rel~='next'][href], a[rel~='next'][href]").ok()?;
+        self.document
+            .select(&selector)
+            .next()
+            .and_then(|el| el.value().attr("href"))
+            .map(|href| self.resolve_url(href))
+    }
+    
+    fn find_load_more(&self) -> Option<String> {
+        [".load-more", "[data-load-more]", "button.load-more", "a.load-more"]
+            .into_iter()
+            .find(|pattern| {
+                Selector::parse(pattern)
+                    .map(|selector| self.document.select(&selector).next().is_some())
+                    .unwrap_or(false)
+            })
+            .map(String::from)
+    }
+    
     fn analyze_pagination(&self, element: &ElementRef) -> PaginationElement {
         let mut pagination = PaginationElement {
             next_selector: None,
@@ -441,31 +518,68 @@ impl UniversalDetector {
             total_pages: None,
             current_page: None,
             pagination_type: "numbered".to_string(),
+            next_url: None,
+            page_urls: Vec::new(),
+            load_more_selector: None,
         };
         
-        // Find next/prev links
-        if let Ok(selector) = Selector::parse("a[rel='next'], .next, a:contains('Next')") {
-            if element.select(&selector).next().is_some() {
-                pagination.next_selector = Some("a[rel='next'], .next".to_string());
-            }
-        }
-        
-        if let Ok(selector) = Selector::parse("a[rel='prev'], .prev, a:contains('Previous')") {
-            if element.select(&selector).next().is_some() {
-                pagination.prev_selector = Some("a[rel='prev'], .prev".to_string());
-            }
+        // Find next/prev links. CSS has no text matching, so "Next"/"Previous"
+        // labels are checked by hand.
+        let is_labelled = |link: &ElementRef, labels: &[&str]| {
+            let text = link.text().collect::<String>().trim().to_lowercase();
+            let aria = link.value().attr("aria-label").unwrap_or_default().to_lowercase();
+            labels.iter().any(|label| text.starts_with(label) || aria.starts_with(label))
+        };
+        let links: Vec<ElementRef> = Selector::parse("a")
+            .map(|selector| element.select(&selector).collect())
+            .unwrap_or_default();
+        let next_selector = Selector::parse("a[rel~='next'], .next").unwrap();
+        let prev_selector = Selector::parse("a[rel~='prev'], .prev").unwrap();
+        
+        let next_link = element
+            .select(&next_selector)
+            .next()
+            .or_else(|| links.iter().copied().find(|l| is_labelled(l, &["next", "›", "»", ">"])));
+        if let Some(next) = next_link {
+            pagination.next_selector = Some("a[rel='next'], .next".to_string());
+            pagination.next_url = next
+                .value()
+                .attr("href")
+                .or_else(|| {
+                    // `.next` may be a wrapper such as <li class="next"><a href>
+                    next.select(&Selector::parse("a[href]").unwrap())
+                        .next()
+                        .and_then(|a| a.value().attr("href"))
+                })
+                .filter(|href| !href.starts_with('#') && !href.starts_with("javascript:"))
+                .map(|href| self.resolve_url(href));
+        }
+        
+        if element.select(&prev_selector).next().is_some()
+            || links.iter().any(|l| is_labelled(l, &["prev", "‹", "«", "<"]))
+        {
+            pagination.prev_selector = Some("a[rel='prev'], .prev".to_string());
         }
         
         // Extract page numbers
-        if let Ok(selector) = Selector::parse("a") {
-            for link in element.select(&selector) {
-                let text = link.text().collect::<String>().trim().to_string();
-                if text.parse::<usize>().is_ok() {
-                    pagination.page_numbers.push(text);
+        for link in &links {
+            let text = link.text().collect::<String>().trim().to_string();
+            if text.parse::<usize>().is_ok() {
+                if let Some(href) = link.value().attr("href") {
+                    pagination.page_urls.push(self.resolve_url(href));
                 }
+                pagination.page_numbers.push(text);
             }
         }
         
+        // The current page is usually rendered without a link
+        if let Ok(selector) = Selector::parse(".current, .active, [aria-current='page']") {
+            pagination.current_page = element
+                .select(&selector)
+                .filter_map(|el| el.text().collect::<String>().trim().parse::<usize>().ok())
+                .next();
+        }
+        
         if !pagination.page_numbers.is_empty() {
             pagination.total_pages = pagination.page_numbers
                 .iter()
@@ -44,12 +44,21 @@ pub enum Error {
     #[error("Storage error: {0}")]
     Storage(String),
 
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
     #[error("Graph error: {0}")]
     Graph(String),
 
     #[error("Intelligence processing error: {0}")]
     Intelligence(String),
 
+    #[error("Webhook delivery failed: {0}")]
+    Webhook(String),
+
+    #[error("Login failed: {0}")]
+    Login(String),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
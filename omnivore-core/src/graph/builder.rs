@@ -1,9 +1,17 @@
 use crate::graph::{Edge, KnowledgeGraph, Node};
+use crate::intelligence::entity::EntityRecognizer;
 use crate::Result;
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub const PAGE_NODE: &str = "page";
+pub const LINKS_TO_EDGE: &str = "links_to";
+pub const MENTIONS_EDGE: &str = "mentions";
 
 pub struct GraphBuilder {
     graph: KnowledgeGraph,
+    /// `(from, to, type)` of the edges `add_page` has added.
+    page_edges: HashSet<(String, String, String)>,
 }
 
 impl Default for GraphBuilder {
@@ -16,6 +24,7 @@ impl GraphBuilder {
     pub fn new() -> Self {
         Self {
             graph: KnowledgeGraph::new(),
+            page_edges: HashSet::new(),
         }
     }
 
@@ -49,6 +58,77 @@ impl GraphBuilder {
         self.graph.add_edge(edge)
     }
 
+    /// Adds a crawled page as a `page` node keyed by its URL, with
+    /// `links_to` edges to the pages it links to and `mentions` edges to the
+    /// entities recognized in `text`. Entity nodes are typed by entity kind
+    /// (`EMAIL`, `URL`, ...) and shared between pages that mention them.
+    /// A page added again, or linking somewhere twice, adds no second edge.
+    pub fn add_page(
+        &mut self,
+        url: &str,
+        title: Option<&str>,
+        text: &str,
+        links: &[String],
+    ) -> Result<()> {
+        self.ensure_page(url);
+        if let Some(idx) = self.graph.node_index.get(url) {
+            let properties = &mut self.graph.graph[*idx].properties;
+            properties.insert("crawled".to_string(), json!(true));
+            if let Some(title) = title {
+                properties.insert("title".to_string(), json!(title));
+            }
+        }
+
+        for link in links {
+            self.ensure_page(link);
+            self.add_page_edge(url, link, LINKS_TO_EDGE, HashMap::new())?;
+        }
+
+        let mut mentioned = BTreeMap::new();
+        for entity in EntityRecognizer::recognize(text)? {
+            let entity_type = serde_json::to_value(&entity.entity_type)?
+                .as_str()
+                .unwrap_or("OTHER")
+                .to_string();
+            let id = format!("{}:{}", entity_type.to_lowercase(), entity.text);
+            *mentioned.entry((id, entity_type, entity.text)).or_insert(0) += 1;
+        }
+        for ((id, entity_type, text), count) in mentioned {
+            let properties = HashMap::from([("text".to_string(), json!(text))]);
+            self.add_entity(id.clone(), entity_type, properties)?;
+            let properties = HashMap::from([("count".to_string(), json!(count))]);
+            self.add_page_edge(url, &id, MENTIONS_EDGE, properties)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_page_edge(
+        &mut self,
+        from: &str,
+        to: &str,
+        edge_type: &str,
+        properties: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        let key = (from.to_string(), to.to_string(), edge_type.to_string());
+        if !self.page_edges.insert(key) {
+            return Ok(());
+        }
+        self.add_relationship(
+            from.to_string(),
+            to.to_string(),
+            edge_type.to_string(),
+            properties,
+        )
+    }
+
+    fn ensure_page(&mut self, url: &str) {
+        if self.graph.get_node(url).is_none() {
+            let properties = HashMap::from([("crawled".to_string(), json!(false))]);
+            let _ = self.add_entity(url.to_string(), PAGE_NODE.to_string(), properties);
+        }
+    }
+
     pub fn build(self) -> KnowledgeGraph {
         self.graph
     }
@@ -4,6 +4,7 @@ pub mod schema;
 
 use crate::{Error, Result};
 use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::Direction;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -76,4 +77,38 @@ impl KnowledgeGraph {
     pub fn edge_count(&self) -> usize {
         self.graph.edge_count()
     }
+
+    /// Nodes in the order they were added.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.graph.node_weights()
+    }
+
+    /// Edges in the order they were added.
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> {
+        self.graph.edge_weights()
+    }
+
+    /// Edges starting at node `id`.
+    pub fn outgoing(&self, id: &str) -> Vec<&Edge> {
+        self.edges_directed(id, Direction::Outgoing)
+    }
+
+    /// Edges ending at node `id`.
+    pub fn incoming(&self, id: &str) -> Vec<&Edge> {
+        self.edges_directed(id, Direction::Incoming)
+    }
+
+    fn edges_directed(&self, id: &str, direction: Direction) -> Vec<&Edge> {
+        let Some(idx) = self.node_index.get(id) else {
+            return Vec::new();
+        };
+        // petgraph walks edges newest first
+        let mut edges: Vec<&Edge> = self
+            .graph
+            .edges_directed(*idx, direction)
+            .map(|edge| edge.weight())
+            .collect();
+        edges.reverse();
+        edges
+    }
 }
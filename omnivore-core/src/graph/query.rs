@@ -1,19 +1,44 @@
 use crate::graph::{KnowledgeGraph, Node};
+use std::collections::{HashSet, VecDeque};
 
 pub struct GraphQuery<'a> {
-    _graph: &'a KnowledgeGraph,
+    graph: &'a KnowledgeGraph,
 }
 
 impl<'a> GraphQuery<'a> {
     pub fn new(graph: &'a KnowledgeGraph) -> Self {
-        Self { _graph: graph }
+        Self { graph }
     }
 
-    pub fn find_by_type(&self, _node_type: &str) -> Vec<&'a Node> {
-        Vec::new()
+    pub fn find_by_type(&self, node_type: &str) -> Vec<&'a Node> {
+        self.graph
+            .nodes()
+            .filter(|node| node.node_type == node_type)
+            .collect()
     }
 
-    pub fn find_connected(&self, _node_id: &str, _max_depth: usize) -> Vec<&'a Node> {
-        Vec::new()
+    /// Nodes reachable from `node_id` within `max_depth` edges, following
+    /// edges in either direction, nearest first. The start node is not
+    /// included.
+    pub fn find_connected(&self, node_id: &str, max_depth: usize) -> Vec<&'a Node> {
+        let Some(&start) = self.graph.node_index.get(node_id) else {
+            return Vec::new();
+        };
+
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        let mut connected = Vec::new();
+        while let Some((idx, depth)) = queue.pop_front() {
+            if depth == max_depth {
+                continue;
+            }
+            for next in self.graph.graph.neighbors_undirected(idx) {
+                if seen.insert(next) {
+                    connected.push(&self.graph.graph[next]);
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+        connected
     }
 }
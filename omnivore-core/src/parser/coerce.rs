@@ -0,0 +1,26 @@
+//! Reading numbers and booleans out of scraped text, shared by schema
+//! coercion and the `to_number`, `to_integer` and `to_boolean` transforms so
+//! both accept the same inputs.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static NUMBER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"-?\d[\d,]*(\.\d+)?([eE][+-]?\d+)?").unwrap());
+
+/// The first number in `s`, without its thousands separators, so
+/// `"Price: $12"` is 12 and `"1,299.00 EUR"` is 1299.
+pub fn parse_number(s: &str) -> Option<f64> {
+    let token = NUMBER_REGEX.find(s)?.as_str().replace(',', "");
+    token.parse().ok()
+}
+
+/// `true`, `yes`, `1` or `on`, and `false`, `no`, `0` or `off`, in any case.
+/// Anything else, empty text included, isn't a boolean.
+pub fn parse_boolean(s: &str) -> Option<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "true" | "yes" | "1" | "on" => Some(true),
+        "false" | "no" | "0" | "off" => Some(false),
+        _ => None,
+    }
+}
@@ -1,3 +1,4 @@
+use super::transform::TransformPipeline;
 use crate::{Error, Result};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -31,6 +32,7 @@ pub enum Transformer {
 impl Extractor {
     pub fn extract(&self, text: &str) -> Result<Vec<HashMap<String, String>>> {
         let mut results = Vec::new();
+        let pipeline = TransformPipeline::from_transformers(&self.transformers)?;
 
         for pattern in &self.patterns {
             let regex = Regex::new(&pattern.regex)
@@ -41,7 +43,7 @@ impl Extractor {
 
                 for (i, group_name) in pattern.capture_groups.iter().enumerate() {
                     if let Some(matched) = captures.get(i + 1) {
-                        let value = self.apply_transformers(matched.as_str());
+                        let value = pipeline.apply_str(matched.as_str());
                         extracted.insert(group_name.clone(), value);
                     }
                 }
@@ -54,44 +56,6 @@ impl Extractor {
 
         Ok(results)
     }
-
-    fn apply_transformers(&self, text: &str) -> String {
-        let mut result = text.to_string();
-
-        for transformer in &self.transformers {
-            result = match transformer {
-                Transformer::Lowercase => result.to_lowercase(),
-                Transformer::Uppercase => result.to_uppercase(),
-                Transformer::Trim => result.trim().to_string(),
-                Transformer::Replace { from, to } => result.replace(from, to),
-                Transformer::Extract { regex } => {
-                    if let Ok(re) = Regex::new(regex) {
-                        if let Some(captures) = re.captures(&result) {
-                            captures
-                                .get(1)
-                                .map(|m| m.as_str().to_string())
-                                .unwrap_or(result)
-                        } else {
-                            result
-                        }
-                    } else {
-                        result
-                    }
-                }
-                Transformer::Split { delimiter } => result
-                    .split(delimiter)
-                    .next()
-                    .unwrap_or(&result)
-                    .to_string(),
-                Transformer::Join { delimiter } => result
-                    .split_whitespace()
-                    .collect::<Vec<_>>()
-                    .join(delimiter),
-            };
-        }
-
-        result
-    }
 }
 
 pub struct EmailExtractor;
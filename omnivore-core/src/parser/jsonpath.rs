@@ -0,0 +1,550 @@
+use crate::{Error, Result};
+use serde_json::Value;
+
+/// A compiled JSONPath expression.
+///
+/// Supports the commonly used subset: `$`, `@`, `.name`, `['name']`, `*`,
+/// recursive descent (`..`), indexes (including negative), slices, unions
+/// and filters such as `[?(@.price < 10 && @.inStock == true)]`.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Child(Selector),
+    Descendant(Selector),
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, i64),
+    Union(Vec<Selector>),
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Or(Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Exists(JsonPath),
+    Compare(Operand, CmpOp, Operand),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Path(JsonPath),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl JsonPath {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let chars: Vec<char> = expression.trim().chars().collect();
+        let mut parser = PathParser {
+            chars: &chars,
+            pos: 0,
+            source: expression,
+        };
+        let path = parser.parse_path()?;
+        parser.skip_ws();
+        if parser.pos < chars.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(path)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        self.select_with_root(root, root)
+    }
+
+    fn select_with_root<'a>(&self, current: &'a Value, root: &'a Value) -> Vec<&'a Value> {
+        let mut nodes = vec![current];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for node in nodes {
+                match segment {
+                    Segment::Child(selector) => apply_selector(selector, node, root, &mut next),
+                    Segment::Descendant(selector) => {
+                        let mut stack = vec![node];
+                        let mut all = Vec::new();
+                        while let Some(value) = stack.pop() {
+                            all.push(value);
+                            match value {
+                                Value::Array(items) => stack.extend(items.iter().rev()),
+                                Value::Object(map) => {
+                                    let children: Vec<&Value> = map.values().collect();
+                                    stack.extend(children.into_iter().rev());
+                                }
+                                _ => {}
+                            }
+                        }
+                        for value in all {
+                            apply_selector(selector, value, root, &mut next);
+                        }
+                    }
+                }
+            }
+            nodes = next;
+        }
+        nodes
+    }
+}
+
+fn apply_selector<'a>(selector: &Selector, value: &'a Value, root: &'a Value, out: &mut Vec<&'a Value>) {
+    match selector {
+        Selector::Name(name) => {
+            if let Some(child) = value.get(name.as_str()) {
+                out.push(child);
+            }
+        }
+        Selector::Wildcard => match value {
+            Value::Array(items) => out.extend(items.iter()),
+            Value::Object(map) => out.extend(map.values()),
+            _ => {}
+        },
+        Selector::Index(index) => {
+            if let Value::Array(items) = value {
+                if let Some(i) = normalize_index(*index, items.len()) {
+                    out.push(&items[i]);
+                }
+            }
+        }
+        Selector::Slice(start, end, step) => {
+            if let Value::Array(items) = value {
+                let len = items.len() as i64;
+                let clamp = |i: i64| if i < 0 { (len + i).max(0) } else { i.min(len) };
+                if *step > 0 {
+                    let mut i = start.map(clamp).unwrap_or(0);
+                    let stop = end.map(clamp).unwrap_or(len);
+                    while i < stop {
+                        out.push(&items[i as usize]);
+                        i += step;
+                    }
+                } else if *step < 0 {
+                    let mut i = start.map(clamp).unwrap_or(len - 1).min(len - 1);
+                    let stop = end.map(clamp).unwrap_or(-1);
+                    while i > stop && i >= 0 {
+                        out.push(&items[i as usize]);
+                        i += step;
+                    }
+                }
+            }
+        }
+        Selector::Union(selectors) => {
+            for selector in selectors {
+                apply_selector(selector, value, root, out);
+            }
+        }
+        Selector::Filter(filter) => {
+            let candidates: Vec<&Value> = match value {
+                Value::Array(items) => items.iter().collect(),
+                Value::Object(map) => map.values().collect(),
+                _ => Vec::new(),
+            };
+            for candidate in candidates {
+                if filter.matches(candidate, root) {
+                    out.push(candidate);
+                }
+            }
+        }
+    }
+}
+
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let i = if index < 0 { len as i64 + index } else { index };
+    if i >= 0 && (i as usize) < len {
+        Some(i as usize)
+    } else {
+        None
+    }
+}
+
+impl Filter {
+    fn matches(&self, current: &Value, root: &Value) -> bool {
+        match self {
+            Filter::Or(a, b) => a.matches(current, root) || b.matches(current, root),
+            Filter::And(a, b) => a.matches(current, root) && b.matches(current, root),
+            Filter::Not(inner) => !inner.matches(current, root),
+            Filter::Exists(path) => path
+                .select_with_root(current, root)
+                .iter()
+                .any(|v| !matches!(v, Value::Bool(false) | Value::Null)),
+            Filter::Compare(left, op, right) => {
+                let left = left.resolve(current, root);
+                let right = right.resolve(current, root);
+                match (left, right) {
+                    (Some(l), Some(r)) => compare(&l, *op, &r),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, current: &Value, root: &Value) -> Option<Value> {
+        match self {
+            Operand::Literal(value) => Some(value.clone()),
+            Operand::Path(path) => path.select_with_root(current, root).first().map(|v| (*v).clone()),
+        }
+    }
+}
+
+fn compare(left: &Value, op: CmpOp, right: &Value) -> bool {
+    use std::cmp::Ordering;
+
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        // Embedded JSON often stores numbers as strings ("price": "9.99").
+        (Value::String(s), Value::Number(n)) => s.trim().parse::<f64>().ok().and_then(|a| a.partial_cmp(&n.as_f64()?)),
+        (Value::Number(n), Value::String(s)) => s.trim().parse::<f64>().ok().and_then(|b| n.as_f64()?.partial_cmp(&b)),
+        _ => {
+            if left == right {
+                Some(Ordering::Equal)
+            } else {
+                None
+            }
+        }
+    };
+
+    match op {
+        CmpOp::Eq => ordering == Some(Ordering::Equal),
+        CmpOp::NotEq => ordering != Some(Ordering::Equal),
+        CmpOp::Lt => ordering == Some(Ordering::Less),
+        CmpOp::LtEq => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        CmpOp::Gt => ordering == Some(Ordering::Greater),
+        CmpOp::GtEq => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+struct PathParser<'s> {
+    chars: &'s [char],
+    pos: usize,
+    source: &'s str,
+}
+
+impl PathParser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::Parse(format!(
+            "Invalid JSONPath '{}' at position {}: {}",
+            self.source, self.pos, message
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    fn parse_path(&mut self) -> Result<JsonPath> {
+        let start = self.pos;
+        self.skip_ws();
+        match self.peek() {
+            Some('$') | Some('@') => self.pos += 1,
+            _ => return Err(self.error("path must start with '$' or '@'")),
+        }
+
+        let mut segments = Vec::new();
+        loop {
+            if self.starts_with("..") {
+                self.pos += 2;
+                let selector = if self.peek() == Some('[') {
+                    self.parse_bracket()?
+                } else {
+                    self.parse_dot_member()?
+                };
+                segments.push(Segment::Descendant(selector));
+            } else if self.peek() == Some('.') {
+                self.pos += 1;
+                segments.push(Segment::Child(self.parse_dot_member()?));
+            } else if self.peek() == Some('[') {
+                segments.push(Segment::Child(self.parse_bracket()?));
+            } else {
+                break;
+            }
+        }
+
+        Ok(JsonPath {
+            source: self.chars[start..self.pos].iter().collect::<String>().trim().to_string(),
+            segments,
+        })
+    }
+
+    fn parse_dot_member(&mut self) -> Result<Selector> {
+        if self.peek() == Some('*') {
+            self.pos += 1;
+            return Ok(Selector::Wildcard);
+        }
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '@' || c == '$')
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.error("expected member name"));
+        }
+        Ok(Selector::Name(self.chars[start..self.pos].iter().collect()))
+    }
+
+    fn parse_bracket(&mut self) -> Result<Selector> {
+        self.expect('[')?;
+        self.skip_ws();
+
+        if self.peek() == Some('?') {
+            self.pos += 1;
+            self.skip_ws();
+            let parenthesized = self.peek() == Some('(');
+            if parenthesized {
+                self.pos += 1;
+            }
+            let filter = self.parse_filter_or()?;
+            if parenthesized {
+                self.expect(')')?;
+            }
+            self.expect(']')?;
+            return Ok(Selector::Filter(filter));
+        }
+
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_ws();
+            selectors.push(self.parse_bracket_item()?);
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+
+        if selectors.len() == 1 {
+            Ok(selectors.remove(0))
+        } else {
+            Ok(Selector::Union(selectors))
+        }
+    }
+
+    fn parse_bracket_item(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('\'') | Some('"') => Ok(Selector::Name(self.parse_string()?)),
+            _ => {
+                let start = self.parse_int()?;
+                self.skip_ws();
+                if self.peek() != Some(':') {
+                    return start
+                        .map(Selector::Index)
+                        .ok_or_else(|| self.error("expected index, name or slice"));
+                }
+                self.pos += 1;
+                self.skip_ws();
+                let end = self.parse_int()?;
+                self.skip_ws();
+                let step = if self.peek() == Some(':') {
+                    self.pos += 1;
+                    self.skip_ws();
+                    self.parse_int()?.unwrap_or(1)
+                } else {
+                    1
+                };
+                Ok(Selector::Slice(start, end, step))
+            }
+        }
+    }
+
+    fn parse_int(&mut self) -> Result<Option<i64>> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<i64>()
+            .map(Some)
+            .map_err(|_| self.error(&format!("invalid integer '{text}'")))
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        let quote = self.peek().ok_or_else(|| self.error("expected string"))?;
+        self.pos += 1;
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == quote {
+                return Ok(out);
+            }
+            if c == '\\' {
+                if let Some(escaped) = self.peek() {
+                    self.pos += 1;
+                    out.push(escaped);
+                    continue;
+                }
+            }
+            out.push(c);
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn parse_filter_or(&mut self) -> Result<Filter> {
+        let mut left = self.parse_filter_and()?;
+        loop {
+            self.skip_ws();
+            if self.starts_with("||") {
+                self.pos += 2;
+                let right = self.parse_filter_and()?;
+                left = Filter::Or(Box::new(left), Box::new(right));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn parse_filter_and(&mut self) -> Result<Filter> {
+        let mut left = self.parse_filter_unary()?;
+        loop {
+            self.skip_ws();
+            if self.starts_with("&&") {
+                self.pos += 2;
+                let right = self.parse_filter_unary()?;
+                left = Filter::And(Box::new(left), Box::new(right));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn parse_filter_unary(&mut self) -> Result<Filter> {
+        self.skip_ws();
+        if self.peek() == Some('!') && !self.starts_with("!=") {
+            self.pos += 1;
+            return Ok(Filter::Not(Box::new(self.parse_filter_unary()?)));
+        }
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            let inner = self.parse_filter_or()?;
+            self.expect(')')?;
+            return Ok(inner);
+        }
+
+        let left = self.parse_operand()?;
+        self.skip_ws();
+        let op = if self.starts_with("==") {
+            Some((CmpOp::Eq, 2))
+        } else if self.starts_with("!=") {
+            Some((CmpOp::NotEq, 2))
+        } else if self.starts_with("<=") {
+            Some((CmpOp::LtEq, 2))
+        } else if self.starts_with(">=") {
+            Some((CmpOp::GtEq, 2))
+        } else if self.starts_with("<") {
+            Some((CmpOp::Lt, 1))
+        } else if self.starts_with(">") {
+            Some((CmpOp::Gt, 1))
+        } else {
+            None
+        };
+
+        match op {
+            Some((op, width)) => {
+                self.pos += width;
+                self.skip_ws();
+                let right = self.parse_operand()?;
+                Ok(Filter::Compare(left, op, right))
+            }
+            None => match left {
+                Operand::Path(path) => Ok(Filter::Exists(path)),
+                Operand::Literal(_) => Err(self.error("expected comparison operator")),
+            },
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand> {
+        self.skip_ws();
+        match self.peek() {
+            Some('@') | Some('$') => Ok(Operand::Path(self.parse_path()?)),
+            Some('\'') | Some('"') => Ok(Operand::Literal(Value::String(self.parse_string()?))),
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                let start = self.pos;
+                self.pos += 1;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+                {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                serde_json::from_str::<Value>(&text)
+                    .map(Operand::Literal)
+                    .map_err(|_| self.error(&format!("invalid number '{text}'")))
+            }
+            _ => {
+                for (word, value) in [
+                    ("true", Value::Bool(true)),
+                    ("false", Value::Bool(false)),
+                    ("null", Value::Null),
+                ] {
+                    if self.starts_with(word) {
+                        self.pos += word.len();
+                        return Ok(Operand::Literal(value));
+                    }
+                }
+                Err(self.error("expected path or literal"))
+            }
+        }
+    }
+}
@@ -1,13 +1,29 @@
+pub mod coerce;
 pub mod extractors;
 pub mod html;
+pub mod jsonpath;
 pub mod schema;
+pub mod template;
+pub mod transform;
+pub mod xpath;
 
 use crate::{Error, Result};
-use scraper::{Html, Selector};
+use once_cell::sync::OnceCell;
+use schema::{FieldError, Schema, ValidationReport};
+use transform::TransformPipeline;
+use xpath::XPath;
+use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
+/// A field to extract. `selector` is CSS by default; prefix it with
+/// `xpath:` to evaluate XPath instead, which also allows walking to parents
+/// and siblings (`xpath:../h2`, `xpath:following-sibling::p[1]`).
+///
+/// A rule with `children` produces records: one object per matched element,
+/// with each child rule evaluated relative to that element.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParseRule {
     pub name: String,
@@ -16,6 +32,8 @@ pub struct ParseRule {
     pub multiple: bool,
     pub required: bool,
     pub transform: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ParseRule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,30 +44,161 @@ pub struct ParseConfig {
     pub extract_metadata: bool,
 }
 
+/// Where a rule's selector is evaluated: the whole document for top-level
+/// rules, or the matched parent element for child rules.
+#[derive(Clone, Copy)]
+enum Scope<'a> {
+    Document(&'a Html),
+    Element,
+}
+
+enum Match<'a> {
+    Element(ElementRef<'a>),
+    Text(String),
+}
+
+/// An item of a repeated record rule left out, as null, because one of its
+/// required fields wasn't found.
+struct SkippedRecord {
+    /// Path of the repeated rule, like `products`.
+    field: String,
+    index: usize,
+    /// Path of the missing field, like `products.price`.
+    missing: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseOutput {
+    pub data: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ValidationReport>,
+}
+
 pub struct Parser {
     config: ParseConfig,
+    schema_dir: PathBuf,
+    schema: OnceCell<Schema>,
 }
 
 impl Parser {
     pub fn new(config: ParseConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            schema_dir: schema::default_schema_dir(),
+            schema: OnceCell::new(),
+        }
+    }
+
+    /// Directory used to resolve `ParseConfig::schema_name`.
+    pub fn with_schema_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.schema_dir = dir.into();
+        self
+    }
+
+    /// Uses `schema` directly instead of resolving `schema_name`.
+    pub fn with_schema(self, schema: Schema) -> Self {
+        let _ = self.schema.set(schema);
+        self
+    }
+
+    pub fn schema(&self) -> Result<Option<&Schema>> {
+        if let Some(schema) = self.schema.get() {
+            return Ok(Some(schema));
+        }
+
+        match &self.config.schema_name {
+            Some(name) => self
+                .schema
+                .get_or_try_init(|| Schema::load(name, &self.schema_dir))
+                .map(Some),
+            None => Ok(None),
+        }
     }
 
     pub fn parse(&self, html: &str) -> Result<Value> {
-        let document = Html::parse_document(html);
-        let mut result = serde_json::Map::new();
+        Ok(self.parse_with_report(html)?.data)
+    }
 
-        for rule in &self.config.rules {
-            let value = self.extract_by_rule(&document, rule)?;
+    /// Parses `html` and, when a schema is configured, coerces and validates
+    /// the extracted records against it.
+    pub fn parse_with_report(&self, html: &str) -> Result<ParseOutput> {
+        let (mut data, skipped) = self.extract(html)?;
 
-            if rule.required && value.is_null() {
-                return Err(Error::Parse(format!(
-                    "Required field '{}' not found",
-                    rule.name
-                )));
+        let validation = self
+            .schema()?
+            .map(|schema| self.validate_records(schema, &mut data, &skipped));
+
+        Ok(ParseOutput { data, validation })
+    }
+
+    /// Validates each item of the repeated record fields (top-level rules
+    /// with `multiple` and `children`) as a record of its own, or the whole
+    /// document as one record when there are none. Skipped items count as
+    /// invalid records, reported with the field they were missing.
+    fn validate_records(
+        &self,
+        schema: &Schema,
+        data: &mut Value,
+        skipped: &[SkippedRecord],
+    ) -> ValidationReport {
+        let record_fields: Vec<&str> = self
+            .config
+            .rules
+            .iter()
+            .filter(|rule| rule.multiple && !rule.children.is_empty())
+            .map(|rule| rule.name.as_str())
+            .collect();
+        if record_fields.is_empty() {
+            return schema.validate_records(std::slice::from_mut(data));
+        }
+
+        let mut records = Vec::new();
+        let mut counts = Vec::new();
+        let mut skipped_records = HashMap::new();
+        for name in &record_fields {
+            for skip in skipped.iter().filter(|skip| skip.field == *name) {
+                skipped_records.insert(records.len() + skip.index, skip);
             }
+            let items = match data.get_mut(*name) {
+                Some(Value::Array(items)) => std::mem::take(items),
+                _ => Vec::new(),
+            };
+            counts.push(items.len());
+            records.extend(items);
+        }
 
-            result.insert(rule.name.clone(), value);
+        let mut report = schema.validate_records(&mut records);
+        for record_report in &mut report.records {
+            if let Some(skip) = skipped_records.get(&record_report.record) {
+                record_report.errors = vec![FieldError {
+                    field: skip.missing.clone(),
+                    message: format!("Required field '{}' not found, record skipped", skip.missing),
+                }];
+            }
+        }
+
+        let mut records = records.into_iter();
+        for (name, count) in record_fields.iter().zip(counts) {
+            if let Some(Value::Array(items)) = data.get_mut(*name) {
+                items.extend(records.by_ref().take(count));
+            }
+        }
+        report
+    }
+
+    fn extract(&self, html: &str) -> Result<(Value, Vec<SkippedRecord>)> {
+        let document = Html::parse_document(html);
+        let root = document.root_element();
+        let mut skipped = Vec::new();
+        let mut result = self.extract_record(
+            Scope::Document(&document),
+            root,
+            &self.config.rules,
+            "",
+            &mut skipped,
+        )?;
+        if let Some(missing) = missing_required(&self.config.rules, &result, "") {
+            return Err(Error::Parse(format!("Required field '{missing}' not found")));
         }
 
         if self.config.extract_metadata {
@@ -57,30 +206,128 @@ impl Parser {
             result.insert("_metadata".to_string(), metadata);
         }
 
-        Ok(Value::Object(result))
+        Ok((Value::Object(result), skipped))
+    }
+
+    fn extract_record(
+        &self,
+        scope: Scope,
+        element: ElementRef,
+        rules: &[ParseRule],
+        path: &str,
+        skipped: &mut Vec<SkippedRecord>,
+    ) -> Result<serde_json::Map<String, Value>> {
+        let mut record = serde_json::Map::new();
+
+        for rule in rules {
+            let field_path = if path.is_empty() {
+                rule.name.clone()
+            } else {
+                format!("{}.{}", path, rule.name)
+            };
+            let value = self.extract_by_rule(scope, element, rule, &field_path, skipped)?;
+            record.insert(rule.name.clone(), value);
+        }
+
+        Ok(record)
     }
 
-    fn extract_by_rule(&self, document: &Html, rule: &ParseRule) -> Result<Value> {
-        let selector = Selector::parse(&rule.selector)
-            .map_err(|e| Error::Parse(format!("Invalid selector '{}': {:?}", rule.selector, e)))?;
+    fn extract_by_rule(
+        &self,
+        scope: Scope,
+        element: ElementRef,
+        rule: &ParseRule,
+        path: &str,
+        skipped: &mut Vec<SkippedRecord>,
+    ) -> Result<Value> {
+        let transform = match &rule.transform {
+            Some(expression) => TransformPipeline::parse(expression).map_err(|e| {
+                Error::Parse(format!("Invalid transform for field '{}': {}", rule.name, e))
+            })?,
+            None => TransformPipeline::default(),
+        };
 
-        let elements: Vec<_> = document.select(&selector).collect();
+        let matches = self.select(scope, element, &rule.selector)?;
 
-        if elements.is_empty() {
+        if matches.is_empty() {
             return Ok(Value::Null);
         }
 
+        let mut values = Vec::new();
+        let limit = if rule.multiple { usize::MAX } else { 1 };
+        for (index, found) in matches.iter().take(limit).enumerate() {
+            let value = match found {
+                Match::Element(el) if !rule.children.is_empty() => {
+                    let record =
+                        self.extract_record(Scope::Element, *el, &rule.children, path, skipped)?;
+                    match missing_required(&rule.children, &record, path) {
+                        // One incomplete item doesn't cost the others
+                        Some(missing) if rule.multiple => {
+                            tracing::warn!(
+                                "Skipping {}[{}]: required field '{}' not found",
+                                path,
+                                index,
+                                missing
+                            );
+                            skipped.push(SkippedRecord {
+                                field: path.to_string(),
+                                index,
+                                missing,
+                            });
+                            values.push(Value::Null);
+                            continue;
+                        }
+                        Some(missing) => {
+                            return Err(Error::Parse(format!(
+                                "Required field '{missing}' not found"
+                            )))
+                        }
+                        None => Value::Object(record),
+                    }
+                }
+                Match::Element(el) => self.extract_value(el, &rule.attribute),
+                Match::Text(text) => Value::String(if self.config.clean_text {
+                    self.clean_text(text)
+                } else {
+                    text.clone()
+                }),
+            };
+            values.push(transform.apply(value));
+        }
+
         if rule.multiple {
-            let values: Vec<Value> = elements
-                .iter()
-                .map(|el| self.extract_value(el, &rule.attribute))
-                .collect();
             Ok(Value::Array(values))
         } else {
-            Ok(self.extract_value(&elements[0], &rule.attribute))
+            Ok(values.into_iter().next().unwrap_or(Value::Null))
         }
     }
 
+    fn select<'a>(&self, scope: Scope<'a>, element: ElementRef<'a>, selector: &str) -> Result<Vec<Match<'a>>> {
+        if let Some(expression) = selector.strip_prefix("xpath:") {
+            let xpath = XPath::parse(expression.trim())?;
+            let nodes = match scope {
+                Scope::Document(document) => xpath.select(document)?,
+                Scope::Element => xpath.select_from(element)?,
+            };
+
+            return Ok(nodes
+                .into_iter()
+                .map(|node| match node.as_element() {
+                    Some(el) => Match::Element(el),
+                    None => Match::Text(node.string_value()),
+                })
+                .collect());
+        }
+
+        let selector = Selector::parse(selector)
+            .map_err(|e| Error::Parse(format!("Invalid selector '{selector}': {e:?}")))?;
+
+        Ok(match scope {
+            Scope::Document(document) => document.select(&selector).map(Match::Element).collect(),
+            Scope::Element => element.select(&selector).map(Match::Element).collect(),
+        })
+    }
+
     fn extract_value(&self, element: &scraper::ElementRef, attribute: &Option<String>) -> Value {
         let text = if let Some(attr) = attribute {
             element
@@ -168,3 +415,22 @@ impl Parser {
         Ok(links)
     }
 }
+
+/// Path of the first required field of `rules` that `record` has no value
+/// for.
+fn missing_required(
+    rules: &[ParseRule],
+    record: &serde_json::Map<String, Value>,
+    path: &str,
+) -> Option<String> {
+    rules
+        .iter()
+        .find(|rule| rule.required && record.get(&rule.name).is_none_or(Value::is_null))
+        .map(|rule| {
+            if path.is_empty() {
+                rule.name.clone()
+            } else {
+                format!("{}.{}", path, rule.name)
+            }
+        })
+}
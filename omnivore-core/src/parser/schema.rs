@@ -1,13 +1,23 @@
+use super::coerce::{parse_boolean, parse_number};
 use crate::{Error, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+static EMAIL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap());
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub name: String,
+    #[serde(default)]
     pub version: String,
     pub fields: Vec<Field>,
+    #[serde(default)]
     pub required: Vec<String>,
 }
 
@@ -15,8 +25,11 @@ pub struct Schema {
 pub struct Field {
     pub name: String,
     pub field_type: FieldType,
+    #[serde(default)]
     pub description: Option<String>,
+    #[serde(default)]
     pub default: Option<Value>,
+    #[serde(default)]
     pub validators: Vec<Validator>,
 }
 
@@ -44,27 +57,161 @@ pub enum Validator {
     Enum { values: Vec<Value> },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordReport {
+    pub record: usize,
+    pub errors: Vec<FieldError>,
+}
+
+/// Outcome of validating a batch of records against a schema. Only records
+/// with at least one error are listed in `records`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub schema: String,
+    pub total_records: usize,
+    pub valid_records: usize,
+    pub records: Vec<RecordReport>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.records.iter().map(|r| r.errors.len()).sum()
+    }
+}
+
+/// Directory searched for schemas referenced by name (`~/.omnivore/schemas`).
+pub fn default_schema_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".omnivore")
+        .join("schemas")
+}
+
 impl Schema {
+    /// Loads `<name>.yaml`, `<name>.yml` or `<name>.json` from `dir`.
+    pub fn load(name: &str, dir: &Path) -> Result<Self> {
+        for extension in ["yaml", "yml", "json"] {
+            let path = dir.join(format!("{name}.{extension}"));
+            if path.is_file() {
+                return Self::load_from_path(&path);
+            }
+        }
+
+        Err(Error::InvalidConfig(format!(
+            "Schema '{}' not found in {}",
+            name,
+            dir.display()
+        )))
+    }
+
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let is_json = path.extension().and_then(|e| e.to_str()) == Some("json");
+
+        let schema = if is_json {
+            serde_json::from_str(&content)?
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| {
+                Error::Parse(format!("Invalid schema file {}: {}", path.display(), e))
+            })?
+        };
+
+        Ok(schema)
+    }
+
     pub fn validate(&self, data: &Value) -> Result<()> {
-        let obj = data
-            .as_object()
-            .ok_or_else(|| Error::Parse("Data must be an object".to_string()))?;
+        match self.errors(data).into_iter().next() {
+            Some(error) => Err(Error::Parse(error.message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Collects every problem with `data` instead of stopping at the first.
+    pub fn errors(&self, data: &Value) -> Vec<FieldError> {
+        let Some(obj) = data.as_object() else {
+            return vec![FieldError {
+                field: String::new(),
+                message: "Data must be an object".to_string(),
+            }];
+        };
+
+        let mut errors = Vec::new();
 
         for required_field in &self.required {
-            if !obj.contains_key(required_field) {
-                return Err(Error::Parse(format!(
-                    "Required field '{required_field}' is missing"
-                )));
+            if obj.get(required_field).is_none_or(Value::is_null) {
+                errors.push(FieldError {
+                    field: required_field.clone(),
+                    message: format!("Required field '{required_field}' is missing"),
+                });
             }
         }
 
         for field in &self.fields {
-            if let Some(value) = obj.get(&field.name) {
-                self.validate_field(field, value)?;
+            match obj.get(&field.name) {
+                Some(Value::Null) | None => {}
+                Some(value) => {
+                    if let Err(Error::Parse(message)) = self.validate_field(field, value) {
+                        errors.push(FieldError {
+                            field: field.name.clone(),
+                            message,
+                        });
+                    }
+                }
             }
         }
 
-        Ok(())
+        errors
+    }
+
+    /// Fills defaults, coerces each record to the schema's field types and
+    /// validates it. Records are modified in place.
+    pub fn validate_records(&self, records: &mut [Value]) -> ValidationReport {
+        let mut reports = Vec::new();
+
+        for (index, record) in records.iter_mut().enumerate() {
+            // normalize() cannot fail; it only fills in missing values
+            let _ = self.normalize(record);
+            self.coerce(record);
+
+            let errors = self.errors(record);
+            if !errors.is_empty() {
+                reports.push(RecordReport {
+                    record: index,
+                    errors,
+                });
+            }
+        }
+
+        ValidationReport {
+            schema: self.name.clone(),
+            total_records: records.len(),
+            valid_records: records.len() - reports.len(),
+            records: reports,
+        }
+    }
+
+    /// Converts values to the declared field types where that is lossless
+    /// enough to be useful ("$1,299.00" to 1299.0, "2024-03-01" to an RFC 3339
+    /// date, and so on). Values that cannot be coerced are left untouched so
+    /// validation can report them.
+    pub fn coerce(&self, data: &mut Value) {
+        if let Value::Object(obj) = data {
+            for field in &self.fields {
+                if let Some(value) = obj.get_mut(&field.name) {
+                    coerce_value(&field.field_type, value);
+                }
+            }
+        }
     }
 
     fn validate_field(&self, field: &Field, value: &Value) -> Result<()> {
@@ -83,6 +230,9 @@ impl Schema {
             (FieldType::String, Value::String(_)) => Ok(()),
             (FieldType::Number, Value::Number(_)) => Ok(()),
             (FieldType::Boolean, Value::Bool(_)) => Ok(()),
+            (FieldType::Date, Value::String(s)) if parse_date(s).is_some() => Ok(()),
+            (FieldType::Url, Value::String(s)) if url::Url::parse(s).is_ok() => Ok(()),
+            (FieldType::Email, Value::String(s)) if EMAIL_REGEX.is_match(s) => Ok(()),
             (FieldType::Array(inner_type), Value::Array(arr)) => {
                 for item in arr {
                     self.validate_type(inner_type, item)?;
@@ -175,7 +325,8 @@ impl Schema {
     pub fn normalize(&self, data: &mut Value) -> Result<()> {
         if let Value::Object(obj) = data {
             for field in &self.fields {
-                if !obj.contains_key(&field.name) {
+                // Parse rules that match nothing produce null, which counts as missing
+                if obj.get(&field.name).is_none_or(Value::is_null) {
                     if let Some(default) = &field.default {
                         obj.insert(field.name.clone(), default.clone());
                     }
@@ -186,3 +337,78 @@ impl Schema {
         Ok(())
     }
 }
+
+fn coerce_value(field_type: &FieldType, value: &mut Value) {
+    let coerced = match (field_type, &*value) {
+        (FieldType::String, Value::Number(n)) => Some(Value::String(n.to_string())),
+        (FieldType::String, Value::Bool(b)) => Some(Value::String(b.to_string())),
+        (FieldType::Number, Value::String(s)) => parse_number(s)
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        (FieldType::Boolean, Value::String(s)) => parse_boolean(s).map(Value::Bool),
+        (FieldType::Date, Value::String(s)) => parse_date(s).map(Value::String),
+        (FieldType::Url, Value::String(s)) => url::Url::parse(s.trim())
+            .ok()
+            .map(|u| Value::String(u.to_string())),
+        (FieldType::Email, Value::String(s)) => {
+            let email = s.trim().trim_start_matches("mailto:").to_lowercase();
+            EMAIL_REGEX.is_match(&email).then_some(Value::String(email))
+        }
+        (FieldType::Array(inner), Value::Array(_)) => {
+            if let Value::Array(items) = value {
+                for item in items.iter_mut() {
+                    coerce_value(inner, item);
+                }
+            }
+            None
+        }
+        (FieldType::Array(inner), scalar) => {
+            let mut item = scalar.clone();
+            coerce_value(inner, &mut item);
+            Some(Value::Array(vec![item]))
+        }
+        (FieldType::Object(fields), Value::Object(_)) => {
+            if let Value::Object(obj) = value {
+                for (key, field_type) in fields {
+                    if let Some(child) = obj.get_mut(key) {
+                        coerce_value(field_type, child);
+                    }
+                }
+            }
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(coerced) = coerced {
+        *value = coerced;
+    }
+}
+
+/// Parses the date formats commonly found on web pages and returns the date
+/// as RFC 3339 (or `YYYY-MM-DD` when there is no time component).
+fn parse_date(s: &str) -> Option<String> {
+    let s = s.trim();
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.to_rfc3339());
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(s) {
+        return Some(dt.to_rfc3339());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, format) {
+            return Some(dt.and_utc().to_rfc3339());
+        }
+    }
+    for format in [
+        "%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y", "%d.%m.%Y", "%B %d, %Y", "%b %d, %Y", "%d %B %Y",
+        "%d %b %Y",
+    ] {
+        if let Ok(date) = NaiveDate::parse_from_str(s, format) {
+            return Some(date.format("%Y-%m-%d").to_string());
+        }
+    }
+
+    None
+}
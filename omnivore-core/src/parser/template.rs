@@ -0,0 +1,547 @@
+use super::jsonpath::JsonPath;
+use super::transform::TransformPipeline;
+use super::xpath::{XNode, XPath};
+use crate::config::{ExtractionTemplate, PatternRule};
+use crate::{Error, Result};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Executes an [`ExtractionTemplate`] against HTML documents.
+///
+/// Every pattern is compiled once up front so a template with a bad
+/// selector, unknown pattern type or unknown transform is rejected before
+/// any page is processed.
+pub struct TemplateEngine {
+    template: ExtractionTemplate,
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateOutput {
+    pub data: Value,
+    pub violations: Vec<String>,
+}
+
+struct CompiledRule {
+    rule: PatternRule,
+    matcher: Matcher,
+    fields: Vec<FieldSpec>,
+    transform: TransformPipeline,
+}
+
+enum Matcher {
+    Css(Selector),
+    XPath(XPath),
+    Regex(Regex),
+    JsonPath(JsonPath),
+}
+
+struct FieldSpec {
+    key: String,
+    explicit_key: bool,
+    source: FieldSource,
+}
+
+enum FieldSource {
+    Text,
+    Html,
+    InnerHtml,
+    Attr(String),
+    /// A bare field name such as `price`: an attribute of that name, or the
+    /// text of a descendant marked `itemprop="price"` / `class="price"`.
+    Named(String, Option<Selector>),
+    Css(Selector, Option<String>),
+    XPath(XPath),
+    JsonPath(JsonPath),
+    Group(String),
+}
+
+impl TemplateEngine {
+    pub fn new(template: ExtractionTemplate) -> Result<Self> {
+        let mut rules = Vec::with_capacity(template.patterns.len());
+
+        for rule in &template.patterns {
+            rules.push(CompiledRule::compile(rule, &template.pipelines)?);
+        }
+
+        Ok(Self { template, rules })
+    }
+
+    pub fn template(&self) -> &ExtractionTemplate {
+        &self.template
+    }
+
+    pub fn execute(&self, html: &str) -> Result<TemplateOutput> {
+        let document = Html::parse_document(html);
+        let json_documents = embedded_json(&document, html);
+        let mut data = Map::new();
+
+        for compiled in &self.rules {
+            let matches = compiled.matches(&document, html, &json_documents)?;
+            let rule = &compiled.rule;
+
+            if rule.required && matches.is_empty() {
+                return Err(Error::Parse(format!(
+                    "Required pattern '{}' matched nothing",
+                    rule.name
+                )));
+            }
+
+            let values: Vec<Value> = matches
+                .into_iter()
+                .map(|value| compiled.apply_transform(value))
+                .collect();
+
+            let value = if rule.multiple {
+                Value::Array(values)
+            } else {
+                values.into_iter().next().unwrap_or(Value::Null)
+            };
+            data.insert(rule.name.clone(), value);
+        }
+
+        let data = Value::Object(data);
+        let violations = match &self.template.output_schema {
+            Some(schema) => validate_json_schema(&data, schema),
+            None => Vec::new(),
+        };
+
+        Ok(TemplateOutput { data, violations })
+    }
+}
+
+impl CompiledRule {
+    fn compile(rule: &PatternRule, pipelines: &[String]) -> Result<Self> {
+        let matcher = match rule.pattern_type.as_str() {
+            "css" => Matcher::Css(parse_selector(&rule.selector)?),
+            "xpath" => Matcher::XPath(XPath::parse(&rule.selector)?),
+            "regex" => Matcher::Regex(Regex::new(&rule.selector).map_err(|e| {
+                Error::Parse(format!("Invalid regex '{}': {}", rule.selector, e))
+            })?),
+            "json_path" | "jsonpath" => Matcher::JsonPath(JsonPath::parse(&rule.selector)?),
+            other => {
+                return Err(Error::InvalidConfig(format!(
+                    "Unknown pattern type '{}' in pattern '{}'",
+                    other, rule.name
+                )))
+            }
+        };
+
+        let fields = rule
+            .extract
+            .iter()
+            .filter(|spec| !spec.trim().is_empty())
+            .map(|spec| FieldSpec::compile(spec, &matcher))
+            .collect::<Result<Vec<_>>>()?;
+
+        // The rule's own transform runs first, then the template-wide pipelines
+        let invalid = |e: Error| {
+            Error::InvalidConfig(format!("Invalid transform in pattern '{}': {}", rule.name, e))
+        };
+        let mut transform = match &rule.transform {
+            Some(expression) => TransformPipeline::parse(expression).map_err(invalid)?,
+            None => TransformPipeline::default(),
+        };
+        for pipeline in pipelines {
+            transform = transform.then(TransformPipeline::parse(pipeline).map_err(invalid)?);
+        }
+
+        Ok(Self {
+            rule: rule.clone(),
+            matcher,
+            fields,
+            transform,
+        })
+    }
+
+    fn matches(&self, document: &Html, source: &str, json_documents: &[Value]) -> Result<Vec<Value>> {
+        match &self.matcher {
+            Matcher::Css(selector) => Ok(document
+                .select(selector)
+                .map(|element| self.element_value(element))
+                .collect()),
+            Matcher::XPath(xpath) => Ok(xpath
+                .select(document)?
+                .into_iter()
+                .map(|node| match node.as_element() {
+                    Some(element) => self.element_value(element),
+                    None => Value::String(node.string_value().trim().to_string()),
+                })
+                .collect()),
+            Matcher::Regex(regex) => Ok(regex
+                .captures_iter(source)
+                .map(|captures| self.captures_value(regex, &captures))
+                .collect()),
+            Matcher::JsonPath(path) => Ok(json_documents
+                .iter()
+                .flat_map(|doc| path.select(doc))
+                .map(|value| self.json_value(value))
+                .collect()),
+        }
+    }
+
+    /// Multi-field matches are transformed field by field.
+    fn apply_transform(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) if !self.fields.is_empty() || self.rule.pattern_type == "regex" => {
+                Value::Object(
+                    map.into_iter()
+                        .map(|(key, value)| (key, self.transform.apply(value)))
+                        .collect(),
+                )
+            }
+            value => self.transform.apply(value),
+        }
+    }
+
+    fn shape(&self, mut resolve: impl FnMut(&FieldSpec) -> Value, default: Value) -> Value {
+        match self.fields.as_slice() {
+            [] => default,
+            [field] if !field.explicit_key => resolve(field),
+            fields => Value::Object(
+                fields
+                    .iter()
+                    .map(|field| (field.key.clone(), resolve(field)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn element_value(&self, element: ElementRef) -> Value {
+        self.shape(
+            |field| field.resolve_element(element),
+            Value::String(element_text(element)),
+        )
+    }
+
+    fn captures_value(&self, regex: &Regex, captures: &regex::Captures) -> Value {
+        let group = |name: &str| -> Value {
+            let found = match name.parse::<usize>() {
+                Ok(index) => captures.get(index),
+                Err(_) => captures.name(name),
+            };
+            found
+                .or_else(|| captures.get(1))
+                .or_else(|| captures.get(0))
+                .map(|m| Value::String(m.as_str().to_string()))
+                .unwrap_or(Value::Null)
+        };
+
+        let named: Vec<&str> = regex.capture_names().flatten().collect();
+        let default = if !named.is_empty() {
+            Value::Object(
+                named
+                    .iter()
+                    .map(|name| {
+                        let value = captures
+                            .name(name)
+                            .map(|m| Value::String(m.as_str().to_string()))
+                            .unwrap_or(Value::Null);
+                        (name.to_string(), value)
+                    })
+                    .collect(),
+            )
+        } else {
+            group("1")
+        };
+
+        self.shape(
+            |field| match &field.source {
+                FieldSource::Group(name) => group(name),
+                _ => group(&field.key),
+            },
+            default,
+        )
+    }
+
+    fn json_value(&self, value: &Value) -> Value {
+        self.shape(|field| field.resolve_json(value), value.clone())
+    }
+}
+
+impl FieldSpec {
+    fn compile(spec: &str, matcher: &Matcher) -> Result<Self> {
+        let spec = spec.trim();
+        let (key, source, explicit_key) = match spec.split_once('=') {
+            Some((key, source)) => (key.trim().to_string(), source.trim(), true),
+            None => (spec.trim_start_matches('@').to_string(), spec, false),
+        };
+
+        let source = match (source, matcher) {
+            (_, Matcher::Regex(_)) => FieldSource::Group(source.to_string()),
+            (_, Matcher::JsonPath(_)) if source.starts_with('$') || source.starts_with('@') => {
+                FieldSource::JsonPath(JsonPath::parse(source)?)
+            }
+            (_, Matcher::JsonPath(_)) => {
+                let path = format!(
+                    "@{}",
+                    source
+                        .split('.')
+                        .map(|part| format!("['{}']", part.replace('\'', "\\'")))
+                        .collect::<String>()
+                );
+                FieldSource::JsonPath(JsonPath::parse(&path)?)
+            }
+            ("text", _) => FieldSource::Text,
+            ("html", _) => FieldSource::Html,
+            ("inner_html", _) => FieldSource::InnerHtml,
+            (attr, _) if attr.starts_with('@') => FieldSource::Attr(attr[1..].to_string()),
+            (_, Matcher::XPath(_)) if explicit_key => FieldSource::XPath(XPath::parse(source)?),
+            (css, _) if explicit_key => match css.rsplit_once(" @") {
+                Some((selector, attr)) => {
+                    FieldSource::Css(parse_selector(selector)?, Some(attr.trim().to_string()))
+                }
+                None => FieldSource::Css(parse_selector(css)?, None),
+            },
+            (name, _) => {
+                let selector = Selector::parse(&format!("[itemprop=\"{name}\"], .{name}"))
+                    .ok()
+                    .or_else(|| Selector::parse(&format!("[itemprop=\"{name}\"]")).ok());
+                FieldSource::Named(name.to_string(), selector)
+            }
+        };
+
+        Ok(Self {
+            key,
+            explicit_key,
+            source,
+        })
+    }
+
+    fn resolve_element(&self, element: ElementRef) -> Value {
+        let value = match &self.source {
+            FieldSource::Text => Some(element_text(element)),
+            FieldSource::Html => Some(element.html()),
+            FieldSource::InnerHtml => Some(element.inner_html()),
+            FieldSource::Attr(attr) => element.value().attr(attr).map(String::from),
+            FieldSource::Named(name, selector) => element
+                .value()
+                .attr(name)
+                .map(String::from)
+                .or_else(|| {
+                    let child = element.select(selector.as_ref()?).next()?;
+                    Some(
+                        child
+                            .value()
+                            .attr("content")
+                            .map(String::from)
+                            .unwrap_or_else(|| element_text(child)),
+                    )
+                }),
+            FieldSource::Css(selector, attr) => {
+                element.select(selector).next().and_then(|child| match attr {
+                    Some(attr) => child.value().attr(attr).map(String::from),
+                    None => Some(element_text(child)),
+                })
+            }
+            FieldSource::XPath(xpath) => xpath
+                .select_from(element)
+                .ok()
+                .and_then(|nodes| nodes.first().map(node_text)),
+            FieldSource::JsonPath(_) | FieldSource::Group(_) => None,
+        };
+
+        value.map(Value::String).unwrap_or(Value::Null)
+    }
+
+    fn resolve_json(&self, value: &Value) -> Value {
+        match &self.source {
+            FieldSource::JsonPath(path) => path.select(value).first().map(|v| (*v).clone()).unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
+}
+
+fn parse_selector(selector: &str) -> Result<Selector> {
+    Selector::parse(selector)
+        .map_err(|e| Error::Parse(format!("Invalid selector '{selector}': {e:?}")))
+}
+
+fn element_text(element: ElementRef) -> String {
+    element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn node_text(node: &XNode) -> String {
+    match node.as_element() {
+        Some(element) => element_text(element),
+        None => node.string_value().trim().to_string(),
+    }
+}
+
+/// Collects the JSON documents embedded in a page (JSON-LD, Next.js
+/// `__NEXT_DATA__` and other `application/json` scripts). A response whose
+/// body is itself JSON is used as-is.
+fn embedded_json(document: &Html, source: &str) -> Vec<Value> {
+    if let Ok(value) = serde_json::from_str::<Value>(source.trim()) {
+        if value.is_object() || value.is_array() {
+            return vec![value];
+        }
+    }
+
+    let selector = Selector::parse(
+        r#"script[type="application/ld+json"], script[type="application/json"], script#__NEXT_DATA__"#,
+    )
+    .unwrap();
+
+    document
+        .select(&selector)
+        .filter_map(|script| {
+            let text = script.text().collect::<String>();
+            serde_json::from_str::<Value>(text.trim()).ok()
+        })
+        .collect()
+}
+
+/// Validates `value` against the subset of JSON Schema used by template
+/// `output_schema`s and returns every violation found.
+pub fn validate_json_schema(value: &Value, schema: &Value) -> Vec<String> {
+    let mut violations = Vec::new();
+    validate_at("$", value, schema, &mut violations);
+    violations
+}
+
+fn validate_at(path: &str, value: &Value, schema: &Value, violations: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| matches_type(value, t)) {
+            violations.push(format!(
+                "{}: expected {}, got {}",
+                path,
+                types.join(" or "),
+                json_type(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            violations.push(format!("{path}: value {value} is not one of {}", Value::Array(options.clone())));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            violations.push(format!("{path}: expected constant {constant}"));
+        }
+    }
+
+    match value {
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    violations.push(format!("{path}: {n} is less than minimum {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    violations.push(format!("{path}: {n} is greater than maximum {max}"));
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    violations.push(format!("{path}: length {len} is shorter than {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    violations.push(format!("{path}: length {len} is longer than {max}"));
+                }
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                match Regex::new(pattern) {
+                    Ok(re) if !re.is_match(s) => {
+                        violations.push(format!("{path}: does not match pattern '{pattern}'"))
+                    }
+                    Err(e) => violations.push(format!("{path}: invalid pattern '{pattern}': {e}")),
+                    _ => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if len < min {
+                    violations.push(format!("{path}: expected at least {min} items, got {len}"));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if len > max {
+                    violations.push(format!("{path}: expected at most {max} items, got {len}"));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(&format!("{path}[{i}]"), item, item_schema, violations);
+                }
+            }
+        }
+        Value::Object(map) => {
+            let mut missing = Vec::new();
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if map.get(key).is_none_or(Value::is_null) {
+                        violations.push(format!("{path}.{key}: required property is missing"));
+                        missing.push(key);
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(properties) = properties {
+                for (key, property_schema) in properties {
+                    if missing.contains(&key.as_str()) {
+                        continue;
+                    }
+                    if let Some(child) = map.get(key) {
+                        validate_at(&format!("{path}.{key}"), child, property_schema, violations);
+                    }
+                }
+            }
+            if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                for key in map.keys() {
+                    if !properties.is_some_and(|p| p.contains_key(key)) {
+                        violations.push(format!("{path}.{key}: additional property is not allowed"));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -0,0 +1,371 @@
+use super::coerce::{parse_boolean, parse_number};
+use super::extractors::Transformer;
+use crate::{Error, Result};
+use regex::Regex;
+use serde_json::Value;
+
+/// A chain of value transforms written as `trim | lowercase | replace(" ", "-")`.
+///
+/// This is the transform language shared by `ParseRule::transform`,
+/// regex `Extractor`s and extraction templates. String transforms applied to
+/// an array are applied to each element; `join`, `first` and `last` operate
+/// on the array itself.
+#[derive(Debug, Clone, Default)]
+pub struct TransformPipeline {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Trim,
+    Lowercase,
+    Uppercase,
+    NormalizeWhitespace,
+    Replace(String, String),
+    RegexReplace(Regex, String),
+    Extract(Regex, usize),
+    Split(String),
+    Join(String),
+    First,
+    Last,
+    ToNumber,
+    ToInteger,
+    ToBoolean,
+    Default(Value),
+}
+
+impl TransformPipeline {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let mut steps = Vec::new();
+
+        for call in split_top_level(expression, '|')? {
+            let call = call.trim();
+            if call.is_empty() {
+                continue;
+            }
+            steps.push(Step::parse(call)?);
+        }
+
+        Ok(Self { steps })
+    }
+
+    /// Builds a pipeline from the structured `Transformer` list used by
+    /// `Extractor` configs.
+    pub fn from_transformers(transformers: &[Transformer]) -> Result<Self> {
+        let mut steps = Vec::new();
+
+        for transformer in transformers {
+            match transformer {
+                Transformer::Lowercase => steps.push(Step::Lowercase),
+                Transformer::Uppercase => steps.push(Step::Uppercase),
+                Transformer::Trim => steps.push(Step::Trim),
+                Transformer::Replace { from, to } => {
+                    steps.push(Step::Replace(from.clone(), to.clone()))
+                }
+                Transformer::Extract { regex } => steps.push(Step::Extract(compile(regex)?, 1)),
+                // A single-value Split keeps the first piece
+                Transformer::Split { delimiter } => {
+                    steps.push(Step::Split(delimiter.clone()));
+                    steps.push(Step::First);
+                }
+                Transformer::Join { delimiter } => steps.push(Step::Join(delimiter.clone())),
+            }
+        }
+
+        Ok(Self { steps })
+    }
+
+    /// Appends the steps of `other` after the steps of `self`.
+    pub fn then(mut self, other: TransformPipeline) -> Self {
+        self.steps.extend(other.steps);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn apply(&self, value: Value) -> Value {
+        self.steps
+            .iter()
+            .fold(value, |value, step| step.apply(value))
+    }
+
+    pub fn apply_str(&self, text: &str) -> String {
+        value_to_string(&self.apply(Value::String(text.to_string())))
+    }
+}
+
+impl Step {
+    fn parse(call: &str) -> Result<Self> {
+        let (name, args) = match call.find('(') {
+            Some(open) => {
+                if !call.ends_with(')') {
+                    return Err(Error::Parse(format!(
+                        "Unterminated argument list in transform '{call}'"
+                    )));
+                }
+                let inner = &call[open + 1..call.len() - 1];
+                let args = if inner.trim().is_empty() {
+                    Vec::new()
+                } else {
+                    split_top_level(inner, ',')?
+                        .into_iter()
+                        .map(|arg| parse_arg(arg.trim()))
+                        .collect::<Result<Vec<_>>>()?
+                };
+                (call[..open].trim(), args)
+            }
+            None => (call, Vec::new()),
+        };
+
+        let arity = |expected: &[usize]| -> Result<()> {
+            if expected.contains(&args.len()) {
+                Ok(())
+            } else {
+                Err(Error::Parse(format!(
+                    "Transform '{}' takes {} argument(s), got {}",
+                    name,
+                    expected
+                        .iter()
+                        .map(|n| n.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" or "),
+                    args.len()
+                )))
+            }
+        };
+
+        let step = match name {
+            "trim" => {
+                arity(&[0])?;
+                Step::Trim
+            }
+            "lowercase" => {
+                arity(&[0])?;
+                Step::Lowercase
+            }
+            "uppercase" => {
+                arity(&[0])?;
+                Step::Uppercase
+            }
+            "normalize_whitespace" => {
+                arity(&[0])?;
+                Step::NormalizeWhitespace
+            }
+            "replace" => {
+                arity(&[2])?;
+                Step::Replace(args[0].clone(), args[1].clone())
+            }
+            "regex_replace" => {
+                arity(&[2])?;
+                Step::RegexReplace(compile(&args[0])?, args[1].clone())
+            }
+            "extract" => {
+                arity(&[1, 2])?;
+                let group = match args.get(1) {
+                    Some(group) => group.parse::<usize>().map_err(|_| {
+                        Error::Parse(format!("Invalid capture group '{group}' in extract()"))
+                    })?,
+                    None => 1,
+                };
+                Step::Extract(compile(&args[0])?, group)
+            }
+            "split" => {
+                arity(&[1])?;
+                Step::Split(args[0].clone())
+            }
+            "join" => {
+                arity(&[1])?;
+                Step::Join(args[0].clone())
+            }
+            "first" => {
+                arity(&[0])?;
+                Step::First
+            }
+            "last" => {
+                arity(&[0])?;
+                Step::Last
+            }
+            "to_number" => {
+                arity(&[0])?;
+                Step::ToNumber
+            }
+            "to_integer" => {
+                arity(&[0])?;
+                Step::ToInteger
+            }
+            "to_boolean" => {
+                arity(&[0])?;
+                Step::ToBoolean
+            }
+            "default" => {
+                arity(&[1])?;
+                Step::Default(
+                    serde_json::from_str(&args[0]).unwrap_or_else(|_| Value::String(args[0].clone())),
+                )
+            }
+            other => return Err(Error::Parse(format!("Unknown transform '{other}'"))),
+        };
+
+        Ok(step)
+    }
+
+    fn apply(&self, value: Value) -> Value {
+        match (self, value) {
+            (Step::Default(default), Value::Null) => default.clone(),
+            (Step::Default(default), Value::String(s)) if s.trim().is_empty() => default.clone(),
+            (Step::Join(delimiter), Value::Array(items)) => Value::String(
+                items
+                    .iter()
+                    .map(value_to_string)
+                    .collect::<Vec<_>>()
+                    .join(delimiter),
+            ),
+            (Step::First, Value::Array(items)) => items.into_iter().next().unwrap_or(Value::Null),
+            (Step::Last, Value::Array(items)) => items.into_iter().last().unwrap_or(Value::Null),
+            (Step::First | Step::Last | Step::Default(_), value) => value,
+            (step, Value::Array(items)) => {
+                Value::Array(items.into_iter().map(|item| step.apply(item)).collect())
+            }
+            (_, Value::Null) => Value::Null,
+            (Step::ToNumber | Step::ToInteger, Value::Number(n)) => {
+                if matches!(self, Step::ToInteger) {
+                    Value::from(n.as_f64().unwrap_or_default().trunc() as i64)
+                } else {
+                    Value::Number(n)
+                }
+            }
+            (Step::ToBoolean, Value::Bool(b)) => Value::Bool(b),
+            (step, Value::String(s)) => step.apply_str(s),
+            (step, other @ (Value::Number(_) | Value::Bool(_))) => step.apply_str(other.to_string()),
+            (_, other) => other,
+        }
+    }
+
+    fn apply_str(&self, s: String) -> Value {
+        match self {
+            Step::Trim => Value::String(s.trim().to_string()),
+            Step::Lowercase => Value::String(s.to_lowercase()),
+            Step::Uppercase => Value::String(s.to_uppercase()),
+            Step::NormalizeWhitespace => {
+                Value::String(s.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            Step::Replace(from, to) => Value::String(s.replace(from.as_str(), to)),
+            Step::RegexReplace(regex, to) => Value::String(regex.replace_all(&s, to.as_str()).into_owned()),
+            Step::Extract(regex, group) => match regex.captures(&s) {
+                Some(captures) => captures
+                    .get(*group)
+                    .or_else(|| captures.get(0))
+                    .map(|m| Value::String(m.as_str().to_string()))
+                    .unwrap_or(Value::String(s)),
+                None => Value::String(s),
+            },
+            Step::Split(delimiter) => Value::Array(
+                s.split(delimiter.as_str())
+                    .map(|part| Value::String(part.to_string()))
+                    .collect(),
+            ),
+            Step::Join(delimiter) => {
+                Value::String(s.split_whitespace().collect::<Vec<_>>().join(delimiter))
+            }
+            Step::ToNumber | Step::ToInteger => match parse_number(&s) {
+                Some(n) if matches!(self, Step::ToInteger) => Value::from(n.trunc() as i64),
+                Some(n) => serde_json::Number::from_f64(n)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null),
+                None => Value::Null,
+            },
+            Step::ToBoolean => parse_boolean(&s).map_or(Value::Null, Value::Bool),
+            Step::First | Step::Last | Step::Default(_) => Value::String(s),
+        }
+    }
+}
+
+/// Renders a transformed value back to text for callers that work with
+/// plain strings.
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(value_to_string)
+            .collect::<Vec<_>>()
+            .join(" "),
+        other => other.to_string(),
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| Error::Parse(format!("Invalid regex '{pattern}': {e}")))
+}
+
+fn parse_arg(arg: &str) -> Result<String> {
+    let mut chars = arg.chars();
+    match chars.next() {
+        Some(quote @ ('"' | '\'')) => {
+            if arg.len() < 2 || !arg.ends_with(quote) {
+                return Err(Error::Parse(format!("Unterminated string argument {arg}")));
+            }
+            let mut out = String::new();
+            let mut escaped = false;
+            for c in arg[1..arg.len() - 1].chars() {
+                if escaped {
+                    out.push(match c {
+                        'n' => '\n',
+                        't' => '\t',
+                        other => other,
+                    });
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else {
+                    out.push(c);
+                }
+            }
+            Ok(out)
+        }
+        _ => Ok(arg.to_string()),
+    }
+}
+
+/// Splits on `separator` outside of quotes and parentheses.
+fn split_top_level(input: &str, separator: char) -> Result<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in input.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&input[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    if quote.is_some() {
+        return Err(Error::Parse(format!("Unterminated string in transform '{input}'")));
+    }
+
+    parts.push(&input[start..]);
+    Ok(parts)
+}
@@ -0,0 +1,1403 @@
+use crate::{Error, Result};
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node};
+use std::collections::HashMap;
+
+/// A compiled XPath 1.0 expression that can be evaluated against a parsed
+/// HTML document.
+#[derive(Debug, Clone)]
+pub struct XPath {
+    source: String,
+    expr: Expr,
+}
+
+/// A node in the XPath data model. Attributes are not tree nodes in
+/// `scraper`, so they are carried alongside their owning element.
+#[derive(Debug, Clone, Copy)]
+pub enum XNode<'a> {
+    Node(NodeRef<'a, Node>),
+    Attribute {
+        owner: NodeRef<'a, Node>,
+        index: usize,
+        name: &'a str,
+        value: &'a str,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum XPathValue<'a> {
+    Nodes(Vec<XNode<'a>>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+impl<'a> XNode<'a> {
+    pub fn string_value(&self) -> String {
+        match self {
+            XNode::Attribute { value, .. } => value.to_string(),
+            XNode::Node(node) => match node.value() {
+                Node::Text(text) => text.to_string(),
+                Node::Comment(comment) => comment.to_string(),
+                Node::ProcessingInstruction(pi) => pi.data.to_string(),
+                _ => node
+                    .descendants()
+                    .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+                    .collect(),
+            },
+        }
+    }
+
+    pub fn as_element(&self) -> Option<ElementRef<'a>> {
+        match self {
+            XNode::Node(node) => ElementRef::wrap(*node),
+            XNode::Attribute { .. } => None,
+        }
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        match self {
+            XNode::Attribute { name, .. } => Some(name),
+            XNode::Node(node) => node.value().as_element().map(|e| e.name()),
+        }
+    }
+
+    fn same_as(&self, other: &XNode<'a>) -> bool {
+        match (self, other) {
+            (XNode::Node(a), XNode::Node(b)) => a.id() == b.id(),
+            (
+                XNode::Attribute { owner: a, index: i, .. },
+                XNode::Attribute { owner: b, index: j, .. },
+            ) => a.id() == b.id() && i == j,
+            _ => false,
+        }
+    }
+}
+
+impl XPathValue<'_> {
+    pub fn to_string_value(&self) -> String {
+        match self {
+            XPathValue::Nodes(nodes) => nodes.first().map(|n| n.string_value()).unwrap_or_default(),
+            XPathValue::String(s) => s.clone(),
+            XPathValue::Number(n) => format_number(*n),
+            XPathValue::Boolean(b) => b.to_string(),
+        }
+    }
+}
+
+impl XPath {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let tokens = tokenize(expression)?;
+        let mut parser = ExprParser { tokens, pos: 0 };
+        let expr = parser.parse_expr()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(Error::Parse(format!(
+                "Unexpected token {:?} in XPath '{}'",
+                parser.tokens[parser.pos], expression
+            )));
+        }
+
+        Ok(Self {
+            source: expression.to_string(),
+            expr,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate<'a>(&self, document: &'a Html) -> Result<XPathValue<'a>> {
+        self.evaluate_at(document.tree.root())
+    }
+
+    /// Evaluates the expression with `element` as the context node, so
+    /// relative paths such as `./span` or `../@href` resolve against it.
+    pub fn evaluate_from<'a>(&self, element: ElementRef<'a>) -> Result<XPathValue<'a>> {
+        self.evaluate_at(*element)
+    }
+
+    pub fn select<'a>(&self, document: &'a Html) -> Result<Vec<XNode<'a>>> {
+        self.expect_nodes(self.evaluate(document)?)
+    }
+
+    pub fn select_from<'a>(&self, element: ElementRef<'a>) -> Result<Vec<XNode<'a>>> {
+        self.expect_nodes(self.evaluate_from(element)?)
+    }
+
+    fn evaluate_at<'a>(&self, node: NodeRef<'a, Node>) -> Result<XPathValue<'a>> {
+        let evaluator = Evaluator::new(node.tree().root());
+        let ctx = Context {
+            node: XNode::Node(node),
+            position: 1,
+            size: 1,
+        };
+
+        Ok(match evaluator.eval(&self.expr, &ctx)? {
+            Val::Nodes(nodes) => XPathValue::Nodes(nodes),
+            Val::Str(s) => XPathValue::String(s),
+            Val::Num(n) => XPathValue::Number(n),
+            Val::Bool(b) => XPathValue::Boolean(b),
+        })
+    }
+
+    fn expect_nodes<'a>(&self, value: XPathValue<'a>) -> Result<Vec<XNode<'a>>> {
+        match value {
+            XPathValue::Nodes(nodes) => Ok(nodes),
+            _ => Err(Error::Parse(format!(
+                "XPath '{}' does not evaluate to a node-set",
+                self.source
+            ))),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Lexer
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    Pipe,
+    ColonColon,
+    Plus,
+    Minus,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Star,
+    Multiply,
+    And,
+    Or,
+    Div,
+    Mod,
+    Literal(String),
+    Number(f64),
+    Name(String),
+}
+
+impl Token {
+    /// Whether a following `*` or operator name must be read as an operator
+    /// (XPath 1.0 section 3.7 disambiguation rule).
+    fn forces_operator(&self) -> bool {
+        !matches!(
+            self,
+            Token::At
+                | Token::ColonColon
+                | Token::LParen
+                | Token::LBracket
+                | Token::Comma
+                | Token::Slash
+                | Token::DoubleSlash
+                | Token::Pipe
+                | Token::Plus
+                | Token::Minus
+                | Token::Eq
+                | Token::NotEq
+                | Token::Lt
+                | Token::LtEq
+                | Token::Gt
+                | Token::GtEq
+                | Token::Multiply
+                | Token::And
+                | Token::Or
+                | Token::Div
+                | Token::Mod
+        )
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let operator_context = tokens.last().is_some_and(|t| t.forces_operator());
+
+        let token = match c {
+            ' ' | '\t' | '\n' | '\r' => {
+                i += 1;
+                continue;
+            }
+            '/' if next == Some('/') => {
+                i += 2;
+                Token::DoubleSlash
+            }
+            '/' => {
+                i += 1;
+                Token::Slash
+            }
+            '(' => {
+                i += 1;
+                Token::LParen
+            }
+            ')' => {
+                i += 1;
+                Token::RParen
+            }
+            '[' => {
+                i += 1;
+                Token::LBracket
+            }
+            ']' => {
+                i += 1;
+                Token::RBracket
+            }
+            '@' => {
+                i += 1;
+                Token::At
+            }
+            ',' => {
+                i += 1;
+                Token::Comma
+            }
+            '|' => {
+                i += 1;
+                Token::Pipe
+            }
+            '+' => {
+                i += 1;
+                Token::Plus
+            }
+            '-' => {
+                i += 1;
+                Token::Minus
+            }
+            '=' => {
+                i += 1;
+                Token::Eq
+            }
+            '!' if next == Some('=') => {
+                i += 2;
+                Token::NotEq
+            }
+            '<' if next == Some('=') => {
+                i += 2;
+                Token::LtEq
+            }
+            '<' => {
+                i += 1;
+                Token::Lt
+            }
+            '>' if next == Some('=') => {
+                i += 2;
+                Token::GtEq
+            }
+            '>' => {
+                i += 1;
+                Token::Gt
+            }
+            ':' if next == Some(':') => {
+                i += 2;
+                Token::ColonColon
+            }
+            '*' => {
+                i += 1;
+                if operator_context {
+                    Token::Multiply
+                } else {
+                    Token::Star
+                }
+            }
+            '.' if next == Some('.') => {
+                i += 2;
+                Token::DotDot
+            }
+            '.' if !next.is_some_and(|n| n.is_ascii_digit()) => {
+                i += 1;
+                Token::Dot
+            }
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .ok_or_else(|| {
+                        Error::Parse(format!("Unterminated string literal in XPath '{input}'"))
+                    })?;
+                let literal: String = chars[i + 1..i + 1 + end].iter().collect();
+                i += end + 2;
+                Token::Literal(literal)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text
+                    .parse::<f64>()
+                    .map_err(|_| Error::Parse(format!("Invalid number '{text}' in XPath")))?;
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() {
+                    let ch = chars[i];
+                    let is_prefix_colon = ch == ':'
+                        && chars.get(i + 1).is_some_and(|n| *n != ':')
+                        && chars.get(i.wrapping_sub(1)).is_some_and(|p| *p != ':');
+                    if ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.' || is_prefix_colon {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let name: String = chars[start..i].iter().collect();
+                match name.as_str() {
+                    "and" if operator_context => Token::And,
+                    "or" if operator_context => Token::Or,
+                    "div" if operator_context => Token::Div,
+                    "mod" if operator_context => Token::Mod,
+                    _ => Token::Name(name),
+                }
+            }
+            other => {
+                return Err(Error::Parse(format!(
+                    "Unexpected character '{other}' in XPath '{input}'"
+                )))
+            }
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+// ---------------------------------------------------------------------------
+// Parser
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    AncestorOrSelf,
+    FollowingSibling,
+    PrecedingSibling,
+    Following,
+    Preceding,
+    Attribute,
+    Itself,
+}
+
+impl Axis {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "child" => Axis::Child,
+            "descendant" => Axis::Descendant,
+            "descendant-or-self" => Axis::DescendantOrSelf,
+            "parent" => Axis::Parent,
+            "ancestor" => Axis::Ancestor,
+            "ancestor-or-self" => Axis::AncestorOrSelf,
+            "following-sibling" => Axis::FollowingSibling,
+            "preceding-sibling" => Axis::PrecedingSibling,
+            "following" => Axis::Following,
+            "preceding" => Axis::Preceding,
+            "attribute" => Axis::Attribute,
+            "self" => Axis::Itself,
+            other => return Err(Error::Parse(format!("Unsupported XPath axis '{other}'"))),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum NodeTest {
+    Name(String),
+    Any,
+    Text,
+    Comment,
+    ProcessingInstruction,
+    Node,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(CmpOp, Box<Expr>, Box<Expr>),
+    Arith(ArithOp, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Function(String, Vec<Expr>),
+    Path { absolute: bool, steps: Vec<Step> },
+    Filter(Box<Expr>, Vec<Expr>),
+    FilterPath(Box<Expr>, Vec<Step>),
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(Error::Parse(format!(
+                "Expected {:?} in XPath, found {:?}",
+                token,
+                self.peek()
+            )))
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while self.eat(&Token::Or) {
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_equality()?;
+        while self.eat(&Token::And) {
+            let right = self.parse_equality()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_equality(&mut self) -> Result<Expr> {
+        let mut left = self.parse_relational()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Eq) => CmpOp::Eq,
+                Some(Token::NotEq) => CmpOp::NotEq,
+                _ => break,
+            };
+            self.pos += 1;
+            let right = self.parse_relational()?;
+            left = Expr::Compare(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_relational(&mut self) -> Result<Expr> {
+        let mut left = self.parse_additive()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Lt) => CmpOp::Lt,
+                Some(Token::LtEq) => CmpOp::LtEq,
+                Some(Token::Gt) => CmpOp::Gt,
+                Some(Token::GtEq) => CmpOp::GtEq,
+                _ => break,
+            };
+            self.pos += 1;
+            let right = self.parse_additive()?;
+            left = Expr::Compare(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => ArithOp::Add,
+                Some(Token::Minus) => ArithOp::Sub,
+                _ => break,
+            };
+            self.pos += 1;
+            let right = self.parse_multiplicative()?;
+            left = Expr::Arith(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Multiply) => ArithOp::Mul,
+                Some(Token::Div) => ArithOp::Div,
+                Some(Token::Mod) => ArithOp::Mod,
+                _ => break,
+            };
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Expr::Arith(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.eat(&Token::Minus) {
+            let inner = self.parse_unary()?;
+            return Ok(Expr::Negate(Box::new(inner)));
+        }
+        self.parse_union()
+    }
+
+    fn parse_union(&mut self) -> Result<Expr> {
+        let mut left = self.parse_path()?;
+        while self.eat(&Token::Pipe) {
+            let right = self.parse_path()?;
+            left = Expr::Union(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_path(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Slash) => {
+                self.pos += 1;
+                let steps = if self.starts_step() {
+                    self.parse_relative_steps()?
+                } else {
+                    Vec::new()
+                };
+                Ok(Expr::Path {
+                    absolute: true,
+                    steps,
+                })
+            }
+            Some(Token::DoubleSlash) => {
+                self.pos += 1;
+                let mut steps = vec![descendant_or_self_step()];
+                steps.extend(self.parse_relative_steps()?);
+                Ok(Expr::Path {
+                    absolute: true,
+                    steps,
+                })
+            }
+            _ if self.starts_filter_expr() => {
+                let primary = self.parse_primary()?;
+                let mut predicates = Vec::new();
+                while self.peek() == Some(&Token::LBracket) {
+                    predicates.push(self.parse_predicate()?);
+                }
+                let filter = if predicates.is_empty() {
+                    primary
+                } else {
+                    Expr::Filter(Box::new(primary), predicates)
+                };
+
+                let mut steps = Vec::new();
+                loop {
+                    if self.eat(&Token::Slash) {
+                        steps.push(self.parse_step()?);
+                    } else if self.eat(&Token::DoubleSlash) {
+                        steps.push(descendant_or_self_step());
+                        steps.push(self.parse_step()?);
+                    } else {
+                        break;
+                    }
+                }
+
+                if steps.is_empty() {
+                    Ok(filter)
+                } else {
+                    Ok(Expr::FilterPath(Box::new(filter), steps))
+                }
+            }
+            _ => Ok(Expr::Path {
+                absolute: false,
+                steps: self.parse_relative_steps()?,
+            }),
+        }
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Name(_) | Token::Star | Token::At | Token::Dot | Token::DotDot)
+        )
+    }
+
+    fn starts_filter_expr(&self) -> bool {
+        match self.peek() {
+            Some(Token::LParen | Token::Literal(_) | Token::Number(_)) => true,
+            Some(Token::Name(name)) => {
+                self.peek_at(1) == Some(&Token::LParen) && !is_node_type(name)
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_relative_steps(&mut self) -> Result<Vec<Step>> {
+        let mut steps = vec![self.parse_step()?];
+        loop {
+            if self.eat(&Token::Slash) {
+                steps.push(self.parse_step()?);
+            } else if self.eat(&Token::DoubleSlash) {
+                steps.push(descendant_or_self_step());
+                steps.push(self.parse_step()?);
+            } else {
+                break;
+            }
+        }
+        Ok(steps)
+    }
+
+    fn parse_step(&mut self) -> Result<Step> {
+        if self.eat(&Token::Dot) {
+            return Ok(Step {
+                axis: Axis::Itself,
+                test: NodeTest::Node,
+                predicates: Vec::new(),
+            });
+        }
+        if self.eat(&Token::DotDot) {
+            return Ok(Step {
+                axis: Axis::Parent,
+                test: NodeTest::Node,
+                predicates: Vec::new(),
+            });
+        }
+
+        let axis = if self.eat(&Token::At) {
+            Axis::Attribute
+        } else if let (Some(Token::Name(name)), Some(Token::ColonColon)) =
+            (self.peek(), self.peek_at(1))
+        {
+            let axis = Axis::from_name(name)?;
+            self.pos += 2;
+            axis
+        } else {
+            Axis::Child
+        };
+
+        let test = match self.peek().cloned() {
+            Some(Token::Star) => {
+                self.pos += 1;
+                NodeTest::Any
+            }
+            Some(Token::Name(name)) if is_node_type(&name) && self.peek_at(1) == Some(&Token::LParen) => {
+                self.pos += 2;
+                // processing-instruction('target') takes an optional literal
+                if let Some(Token::Literal(_)) = self.peek() {
+                    self.pos += 1;
+                }
+                self.expect(&Token::RParen)?;
+                match name.as_str() {
+                    "text" => NodeTest::Text,
+                    "comment" => NodeTest::Comment,
+                    "processing-instruction" => NodeTest::ProcessingInstruction,
+                    _ => NodeTest::Node,
+                }
+            }
+            Some(Token::Name(name)) => {
+                self.pos += 1;
+                NodeTest::Name(name.to_lowercase())
+            }
+            other => {
+                return Err(Error::Parse(format!(
+                    "Expected node test in XPath, found {other:?}"
+                )))
+            }
+        };
+
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::LBracket) {
+            predicates.push(self.parse_predicate()?);
+        }
+
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+        })
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr> {
+        self.expect(&Token::LBracket)?;
+        let expr = self.parse_expr()?;
+        self.expect(&Token::RBracket)?;
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.peek().cloned() {
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                self.expect(&Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Literal(s)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(s))
+            }
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                Ok(Expr::Number(n))
+            }
+            Some(Token::Name(name)) => {
+                self.pos += 1;
+                self.expect(&Token::LParen)?;
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.parse_expr()?);
+                        if self.eat(&Token::Comma) {
+                            continue;
+                        }
+                        self.expect(&Token::RParen)?;
+                        break;
+                    }
+                }
+                Ok(Expr::Function(name, args))
+            }
+            other => Err(Error::Parse(format!(
+                "Unexpected token {other:?} in XPath expression"
+            ))),
+        }
+    }
+}
+
+fn is_node_type(name: &str) -> bool {
+    matches!(name, "node" | "text" | "comment" | "processing-instruction")
+}
+
+fn descendant_or_self_step() -> Step {
+    Step {
+        axis: Axis::DescendantOrSelf,
+        test: NodeTest::Node,
+        predicates: Vec::new(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Evaluation
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+enum Val<'a> {
+    Nodes(Vec<XNode<'a>>),
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+impl Val<'_> {
+    fn to_bool(&self) -> bool {
+        match self {
+            Val::Nodes(nodes) => !nodes.is_empty(),
+            Val::Str(s) => !s.is_empty(),
+            Val::Num(n) => *n != 0.0 && !n.is_nan(),
+            Val::Bool(b) => *b,
+        }
+    }
+
+    fn to_num(&self) -> f64 {
+        match self {
+            Val::Nodes(_) | Val::Str(_) => string_to_number(&self.to_str()),
+            Val::Num(n) => *n,
+            Val::Bool(b) => {
+                if *b {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    fn to_str(&self) -> String {
+        match self {
+            Val::Nodes(nodes) => nodes.first().map(|n| n.string_value()).unwrap_or_default(),
+            Val::Str(s) => s.clone(),
+            Val::Num(n) => format_number(*n),
+            Val::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+struct Context<'a> {
+    node: XNode<'a>,
+    position: usize,
+    size: usize,
+}
+
+struct Evaluator<'a> {
+    root: NodeRef<'a, Node>,
+    order: HashMap<NodeId, usize>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(root: NodeRef<'a, Node>) -> Self {
+        let order = root
+            .descendants()
+            .enumerate()
+            .map(|(i, node)| (node.id(), i))
+            .collect();
+        Self { root, order }
+    }
+
+    fn order_key(&self, node: &XNode<'a>) -> (usize, usize) {
+        match node {
+            XNode::Node(n) => (self.order.get(&n.id()).copied().unwrap_or(0), 0),
+            XNode::Attribute { owner, index, .. } => (
+                self.order.get(&owner.id()).copied().unwrap_or(0),
+                index + 1,
+            ),
+        }
+    }
+
+    fn sort_dedup(&self, nodes: &mut Vec<XNode<'a>>) {
+        nodes.sort_by_key(|n| self.order_key(n));
+        nodes.dedup_by(|a, b| a.same_as(b));
+    }
+
+    fn eval(&self, expr: &Expr, ctx: &Context<'a>) -> Result<Val<'a>> {
+        match expr {
+            Expr::Or(a, b) => Ok(Val::Bool(
+                self.eval(a, ctx)?.to_bool() || self.eval(b, ctx)?.to_bool(),
+            )),
+            Expr::And(a, b) => Ok(Val::Bool(
+                self.eval(a, ctx)?.to_bool() && self.eval(b, ctx)?.to_bool(),
+            )),
+            Expr::Compare(op, a, b) => {
+                let left = self.eval(a, ctx)?;
+                let right = self.eval(b, ctx)?;
+                Ok(Val::Bool(compare(*op, &left, &right)))
+            }
+            Expr::Arith(op, a, b) => {
+                let x = self.eval(a, ctx)?.to_num();
+                let y = self.eval(b, ctx)?.to_num();
+                Ok(Val::Num(match op {
+                    ArithOp::Add => x + y,
+                    ArithOp::Sub => x - y,
+                    ArithOp::Mul => x * y,
+                    ArithOp::Div => x / y,
+                    ArithOp::Mod => x % y,
+                }))
+            }
+            Expr::Negate(inner) => Ok(Val::Num(-self.eval(inner, ctx)?.to_num())),
+            Expr::Union(a, b) => {
+                let mut nodes = self.eval_nodes(a, ctx)?;
+                nodes.extend(self.eval_nodes(b, ctx)?);
+                self.sort_dedup(&mut nodes);
+                Ok(Val::Nodes(nodes))
+            }
+            Expr::Literal(s) => Ok(Val::Str(s.clone())),
+            Expr::Number(n) => Ok(Val::Num(*n)),
+            Expr::Function(name, args) => self.call(name, args, ctx),
+            Expr::Path { absolute, steps } => {
+                let start = if *absolute {
+                    XNode::Node(self.root)
+                } else {
+                    ctx.node
+                };
+                Ok(Val::Nodes(self.apply_steps(vec![start], steps)?))
+            }
+            Expr::Filter(primary, predicates) => {
+                let mut nodes = self.eval_nodes(primary, ctx)?;
+                for predicate in predicates {
+                    nodes = self.apply_predicate(nodes, predicate)?;
+                }
+                Ok(Val::Nodes(nodes))
+            }
+            Expr::FilterPath(filter, steps) => {
+                let nodes = self.eval_nodes(filter, ctx)?;
+                Ok(Val::Nodes(self.apply_steps(nodes, steps)?))
+            }
+        }
+    }
+
+    fn eval_nodes(&self, expr: &Expr, ctx: &Context<'a>) -> Result<Vec<XNode<'a>>> {
+        match self.eval(expr, ctx)? {
+            Val::Nodes(nodes) => Ok(nodes),
+            other => Err(Error::Parse(format!(
+                "Expected a node-set in XPath, got {other:?}"
+            ))),
+        }
+    }
+
+    fn apply_steps(&self, mut nodes: Vec<XNode<'a>>, steps: &[Step]) -> Result<Vec<XNode<'a>>> {
+        for step in steps {
+            let mut next = Vec::new();
+            for node in &nodes {
+                let mut candidates: Vec<XNode<'a>> = self
+                    .axis_nodes(node, step.axis)
+                    .into_iter()
+                    .filter(|n| self.matches_test(n, &step.test, step.axis))
+                    .collect();
+
+                // Predicates see positions in axis order, which is reverse
+                // document order for the reverse axes.
+                for predicate in &step.predicates {
+                    candidates = self.apply_predicate(candidates, predicate)?;
+                }
+                next.extend(candidates);
+            }
+            self.sort_dedup(&mut next);
+            nodes = next;
+        }
+        Ok(nodes)
+    }
+
+    fn apply_predicate(&self, nodes: Vec<XNode<'a>>, predicate: &Expr) -> Result<Vec<XNode<'a>>> {
+        let size = nodes.len();
+        let mut kept = Vec::new();
+        for (i, node) in nodes.into_iter().enumerate() {
+            let ctx = Context {
+                node,
+                position: i + 1,
+                size,
+            };
+            let keep = match self.eval(predicate, &ctx)? {
+                Val::Num(n) => n == (i + 1) as f64,
+                other => other.to_bool(),
+            };
+            if keep {
+                kept.push(node);
+            }
+        }
+        Ok(kept)
+    }
+
+    fn axis_nodes(&self, node: &XNode<'a>, axis: Axis) -> Vec<XNode<'a>> {
+        let wrap = |n: NodeRef<'a, Node>| XNode::Node(n);
+        let n = match node {
+            XNode::Node(n) => *n,
+            XNode::Attribute { owner, .. } => {
+                return match axis {
+                    Axis::Itself => vec![*node],
+                    Axis::Parent => vec![wrap(*owner)],
+                    Axis::Ancestor => std::iter::once(*owner)
+                        .chain(owner.ancestors())
+                        .map(wrap)
+                        .collect(),
+                    Axis::AncestorOrSelf => std::iter::once(*node)
+                        .chain(std::iter::once(*owner).chain(owner.ancestors()).map(wrap))
+                        .collect(),
+                    Axis::Following => {
+                        let mut nodes: Vec<XNode<'a>> =
+                            owner.descendants().skip(1).map(wrap).collect();
+                        nodes.extend(self.axis_nodes(&wrap(*owner), Axis::Following));
+                        nodes
+                    }
+                    Axis::Preceding => self.axis_nodes(&wrap(*owner), Axis::Preceding),
+                    _ => Vec::new(),
+                };
+            }
+        };
+
+        let nodes: Vec<XNode<'a>> = match axis {
+            Axis::Child => n.children().map(wrap).collect(),
+            Axis::Descendant => n.descendants().skip(1).map(wrap).collect(),
+            Axis::DescendantOrSelf => n.descendants().map(wrap).collect(),
+            Axis::Parent => n.parent().map(wrap).into_iter().collect(),
+            Axis::Ancestor => n.ancestors().map(wrap).collect(),
+            Axis::AncestorOrSelf => std::iter::once(n).chain(n.ancestors()).map(wrap).collect(),
+            Axis::FollowingSibling => n.next_siblings().map(wrap).collect(),
+            Axis::PrecedingSibling => n.prev_siblings().map(wrap).collect(),
+            Axis::Itself => vec![wrap(n)],
+            Axis::Attribute => match n.value().as_element() {
+                Some(element) => element
+                    .attrs()
+                    .enumerate()
+                    .map(|(index, (name, value))| XNode::Attribute {
+                        owner: n,
+                        index,
+                        name,
+                        value,
+                    })
+                    .collect(),
+                None => Vec::new(),
+            },
+            Axis::Following => {
+                let mut nodes = Vec::new();
+                for current in std::iter::once(n).chain(n.ancestors()) {
+                    for sibling in current.next_siblings() {
+                        nodes.extend(sibling.descendants().map(wrap));
+                    }
+                }
+                nodes.sort_by_key(|x| self.order_key(x));
+                nodes
+            }
+            Axis::Preceding => {
+                let position = self.order.get(&n.id()).copied().unwrap_or(0);
+                let ancestors: Vec<NodeId> = n.ancestors().map(|a| a.id()).collect();
+                let mut nodes: Vec<XNode<'a>> = self
+                    .root
+                    .descendants()
+                    .take(position)
+                    .filter(|x| !ancestors.contains(&x.id()))
+                    .map(wrap)
+                    .collect();
+                nodes.reverse();
+                nodes
+            }
+        };
+
+        nodes
+            .into_iter()
+            .filter(|x| match x {
+                XNode::Node(node) => !matches!(node.value(), Node::Doctype(_)),
+                XNode::Attribute { .. } => true,
+            })
+            .collect()
+    }
+
+    fn matches_test(&self, node: &XNode<'a>, test: &NodeTest, axis: Axis) -> bool {
+        match node {
+            XNode::Attribute { name, .. } => match test {
+                NodeTest::Name(expected) => name.eq_ignore_ascii_case(expected),
+                NodeTest::Any | NodeTest::Node => true,
+                _ => false,
+            },
+            XNode::Node(n) => {
+                // The principal node type of the attribute axis is attribute,
+                // so element-only tests never match real nodes there.
+                if axis == Axis::Attribute {
+                    return false;
+                }
+                match test {
+                    NodeTest::Name(expected) => n
+                        .value()
+                        .as_element()
+                        .is_some_and(|e| e.name().eq_ignore_ascii_case(expected)),
+                    NodeTest::Any => n.value().is_element(),
+                    NodeTest::Text => n.value().is_text(),
+                    NodeTest::Comment => n.value().is_comment(),
+                    NodeTest::ProcessingInstruction => {
+                        matches!(n.value(), Node::ProcessingInstruction(_))
+                    }
+                    NodeTest::Node => true,
+                }
+            }
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expr], ctx: &Context<'a>) -> Result<Val<'a>> {
+        let arity = |min: usize, max: usize| -> Result<()> {
+            if args.len() < min || args.len() > max {
+                Err(Error::Parse(format!(
+                    "XPath function {name}() called with {} argument(s)",
+                    args.len()
+                )))
+            } else {
+                Ok(())
+            }
+        };
+        let arg_str = |i: usize| -> Result<String> {
+            match args.get(i) {
+                Some(expr) => Ok(self.eval(expr, ctx)?.to_str()),
+                None => Ok(ctx.node.string_value()),
+            }
+        };
+        let arg_num = |i: usize| -> Result<f64> { Ok(self.eval(&args[i], ctx)?.to_num()) };
+
+        match name {
+            "last" => {
+                arity(0, 0)?;
+                Ok(Val::Num(ctx.size as f64))
+            }
+            "position" => {
+                arity(0, 0)?;
+                Ok(Val::Num(ctx.position as f64))
+            }
+            "count" => {
+                arity(1, 1)?;
+                Ok(Val::Num(self.eval_nodes(&args[0], ctx)?.len() as f64))
+            }
+            "name" | "local-name" => {
+                arity(0, 1)?;
+                let node = match args.first() {
+                    Some(expr) => self.eval_nodes(expr, ctx)?.first().copied(),
+                    None => Some(ctx.node),
+                };
+                Ok(Val::Str(
+                    node.and_then(|n| n.name()).unwrap_or_default().to_string(),
+                ))
+            }
+            "string" => {
+                arity(0, 1)?;
+                Ok(Val::Str(arg_str(0)?))
+            }
+            "concat" => {
+                if args.len() < 2 {
+                    return Err(Error::Parse(
+                        "XPath function concat() needs at least 2 arguments".to_string(),
+                    ));
+                }
+                let mut out = String::new();
+                for expr in args {
+                    out.push_str(&self.eval(expr, ctx)?.to_str());
+                }
+                Ok(Val::Str(out))
+            }
+            "contains" => {
+                arity(2, 2)?;
+                Ok(Val::Bool(arg_str(0)?.contains(&arg_str(1)?)))
+            }
+            "starts-with" => {
+                arity(2, 2)?;
+                Ok(Val::Bool(arg_str(0)?.starts_with(&arg_str(1)?)))
+            }
+            "ends-with" => {
+                arity(2, 2)?;
+                Ok(Val::Bool(arg_str(0)?.ends_with(&arg_str(1)?)))
+            }
+            "substring-before" => {
+                arity(2, 2)?;
+                let s = arg_str(0)?;
+                let needle = arg_str(1)?;
+                Ok(Val::Str(
+                    s.find(&needle).map(|i| s[..i].to_string()).unwrap_or_default(),
+                ))
+            }
+            "substring-after" => {
+                arity(2, 2)?;
+                let s = arg_str(0)?;
+                let needle = arg_str(1)?;
+                Ok(Val::Str(
+                    s.find(&needle)
+                        .map(|i| s[i + needle.len()..].to_string())
+                        .unwrap_or_default(),
+                ))
+            }
+            "substring" => {
+                arity(2, 3)?;
+                let chars: Vec<char> = arg_str(0)?.chars().collect();
+                let start = xpath_round(arg_num(1)?);
+                let end = if args.len() == 3 {
+                    start + xpath_round(arg_num(2)?)
+                } else {
+                    f64::INFINITY
+                };
+                let out: String = chars
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| {
+                        let pos = (*i + 1) as f64;
+                        pos >= start && pos < end
+                    })
+                    .map(|(_, c)| *c)
+                    .collect();
+                Ok(Val::Str(out))
+            }
+            "string-length" => {
+                arity(0, 1)?;
+                Ok(Val::Num(arg_str(0)?.chars().count() as f64))
+            }
+            "normalize-space" => {
+                arity(0, 1)?;
+                Ok(Val::Str(
+                    arg_str(0)?.split_whitespace().collect::<Vec<_>>().join(" "),
+                ))
+            }
+            "translate" => {
+                arity(3, 3)?;
+                let from: Vec<char> = arg_str(1)?.chars().collect();
+                let to: Vec<char> = arg_str(2)?.chars().collect();
+                let out = arg_str(0)?
+                    .chars()
+                    .filter_map(|c| match from.iter().position(|f| *f == c) {
+                        Some(i) => to.get(i).copied(),
+                        None => Some(c),
+                    })
+                    .collect();
+                Ok(Val::Str(out))
+            }
+            "lower-case" => {
+                arity(1, 1)?;
+                Ok(Val::Str(arg_str(0)?.to_lowercase()))
+            }
+            "upper-case" => {
+                arity(1, 1)?;
+                Ok(Val::Str(arg_str(0)?.to_uppercase()))
+            }
+            "not" => {
+                arity(1, 1)?;
+                Ok(Val::Bool(!self.eval(&args[0], ctx)?.to_bool()))
+            }
+            "true" => {
+                arity(0, 0)?;
+                Ok(Val::Bool(true))
+            }
+            "false" => {
+                arity(0, 0)?;
+                Ok(Val::Bool(false))
+            }
+            "boolean" => {
+                arity(1, 1)?;
+                Ok(Val::Bool(self.eval(&args[0], ctx)?.to_bool()))
+            }
+            "number" => {
+                arity(0, 1)?;
+                match args.first() {
+                    Some(expr) => Ok(Val::Num(self.eval(expr, ctx)?.to_num())),
+                    None => Ok(Val::Num(string_to_number(&ctx.node.string_value()))),
+                }
+            }
+            "sum" => {
+                arity(1, 1)?;
+                Ok(Val::Num(
+                    self.eval_nodes(&args[0], ctx)?
+                        .iter()
+                        .map(|n| string_to_number(&n.string_value()))
+                        .sum(),
+                ))
+            }
+            "floor" => {
+                arity(1, 1)?;
+                Ok(Val::Num(arg_num(0)?.floor()))
+            }
+            "ceiling" => {
+                arity(1, 1)?;
+                Ok(Val::Num(arg_num(0)?.ceil()))
+            }
+            "round" => {
+                arity(1, 1)?;
+                Ok(Val::Num(xpath_round(arg_num(0)?)))
+            }
+            other => Err(Error::Parse(format!(
+                "Unsupported XPath function '{other}()'"
+            ))),
+        }
+    }
+}
+
+fn compare(op: CmpOp, left: &Val<'_>, right: &Val<'_>) -> bool {
+    match (left, right) {
+        (Val::Nodes(a), Val::Nodes(b)) => a.iter().any(|x| {
+            let xs = x.string_value();
+            b.iter()
+                .any(|y| compare_atomic(op, &Val::Str(xs.clone()), &Val::Str(y.string_value())))
+        }),
+        (Val::Nodes(nodes), other) => compare_node_set(op, nodes, other, false),
+        (other, Val::Nodes(nodes)) => compare_node_set(op, nodes, other, true),
+        _ => compare_atomic(op, left, right),
+    }
+}
+
+fn compare_node_set(op: CmpOp, nodes: &[XNode<'_>], other: &Val<'_>, swapped: bool) -> bool {
+    if let Val::Bool(_) = other {
+        let set = Val::Bool(!nodes.is_empty());
+        return if swapped {
+            compare_atomic(op, other, &set)
+        } else {
+            compare_atomic(op, &set, other)
+        };
+    }
+
+    nodes.iter().any(|node| {
+        let value = match other {
+            Val::Num(_) => Val::Num(string_to_number(&node.string_value())),
+            _ => Val::Str(node.string_value()),
+        };
+        if swapped {
+            compare_atomic(op, other, &value)
+        } else {
+            compare_atomic(op, &value, other)
+        }
+    })
+}
+
+fn compare_atomic(op: CmpOp, left: &Val<'_>, right: &Val<'_>) -> bool {
+    match op {
+        CmpOp::Eq | CmpOp::NotEq => {
+            let equal = if matches!(left, Val::Bool(_)) || matches!(right, Val::Bool(_)) {
+                left.to_bool() == right.to_bool()
+            } else if matches!(left, Val::Num(_)) || matches!(right, Val::Num(_)) {
+                left.to_num() == right.to_num()
+            } else {
+                left.to_str() == right.to_str()
+            };
+            equal == (op == CmpOp::Eq)
+        }
+        CmpOp::Lt => left.to_num() < right.to_num(),
+        CmpOp::LtEq => left.to_num() <= right.to_num(),
+        CmpOp::Gt => left.to_num() > right.to_num(),
+        CmpOp::GtEq => left.to_num() >= right.to_num(),
+    }
+}
+
+fn string_to_number(s: &str) -> f64 {
+    s.trim().parse::<f64>().unwrap_or(f64::NAN)
+}
+
+fn xpath_round(n: f64) -> f64 {
+    if n.is_nan() || n.is_infinite() {
+        n
+    } else {
+        (n + 0.5).floor()
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 {
+            "Infinity".to_string()
+        } else {
+            "-Infinity".to_string()
+        }
+    } else if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
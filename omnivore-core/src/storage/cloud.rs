@@ -0,0 +1,249 @@
+//! Uploads crawl output to object storage.
+//!
+//! A crawl's files go under one key prefix, built from
+//! `CloudConfig::key_layout`:
+//!
+//! - `results.json`: the stats and full results, like `omnivore crawl --include-raw`
+//! - `tables/page_0001_table_1.csv`: each table extracted from each page
+//! - `html/page_0001.html`: each page's raw HTML, when asked for
+//!
+//! Only S3 and S3-compatible services such as MinIO are supported so far.
+//! Other providers plug in by implementing `CloudStore`.
+
+use crate::config::CloudConfig;
+use crate::{CrawlResult, CrawlStats, Error, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::NaiveDate;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path;
+use object_store::{
+    Attribute, Attributes, BackoffConfig, ObjectStore, PutMultipartOptions, PutOptions, PutPayload,
+    RetryConfig,
+};
+use std::time::Duration;
+
+/// Smallest part S3 accepts in a multipart upload, other than the last.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Files uploaded at the same time.
+const CONCURRENT_UPLOADS: usize = 8;
+
+/// Parts of one multipart upload sent at the same time.
+const CONCURRENT_PARTS: usize = 4;
+
+impl From<object_store::Error> for Error {
+    fn from(e: object_store::Error) -> Self {
+        Error::Storage(e.to_string())
+    }
+}
+
+/// Somewhere files can be uploaded to by key.
+#[async_trait]
+pub trait CloudStore: Send + Sync {
+    async fn put(&self, key: &str, body: Bytes, content_type: &str) -> Result<()>;
+}
+
+/// Creates the store `config.provider` names.
+pub fn connect(config: &CloudConfig) -> Result<Box<dyn CloudStore>> {
+    match config.provider.as_str() {
+        "s3" => Ok(Box::new(S3Store::new(config)?)),
+        "gcs" | "azure" => Err(Error::InvalidConfig(format!(
+            "Cloud provider '{}' isn't supported yet",
+            config.provider
+        ))),
+        other => Err(Error::InvalidConfig(format!(
+            "Unknown cloud provider '{}', expected s3",
+            other
+        ))),
+    }
+}
+
+/// An S3 bucket, or a bucket of an S3-compatible service at
+/// `CloudConfig::endpoint`. Files larger than the part size are uploaded in
+/// parts, and failed requests are retried with exponential backoff.
+pub struct S3Store {
+    store: AmazonS3,
+    part_size: usize,
+}
+
+impl S3Store {
+    /// Uses `CloudConfig::credentials` as `ACCESS_KEY_ID:SECRET_ACCESS_KEY`,
+    /// or else the usual `AWS_*` environment variables.
+    pub fn new(config: &CloudConfig) -> Result<Self> {
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(&config.bucket)
+            .with_retry(RetryConfig {
+                backoff: BackoffConfig {
+                    init_backoff: Duration::from_millis(200),
+                    max_backoff: Duration::from_secs(10),
+                    base: 2.0,
+                },
+                max_retries: config.max_retries,
+                retry_timeout: Duration::from_secs(180),
+            });
+        if let Some(credentials) = &config.credentials {
+            let (key_id, secret) = credentials.split_once(':').ok_or_else(|| {
+                Error::InvalidConfig(
+                    "credentials must be ACCESS_KEY_ID:SECRET_ACCESS_KEY".to_string(),
+                )
+            })?;
+            builder = builder
+                .with_access_key_id(key_id)
+                .with_secret_access_key(secret);
+        }
+        if let Some(region) = &config.region {
+            builder = builder.with_region(region);
+        }
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder
+                .with_allow_http(endpoint.starts_with("http://"))
+                .with_endpoint(endpoint);
+        }
+
+        Ok(Self {
+            store: builder.build()?,
+            part_size: (config.part_size_mb * 1024 * 1024).max(MIN_PART_SIZE),
+        })
+    }
+
+    async fn put_multipart(&self, path: &Path, body: Bytes, attributes: Attributes) -> Result<()> {
+        let opts = PutMultipartOptions {
+            attributes,
+            ..Default::default()
+        };
+        let mut upload = self.store.put_multipart_opts(path, opts).await?;
+        let parts: Vec<_> = (0..body.len())
+            .step_by(self.part_size)
+            .map(|start| {
+                let end = (start + self.part_size).min(body.len());
+                upload.put_part(PutPayload::from(body.slice(start..end)))
+            })
+            .collect();
+
+        let uploaded = match stream::iter(parts)
+            .buffer_unordered(CONCURRENT_PARTS)
+            .try_collect::<Vec<_>>()
+            .await
+        {
+            Ok(_) => upload.complete().await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = uploaded {
+            if let Err(abort) = upload.abort().await {
+                tracing::warn!("Failed to abort the upload of {}: {}", path, abort);
+            }
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CloudStore for S3Store {
+    async fn put(&self, key: &str, body: Bytes, content_type: &str) -> Result<()> {
+        let path = Path::from(key);
+        let attributes =
+            Attributes::from_iter([(Attribute::ContentType, content_type.to_string())]);
+
+        if body.len() > self.part_size {
+            return self.put_multipart(&path, body, attributes).await;
+        }
+        let opts = PutOptions {
+            attributes,
+            ..Default::default()
+        };
+        self.store.put_opts(&path, body.into(), opts).await?;
+        Ok(())
+    }
+}
+
+/// The key prefix for a crawl: `layout` with `{session}`, `{date}`
+/// (`YYYY-MM-DD`) and `{domain}` replaced, and without leading or trailing
+/// slashes.
+pub fn key_prefix(layout: &str, session: &str, date: NaiveDate, domain: &str) -> String {
+    layout
+        .replace("{session}", session)
+        .replace("{date}", &date.format("%Y-%m-%d").to_string())
+        .replace("{domain}", domain)
+        .trim_matches('/')
+        .to_string()
+}
+
+/// What `export` uploaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub files: usize,
+    pub bytes: usize,
+}
+
+/// Uploads a crawl's results, tables and, with `include_html`, raw HTML
+/// under `prefix` in `store`.
+pub async fn export(
+    store: &dyn CloudStore,
+    prefix: &str,
+    stats: &CrawlStats,
+    results: &[CrawlResult],
+    include_html: bool,
+) -> Result<ExportSummary> {
+    let key = |name: String| {
+        if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        }
+    };
+
+    let mut files = vec![(
+        key("results.json".to_string()),
+        Bytes::from(serde_json::to_vec_pretty(&serde_json::json!({
+            "stats": stats,
+            "results": results,
+        }))?),
+        "application/json",
+    )];
+    for (idx, result) in results.iter().enumerate() {
+        if let Some(cleaned) = &result.cleaned_content {
+            for (table_idx, table) in cleaned.tables.iter().enumerate() {
+                files.push((
+                    key(format!(
+                        "tables/page_{:04}_table_{}.csv",
+                        idx + 1,
+                        table_idx + 1
+                    )),
+                    Bytes::from(table.to_csv()),
+                    "text/csv",
+                ));
+            }
+        }
+        if include_html {
+            files.push((
+                key(format!("html/page_{:04}.html", idx + 1)),
+                Bytes::from(result.content.clone()),
+                "text/html; charset=utf-8",
+            ));
+        }
+    }
+
+    let summary = ExportSummary {
+        files: files.len(),
+        bytes: files.iter().map(|(_, body, _)| body.len()).sum(),
+    };
+    stream::iter(files)
+        .map(|(key, body, content_type)| async move {
+            store
+                .put(&key, body, content_type)
+                .await
+                .map_err(|e| match e {
+                    Error::Storage(message) => {
+                        Error::Storage(format!("Failed to upload {}: {}", key, message))
+                    }
+                    other => other,
+                })
+        })
+        .buffer_unordered(CONCURRENT_UPLOADS)
+        .try_collect::<Vec<_>>()
+        .await?;
+    Ok(summary)
+}
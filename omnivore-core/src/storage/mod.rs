@@ -1,5 +1,9 @@
+#[cfg(feature = "cloud")]
+pub mod cloud;
 pub mod graph_db;
 pub mod kv;
+#[cfg(feature = "database")]
+pub mod sql;
 pub mod vector_db;
 
 use serde::{Deserialize, Serialize};
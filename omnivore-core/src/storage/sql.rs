@@ -0,0 +1,568 @@
+//! Writes crawl sessions into the `sessions`, `pages`, `links` and
+//! `entities` tables of `scripts/init-db.sql`, in PostgreSQL or SQLite.
+//!
+//! Row ids are derived from the session id and the row's content, so writing
+//! the same session twice updates its rows instead of duplicating them.
+
+use crate::config::DatabaseConfig;
+use crate::intelligence::entity::EntityRecognizer;
+use crate::{CrawlResult, Error, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::types::Json;
+use sqlx::{PgPool, QueryBuilder, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Bind parameters per statement, under the limits of both PostgreSQL
+/// (65535) and SQLite (32766).
+const MAX_PARAMS: usize = 32_000;
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        Error::Database(e.to_string())
+    }
+}
+
+enum SqlPool {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+/// Runs `$body` with `$pool` bound to the backend's pool, and `$db` (when
+/// given) to its database type, so the same query code is compiled for each
+/// backend.
+macro_rules! on_pool {
+    ($pool:expr, |$p:ident| $body:expr) => {
+        match $pool {
+            SqlPool::Postgres($p) => $body,
+            SqlPool::Sqlite($p) => $body,
+        }
+    };
+    ($pool:expr, |$p:ident, $db:ident| $body:expr) => {
+        match $pool {
+            SqlPool::Postgres($p) => {
+                type $db = sqlx::Postgres;
+                $body
+            }
+            SqlPool::Sqlite($p) => {
+                type $db = sqlx::Sqlite;
+                $body
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Paused,
+}
+
+impl SessionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::Pending => "pending",
+            SessionStatus::Running => "running",
+            SessionStatus::Completed => "completed",
+            SessionStatus::Failed => "failed",
+            SessionStatus::Paused => "paused",
+        }
+    }
+}
+
+/// A row of the `sessions` table.
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    pub id: Uuid,
+    pub name: String,
+    pub start_url: String,
+    pub max_depth: u32,
+    pub max_workers: usize,
+    pub status: SessionStatus,
+    /// Stored as is in the `settings` column, e.g. the crawl configuration.
+    pub settings: Value,
+}
+
+/// Rows written by `SqlSink::flush`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteSummary {
+    pub pages: usize,
+    pub links: usize,
+    pub entities: usize,
+}
+
+struct PageRow {
+    id: Uuid,
+    url: String,
+    title: Option<String>,
+    content: Option<String>,
+    html_content: Option<String>,
+    status_code: i32,
+    response_headers: Value,
+    content_type: Option<String>,
+    content_length: i64,
+    depth: i32,
+    parent_url: Option<String>,
+    crawled_at: DateTime<Utc>,
+    metadata: Value,
+}
+
+struct LinkRow {
+    id: Uuid,
+    source_page_id: Uuid,
+    source_url: String,
+    target_url: String,
+}
+
+struct EntityRow {
+    id: Uuid,
+    page_id: Uuid,
+    entity_type: String,
+    entity_value: String,
+    confidence: f64,
+    position_start: i32,
+    position_end: i32,
+}
+
+/// Buffers the pages of one crawl session and writes them in batches of
+/// `DatabaseConfig::batch_size`, each in one transaction. Call
+/// `start_session` before `write`, and `finish_session` at the end.
+///
+/// `DatabaseConfig::table_name` is a prefix for the table names, so
+/// `crawl_` writes to `crawl_sessions`, `crawl_pages` and so on. Leave it
+/// empty to use the tables created by `scripts/init-db.sql`.
+pub struct SqlSink {
+    pool: SqlPool,
+    prefix: String,
+    batch_size: usize,
+    include_html: bool,
+    session_id: Option<Uuid>,
+    /// Depth and first linking page of every URL seen in the session, the
+    /// same way the frontier counts them.
+    discovered: HashMap<String, (i32, Option<String>)>,
+    pages: Vec<PageRow>,
+    links: Vec<LinkRow>,
+    entities: Vec<EntityRow>,
+}
+
+impl SqlSink {
+    /// Connects to a `postgres://` or `sqlite:` connection string. SQLite
+    /// databases are created if they don't exist.
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self> {
+        let prefix = config.table_name.clone();
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(Error::InvalidConfig(format!(
+                "table_name '{}' may only contain letters, digits and underscores",
+                prefix
+            )));
+        }
+
+        let url = config.connection_string.as_str();
+        let pool = if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            // Keeps "relation already exists" notices out of the logs
+            let options =
+                PgConnectOptions::from_str(url)?.options([("client_min_messages", "warning")]);
+            SqlPool::Postgres(
+                PgPoolOptions::new()
+                    .max_connections(4)
+                    .connect_with(options)
+                    .await?,
+            )
+        } else if url.starts_with("sqlite:") {
+            let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+            SqlPool::Sqlite(
+                SqlitePoolOptions::new()
+                    .max_connections(1)
+                    .connect_with(options)
+                    .await?,
+            )
+        } else {
+            return Err(Error::InvalidConfig(format!(
+                "Unsupported database '{}', expected a postgres:// or sqlite: connection string",
+                url
+            )));
+        };
+
+        Ok(Self {
+            pool,
+            prefix,
+            batch_size: config.batch_size.max(1) as usize,
+            include_html: false,
+            session_id: None,
+            discovered: HashMap::new(),
+            pages: Vec::new(),
+            links: Vec::new(),
+            entities: Vec::new(),
+        })
+    }
+
+    /// Also store each page's raw HTML in `html_content`.
+    pub fn include_html(mut self, include: bool) -> Self {
+        self.include_html = include;
+        self
+    }
+
+    fn table(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// Creates the four tables if they don't exist, with the columns of
+    /// `scripts/init-db.sql`.
+    pub async fn create_tables(&self) -> Result<()> {
+        let (uuid, json, timestamp) = match self.pool {
+            SqlPool::Postgres(_) => ("UUID", "JSONB", "TIMESTAMP WITH TIME ZONE"),
+            SqlPool::Sqlite(_) => ("BLOB", "TEXT", "TEXT"),
+        };
+        let statements = [
+            format!(
+                "CREATE TABLE IF NOT EXISTS {sessions} (
+                    id {uuid} PRIMARY KEY,
+                    name VARCHAR(255) NOT NULL,
+                    start_url TEXT NOT NULL,
+                    max_depth INTEGER DEFAULT 5,
+                    max_workers INTEGER DEFAULT 10,
+                    created_at {timestamp} DEFAULT CURRENT_TIMESTAMP,
+                    updated_at {timestamp} DEFAULT CURRENT_TIMESTAMP,
+                    status VARCHAR(20) DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'failed', 'paused')),
+                    settings {json} DEFAULT '{{}}'
+                )",
+                sessions = self.table("sessions"),
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {pages} (
+                    id {uuid} PRIMARY KEY,
+                    session_id {uuid} REFERENCES {sessions}(id) ON DELETE CASCADE,
+                    url TEXT NOT NULL,
+                    title TEXT,
+                    content TEXT,
+                    html_content TEXT,
+                    status_code INTEGER,
+                    response_headers {json},
+                    content_type VARCHAR(255),
+                    content_length BIGINT,
+                    depth INTEGER NOT NULL DEFAULT 0,
+                    parent_url TEXT,
+                    discovered_at {timestamp} DEFAULT CURRENT_TIMESTAMP,
+                    crawled_at {timestamp},
+                    metadata {json} DEFAULT '{{}}',
+                    error_message TEXT
+                )",
+                pages = self.table("pages"),
+                sessions = self.table("sessions"),
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {links} (
+                    id {uuid} PRIMARY KEY,
+                    session_id {uuid} REFERENCES {sessions}(id) ON DELETE CASCADE,
+                    source_page_id {uuid} REFERENCES {pages}(id) ON DELETE CASCADE,
+                    source_url TEXT NOT NULL,
+                    target_url TEXT NOT NULL,
+                    link_text TEXT,
+                    link_type VARCHAR(50) DEFAULT 'href',
+                    discovered_at {timestamp} DEFAULT CURRENT_TIMESTAMP,
+                    processed BOOLEAN DEFAULT FALSE
+                )",
+                links = self.table("links"),
+                sessions = self.table("sessions"),
+                pages = self.table("pages"),
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {entities} (
+                    id {uuid} PRIMARY KEY,
+                    session_id {uuid} REFERENCES {sessions}(id) ON DELETE CASCADE,
+                    page_id {uuid} REFERENCES {pages}(id) ON DELETE CASCADE,
+                    entity_type VARCHAR(100) NOT NULL,
+                    entity_value TEXT NOT NULL,
+                    confidence FLOAT DEFAULT 0.0,
+                    context TEXT,
+                    position_start INTEGER,
+                    position_end INTEGER,
+                    metadata {json} DEFAULT '{{}}',
+                    created_at {timestamp} DEFAULT CURRENT_TIMESTAMP
+                )",
+                entities = self.table("entities"),
+                sessions = self.table("sessions"),
+                pages = self.table("pages"),
+            ),
+        ];
+
+        on_pool!(&self.pool, |pool| {
+            for statement in &statements {
+                sqlx::query(statement).execute(pool).await?;
+            }
+        });
+        Ok(())
+    }
+
+    /// Inserts or updates `session` and makes it the session `write` adds
+    /// pages to.
+    pub async fn start_session(&mut self, session: &SessionRecord) -> Result<()> {
+        self.flush().await?;
+        self.session_id = Some(session.id);
+        self.discovered.clear();
+        self.discovered.insert(session.start_url.clone(), (0, None));
+        if let Ok(url) = url::Url::parse(&session.start_url) {
+            self.discovered.insert(url.to_string(), (0, None));
+        }
+
+        let sql = format!(
+            "INSERT INTO {} (id, name, start_url, max_depth, max_workers, created_at, updated_at, status, settings) \
+             VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $8) \
+             ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, start_url = EXCLUDED.start_url, \
+             max_depth = EXCLUDED.max_depth, max_workers = EXCLUDED.max_workers, \
+             updated_at = EXCLUDED.updated_at, status = EXCLUDED.status, settings = EXCLUDED.settings",
+            self.table("sessions")
+        );
+        let now = Utc::now();
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(&sql)
+                .bind(session.id)
+                .bind(&session.name)
+                .bind(&session.start_url)
+                .bind(session.max_depth as i32)
+                .bind(session.max_workers as i32)
+                .bind(now)
+                .bind(session.status.as_str())
+                .bind(Json(&session.settings))
+                .execute(pool)
+                .await?;
+        });
+        Ok(())
+    }
+
+    /// Buffers `result` with its links and entities, writing the buffer once
+    /// it holds `batch_size` pages.
+    pub async fn write(&mut self, result: &CrawlResult) -> Result<WriteSummary> {
+        let session_id = self
+            .session_id
+            .ok_or_else(|| Error::Database("No session started".to_string()))?;
+        let page_id = Uuid::new_v5(&session_id, result.url.as_bytes());
+        let (depth, parent_url) = self
+            .discovered
+            .get(&result.url)
+            .cloned()
+            .unwrap_or((0, None));
+
+        for target in &result.links {
+            self.discovered
+                .entry(target.clone())
+                .or_insert_with(|| (depth + 1, Some(result.url.clone())));
+            self.links.push(LinkRow {
+                id: Uuid::new_v5(
+                    &session_id,
+                    format!("{}\n{}", result.url, target).as_bytes(),
+                ),
+                source_page_id: page_id,
+                source_url: result.url.clone(),
+                target_url: target.clone(),
+            });
+        }
+
+        let cleaned = result.cleaned_content.as_ref();
+        let text = cleaned.and_then(|c| c.content.clone());
+        let entities = EntityRecognizer::recognize(text.as_deref().unwrap_or_default())?;
+        for entity in entities {
+            let entity_type = serde_json::to_value(&entity.entity_type)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            let key = format!("{}\n{}\n{}", entity_type, entity.start, entity.text);
+            self.entities.push(EntityRow {
+                id: Uuid::new_v5(&page_id, key.as_bytes()),
+                page_id,
+                entity_type,
+                entity_value: entity.text,
+                confidence: entity.confidence as f64,
+                position_start: entity.start as i32,
+                position_end: entity.end as i32,
+            });
+        }
+
+        let content_type = result
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.clone());
+        self.pages.push(PageRow {
+            id: page_id,
+            url: result.url.clone(),
+            title: cleaned.and_then(|c| c.title.clone()),
+            content: text,
+            html_content: self.include_html.then(|| result.content.clone()),
+            status_code: result.status_code as i32,
+            response_headers: serde_json::to_value(&result.headers)?,
+            content_type,
+            content_length: result.content.len() as i64,
+            depth,
+            parent_url,
+            crawled_at: result.crawled_at,
+            metadata: result.extracted_data.clone(),
+        });
+
+        if self.pages.len() >= self.batch_size {
+            self.flush().await
+        } else {
+            Ok(WriteSummary::default())
+        }
+    }
+
+    /// Writes the buffered pages, links and entities in one transaction.
+    pub async fn flush(&mut self) -> Result<WriteSummary> {
+        let Some(session_id) = self.session_id else {
+            return Ok(WriteSummary::default());
+        };
+        let pages = dedup(std::mem::take(&mut self.pages), |row| row.id);
+        let links = dedup(std::mem::take(&mut self.links), |row| row.id);
+        let entities = dedup(std::mem::take(&mut self.entities), |row| row.id);
+        let summary = WriteSummary {
+            pages: pages.len(),
+            links: links.len(),
+            entities: entities.len(),
+        };
+        if pages.is_empty() && links.is_empty() && entities.is_empty() {
+            return Ok(summary);
+        }
+
+        let pages_table = self.table("pages");
+        let links_table = self.table("links");
+        let entities_table = self.table("entities");
+
+        on_pool!(&self.pool, |pool, Db| {
+            let mut tx = pool.begin().await?;
+
+            for chunk in pages.chunks(MAX_PARAMS / 14) {
+                let mut query = QueryBuilder::<Db>::new(format!(
+                    "INSERT INTO {} (id, session_id, url, title, content, html_content, status_code, \
+                     response_headers, content_type, content_length, depth, parent_url, crawled_at, metadata) ",
+                    pages_table
+                ));
+                query.push_values(chunk, |mut row, page| {
+                    row.push_bind(page.id)
+                        .push_bind(session_id)
+                        .push_bind(&page.url)
+                        .push_bind(&page.title)
+                        .push_bind(&page.content)
+                        .push_bind(&page.html_content)
+                        .push_bind(page.status_code)
+                        .push_bind(Json(&page.response_headers))
+                        .push_bind(&page.content_type)
+                        .push_bind(page.content_length)
+                        .push_bind(page.depth)
+                        .push_bind(&page.parent_url)
+                        .push_bind(page.crawled_at)
+                        .push_bind(Json(&page.metadata));
+                });
+                query.push(
+                    " ON CONFLICT (id) DO UPDATE SET title = EXCLUDED.title, content = EXCLUDED.content, \
+                     html_content = EXCLUDED.html_content, status_code = EXCLUDED.status_code, \
+                     response_headers = EXCLUDED.response_headers, content_type = EXCLUDED.content_type, \
+                     content_length = EXCLUDED.content_length, depth = EXCLUDED.depth, \
+                     parent_url = EXCLUDED.parent_url, crawled_at = EXCLUDED.crawled_at, \
+                     metadata = EXCLUDED.metadata, error_message = NULL",
+                );
+                query.build().execute(&mut *tx).await?;
+            }
+
+            for chunk in links.chunks(MAX_PARAMS / 5) {
+                let mut query = QueryBuilder::<Db>::new(format!(
+                    "INSERT INTO {} (id, session_id, source_page_id, source_url, target_url) ",
+                    links_table
+                ));
+                query.push_values(chunk, |mut row, link| {
+                    row.push_bind(link.id)
+                        .push_bind(session_id)
+                        .push_bind(link.source_page_id)
+                        .push_bind(&link.source_url)
+                        .push_bind(&link.target_url);
+                });
+                query.push(" ON CONFLICT (id) DO NOTHING");
+                query.build().execute(&mut *tx).await?;
+            }
+
+            for chunk in entities.chunks(MAX_PARAMS / 8) {
+                let mut query = QueryBuilder::<Db>::new(format!(
+                    "INSERT INTO {} (id, session_id, page_id, entity_type, entity_value, confidence, \
+                     position_start, position_end) ",
+                    entities_table
+                ));
+                query.push_values(chunk, |mut row, entity| {
+                    row.push_bind(entity.id)
+                        .push_bind(session_id)
+                        .push_bind(entity.page_id)
+                        .push_bind(&entity.entity_type)
+                        .push_bind(&entity.entity_value)
+                        .push_bind(entity.confidence)
+                        .push_bind(entity.position_start)
+                        .push_bind(entity.position_end);
+                });
+                query.push(
+                    " ON CONFLICT (id) DO UPDATE SET entity_value = EXCLUDED.entity_value, \
+                     confidence = EXCLUDED.confidence, position_end = EXCLUDED.position_end",
+                );
+                query.build().execute(&mut *tx).await?;
+            }
+
+            tx.commit().await?;
+        });
+
+        Ok(summary)
+    }
+
+    /// Writes what is left in the buffer, marks links to pages crawled in
+    /// the session as processed, and sets the session's status.
+    pub async fn finish_session(&mut self, status: SessionStatus) -> Result<WriteSummary> {
+        let summary = self.flush().await?;
+        let Some(session_id) = self.session_id.take() else {
+            return Ok(summary);
+        };
+
+        let processed = format!(
+            "UPDATE {links} SET processed = TRUE WHERE session_id = $1 \
+             AND target_url IN (SELECT url FROM {pages} WHERE session_id = $1)",
+            links = self.table("links"),
+            pages = self.table("pages"),
+        );
+        let finished = format!(
+            "UPDATE {} SET status = $1, updated_at = $2 WHERE id = $3",
+            self.table("sessions")
+        );
+        on_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            sqlx::query(&processed)
+                .bind(session_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&finished)
+                .bind(status.as_str())
+                .bind(Utc::now())
+                .bind(session_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        });
+        Ok(summary)
+    }
+}
+
+/// Keeps the last row for each id. PostgreSQL refuses to upsert the same
+/// row twice in one statement.
+fn dedup<T>(rows: Vec<T>, id: impl Fn(&T) -> Uuid) -> Vec<T> {
+    let mut seen = HashSet::new();
+    let mut rows: Vec<T> = rows
+        .into_iter()
+        .rev()
+        .filter(|row| seen.insert(id(row)))
+        .collect();
+    rows.reverse();
+    rows
+}
@@ -0,0 +1,186 @@
+//! Logging and trace export for the Omnivore binaries.
+//!
+//! The crawler records each URL's lifecycle as one trace: a `crawl_url` span
+//! with `frontier_pop`, `politeness_wait`, `fetch`, `extract_content`,
+//! `extract_links` and `sink_write` spans under it. `init` sends those spans
+//! to an OpenTelemetry collector over OTLP, or prints them as JSON lines.
+
+use crate::{Error, Result};
+use opentelemetry::trace::{Status, TracerProvider};
+use opentelemetry::Value;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider, SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map};
+use std::fmt;
+use std::io::Write;
+use std::sync::Mutex;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Service name spans are reported under.
+pub const SERVICE_NAME: &str = "omnivore";
+
+/// Where spans are sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceExporter {
+    /// OTLP over gRPC, e.g. `http://localhost:4317` for a local collector.
+    Otlp { endpoint: String },
+    /// One JSON object per span on stdout, see `JsonExporter`.
+    Stdout,
+}
+
+/// Keeps trace export running. Dropping it, or calling `shutdown`, exports
+/// the spans still buffered.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(mut self) {
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to export remaining spans: {}", e);
+            }
+        }
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Installs the global subscriber: log lines filtered by `filter` and, with
+/// an `exporter`, the spans of Omnivore's own crates that pass the same
+/// filter. Spans of HTTP and gRPC libraries are left out, so exporting
+/// doesn't trace itself.
+pub fn init(filter: EnvFilter, exporter: Option<TraceExporter>) -> Result<Telemetry> {
+    let provider = exporter.map(tracer_provider).transpose()?;
+    let own_spans = filter_fn(|metadata| metadata.target().starts_with("omnivore"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(provider.as_ref().map(|p| layer(p).with_filter(own_spans)))
+        .try_init()
+        .map_err(|e| Error::InvalidConfig(format!("Failed to install tracing: {}", e)))?;
+    Ok(Telemetry { provider })
+}
+
+/// A tracer provider for `exporter`: spans are sent over OTLP in batches,
+/// and printed to stdout as each one ends. Must be called from within a
+/// Tokio runtime when exporting over OTLP.
+pub fn tracer_provider(exporter: TraceExporter) -> Result<SdkTracerProvider> {
+    let builder = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build());
+    let builder = match exporter {
+        TraceExporter::Otlp { endpoint } => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()
+                .map_err(|e| Error::InvalidConfig(format!("Invalid OTLP exporter: {}", e)))?;
+            builder.with_batch_exporter(exporter)
+        }
+        TraceExporter::Stdout => builder.with_simple_exporter(JsonExporter::stdout()),
+    };
+    Ok(builder.build())
+}
+
+/// A `tracing` layer that records spans through `provider`.
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+}
+
+/// Writes each span as a line of JSON with its trace and span ids, parent
+/// span id (`null` for the root), name, start and end times, duration in
+/// milliseconds, status and attributes.
+pub struct JsonExporter<W> {
+    writer: Mutex<W>,
+}
+
+impl JsonExporter<std::io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl<W: Write + Send> JsonExporter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W> fmt::Debug for JsonExporter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonExporter").finish_non_exhaustive()
+    }
+}
+
+impl<W: Write + Send> SpanExporter for JsonExporter<W> {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        for span in &batch {
+            let line = span_json(span);
+            // A closed stdout shouldn't take the crawl down with it
+            let _ = writeln!(writer, "{}", line);
+        }
+        let _ = writer.flush();
+        Ok(())
+    }
+}
+
+fn span_json(span: &SpanData) -> serde_json::Value {
+    let attributes: Map<_, _> = span
+        .attributes
+        .iter()
+        .map(|kv| (kv.key.to_string(), attribute_json(&kv.value)))
+        .collect();
+    let parent = (span.parent_span_id != opentelemetry::trace::SpanId::INVALID)
+        .then(|| span.parent_span_id.to_string());
+    let status = match &span.status {
+        Status::Unset => json!("unset"),
+        Status::Ok => json!("ok"),
+        Status::Error { description } => json!({ "error": description }),
+    };
+    let start: chrono::DateTime<chrono::Utc> = span.start_time.into();
+    let end: chrono::DateTime<chrono::Utc> = span.end_time.into();
+
+    json!({
+        "trace_id": span.span_context.trace_id().to_string(),
+        "span_id": span.span_context.span_id().to_string(),
+        "parent_span_id": parent,
+        "name": span.name,
+        "start_time": start.to_rfc3339(),
+        "end_time": end.to_rfc3339(),
+        "duration_ms": (end - start).num_microseconds().unwrap_or_default() as f64 / 1000.0,
+        "status": status,
+        "attributes": attributes,
+    })
+}
+
+fn attribute_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(b) => json!(b),
+        Value::I64(i) => json!(i),
+        Value::F64(f) => json!(f),
+        Value::String(s) => json!(s.as_str()),
+        other => json!(other.to_string()),
+    }
+}
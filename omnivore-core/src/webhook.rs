@@ -0,0 +1,299 @@
+//! Webhook notifications for crawl lifecycle events.
+//!
+//! Each event is POSTed to `OutputConfig::webhook_url` as JSON:
+//!
+//! ```json
+//! {"id": "…", "crawl_id": "…", "created_at": "…", "event": "crawl.completed", "data": {…}}
+//! ```
+//!
+//! With `WebhookConfig::secret` set, `X-Omnivore-Signature` is
+//! `sha256=<hex>`, the HMAC-SHA256 of `<X-Omnivore-Timestamp>.<body>`, so
+//! receivers can check both where a delivery came from and how old it is.
+//! Network errors, timeouts, 429s and 5xx responses are retried with
+//! exponential backoff. Deliveries that fail every attempt are appended to a
+//! JSON lines dead letter file, from which they can be replayed.
+
+use crate::config::WebhookConfig;
+use crate::{CrawlConfig, CrawlResult, CrawlStats, Error, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub const SIGNATURE_HEADER: &str = "X-Omnivore-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Omnivore-Timestamp";
+pub const EVENT_HEADER: &str = "X-Omnivore-Event";
+pub const DELIVERY_HEADER: &str = "X-Omnivore-Delivery";
+
+/// How long a receiver gets to answer before the attempt counts as failed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something that happened during a crawl.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data")]
+pub enum WebhookEvent {
+    #[serde(rename = "crawl.started")]
+    CrawlStarted {
+        start_url: String,
+        config: Box<CrawlConfig>,
+    },
+    #[serde(rename = "crawl.completed")]
+    CrawlCompleted {
+        start_url: String,
+        stats: CrawlStats,
+    },
+    /// The crawl, or exporting or saving its results, stopped on an error.
+    #[serde(rename = "crawl.failed")]
+    CrawlFailed { start_url: String, error: String },
+    /// Sent once per crawl, when the share of failed pages first reaches
+    /// `WebhookConfig::error_threshold`.
+    #[serde(rename = "crawl.error_threshold")]
+    ErrorThreshold {
+        start_url: String,
+        error_rate: f64,
+        threshold: f64,
+        stats: CrawlStats,
+    },
+    /// A page whose content differs from the previous crawl's, or which
+    /// wasn't in it, in incremental mode.
+    #[serde(rename = "page.changed")]
+    PageChanged {
+        url: String,
+        previous_hash: Option<String>,
+        hash: String,
+    },
+}
+
+impl WebhookEvent {
+    /// The event's name, as in the `event` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::CrawlStarted { .. } => "crawl.started",
+            WebhookEvent::CrawlCompleted { .. } => "crawl.completed",
+            WebhookEvent::CrawlFailed { .. } => "crawl.failed",
+            WebhookEvent::ErrorThreshold { .. } => "crawl.error_threshold",
+            WebhookEvent::PageChanged { .. } => "page.changed",
+        }
+    }
+}
+
+/// The body of one webhook request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    /// Stays the same across retries, so receivers can drop duplicates.
+    pub id: Uuid,
+    pub crawl_id: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+/// The `X-Omnivore-Signature` value for `body` sent at `timestamp` (Unix
+/// seconds).
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex(&mac.finalize().into_bytes()))
+}
+
+/// The hex SHA-256 of a page's content, as in `page.changed` events.
+pub fn content_hash(content: &str) -> String {
+    hex(&Sha256::digest(content.as_bytes()))
+}
+
+/// `page.changed` events for the results whose content hash isn't the one in
+/// `hashes` (URL to hash, from the previous crawl), and `hashes` updated with
+/// the new ones. Pages are compared by their cleaned text, so ads, tokens and
+/// timestamps in the markup don't count as changes; pages without cleaned
+/// text by their HTML.
+pub fn changed_pages(
+    hashes: &mut HashMap<String, String>,
+    results: &[CrawlResult],
+) -> Vec<WebhookEvent> {
+    let mut events = Vec::new();
+    for result in results {
+        let content = result
+            .cleaned_content
+            .as_ref()
+            .and_then(|cleaned| cleaned.content.as_deref())
+            .unwrap_or(&result.content);
+        let hash = content_hash(content);
+        let previous_hash = hashes.insert(result.url.clone(), hash.clone());
+        if previous_hash.as_ref() != Some(&hash) {
+            events.push(WebhookEvent::PageChanged {
+                url: result.url.clone(),
+                previous_hash,
+                hash,
+            });
+        }
+    }
+    events
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Sends one crawl's events to a webhook URL.
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+    crawl_id: String,
+    config: WebhookConfig,
+    dead_letter_path: Option<PathBuf>,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str, crawl_id: &str, config: &WebhookConfig) -> Result<Self> {
+        url::Url::parse(url)
+            .map_err(|e| Error::InvalidConfig(format!("Invalid webhook URL '{}': {}", url, e)))?;
+        let client = reqwest::Client::builder()
+            .user_agent("Omnivore/1.0")
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        let dead_letter_path = config.dead_letter_path.clone().or_else(|| {
+            dirs::home_dir().map(|home| home.join(".omnivore").join("webhook_dead_letters.jsonl"))
+        });
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            crawl_id: crawl_id.to_string(),
+            config: config.clone(),
+            dead_letter_path,
+        })
+    }
+
+    /// The share of finished pages that failed, if enough pages have
+    /// finished and it's at or over the threshold.
+    pub fn exceeded_error_rate(&self, stats: &CrawlStats) -> Option<f64> {
+        let finished = stats.successful + stats.failed;
+        if finished == 0 || finished < self.config.error_min_pages {
+            return None;
+        }
+        let rate = stats.failed as f64 / finished as f64;
+        (rate >= self.config.error_threshold).then_some(rate)
+    }
+
+    /// Delivers `event`, retrying as configured. When every attempt fails,
+    /// the delivery is written to the dead letter file and an error returned.
+    pub async fn send(&self, event: WebhookEvent) -> Result<()> {
+        let delivery = Delivery {
+            id: Uuid::new_v4(),
+            crawl_id: self.crawl_id.clone(),
+            created_at: Utc::now(),
+            event,
+        };
+        let body = serde_json::to_vec(&delivery)?;
+
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match self.post(&delivery, &body).await {
+                Ok(()) => return Ok(()),
+                Err(failure) if failure.retryable && attempts <= self.config.max_retries => {
+                    let backoff = self
+                        .config
+                        .retry_backoff_ms
+                        .saturating_mul(1 << (attempts - 1).min(16));
+                    tracing::debug!(
+                        "Webhook {} delivery {} failed ({}), retrying in {}ms",
+                        delivery.event.name(),
+                        delivery.id,
+                        failure.error,
+                        backoff
+                    );
+                    tokio::time::sleep(Duration::from_millis(backoff)).await;
+                }
+                Err(failure) => break failure.error,
+            }
+        };
+
+        tracing::warn!(
+            "Webhook {} delivery {} failed after {} attempts: {}",
+            delivery.event.name(),
+            delivery.id,
+            attempts,
+            error
+        );
+        if let Err(e) = self.dead_letter(&delivery, attempts, &error) {
+            tracing::error!("Failed to write the webhook dead letter: {}", e);
+        }
+        Err(Error::Webhook(format!(
+            "{} to {}: {}",
+            delivery.event.name(),
+            self.url,
+            error
+        )))
+    }
+
+    async fn post(&self, delivery: &Delivery, body: &[u8]) -> std::result::Result<(), Failure> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, delivery.event.name())
+            .header(DELIVERY_HEADER, delivery.id.to_string());
+        if let Some(secret) = &self.config.secret {
+            let timestamp = Utc::now().timestamp();
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, sign(secret, timestamp, body));
+        }
+
+        match request.body(body.to_vec()).send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                Err(Failure {
+                    error: format!("HTTP {}", status),
+                    retryable: status.is_server_error()
+                        || status == StatusCode::TOO_MANY_REQUESTS
+                        || status == StatusCode::REQUEST_TIMEOUT,
+                })
+            }
+            Err(e) => Err(Failure {
+                error: e.to_string(),
+                retryable: true,
+            }),
+        }
+    }
+
+    fn dead_letter(&self, delivery: &Delivery, attempts: usize, error: &str) -> Result<()> {
+        let Some(path) = &self.dead_letter_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::json!({
+            "failed_at": Utc::now(),
+            "url": self.url,
+            "attempts": attempts,
+            "error": error,
+            "delivery": delivery,
+        });
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+/// Why one attempt failed, and whether it's worth another.
+struct Failure {
+    error: String,
+    retryable: bool,
+}
@@ -1,5 +1,6 @@
+use omnivore_core::parser::schema::Schema;
 use omnivore_core::parser::{ParseConfig, ParseRule, Parser};
-use serde_json::json;
+use serde_json::{json, Value};
 
 #[test]
 fn test_parser_creation() {
@@ -27,6 +28,7 @@ fn test_parse_with_rules() {
                 multiple: false,
                 required: false,
                 transform: None,
+                children: vec![],
             },
             ParseRule {
                 name: "body".to_string(),
@@ -35,6 +37,7 @@ fn test_parse_with_rules() {
                 multiple: false,
                 required: false,
                 transform: None,
+                children: vec![],
             },
         ],
         schema_name: None,
@@ -109,3 +112,361 @@ fn test_html_parser() {
     assert_eq!(og_data["title"], json!("Test Title"));
     assert_eq!(og_data["description"], json!("Test Description"));
 }
+
+#[test]
+fn test_parse_validates_against_named_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("product.yaml"),
+        r#"
+name: product
+version: "1.0"
+required: [name, price]
+fields:
+  - name: name
+    field_type: string
+  - name: price
+    field_type: number
+    validators:
+      - type: Min
+        value: 0
+  - name: released
+    field_type: date
+  - name: homepage
+    field_type: url
+  - name: contact
+    field_type: email
+  - name: currency
+    field_type: string
+    default: USD
+"#,
+    )
+    .unwrap();
+
+    let rule = |name: &str, selector: &str| ParseRule {
+        name: name.to_string(),
+        selector: selector.to_string(),
+        attribute: None,
+        multiple: false,
+        required: false,
+        transform: None,
+        children: vec![],
+    };
+
+    let config = ParseConfig {
+        rules: vec![
+            rule("name", "h1"),
+            rule("price", ".price"),
+            rule("released", ".released"),
+            rule("homepage", ".homepage"),
+            rule("contact", ".contact"),
+        ],
+        schema_name: Some("product".to_string()),
+        clean_text: true,
+        extract_metadata: false,
+    };
+
+    let parser = Parser::new(config).with_schema_dir(dir.path());
+    let html = r#"<html><body>
+        <h1>Widget</h1>
+        <span class="price">Price: $1,299.50 EUR</span>
+        <span class="released">March 5, 2024</span>
+        <span class="homepage">not a url</span>
+        <span class="contact">Sales@Example.com</span>
+    </body></html>"#;
+
+    let output = parser.parse_with_report(html).unwrap();
+    assert_eq!(output.data["price"], json!(1299.5));
+    assert_eq!(output.data["released"], json!("2024-03-05"));
+    assert_eq!(output.data["contact"], json!("sales@example.com"));
+    assert_eq!(output.data["currency"], json!("USD"));
+
+    let report = output.validation.unwrap();
+    assert_eq!(report.total_records, 1);
+    assert_eq!(report.records.len(), 1);
+    assert_eq!(report.records[0].errors.len(), 1);
+    assert_eq!(report.records[0].errors[0].field, "homepage");
+}
+
+#[test]
+fn test_repeated_records_validated_one_by_one() {
+    let schema: Schema = serde_json::from_value(json!({
+        "name": "product",
+        "required": ["name", "price"],
+        "fields": [
+            {"name": "name", "field_type": "string"},
+            {"name": "price", "field_type": "number", "validators": [{"type": "Min", "value": 0}]}
+        ]
+    }))
+    .unwrap();
+    let rule = |name: &str, selector: &str| ParseRule {
+        name: name.to_string(),
+        selector: selector.to_string(),
+        attribute: None,
+        multiple: false,
+        required: false,
+        transform: None,
+        children: vec![],
+    };
+    let config = ParseConfig {
+        rules: vec![
+            rule("title", "title"),
+            ParseRule {
+                multiple: true,
+                children: vec![rule("name", "h2"), rule("price", ".price")],
+                ..rule("products", ".product")
+            },
+        ],
+        schema_name: None,
+        clean_text: true,
+        extract_metadata: false,
+    };
+
+    let html = r#"<html><head><title>Tools</title></head><body>
+        <div class="product"><h2>Hammer</h2><span class="price">$12</span></div>
+        <div class="product"><h2>Saw</h2><span class="price">-3</span></div>
+        <div class="product"><h2>Drill</h2><span class="price">1,299.00 EUR</span></div>
+    </body></html>"#;
+    let output = Parser::new(config).with_schema(schema).parse_with_report(html).unwrap();
+
+    assert_eq!(output.data["title"], json!("Tools"));
+    let prices: Vec<&Value> = output.data["products"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|product| &product["price"])
+        .collect();
+    assert_eq!(prices, [&json!(12.0), &json!(-3.0), &json!(1299.0)]);
+
+    let report = output.validation.unwrap();
+    assert_eq!(report.total_records, 3);
+    assert_eq!(report.valid_records, 2);
+    assert_eq!(report.records.len(), 1);
+    assert_eq!(report.records[0].record, 1);
+    assert_eq!(report.records[0].errors[0].field, "price");
+}
+
+#[test]
+fn test_incomplete_record_skipped() {
+    let schema: Schema = serde_json::from_value(json!({
+        "name": "product",
+        "fields": [{"name": "name", "field_type": "string"}, {"name": "price", "field_type": "number"}]
+    }))
+    .unwrap();
+    let rule = |name: &str, selector: &str, required: bool| ParseRule {
+        name: name.to_string(),
+        selector: selector.to_string(),
+        attribute: None,
+        multiple: false,
+        required,
+        transform: None,
+        children: vec![],
+    };
+    let config = ParseConfig {
+        rules: vec![ParseRule {
+            multiple: true,
+            children: vec![rule("name", "h2", false), rule("price", ".price", true)],
+            ..rule("products", ".product", false)
+        }],
+        schema_name: None,
+        clean_text: true,
+        extract_metadata: false,
+    };
+
+    let html = r#"<html><body>
+        <div class="product"><h2>Hammer</h2><span class="price">12</span></div>
+        <div class="product"><h2>Saw</h2></div>
+        <div class="product"><h2>Drill</h2><span class="price">80</span></div>
+    </body></html>"#;
+    let parser = Parser::new(config).with_schema(schema);
+    let output = parser.parse_with_report(html).unwrap();
+
+    let products = output.data["products"].as_array().unwrap();
+    assert_eq!(products.len(), 3);
+    assert_eq!(products[0]["name"], json!("Hammer"));
+    assert_eq!(products[1], json!(null));
+    assert_eq!(products[2]["price"], json!(80.0));
+
+    let report = output.validation.unwrap();
+    assert_eq!((report.total_records, report.valid_records), (3, 2));
+    assert_eq!(report.records[0].record, 1);
+    assert_eq!(report.records[0].errors[0].field, "products.price");
+}
+
+#[test]
+fn test_parse_unknown_schema_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = ParseConfig {
+        rules: vec![],
+        schema_name: Some("missing".to_string()),
+        clean_text: true,
+        extract_metadata: false,
+    };
+
+    let parser = Parser::new(config).with_schema_dir(dir.path());
+    assert!(parser.parse("<html></html>").is_err());
+}
+
+#[test]
+fn test_parse_rule_transform_pipeline() {
+    let config = ParseConfig {
+        rules: vec![
+            ParseRule {
+                name: "slug".to_string(),
+                selector: "h1".to_string(),
+                attribute: None,
+                multiple: false,
+                required: false,
+                transform: Some(r#"trim | lowercase | replace(" ", "-")"#.to_string()),
+                children: vec![],
+            },
+            ParseRule {
+                name: "prices".to_string(),
+                selector: ".price".to_string(),
+                attribute: None,
+                multiple: true,
+                required: false,
+                transform: Some("to_number".to_string()),
+                children: vec![],
+            },
+            ParseRule {
+                name: "tags".to_string(),
+                selector: ".tags".to_string(),
+                attribute: Some("data-tags".to_string()),
+                multiple: false,
+                required: false,
+                transform: Some("split(',') | trim | uppercase".to_string()),
+                children: vec![],
+            },
+            ParseRule {
+                name: "in_stock".to_string(),
+                selector: ".stock".to_string(),
+                attribute: None,
+                multiple: true,
+                required: false,
+                transform: Some("to_boolean".to_string()),
+                children: vec![],
+            },
+        ],
+        schema_name: None,
+        clean_text: true,
+        extract_metadata: false,
+    };
+
+    let html = r#"<html><body>
+        <h1> Hello Big World </h1>
+        <span class="price">$10.50</span><span class="price">Price: $3</span>
+        <span class="price">1,299.00 EUR</span><span class="price">n/a</span>
+        <div class="tags" data-tags="rust, web ,crawler"></div>
+        <span class="stock">Yes</span><span class="stock">off</span><span class="stock"></span>
+    </body></html>"#;
+    let result = Parser::new(config).parse(html).unwrap();
+
+    assert_eq!(result["slug"], json!("hello-big-world"));
+    assert_eq!(result["prices"], json!([10.5, 3.0, 1299.0, null]));
+    assert_eq!(result["tags"], json!(["RUST", "WEB", "CRAWLER"]));
+    // Same rules as schema coercion: empty text isn't false
+    assert_eq!(result["in_stock"], json!([true, false, null]));
+}
+
+#[test]
+fn test_unknown_transform_is_an_error() {
+    let config = ParseConfig {
+        rules: vec![ParseRule {
+            name: "title".to_string(),
+            selector: "title".to_string(),
+            attribute: None,
+            multiple: false,
+            required: false,
+            transform: Some("trim | reverse".to_string()),
+            children: vec![],
+        }],
+        schema_name: None,
+        clean_text: true,
+        extract_metadata: false,
+    };
+
+    let html = "<html><head><title>Test</title></head></html>";
+    assert!(Parser::new(config).parse(html).is_err());
+}
+
+#[test]
+fn test_nested_record_extraction() {
+    let field = |name: &str, selector: &str, attribute: Option<&str>| ParseRule {
+        name: name.to_string(),
+        selector: selector.to_string(),
+        attribute: attribute.map(String::from),
+        multiple: false,
+        required: false,
+        transform: None,
+        children: vec![],
+    };
+
+    let config = ParseConfig {
+        rules: vec![ParseRule {
+            name: "products".to_string(),
+            selector: ".product".to_string(),
+            attribute: None,
+            multiple: true,
+            required: false,
+            transform: None,
+            children: vec![
+                field("name", "h2", None),
+                ParseRule {
+                    required: true,
+                    transform: Some("to_number".to_string()),
+                    ..field("price", ".price", None)
+                },
+                field("image", "img", Some("src")),
+                field("category", "xpath:../@data-category", None),
+                field("note", "xpath:following-sibling::p[1]", None),
+                ParseRule {
+                    multiple: true,
+                    children: vec![
+                        field("color", ".color", None),
+                        field("sku", "xpath:@data-sku", None),
+                    ],
+                    ..field("variants", ".variant", None)
+                },
+            ],
+        }],
+        schema_name: None,
+        clean_text: true,
+        extract_metadata: false,
+    };
+
+    let html = r#"<html><body><section data-category="tools">
+        <div class="product">
+            <h2>Hammer</h2><span class="price">$12</span><img src="/h.png">
+            <ul>
+                <li class="variant" data-sku="H-R"><span class="color">Red</span></li>
+                <li class="variant" data-sku="H-B"><span class="color">Blue</span></li>
+            </ul>
+        </div>
+        <p>Best seller</p>
+        <div class="product">
+            <h2>Saw</h2><span class="price">$30</span>
+        </div>
+    </section></body></html>"#;
+
+    let result = Parser::new(config).parse(html).unwrap();
+    let products = result["products"].as_array().unwrap();
+
+    assert_eq!(products.len(), 2);
+    assert_eq!(
+        products[0],
+        json!({
+            "name": "Hammer",
+            "price": 12.0,
+            "image": "/h.png",
+            "category": "tools",
+            "note": "Best seller",
+            "variants": [
+                {"color": "Red", "sku": "H-R"},
+                {"color": "Blue", "sku": "H-B"}
+            ]
+        })
+    );
+    assert_eq!(products[1]["image"], json!(null));
+    assert_eq!(products[1]["variants"], json!(null));
+}
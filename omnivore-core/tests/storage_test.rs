@@ -0,0 +1,310 @@
+#![cfg(feature = "database")]
+
+use omnivore_core::config::DatabaseConfig;
+use omnivore_core::storage::sql::{SessionRecord, SessionStatus, SqlSink};
+use omnivore_core::CrawlResult;
+use serde_json::{json, Value};
+use sqlx::AnyPool;
+use uuid::Uuid;
+
+fn result(url: &str, text: &str, links: &[&str]) -> CrawlResult {
+    serde_json::from_value(json!({
+        "url": url,
+        "status_code": 200,
+        "content": format!("<html><body>{}</body></html>", text),
+        "cleaned_content": { "title": format!("Title of {}", url), "content": text, "tables": [], "links": [], "word_count": 1 },
+        "headers": { "content-type": "text/html; charset=utf-8" },
+        "extracted_data": { "lang": "en" },
+        "links": links,
+        "crawled_at": "2025-01-15T10:30:00Z",
+    }))
+    .unwrap()
+}
+
+fn session(id: Uuid) -> SessionRecord {
+    SessionRecord {
+        id,
+        name: "shop".to_string(),
+        start_url: "https://shop.example.com/".to_string(),
+        max_depth: 3,
+        max_workers: 2,
+        status: SessionStatus::Running,
+        settings: json!({ "delay_ms": 100 }),
+    }
+}
+
+async fn write_session(config: &DatabaseConfig, id: Uuid) {
+    let mut sink = SqlSink::connect(config).await.unwrap().include_html(true);
+    sink.create_tables().await.unwrap();
+    sink.start_session(&session(id)).await.unwrap();
+
+    let results = [
+        result(
+            "https://shop.example.com/",
+            "Mail sales@example.com",
+            &[
+                "https://shop.example.com/about",
+                "https://shop.example.com/cart",
+            ],
+        ),
+        result(
+            "https://shop.example.com/about",
+            "About us",
+            &["https://shop.example.com/", "https://shop.example.com/team"],
+        ),
+        result("https://shop.example.com/team", "Team", &[]),
+    ];
+    let mut written = 0;
+    for result in &results {
+        written += sink.write(result).await.unwrap().pages;
+    }
+    // batch_size 2 writes the first two pages before the session ends
+    assert_eq!(written, 2);
+    let summary = sink.finish_session(SessionStatus::Completed).await.unwrap();
+    assert_eq!(summary.pages, 1);
+}
+
+async fn count(pool: &AnyPool, sql: &str) -> i64 {
+    sqlx::query_scalar(sql).fetch_one(pool).await.unwrap()
+}
+
+async fn check_tables(config: &DatabaseConfig) {
+    let prefix = &config.table_name;
+    let id = Uuid::new_v4();
+    write_session(config, id).await;
+    // Writing the session again updates its rows
+    write_session(config, id).await;
+
+    sqlx::any::install_default_drivers();
+    let pool = AnyPool::connect(&config.connection_string).await.unwrap();
+    let status: String = sqlx::query_scalar(&format!("SELECT status FROM {prefix}sessions"))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "completed");
+    assert_eq!(
+        count(&pool, &format!("SELECT COUNT(*) FROM {prefix}pages")).await,
+        3
+    );
+    assert_eq!(
+        count(&pool, &format!("SELECT COUNT(*) FROM {prefix}links")).await,
+        4
+    );
+    assert_eq!(
+        count(
+            &pool,
+            &format!("SELECT COUNT(*) FROM {prefix}links WHERE processed")
+        )
+        .await,
+        3
+    );
+    assert_eq!(
+        count(
+            &pool,
+            &format!("SELECT COUNT(*) FROM {prefix}entities WHERE entity_type = 'EMAIL'")
+        )
+        .await,
+        1
+    );
+
+    let (depth, parent, headers, content_type): (i64, Option<String>, String, String) =
+        sqlx::query_as(&format!(
+            "SELECT CAST(depth AS BIGINT), parent_url, CAST(response_headers AS TEXT), content_type \
+             FROM {prefix}pages WHERE url = 'https://shop.example.com/team'"
+        ))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(depth, 2);
+    assert_eq!(parent.as_deref(), Some("https://shop.example.com/about"));
+    let headers: Value = serde_json::from_str(&headers).unwrap();
+    assert_eq!(headers["content-type"], "text/html; charset=utf-8");
+    assert_eq!(content_type, "text/html; charset=utf-8");
+}
+
+#[tokio::test]
+async fn test_sqlite_sink() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let config = DatabaseConfig {
+        connection_string: format!("sqlite:{}", dir.path().join("crawl.db").display()),
+        table_name: String::new(),
+        batch_size: 2,
+    };
+    check_tables(&config).await;
+}
+
+/// Runs against the database in `OMNIVORE_TEST_DATABASE_URL`, e.g.
+/// `postgres://localhost/omnivore_test`, and is skipped without it.
+#[tokio::test]
+async fn test_postgres_sink() {
+    let Ok(connection_string) = std::env::var("OMNIVORE_TEST_DATABASE_URL") else {
+        return;
+    };
+    let config = DatabaseConfig {
+        connection_string,
+        table_name: format!("test_{}_", Uuid::new_v4().simple()),
+        batch_size: 2,
+    };
+    check_tables(&config).await;
+
+    let pool = AnyPool::connect(&config.connection_string).await.unwrap();
+    for table in ["entities", "links", "pages", "sessions"] {
+        sqlx::query(&format!("DROP TABLE {}{}", config.table_name, table))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_bad_config() {
+    let mut config = DatabaseConfig {
+        connection_string: "mysql://localhost/crawl".to_string(),
+        table_name: String::new(),
+        batch_size: 100,
+    };
+    assert!(SqlSink::connect(&config).await.is_err());
+
+    config.connection_string = "sqlite::memory:".to_string();
+    config.table_name = "pages; DROP TABLE pages".to_string();
+    assert!(SqlSink::connect(&config).await.is_err());
+}
+
+#[cfg(feature = "cloud")]
+mod cloud {
+    use chrono::NaiveDate;
+    use omnivore_core::config::CloudConfig;
+    use omnivore_core::storage::cloud::{connect, export, key_prefix};
+    use omnivore_core::CrawlStats;
+    use object_store::aws::AmazonS3Builder;
+    use object_store::path::Path;
+    use object_store::ObjectStore;
+    use serde_json::{json, Value};
+
+    fn config(provider: &str) -> CloudConfig {
+        CloudConfig {
+            provider: provider.to_string(),
+            bucket: std::env::var("OMNIVORE_TEST_S3_BUCKET")
+                .unwrap_or_else(|_| "omnivore-test".to_string()),
+            credentials: Some("minioadmin:minioadmin".to_string()),
+            endpoint: std::env::var("OMNIVORE_TEST_S3_ENDPOINT").ok(),
+            region: Some("us-east-1".to_string()),
+            key_layout: "crawls/{domain}/{date}/{session}/".to_string(),
+            part_size_mb: 5,
+            max_retries: 3,
+        }
+    }
+
+    #[test]
+    fn test_key_layout() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        assert_eq!(
+            key_prefix(&config("s3").key_layout, "shop", date, "example.com"),
+            "crawls/example.com/2025-01-15/shop"
+        );
+        assert!(connect(&config("gcs")).is_err());
+        assert!(connect(&config("dropbox")).is_err());
+    }
+
+    /// Runs against the S3-compatible endpoint in `OMNIVORE_TEST_S3_ENDPOINT`,
+    /// e.g. a MinIO at `http://localhost:9000` with a bucket named
+    /// `omnivore-test`, and is skipped without it.
+    #[tokio::test]
+    async fn test_s3_export() {
+        let config = config("s3");
+        let Some(endpoint) = config.endpoint.clone() else {
+            return;
+        };
+        let mut page = super::result("https://shop.example.com/", "Prices", &[]);
+        page.cleaned_content.as_mut().unwrap().tables = serde_json::from_value(json!([{
+            "headers": ["Item", "Price"],
+            "rows": [["Tea", "3"], ["Cake, large", "5"]],
+            "title": null,
+            "caption": null,
+            "footnotes": [],
+        }]))
+        .unwrap();
+        // Over the part size, so it's uploaded in two parts
+        let mut large = super::result("https://shop.example.com/large", "Large", &[]);
+        large.content = "x".repeat(6 * 1024 * 1024);
+        let stats = CrawlStats {
+            total_urls: 2,
+            successful: 2,
+            failed: 0,
+            in_progress: 0,
+            average_response_time_ms: 10.0,
+            start_time: chrono::Utc::now(),
+            elapsed_time: std::time::Duration::from_secs(1),
+            proxies: Vec::new(),
+        };
+
+        let session = uuid::Uuid::new_v4().to_string();
+        let prefix = key_prefix(
+            &config.key_layout,
+            &session,
+            chrono::Utc::now().date_naive(),
+            "shop.example.com",
+        );
+        let store = connect(&config).unwrap();
+        let summary = export(store.as_ref(), &prefix, &stats, &[page, large], true)
+            .await
+            .unwrap();
+        assert_eq!(summary.files, 4);
+
+        let bucket = AmazonS3Builder::new()
+            .with_bucket_name(&config.bucket)
+            .with_region("us-east-1")
+            .with_access_key_id("minioadmin")
+            .with_secret_access_key("minioadmin")
+            .with_endpoint(&endpoint)
+            .with_allow_http(true)
+            .build()
+            .unwrap();
+        let get = |name: &str| {
+            let path = Path::from(format!("{}/{}", prefix, name));
+            let bucket = &bucket;
+            async move { bucket.get(&path).await.unwrap().bytes().await.unwrap() }
+        };
+
+        let results: Value = serde_json::from_slice(&get("results.json").await).unwrap();
+        assert_eq!(results["stats"]["total_urls"], 2);
+        assert_eq!(results["results"][1]["url"], "https://shop.example.com/large");
+        assert_eq!(
+            get("tables/page_0001_table_1.csv").await,
+            "Item,Price\nTea,3\n\"Cake, large\",5\n"
+        );
+        assert_eq!(get("html/page_0002.html").await.len(), 6 * 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_upload_error() {
+        let store = connect(&CloudConfig {
+            endpoint: Some("http://127.0.0.1:1".to_string()),
+            max_retries: 0,
+            ..config("s3")
+        })
+        .unwrap();
+        let stats = CrawlStats {
+            total_urls: 0,
+            successful: 0,
+            failed: 0,
+            in_progress: 0,
+            average_response_time_ms: 0.0,
+            start_time: chrono::Utc::now(),
+            elapsed_time: std::time::Duration::ZERO,
+            proxies: Vec::new(),
+        };
+
+        // The failed file is named, with the store's error wrapped once
+        let error = export(store.as_ref(), "crawls/test", &stats, &[], false)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Storage error: Failed to upload crawls/test/results.json: "),
+            "{}",
+            error
+        );
+        assert_eq!(error.matches("Storage error").count(), 1, "{}", error);
+    }
+}
@@ -0,0 +1,180 @@
+use omnivore_core::config::{ExtractionTemplate, PatternRule};
+use omnivore_core::parser::jsonpath::JsonPath;
+use omnivore_core::parser::template::TemplateEngine;
+use omnivore_core::parser::xpath::{XPath, XPathValue};
+use scraper::Html;
+use serde_json::json;
+
+const PRODUCT_PAGE: &str = r#"
+<html>
+<head>
+    <title>Shop</title>
+    <script type="application/ld+json">
+        {"@type": "Product", "name": "Widget", "offers": {"price": "19.99", "priceCurrency": "USD"}}
+    </script>
+</head>
+<body>
+    <div class="product" data-sku="A1">
+        <h2 class="name">  Red   Widget </h2>
+        <span class="price">$19.99</span>
+        <a href="/p/a1">View</a>
+    </div>
+    <div class="product" data-sku="B2">
+        <h2 class="name">Blue Widget</h2>
+        <span class="price">$24.50</span>
+        <a href="/p/b2">View</a>
+    </div>
+    <p>Contact: sales@example.com or support@example.com</p>
+</body>
+</html>
+"#;
+
+fn rule(name: &str, pattern_type: &str, selector: &str, extract: &[&str]) -> PatternRule {
+    PatternRule {
+        name: name.to_string(),
+        pattern_type: pattern_type.to_string(),
+        selector: selector.to_string(),
+        extract: extract.iter().map(|s| s.to_string()).collect(),
+        transform: None,
+        required: false,
+        multiple: false,
+    }
+}
+
+fn template(patterns: Vec<PatternRule>) -> ExtractionTemplate {
+    ExtractionTemplate {
+        name: "test".to_string(),
+        description: "test template".to_string(),
+        version: "1.0.0".to_string(),
+        author: None,
+        patterns,
+        pipelines: Vec::new(),
+        output_schema: None,
+    }
+}
+
+#[test]
+fn test_xpath_evaluation() {
+    let document = Html::parse_document(PRODUCT_PAGE);
+
+    let names = XPath::parse("//div[@class='product']/h2").unwrap();
+    let nodes = names.select(&document).unwrap();
+    assert_eq!(nodes.len(), 2);
+
+    let sku = XPath::parse("//div[span[contains(., '24')]]/@data-sku").unwrap();
+    assert_eq!(sku.evaluate(&document).unwrap().to_string_value(), "B2");
+
+    let count = XPath::parse("count(//a[starts-with(@href, '/p/')])").unwrap();
+    match count.evaluate(&document).unwrap() {
+        XPathValue::Number(n) => assert_eq!(n, 2.0),
+        other => panic!("expected number, got {other:?}"),
+    }
+
+    let last = XPath::parse("normalize-space((//h2)[last()])").unwrap();
+    assert_eq!(last.evaluate(&document).unwrap().to_string_value(), "Blue Widget");
+
+    assert!(XPath::parse("//div[").is_err());
+}
+
+#[test]
+fn test_jsonpath_selection() {
+    let data = json!({
+        "props": {"pageProps": {"items": [
+            {"id": 1, "price": 5, "tags": ["a"]},
+            {"id": 2, "price": 15, "tags": ["b"]},
+            {"id": 3, "price": 25}
+        ]}}
+    });
+
+    let ids = JsonPath::parse("$.props.pageProps.items[?(@.price > 10)].id").unwrap();
+    assert_eq!(ids.select(&data), vec![&json!(2), &json!(3)]);
+
+    let last = JsonPath::parse("$..items[-1].id").unwrap();
+    assert_eq!(last.select(&data), vec![&json!(3)]);
+
+    let tags = JsonPath::parse("$..tags[*]").unwrap();
+    assert_eq!(tags.select(&data).len(), 2);
+}
+
+#[test]
+fn test_template_css_xpath_regex_json_path() {
+    let mut products = rule(
+        "products",
+        "css",
+        ".product",
+        &["sku=@data-sku", "name", "price=.price", "link=a @href"],
+    );
+    products.multiple = true;
+
+    let mut emails = rule(
+        "emails",
+        "regex",
+        r"(?P<user>[a-z]+)@(?P<domain>example\.com)",
+        &[],
+    );
+    emails.multiple = true;
+
+    let mut price = rule("ld_price", "json_path", "$.offers.price", &[]);
+    price.transform = Some("to_number".to_string());
+
+    let engine = TemplateEngine::new(template(vec![
+        products,
+        rule("first_name", "xpath", "//h2[1]", &[]),
+        emails,
+        price,
+    ]))
+    .unwrap();
+
+    let output = engine.execute(PRODUCT_PAGE).unwrap();
+    assert!(output.violations.is_empty());
+    assert_eq!(
+        output.data["products"][0],
+        json!({"sku": "A1", "name": "Red Widget", "price": "$19.99", "link": "/p/a1"})
+    );
+    assert_eq!(output.data["products"].as_array().unwrap().len(), 2);
+    assert_eq!(output.data["first_name"], json!("Red Widget"));
+    assert_eq!(
+        output.data["emails"][1],
+        json!({"user": "support", "domain": "example.com"})
+    );
+    assert_eq!(output.data["ld_price"], json!(19.99));
+}
+
+#[test]
+fn test_template_rejects_unknown_types_and_transforms() {
+    let bad_type = rule("x", "jq", ".foo", &[]);
+    assert!(TemplateEngine::new(template(vec![bad_type])).is_err());
+
+    let mut bad_transform = rule("x", "css", "h2", &[]);
+    bad_transform.transform = Some("trim | shout".to_string());
+    assert!(TemplateEngine::new(template(vec![bad_transform])).is_err());
+
+    let mut required = rule("missing", "css", ".does-not-exist", &[]);
+    required.required = true;
+    let engine = TemplateEngine::new(template(vec![required])).unwrap();
+    assert!(engine.execute(PRODUCT_PAGE).is_err());
+}
+
+#[test]
+fn test_template_output_schema_violations() {
+    let mut prices = rule("prices", "css", ".price", &[]);
+    prices.multiple = true;
+
+    let mut tmpl = template(vec![prices, rule("title", "css", "title", &[])]);
+    tmpl.pipelines = vec!["to_number".to_string()];
+    tmpl.output_schema = Some(json!({
+        "type": "object",
+        "required": ["title", "prices"],
+        "properties": {
+            "prices": {"type": "array", "items": {"type": "number", "maximum": 20}},
+            "title": {"type": "string"}
+        }
+    }));
+
+    let output = TemplateEngine::new(tmpl).unwrap().execute(PRODUCT_PAGE).unwrap();
+    assert_eq!(output.data["prices"], json!([19.99, 24.5]));
+    // "Shop" is not numeric, and 24.5 exceeds the maximum
+    assert_eq!(output.violations.len(), 2, "{:?}", output.violations);
+    assert!(output.violations.iter().any(|v| v.starts_with("$.prices[1]")));
+    assert!(output.violations.iter().any(|v| v.starts_with("$.title")));
+}
@@ -0,0 +1,270 @@
+use omnivore_core::config::WebhookConfig;
+use omnivore_core::webhook::{
+    changed_pages, content_hash, sign, WebhookEvent, WebhookNotifier, EVENT_HEADER,
+    SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use omnivore_core::{CrawlResult, CrawlStats};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+fn config(dead_letters: &Path) -> WebhookConfig {
+    WebhookConfig {
+        secret: Some("s3cret".to_string()),
+        max_retries: 2,
+        retry_backoff_ms: 1,
+        dead_letter_path: Some(dead_letters.to_path_buf()),
+        ..Default::default()
+    }
+}
+
+fn stats(successful: usize, failed: usize) -> CrawlStats {
+    CrawlStats {
+        total_urls: successful + failed,
+        successful,
+        failed,
+        in_progress: 0,
+        average_response_time_ms: 12.5,
+        start_time: chrono::Utc::now(),
+        elapsed_time: std::time::Duration::from_secs(3),
+        proxies: Vec::new(),
+    }
+}
+
+fn page(url: &str, content: &str) -> CrawlResult {
+    serde_json::from_value(json!({
+        "url": url,
+        "status_code": 200,
+        "content": content,
+        "cleaned_content": null,
+        "headers": {},
+        "extracted_data": {},
+        "links": [],
+        "crawled_at": "2025-01-15T10:30:00Z",
+    }))
+    .unwrap()
+}
+
+fn changed() -> WebhookEvent {
+    WebhookEvent::PageChanged {
+        url: "https://example.com/prices".to_string(),
+        previous_hash: Some("abc".to_string()),
+        hash: "def".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_signed_delivery() {
+    let mut server = mockito::Server::new_async().await;
+    let hook = server
+        .mock("POST", "/hooks")
+        .match_header(EVENT_HEADER, "crawl.completed")
+        .match_request(|request| {
+            let header = |name| {
+                request.header(name)[0]
+                    .to_str()
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let timestamp: i64 = header(TIMESTAMP_HEADER).parse().unwrap_or_default();
+            let body = request.body().unwrap();
+            header(SIGNATURE_HEADER) == sign("s3cret", timestamp, body)
+        })
+        .with_status(204)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let notifier = WebhookNotifier::new(
+        &format!("{}/hooks", server.url()),
+        "crawl-1",
+        &config(&dir.path().join("dead.jsonl")),
+    )
+    .unwrap();
+    notifier
+        .send(WebhookEvent::CrawlCompleted {
+            start_url: "https://example.com/".to_string(),
+            stats: stats(9, 1),
+        })
+        .await
+        .unwrap();
+
+    hook.assert_async().await;
+    assert!(!dir.path().join("dead.jsonl").exists());
+}
+
+#[test]
+fn test_delivery_body() {
+    let signature = sign("s3cret", 1700000000, b"{}");
+    assert!(signature.starts_with("sha256="));
+    assert_eq!(signature.len(), "sha256=".len() + 64);
+    assert_ne!(signature, sign("other", 1700000000, b"{}"));
+    assert_ne!(signature, sign("s3cret", 1700000001, b"{}"));
+
+    let delivery = omnivore_core::webhook::Delivery {
+        id: uuid::Uuid::new_v4(),
+        crawl_id: "crawl-1".to_string(),
+        created_at: chrono::Utc::now(),
+        event: changed(),
+    };
+    let body: Value = serde_json::to_value(&delivery).unwrap();
+    assert_eq!(body["event"], "page.changed");
+    assert_eq!(body["crawl_id"], "crawl-1");
+    assert_eq!(body["data"]["url"], "https://example.com/prices");
+    assert_eq!(body["data"]["previous_hash"], "abc");
+}
+
+#[tokio::test]
+async fn test_retries_with_backoff() {
+    let mut server = mockito::Server::new_async().await;
+    let unavailable = server
+        .mock("POST", "/")
+        .with_status(503)
+        .expect(2)
+        .create_async()
+        .await;
+    let ok = server
+        .mock("POST", "/")
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let notifier = WebhookNotifier::new(
+        &server.url(),
+        "crawl-1",
+        &config(&dir.path().join("dead.jsonl")),
+    )
+    .unwrap();
+    notifier.send(changed()).await.unwrap();
+
+    unavailable.assert_async().await;
+    ok.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_dead_letter() {
+    let mut server = mockito::Server::new_async().await;
+    let unavailable = server
+        .mock("POST", "/")
+        .with_status(500)
+        .expect(3)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let dead_letters = dir.path().join("hooks").join("dead.jsonl");
+    let notifier = WebhookNotifier::new(&server.url(), "crawl-1", &config(&dead_letters)).unwrap();
+    assert!(notifier.send(changed()).await.is_err());
+    unavailable.assert_async().await;
+
+    let content = std::fs::read_to_string(&dead_letters).unwrap();
+    let lines: Vec<Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["attempts"], 3);
+    assert_eq!(lines[0]["error"], "HTTP 500 Internal Server Error");
+    assert_eq!(lines[0]["delivery"]["event"], "page.changed");
+}
+
+#[tokio::test]
+async fn test_client_errors_not_retried() {
+    let mut server = mockito::Server::new_async().await;
+    let rejected = server
+        .mock("POST", "/")
+        .with_status(410)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let notifier = WebhookNotifier::new(
+        &server.url(),
+        "crawl-1",
+        &config(&dir.path().join("dead.jsonl")),
+    )
+    .unwrap();
+    assert!(notifier.send(changed()).await.is_err());
+    rejected.assert_async().await;
+}
+
+#[test]
+fn test_error_threshold() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = WebhookConfig {
+        error_threshold: 0.25,
+        error_min_pages: 8,
+        ..config(&dir.path().join("dead.jsonl"))
+    };
+    let notifier = WebhookNotifier::new("http://localhost/hooks", "crawl-1", &config).unwrap();
+
+    // Too few pages to tell yet
+    assert_eq!(notifier.exceeded_error_rate(&stats(2, 4)), None);
+    assert_eq!(notifier.exceeded_error_rate(&stats(7, 2)), None);
+    assert_eq!(notifier.exceeded_error_rate(&stats(6, 2)), Some(0.25));
+    assert!(WebhookNotifier::new("not a url", "crawl-1", &config).is_err());
+}
+
+#[test]
+fn test_changed_pages() {
+    let mut hashes = HashMap::from([
+        ("https://example.com/".to_string(), content_hash("home")),
+        (
+            "https://example.com/prices".to_string(),
+            content_hash("tea: 3"),
+        ),
+    ]);
+    let results = [
+        page("https://example.com/", "home"),
+        page("https://example.com/prices", "tea: 4"),
+        page("https://example.com/new", "new"),
+    ];
+
+    let events = changed_pages(&mut hashes, &results);
+    let changed: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            WebhookEvent::PageChanged {
+                url,
+                previous_hash,
+                hash,
+            } => (url.as_str(), previous_hash.clone(), hash.clone()),
+            other => panic!("unexpected {}", other.name()),
+        })
+        .collect();
+    assert_eq!(
+        changed,
+        [
+            (
+                "https://example.com/prices",
+                Some(content_hash("tea: 3")),
+                content_hash("tea: 4")
+            ),
+            ("https://example.com/new", None, content_hash("new")),
+        ]
+    );
+    assert_eq!(hashes.len(), 3);
+    assert!(changed_pages(&mut hashes, &results).is_empty());
+
+    // Only the cleaned text counts when there is some
+    let cleaned = |html: &str, text: &str| {
+        let mut result = page("https://example.com/article", html);
+        result.cleaned_content = serde_json::from_value(json!({
+            "content": text,
+            "tables": [],
+            "links": [],
+            "word_count": 1,
+        }))
+        .unwrap();
+        result
+    };
+    changed_pages(&mut hashes, &[cleaned("<p>Hi</p><!-- ad 1 -->", "Hi")]);
+    assert!(changed_pages(&mut hashes, &[cleaned("<p>Hi</p><!-- ad 2 -->", "Hi")]).is_empty());
+    assert_eq!(
+        changed_pages(&mut hashes, &[cleaned("<p>Hello</p>", "Hello")]).len(),
+        1
+    );
+}